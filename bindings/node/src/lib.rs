@@ -0,0 +1,219 @@
+//! Node.js bindings of zorq-acl.
+//!
+//! The class `Acl` exposes the core API to Node services, so they share the policy engine and the
+//! policy files of Rust services. `null` or `undefined` is the wildcard. Methods are camel cased
+//! and errors are thrown with the message of `zorq_acl::Error`:
+//!
+//! ```js
+//! const { Acl } = require("zorq-acl");
+//!
+//! const acl = Acl.loadJson("policy.json");
+//!
+//! acl.allow("staff", "latest", "revise");
+//! acl.isAllowed("staff", "latest", "revise"); // true
+//! acl.explain("guest", "latest", "revise").matched; // "*→*: *"
+//! ```
+//!
+//! Names given to `addRole`, `addResource` and rules are leaked once to obtain the `'static`
+//! lifetime required by the `Acl`. Queries never leak: unknown roles and resources are rejected,
+//! privileges not named by any rule are decided alike.
+
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::collections::HashSet;
+use zorq_acl::Access;
+
+fn error(error: zorq_acl::Error) -> Error {
+    Error::from_reason(error.to_string())
+} // error
+
+/// The decision of a query and the rule which made it.
+#[napi(object)]
+pub struct Explanation {
+    /// "allow" or "deny"
+    pub decision:    String,
+    /// the query, e.g. "staff→latest: revise"
+    pub query:       String,
+    /// the query of the deciding rule, e.g. "guest→*: *"
+    pub matched:     String,
+    /// true if decided by a bypass role
+    pub bypass:      bool,
+    /// the description of the deciding rule
+    pub description: Option<String>,
+    /// the message of the deciding deny rule
+    pub message:     Option<String>,
+} // struct Explanation
+
+/// An access control list.
+#[napi]
+pub struct Acl {
+    acl:   zorq_acl::Acl,
+    names: HashSet<&'static str>,
+} // struct Acl
+
+#[napi]
+impl Acl {
+
+    /// Creates an empty `Acl` denying everything.
+    #[napi(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Acl{acl: zorq_acl::Acl::new(), names: HashSet::new()}
+    } // new
+
+    /// Creates an `Acl` from a JSON policy document.
+    #[napi(factory)]
+    pub fn from_json(source: String) -> Result<Self> {
+        zorq_acl::Acl::from_json(&source).map(Self::wrap).map_err(error)
+    } // from_json
+
+    /// Creates an `Acl` from a JSON policy file.
+    #[napi(factory)]
+    pub fn load_json(path: String) -> Result<Self> {
+        zorq_acl::Acl::load_json(&path).map(Self::wrap).map_err(error)
+    } // load_json
+
+    /// Exports the `Acl` as JSON policy document.
+    #[napi]
+    pub fn to_json(&self) -> String {
+        self.acl.to_json()
+    } // to_json
+
+    /// Adds a role inheriting from parents.
+    #[napi]
+    pub fn add_role(&mut self, name: String, parents: Option<Vec<String>>) -> Result<()> {
+        let name    = self.intern(&name);
+        let parents = parents.unwrap_or_default().iter().map(|parent| self.intern(parent)).collect();
+
+        self.acl.add_role(name, parents).map_err(error)
+    } // add_role
+
+    /// Adds a resource below parent.
+    #[napi]
+    pub fn add_resource(&mut self, name: String, parent: Option<String>) -> Result<()> {
+        let name   = self.intern(&name);
+        let parent = parent.map(|parent| self.intern(&parent));
+
+        self.acl.add_resource(name, parent).map_err(error)
+    } // add_resource
+
+    /// Allows privilege on resource for role.
+    #[napi]
+    pub fn allow(&mut self, role: Option<String>, resource: Option<String>, privilege: Option<String>) -> Result<()> {
+        self.set_rule(role, resource, privilege, Access::Allow)
+    } // allow
+
+    /// Denies privilege on resource for role.
+    #[napi]
+    pub fn deny(&mut self, role: Option<String>, resource: Option<String>, privilege: Option<String>) -> Result<()> {
+        self.set_rule(role, resource, privilege, Access::Deny)
+    } // deny
+
+    /// Returns true if privilege on resource is allowed for role.
+    #[napi]
+    pub fn is_allowed(&self, role: Option<String>, resource: Option<String>, privilege: Option<String>) -> Result<bool> {
+        self.decide(role, resource, privilege).map(|decision| decision.is_allowed())
+    } // is_allowed
+
+    /// Returns true if privilege on resource is denied for role.
+    #[napi]
+    pub fn is_denied(&self, role: Option<String>, resource: Option<String>, privilege: Option<String>) -> Result<bool> {
+        self.decide(role, resource, privilege).map(|decision| decision.is_denied())
+    } // is_denied
+
+    /// Decides the query and returns the rule which made the decision.
+    #[napi]
+    pub fn explain(&self, role: Option<String>, resource: Option<String>, privilege: Option<String>) -> Result<Explanation> {
+        let decision = self.decide(role, resource, privilege.clone())?;
+        let query    = match privilege {
+            Some(name) => format!("{}→{}: {}", decision.query.role.unwrap_or("*"), decision.query.resource.unwrap_or("*"), name),
+            None       => decision.query.to_string(),
+        }; // match
+
+        Ok(Explanation{
+            decision:    decision.rule.access().to_string().to_lowercase(),
+            query,
+            matched:     decision.matched.to_string(),
+            bypass:      decision.bypass,
+            description: self.acl.get_decision_meta(&decision).and_then(|meta| meta.description.clone()),
+            message:     self.acl.get_denial_message(&decision).map(String::from),
+        }) // Explanation
+    } // explain
+
+    /// Returns the role and its ancestors in search order.
+    #[napi]
+    pub fn role_lineage(&self, name: String) -> Result<Vec<String>> {
+        let name = self.role(&name)?;
+
+        Ok(self.acl.get_role_lineage(name).into_iter().map(String::from).collect())
+    } // role_lineage
+
+    /// Returns the resource and its ancestors.
+    #[napi]
+    pub fn resource_lineage(&self, name: String) -> Result<Vec<String>> {
+        let name = self.resource(&name)?;
+
+        Ok(self.acl.get_resource_lineage(name).into_iter().map(String::from).collect())
+    } // resource_lineage
+
+    /// Locks the `Acl` to cache decisions, rules can't be changed until unlocked.
+    #[napi]
+    pub fn lock(&mut self) {
+        self.acl.lock();
+    } // lock
+
+    /// Unlocks the `Acl` and purges the cache.
+    #[napi]
+    pub fn unlock(&mut self) {
+        self.acl.unlock();
+    } // unlock
+
+} // impl Acl
+
+impl Acl {
+
+    /// Wraps acl and collects its names, so they aren't leaked again.
+    fn wrap(acl: zorq_acl::Acl) -> Self {
+        let mut names: HashSet<&'static str> = acl.roles().chain(acl.resources()).chain(acl.privileges()).collect();
+
+        names.extend(acl.rules().filter_map(|(query, _, _)| query.privilege));
+        Acl{acl, names}
+    } // wrap
+
+    /// Returns name borrowed for the `'static` lifetime, leaking it at most once.
+    fn intern(&mut self, name: &str) -> &'static str {
+        if let Some(known) = self.names.get(name) {
+            return known;
+        } // if
+        let name: &'static str = Box::leak(String::from(name).into_boxed_str());
+
+        self.names.insert(name);
+        name
+    } // intern
+
+    fn role(&self, name: &str) -> Result<&'static str> {
+        self.acl.roles().find(|role| *role == name).ok_or_else(|| error(zorq_acl::Error::MissingRole(String::from(name))))
+    } // role
+
+    fn resource(&self, name: &str) -> Result<&'static str> {
+        self.acl.resources().find(|resource| *resource == name).ok_or_else(|| error(zorq_acl::Error::MissingResource(String::from(name))))
+    } // resource
+
+    fn set_rule(&mut self, role: Option<String>, resource: Option<String>, privilege: Option<String>, access: Access) -> Result<()> {
+        let role      = role.map(|name| self.intern(&name));
+        let resource  = resource.map(|name| self.intern(&name));
+        let privilege = privilege.map(|name| self.intern(&name));
+
+        self.acl.set_rule(role, resource, privilege, access).map_err(error)
+    } // set_rule
+
+    fn decide(&self, role: Option<String>, resource: Option<String>, privilege: Option<String>) -> Result<zorq_acl::Decision> {
+        let role      = role.map(|name| self.role(&name)).transpose()?;
+        let resource  = resource.map(|name| self.resource(&name)).transpose()?;
+        // privileges no rule names are decided like the empty one, which no rule names either
+        let privilege = privilege.map(|name| self.names.get(name.as_str()).copied().unwrap_or(""));
+
+        Ok(self.acl.decide(role, resource, privilege))
+    } // decide
+
+} // impl Acl
@@ -0,0 +1,244 @@
+//! Python bindings of zorq-acl.
+//!
+//! The module `zorq_acl` exposes the core API to Python scripts and services, so they share the
+//! policy engine and the policy files of Rust services. `None` is the wildcard and errors are
+//! raised as `zorq_acl.AclError`:
+//!
+//! ```python
+//! from zorq_acl import Acl
+//!
+//! acl = Acl.load_json("policy.json")
+//!
+//! acl.allow("staff", "latest", "revise")
+//! acl.is_allowed("staff", "latest", "revise")          # True
+//! acl.explain("guest", "latest", "revise").matched     # '*→*: *'
+//! ```
+//!
+//! Names given to `add_role`, `add_resource` and rules are leaked once to obtain the `'static`
+//! lifetime required by the `Acl`. Queries never leak: unknown roles and resources are rejected,
+//! privileges not named by any rule are decided alike.
+
+use acl::Access;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use std::collections::HashSet;
+
+create_exception!(zorq_acl, AclError, PyException, "An error of the access control list.");
+
+fn error(error: acl::Error) -> PyErr {
+    AclError::new_err(error.to_string())
+} // error
+
+/// The decision of a query and the rule which made it.
+#[pyclass(frozen, get_all, module = "zorq_acl")]
+pub struct Explanation {
+    /// "allow" or "deny"
+    decision:    String,
+    /// the query, e.g. "staff→latest: revise"
+    query:       String,
+    /// the query of the deciding rule, e.g. "guest→*: *"
+    matched:     String,
+    /// true if decided by a bypass role
+    bypass:      bool,
+    /// the description of the deciding rule
+    description: Option<String>,
+    /// the message of the deciding deny rule
+    message:     Option<String>,
+} // struct Explanation
+
+#[pymethods]
+impl Explanation {
+
+    fn __repr__(&self) -> String {
+        format!("<Explanation {} {} by {}>", self.decision, self.query, self.matched)
+    } // __repr__
+
+} // impl Explanation
+
+/// An access control list.
+#[pyclass(unsendable, module = "zorq_acl")]
+pub struct Acl {
+    acl:   acl::Acl,
+    names: HashSet<&'static str>,
+} // struct Acl
+
+#[pymethods]
+impl Acl {
+
+    /// Creates an empty `Acl` denying everything.
+    #[new]
+    fn new() -> Self {
+        Acl{acl: acl::Acl::new(), names: HashSet::new()}
+    } // new
+
+    /// Creates an `Acl` from a JSON policy document.
+    #[staticmethod]
+    fn from_json(source: &str) -> PyResult<Self> {
+        acl::Acl::from_json(source).map(Self::wrap).map_err(error)
+    } // from_json
+
+    /// Creates an `Acl` from a JSON policy file.
+    #[staticmethod]
+    fn load_json(path: &str) -> PyResult<Self> {
+        acl::Acl::load_json(path).map(Self::wrap).map_err(error)
+    } // load_json
+
+    /// Exports the `Acl` as JSON policy document.
+    fn to_json(&self) -> String {
+        self.acl.to_json()
+    } // to_json
+
+    /// Adds a role inheriting from parents.
+    #[pyo3(signature = (name, parents=vec![]))]
+    fn add_role(&mut self, name: &str, parents: Vec<String>) -> PyResult<()> {
+        let name    = self.intern(name);
+        let parents = parents.iter().map(|parent| self.intern(parent)).collect();
+
+        self.acl.add_role(name, parents).map_err(error)
+    } // add_role
+
+    /// Adds a resource below parent.
+    #[pyo3(signature = (name, parent=None))]
+    fn add_resource(&mut self, name: &str, parent: Option<&str>) -> PyResult<()> {
+        let name   = self.intern(name);
+        let parent = parent.map(|parent| self.intern(parent));
+
+        self.acl.add_resource(name, parent).map_err(error)
+    } // add_resource
+
+    /// Allows privilege on resource for role.
+    #[pyo3(signature = (role=None, resource=None, privilege=None))]
+    fn allow(&mut self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> PyResult<()> {
+        self.set_rule(role, resource, privilege, Access::Allow)
+    } // allow
+
+    /// Denies privilege on resource for role.
+    #[pyo3(signature = (role=None, resource=None, privilege=None))]
+    fn deny(&mut self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> PyResult<()> {
+        self.set_rule(role, resource, privilege, Access::Deny)
+    } // deny
+
+    /// Returns True if privilege on resource is allowed for role.
+    #[pyo3(signature = (role=None, resource=None, privilege=None))]
+    fn is_allowed(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> PyResult<bool> {
+        self.decide(role, resource, privilege).map(|decision| decision.is_allowed())
+    } // is_allowed
+
+    /// Returns True if privilege on resource is denied for role.
+    #[pyo3(signature = (role=None, resource=None, privilege=None))]
+    fn is_denied(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> PyResult<bool> {
+        self.decide(role, resource, privilege).map(|decision| decision.is_denied())
+    } // is_denied
+
+    /// Decides the query and returns the rule which made the decision.
+    #[pyo3(signature = (role=None, resource=None, privilege=None))]
+    fn explain(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> PyResult<Explanation> {
+        let decision = self.decide(role, resource, privilege)?;
+        let query    = match privilege {
+            Some(name) => format!("{}→{}: {}", decision.query.role.unwrap_or("*"), decision.query.resource.unwrap_or("*"), name),
+            None       => decision.query.to_string(),
+        }; // match
+
+        Ok(Explanation{
+            decision:    decision.rule.access().to_string().to_lowercase(),
+            query,
+            matched:     decision.matched.to_string(),
+            bypass:      decision.bypass,
+            description: self.acl.get_decision_meta(&decision).and_then(|meta| meta.description.clone()),
+            message:     self.acl.get_denial_message(&decision).map(String::from),
+        }) // Explanation
+    } // explain
+
+    /// Returns the role and its ancestors in search order.
+    fn role_lineage(&self, name: &str) -> PyResult<Vec<&'static str>> {
+        Ok(self.acl.get_role_lineage(self.role(name)?))
+    } // role_lineage
+
+    /// Returns the resource and its ancestors.
+    fn resource_lineage(&self, name: &str) -> PyResult<Vec<&'static str>> {
+        Ok(self.acl.get_resource_lineage(self.resource(name)?))
+    } // resource_lineage
+
+    /// Returns the roles.
+    fn roles(&self) -> Vec<&'static str> {
+        self.acl.roles().collect()
+    } // roles
+
+    /// Returns the resources.
+    fn resources(&self) -> Vec<&'static str> {
+        self.acl.resources().collect()
+    } // resources
+
+    /// Locks the `Acl` to cache decisions, rules can't be changed until unlocked.
+    fn lock(&mut self) {
+        self.acl.lock();
+    } // lock
+
+    /// Unlocks the `Acl` and purges the cache.
+    fn unlock(&mut self) {
+        self.acl.unlock();
+    } // unlock
+
+    fn __repr__(&self) -> String {
+        format!("<Acl {} roles, {} resources, {} rules>", self.acl.roles().count(), self.acl.resources().count(), self.acl.rules().count())
+    } // __repr__
+
+} // impl Acl
+
+impl Acl {
+
+    /// Wraps acl and collects its names, so they aren't leaked again.
+    fn wrap(acl: acl::Acl) -> Self {
+        let mut names: HashSet<&'static str> = acl.roles().chain(acl.resources()).chain(acl.privileges()).collect();
+
+        names.extend(acl.rules().filter_map(|(query, _, _)| query.privilege));
+        Acl{acl, names}
+    } // wrap
+
+    /// Returns name borrowed for the `'static` lifetime, leaking it at most once.
+    fn intern(&mut self, name: &str) -> &'static str {
+        if let Some(known) = self.names.get(name) {
+            return known;
+        } // if
+        let name: &'static str = Box::leak(String::from(name).into_boxed_str());
+
+        self.names.insert(name);
+        name
+    } // intern
+
+    fn role(&self, name: &str) -> PyResult<&'static str> {
+        self.acl.roles().find(|role| *role == name).ok_or_else(|| error(acl::Error::MissingRole(String::from(name))))
+    } // role
+
+    fn resource(&self, name: &str) -> PyResult<&'static str> {
+        self.acl.resources().find(|resource| *resource == name).ok_or_else(|| error(acl::Error::MissingResource(String::from(name))))
+    } // resource
+
+    fn set_rule(&mut self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>, access: Access) -> PyResult<()> {
+        let role      = role.map(|name| self.intern(name));
+        let resource  = resource.map(|name| self.intern(name));
+        let privilege = privilege.map(|name| self.intern(name));
+
+        self.acl.set_rule(role, resource, privilege, access).map_err(error)
+    } // set_rule
+
+    fn decide(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> PyResult<acl::Decision> {
+        let role      = role.map(|name| self.role(name)).transpose()?;
+        let resource  = resource.map(|name| self.resource(name)).transpose()?;
+        // privileges no rule names are decided like the empty one, which no rule names either
+        let privilege = privilege.map(|name| self.names.get(name).copied().unwrap_or(""));
+
+        Ok(self.acl.decide(role, resource, privilege))
+    } // decide
+
+} // impl Acl
+
+/// Access control lists of zorq-acl.
+#[pymodule]
+fn zorq_acl(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Acl>()?;
+    m.add_class::<Explanation>()?;
+    m.add("AclError", m.py().get_type::<AclError>())?;
+    Ok(())
+} // zorq_acl
@@ -0,0 +1,308 @@
+//! Derive macros for zorq-acl. Use them through the `derive` feature of zorq-acl, which
+//! re-exports them in module `zorq_acl::domain`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, FnArg, Ident, Index, ItemFn, LitStr, Pat};
+
+
+// Attributes /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The id given by an `#[acl(...)]` attribute.
+enum Id {
+    /// `#[acl(id)]` on a field
+    Field,
+    /// `#[acl(id = "...")]` on the type
+    Fixed(LitStr),
+} // enum Id
+
+fn parse_id(attrs: &[Attribute]) -> Result<Option<Id>, Error> {
+    let mut id = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("acl")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("id") {
+                return Err(meta.error("unknown acl attribute, expected `id`"));
+            } // if
+            if meta.input.peek(syn::Token![=]) {
+                id = Some(Id::Fixed(meta.value()?.parse()?));
+            } else {
+                id = Some(Id::Field);
+            } // else
+            Ok(())
+        })?;
+    } // for
+    Ok(id)
+} // parse_id
+
+/// The `#[acl(...)]` attributes of a privilege variant.
+#[derive(Default)]
+struct Variant {
+    /// `id = "..."`
+    id:          Option<LitStr>,
+    /// `label = "..."`
+    label:       Option<LitStr>,
+    /// `description = "..."`
+    description: Option<LitStr>,
+} // struct Variant
+
+fn parse_variant(attrs: &[Attribute]) -> Result<Variant, Error> {
+    let mut variant = Variant::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("acl")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                if !meta.input.peek(syn::Token![=]) {
+                    return Err(meta.error("expected `#[acl(id = \"...\")]` on the variant"));
+                } // if
+                variant.id = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("label") {
+                variant.label = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("description") {
+                variant.description = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unknown acl attribute, expected `id`, `label` or `description`"));
+            } // else
+            Ok(())
+        })?;
+    } // for
+    Ok(variant)
+} // parse_variant
+
+/// Quotes an optional string literal as `Option<&'static str>`.
+fn quote_option(lit: Option<LitStr>) -> TokenStream2 {
+    match lit {
+        Some(lit) => quote!(::core::option::Option::Some(#lit)),
+        None      => quote!(::core::option::Option::None),
+    } // match
+} // quote_option
+
+
+// Derive /////////////////////////////////////////////////////////////////////////////////////////
+
+
+fn expand(input: DeriveInput, trait_path: TokenStream2, method: TokenStream2) -> Result<TokenStream2, Error> {
+    let body = match parse_id(&input.attrs)? {
+        Some(Id::Fixed(id)) => quote!(#id),
+        Some(Id::Field)     => return Err(Error::new_spanned(&input.ident,
+            "`#[acl(id)]` belongs on a field, use `#[acl(id = \"...\")]` on the type")),
+        None                => {
+            let fields = match &input.data {
+                Data::Struct(data) => &data.fields,
+                _                  => return Err(Error::new_spanned(&input.ident,
+                    "expected `#[acl(id = \"...\")]` on the type")),
+            }; // match
+            let mut found = None;
+
+            for (i, field) in fields.iter().enumerate() {
+                match parse_id(&field.attrs)? {
+                    Some(Id::Field)     => {
+                        if found.is_some() {
+                            return Err(Error::new_spanned(field, "duplicate `#[acl(id)]` field"));
+                        } // if
+                        found = Some(match (&field.ident, fields) {
+                            (Some(ident), _)        => quote!(#ident),
+                            (None, Fields::Unnamed(_)) => {
+                                let index = Index::from(i);
+
+                                quote!(#index)
+                            }, // None
+                            (None, _)               => unreachable!(),
+                        }); // match
+                    }, // Some
+                    Some(Id::Fixed(id)) => return Err(Error::new_spanned(id,
+                        "`#[acl(id = \"...\")]` belongs on the type, use `#[acl(id)]` on a field")),
+                    None                => (),
+                } // match
+            } // for
+            match found {
+                Some(member) => quote!(::core::convert::AsRef::<str>::as_ref(&self.#member)),
+                None         => return Err(Error::new_spanned(&input.ident,
+                    "expected `#[acl(id)]` on a field or `#[acl(id = \"...\")]` on the type")),
+            } // match
+        }, // None
+    }; // match
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #trait_path for #ident #ty_generics #where_clause {
+            fn #method(&self) -> &str {
+                #body
+            }
+        }
+    })
+} // expand
+
+/// Converts a variant name to snake case, e.g. `PublishDraft` to `publish_draft`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            } // if
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        } // else
+    } // for
+    snake
+} // snake_case
+
+fn expand_privilege(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _                => return Err(Error::new_spanned(&input.ident, "expected an enum of privileges")),
+    }; // match
+    let ident            = &input.ident;
+    let mut members      = vec![];
+    let mut ids          = vec![];
+    let mut labels       = vec![];
+    let mut descriptions = vec![];
+
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(variant, "expected a unit variant"));
+        } // if
+        let attrs = parse_variant(&variant.attrs)?;
+
+        members.push(variant.ident.clone());
+        ids.push(attrs.id.unwrap_or_else(|| LitStr::new(&snake_case(&variant.ident.to_string()), variant.ident.span())));
+        labels.push(quote_option(attrs.label));
+        descriptions.push(quote_option(attrs.description));
+    } // for
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::zorq_acl::domain::AclPrivilege for #ident #ty_generics #where_clause {
+            const ALL: &'static [Self] = &[#(#ident::#members),*];
+
+            fn privilege_id(&self) -> &'static str {
+                match self {
+                    #(#ident::#members => #ids,)*
+                }
+            }
+
+            fn label(&self) -> ::core::option::Option<&'static str> {
+                match self {
+                    #(#ident::#members => #labels,)*
+                }
+            }
+
+            fn description(&self) -> ::core::option::Option<&'static str> {
+                match self {
+                    #(#ident::#members => #descriptions,)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#ident #ty_generics> for ::core::option::Option<&'static str> #where_clause {
+            fn from(privilege: #ident #ty_generics) -> Self {
+                ::core::option::Option::Some(::zorq_acl::domain::AclPrivilege::privilege_id(&privilege))
+            }
+        }
+    })
+} // expand_privilege
+
+/// Derives `zorq_acl::domain::AclRole`. The role id is taken from the field annotated with
+/// `#[acl(id)]` or fixed by `#[acl(id = "...")]` on the type.
+#[proc_macro_derive(AclRole, attributes(acl))]
+pub fn derive_acl_role(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input, quote!(::zorq_acl::domain::AclRole), quote!(role_id))
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+} // derive_acl_role
+
+/// Derives `zorq_acl::domain::AclResource`. The resource id is taken from the field annotated
+/// with `#[acl(id)]` or fixed by `#[acl(id = "...")]` on the type.
+#[proc_macro_derive(AclResource, attributes(acl))]
+pub fn derive_acl_resource(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input, quote!(::zorq_acl::domain::AclResource), quote!(resource_id))
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+} // derive_acl_resource
+
+/// Derives `zorq_acl::domain::AclPrivilege` for an enum of unit variants and converts it into a
+/// privilege. Each variant is named by its snake case name or by `#[acl(id = "...")]` and may be
+/// described by `#[acl(label = "...", description = "...")]`.
+#[proc_macro_derive(AclPrivilege, attributes(acl))]
+pub fn derive_acl_privilege(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_privilege(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+} // derive_acl_privilege
+
+
+// Attribute //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Guards a function returning a `Result` whose error implements `From<AccessDenied>`. Before the
+/// body runs, `AclContext::require` is called on the context parameter, named `ctx` unless given
+/// by `context = "..."`. Without `resource` the privilege is required on all resources.
+///
+/// `#[require_privilege(resource = "newsletter", privilege = "publish", context = "request")]`
+#[proc_macro_attribute]
+pub fn require_privilege(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut resource: Option<LitStr> = None;
+    let mut privilege: Option<LitStr> = None;
+    let mut context: Option<LitStr> = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("resource") {
+            resource = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("privilege") {
+            privilege = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("context") {
+            context = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("unknown argument, expected `resource`, `privilege` or `context`"));
+        } // else
+        Ok(())
+    });
+
+    parse_macro_input!(args with parser);
+    let function = parse_macro_input!(item as ItemFn);
+
+    expand_require(function, resource, privilege, context)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+} // require_privilege
+
+fn expand_require(mut function: ItemFn, resource: Option<LitStr>, privilege: Option<LitStr>, context: Option<LitStr>) -> Result<TokenStream2, Error> {
+    let privilege = privilege.ok_or_else(|| Error::new_spanned(&function.sig.ident, "missing argument `privilege`"))?;
+    let context   = match context {
+        Some(context) => context.parse::<Ident>()?,
+        None          => Ident::new("ctx", function.sig.ident.span()),
+    }; // match
+    let known     = function.sig.inputs.iter().any(|input| match input {
+        FnArg::Typed(typed) => matches!(&*typed.pat, Pat::Ident(pat) if pat.ident == context),
+        FnArg::Receiver(_)  => false,
+    }); // any
+
+    if !known {
+        return Err(Error::new_spanned(&function.sig, format!("missing context parameter `{}`", context)));
+    } // if
+    let resource = match resource {
+        Some(resource) => quote!(::core::option::Option::Some(#resource)),
+        None           => quote!(::core::option::Option::None),
+    }; // match
+
+    function.block.stmts.insert(0, parse_quote! {
+        {
+            use ::zorq_acl::domain::AclContext as _;
+            #context.require(#resource, ::core::option::Option::Some(#privilege))?;
+        }
+    });
+    Ok(quote!(#function))
+} // expand_require
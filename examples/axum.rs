@@ -0,0 +1,186 @@
+//! A web service authorizing requests by JSON web tokens.
+//!
+//! Clients send a token of their identity provider as `Authorization: Bearer` header. The
+//! middleware `login` decodes it, maps the groups it claims to roles of the `Acl` and stores them
+//! in the session, where the extractor `session::Authorized<T>` of each handler reads them.
+//! Groups without a role are ignored, so tokens never add names to the `Acl`. Requests without a
+//! token keep the roles of their session, requests without roles are decided for the default
+//! role.
+//!
+//! Run with `cargo run --example axum --features axum`, then query it with the token printed at
+//! startup: `curl -X PUT -H "Authorization: Bearer <token>" localhost:3000/news`.
+
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use http::{header, HeaderMap, StatusCode};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tower_sessions::{MemoryStore, Session, SessionManagerLayer};
+use zorq_acl::session::{insert_session_roles, Authorized, Grants, Permission};
+use zorq_acl::*;
+
+/// The key signing tokens, shared with the identity provider.
+const SECRET: &[u8] = b"zorq-acl example secret";
+
+/// The roles of the groups claimed by tokens.
+const GROUP_ROLES: [(&str, &str); 3] = [("readers", "guest"), ("newsroom", "staff"), ("it", "admin")];
+
+/// The claims of a token.
+#[derive(Debug, Deserialize, Serialize)]
+struct Claims {
+    sub:    String,
+    groups: Vec<String>,
+    exp:    u64,
+} // struct Claims
+
+struct ViewNews;
+
+impl Permission for ViewNews {
+    const RESOURCE:  Option<&'static str> = Some("news");
+    const PRIVILEGE: Option<&'static str> = Some("view");
+} // impl Permission for ViewNews
+
+struct EditNews;
+
+impl Permission for EditNews {
+    const RESOURCE:  Option<&'static str> = Some("news");
+    const PRIVILEGE: Option<&'static str> = Some("edit");
+} // impl Permission for EditNews
+
+/// Defines the roles, resources and rules of the service.
+fn setup() -> Result<Acl, Error> {
+    let mut acl = Acl::new();
+
+    acl.add_role("guest", vec![])?;
+    acl.add_role("staff", vec!["guest"])?;
+    acl.add_role("admin", vec!["staff"])?;
+    acl.add_resource("news", None)?;
+
+    acl.allow(Some("guest"), Some("news"), Some("view"))?;
+    acl.allow(Some("staff"), Some("news"), Some("edit"))?;
+    acl.allow(Some("admin"), None, None)?;
+    acl.set_default_role("guest")?;
+    Ok(acl)
+} // setup
+
+/// Returns the roles of the groups claimed.
+fn roles_of(claims: &Claims) -> Vec<&'static str> {
+    GROUP_ROLES.iter()
+        .filter(|(group, _)| claims.groups.iter().any(|claimed| claimed == group))
+        .map(|(_, role)| *role)
+        .collect()
+} // roles_of
+
+/// Decodes the bearer token of headers, None without one.
+fn decode_claims(headers: &HeaderMap) -> Result<Option<Claims>, StatusCode> {
+    let token = match headers.get(header::AUTHORIZATION) {
+        Some(value) => value.to_str().ok().and_then(|value| value.strip_prefix("Bearer ")).ok_or(StatusCode::UNAUTHORIZED)?,
+        None        => return Ok(None),
+    }; // match
+
+    jsonwebtoken::decode::<Claims>(token, &DecodingKey::from_secret(SECRET), &Validation::new(Algorithm::HS256))
+        .map(|data| Some(data.claims))
+        .map_err(|_| StatusCode::UNAUTHORIZED)
+} // decode_claims
+
+/// Returns a token claiming groups for a day, as issued by the identity provider.
+fn issue_token(sub: &str, groups: &[&str]) -> String {
+    let claims = Claims{
+        sub:    String::from(sub),
+        groups: groups.iter().map(|group| String::from(*group)).collect(),
+        exp:    jsonwebtoken::get_current_timestamp() + 24 * 60 * 60,
+    }; // Claims
+
+    jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET)).expect("encodable claims")
+} // issue_token
+
+/// Stores the roles of the token of request in the session, rejecting invalid tokens with 401.
+async fn login(session: Session, request: Request, next: Next) -> Result<Response, StatusCode> {
+    if let Some(claims) = decode_claims(request.headers())? {
+        log::trace!("logging in {} with groups {:?}", claims.sub, claims.groups);
+        insert_session_roles(&session, &roles_of(&claims)).await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    } // if
+    Ok(next.run(request).await)
+} // login
+
+async fn view(authorized: Authorized<ViewNews>) -> String {
+    format!("news for {:?}", authorized.roles())
+} // view
+
+async fn edit(authorized: Authorized<EditNews>) -> String {
+    format!("news edited by {:?}", authorized.roles())
+} // edit
+
+/// Returns the routes of the service deciding by grants.
+fn app(grants: Grants) -> Router {
+    Router::new()
+        .route("/news", get(view).put(edit))
+        .layer(middleware::from_fn(login))
+        .layer(SessionManagerLayer::new(MemoryStore::default()).with_secure(false))
+        .with_state(Arc::new(grants))
+} // app
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let acl      = setup().map_err(|e| e.to_string())?;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+
+    println!("token of the newsroom: {}", issue_token("alice", &["newsroom"]));
+    axum::serve(listener, app(acl.grants())).await?;
+    Ok(())
+} // main
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    /// Returns the status of a request to the service, with a bearer token if given.
+    async fn status(method: &str, token: Option<&str>) -> StatusCode {
+        let mut request = Request::builder().method(method).uri("/news");
+
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        } // if
+        let app = app(setup().unwrap().grants());
+
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    } // status
+
+    #[test]
+    fn mapping() {
+        let claims = Claims{sub: String::from("bob"), groups: vec![String::from("it"), String::from("sales")], exp: 0};
+
+        assert_eq!(roles_of(&claims), vec!["admin"]);
+    } // mapping
+
+    #[tokio::test]
+    async fn service() {
+        let newsroom = issue_token("alice", &["newsroom"]);
+        let readers  = issue_token("bob", &["readers", "sales"]);
+        let forged   = jsonwebtoken::encode(&Header::default(), &Claims{sub: String::from("eve"), groups: vec![String::from("it")], exp: u64::MAX},
+            &EncodingKey::from_secret(b"guessed")).unwrap();
+
+        assert_eq!(status("GET", None).await, StatusCode::OK);
+        assert_eq!(status("PUT", None).await, StatusCode::FORBIDDEN);
+        assert_eq!(status("PUT", Some(&newsroom)).await, StatusCode::OK);
+        assert_eq!(status("PUT", Some(&readers)).await, StatusCode::FORBIDDEN);
+        assert_eq!(status("GET", Some(&readers)).await, StatusCode::OK);
+        assert_eq!(status("GET", Some(&forged)).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status("GET", Some("garbage")).await, StatusCode::UNAUTHORIZED);
+    } // service
+
+} // mod tests
@@ -0,0 +1,100 @@
+//! A content management system with per-object ownership.
+//!
+//! Articles are resources below their section. Each article records its owner and status as
+//! resource metadata, which assertions consult at query time: authors edit their own articles,
+//! editors edit all of them and guests only view published ones.
+//!
+//! Run with `cargo run --example cms`.
+
+use zorq_acl::*;
+
+/// Defines the roles, sections and rules of the CMS.
+fn setup() -> Result<Acl, Error> {
+    let mut acl = Acl::new();
+
+    acl.add_role("guest", vec![])?;
+    acl.add_role("author", vec!["guest"])?;
+    acl.add_role("editor", vec!["author"])?;
+    acl.add_role("alice", vec!["author"])?;
+    acl.add_role("bob", vec!["author"])?;
+    acl.add_role("carol", vec!["editor"])?;
+
+    acl.add_resource("articles", None)?;
+    acl.add_resource("news", Some("articles"))?;
+    acl.add_resource("reviews", Some("articles"))?;
+
+    // the owner of the queried article is the queried role
+    acl.add_assertion("owner", |acl: &Acl, query: &Query| match (query.role, query.resource) {
+        (Some(role), Some(resource)) => acl.get_resource_meta(resource, "owner") == Some(role),
+        _                            => false,
+    });
+    acl.add_assertion("published", |acl: &Acl, query: &Query| {
+        query.resource.and_then(|resource| acl.get_resource_meta(resource, "status")) == Some("published")
+    });
+
+    acl.allow_if(Some("guest"), Some("articles"), Some("view"), "published")?;
+    acl.allow_if(Some("author"), Some("articles"), Some("view"), "owner")?;
+    acl.allow_if(Some("author"), Some("articles"), Some("edit"), "owner")?;
+    acl.allow(Some("editor"), Some("articles"), None)?;
+    Ok(acl)
+} // setup
+
+/// Adds an article owned by owner to section.
+fn publish(acl: &mut Acl, article: &'static str, section: &'static str, owner: &str, status: &str) -> Result<(), Error> {
+    acl.add_resource(article, Some(section))?;
+    acl.set_resource_kind(article, Some("article"))?;
+    acl.set_resource_meta(article, "owner", Some(owner))?;
+    acl.set_resource_meta(article, "status", Some(status))
+} // publish
+
+fn main() -> Result<(), Error> {
+    env_logger::init();
+
+    let mut acl = setup()?;
+
+    publish(&mut acl, "rust-2024", "news", "alice", "published")?;
+    publish(&mut acl, "draft-review", "reviews", "bob", "draft")?;
+
+    for role in ["guest", "alice", "bob", "carol"] {
+        for article in acl.resources_of_kind("article").collect::<Vec<_>>() {
+            for privilege in ["view", "edit"] {
+                println!("{}", acl.decide(Some(role), Some(article), Some(privilege)));
+            } // for
+        } // for
+    } // for
+
+    // ownership moves with the metadata, no rule changes
+    acl.set_resource_meta("draft-review", "owner", Some("alice"))?;
+    println!("{}", acl.decide(Some("alice"), Some("draft-review"), Some("edit")));
+    Ok(())
+} // main
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn ownership() {
+        let mut acl = setup().unwrap();
+
+        assert!(publish(&mut acl, "rust-2024", "news", "alice", "published").is_ok());
+        assert!(publish(&mut acl, "draft-review", "reviews", "bob", "draft").is_ok());
+
+        assert!(acl.is_allowed(Some("guest"), Some("rust-2024"), Some("view")));
+        assert!(acl.is_denied(Some("guest"), Some("draft-review"), Some("view")));
+        assert!(acl.is_allowed(Some("bob"), Some("draft-review"), Some("edit")));
+        assert!(acl.is_denied(Some("alice"), Some("draft-review"), Some("edit")));
+        assert!(acl.is_allowed(Some("alice"), Some("rust-2024"), Some("edit")));
+        assert!(acl.is_allowed(Some("carol"), Some("draft-review"), Some("edit")));
+
+        assert!(acl.set_resource_meta("draft-review", "owner", Some("alice")).is_ok());
+        assert!(acl.is_allowed(Some("alice"), Some("draft-review"), Some("edit")));
+        assert!(acl.is_denied(Some("bob"), Some("draft-review"), Some("edit")));
+    } // ownership
+
+} // mod tests
@@ -0,0 +1,191 @@
+//! Interactive shell for policy documents.
+//!
+//! `cargo run --example repl --features json -- policy.json` loads the policy, if given, and reads
+//! commands from stdin. `*` is the wildcard, `help` lists all commands:
+//!
+//! ```text
+//! acl> role staff guest
+//! acl> allow staff latest revise
+//! acl> check staff latest revise
+//! ALLOW staff→latest: revise
+//! acl> save
+//! ```
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use zorq_acl::*;
+
+const HELP: &str = "\
+load <file>                               load a JSON policy document
+save [<file>]                             save the policy to file or the loaded file
+role <name> [<parent>...]                 add a role
+resource <name> [<parent>]                add a resource
+allow <role> <resource> <privilege>       add an allow rule
+deny <role> <resource> <privilege>        add a deny rule
+check <role> <resource> <privilege>       decide a query
+explain <role> <resource> <privilege>     decide a query and show the deciding rule
+lineage <role or resource>                show the ancestors in search order
+roles | resources | rules                 list the policy
+report                                    print the markdown permission report
+help | quit";
+
+struct Repl {
+    acl:   Acl,
+    path:  Option<String>,
+    names: HashSet<&'static str>,
+} // struct Repl
+
+impl Repl {
+
+    /// Returns name borrowed for the `'static` lifetime required by the `Acl`. Each name is leaked
+    /// at most once.
+    fn intern(&mut self, name: &str) -> &'static str {
+        if let Some(known) = self.names.get(name) {
+            return known;
+        } // if
+        let name: &'static str = Box::leak(String::from(name).into_boxed_str());
+
+        self.names.insert(name);
+        name
+    } // intern
+
+    /// Returns None for the wildcard.
+    fn wildcard(&mut self, name: &str) -> Option<&'static str> {
+        match name {
+            "*"  => None,
+            name => Some(self.intern(name)),
+        } // match
+    } // wildcard
+
+    fn query(&mut self, args: &[&str]) -> Result<Query, String> {
+        match args {
+            [role, resource, privilege] => Ok(Query{role: self.wildcard(role), resource: self.wildcard(resource), privilege: self.wildcard(privilege)}),
+            _                           => Err(String::from("expected <role> <resource> <privilege>")),
+        } // match
+    } // query
+
+    fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, args)  = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None                  => return Ok(String::new()),
+        }; // match
+
+        match command {
+            "load"      => {
+                let path = args.first().ok_or("expected <file>")?;
+
+                self.acl  = Acl::load_json(path).map_err(|e| e.to_string())?;
+                self.path = Some(path.to_string());
+                Ok(format!("loaded {} roles, {} resources and {} rules", self.acl.roles().count(), self.acl.resources().count(), self.acl.rules().count()))
+            }, // load
+            "save"      => {
+                let path = args.first().map(|path| path.to_string()).or_else(|| self.path.clone()).ok_or("expected <file>")?;
+
+                std::fs::write(&path, self.acl.to_json()).map_err(|e| e.to_string())?;
+                self.path = Some(path.clone());
+                Ok(format!("saved {}", path))
+            }, // save
+            "role"      => {
+                let name    = self.intern(args.first().ok_or("expected <name>")?);
+                let parents = args[1..].iter().map(|parent| self.intern(parent)).collect();
+
+                self.acl.add_role(name, parents).map(|_| String::new()).map_err(|e| e.to_string())
+            }, // role
+            "resource"  => {
+                let name   = self.intern(args.first().ok_or("expected <name>")?);
+                let parent = args.get(1).map(|parent| self.intern(parent));
+
+                self.acl.add_resource(name, parent).map(|_| String::new()).map_err(|e| e.to_string())
+            }, // resource
+            "allow" | "deny" => {
+                let Query{role, resource, privilege} = self.query(args)?;
+                let access = if command == "allow" { Access::Allow } else { Access::Deny };
+
+                self.acl.set_rule(role, resource, privilege, access).map(|_| String::new()).map_err(|e| e.to_string())
+            }, // allow | deny
+            "check"     => {
+                let Query{role, resource, privilege} = self.query(args)?;
+                let decision = self.acl.decide(role, resource, privilege);
+
+                Ok(format!("{} {}", decision.rule, decision.query))
+            }, // check
+            "explain"   => {
+                let Query{role, resource, privilege} = self.query(args)?;
+                let decision = self.acl.decide(role, resource, privilege);
+                let mut text = format!("{} {}\n", decision.rule, decision.query);
+
+                if decision.bypass {
+                    text.push_str("decided by the bypass role");
+                } else {
+                    text.push_str(&format!("decided by {} {}", decision.rule, decision.matched));
+                    if let Some(description) = self.acl.get_decision_meta(&decision).and_then(|meta| meta.description.as_ref()) {
+                        text.push_str(&format!(": {}", description));
+                    } // if
+                } // else
+                if let Some(role) = role {
+                    text.push_str(&format!("\nroles searched: {}", self.acl.get_role_lineage(role).join(" → ")));
+                } // if
+                if let Some(resource) = resource {
+                    text.push_str(&format!("\nresources searched: {}", self.acl.get_resource_lineage(resource).join(" → ")));
+                } // if
+                Ok(text)
+            }, // explain
+            "lineage"   => {
+                let name = self.intern(args.first().ok_or("expected <role or resource>")?);
+
+                if self.acl.has_role(name) {
+                    Ok(self.acl.get_role_lineage(name).join(" → "))
+                } else if self.acl.has_resource(name) {
+                    Ok(self.acl.get_resource_lineage(name).join(" → "))
+                } else {
+                    Err(format!("unknown role or resource: {}", name))
+                } // else
+            }, // lineage
+            "roles"     => Ok(self.acl.roles().collect::<Vec<_>>().join("\n")),
+            "resources" => Ok(self.acl.resources().collect::<Vec<_>>().join("\n")),
+            "rules"     => {
+                let mut rules: Vec<String> = self.acl.rules().map(|(query, rule, _)| format!("{} {}", rule, query)).collect();
+
+                rules.sort();
+                Ok(rules.join("\n"))
+            }, // rules
+            "report"    => Ok(self.acl.to_markdown_report()),
+            "help"      => Ok(String::from(HELP)),
+            _           => Err(format!("unknown command: {}, try help", command)),
+        } // match
+    } // execute
+
+} // impl Repl
+
+fn main() -> io::Result<()> {
+    env_logger::init();
+
+    let mut repl = Repl{acl: Acl::new(), path: None, names: HashSet::new()};
+
+    if let Some(path) = std::env::args().nth(1) {
+        match repl.execute(&format!("load {}", path)) {
+            Ok(message) => println!("{}", message),
+            Err(error)  => eprintln!("error: {}", error),
+        } // match
+    } // if
+
+    let stdin = io::stdin();
+
+    loop {
+        print!("acl> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+
+        if stdin.lock().read_line(&mut line)? == 0 || line.trim() == "quit" {
+            break;
+        } // if
+        match repl.execute(&line) {
+            Ok(output) if output.is_empty() => (),
+            Ok(output)                      => println!("{}", output),
+            Err(error)                      => eprintln!("error: {}", error),
+        } // match
+    } // loop
+    Ok(())
+} // main
@@ -0,0 +1,115 @@
+//! A software-as-a-service policy shared by tenants.
+//!
+//! Each tenant owns a resource subtree, built at runtime from the tenant records. Plans are
+//! privilege bundles, so upgrading a plan changes what every tenant on it may do. Tenant admins
+//! manage their own subtree through a scoped view, and support staff is granted temporary access
+//! to a tenant by a session overlay, e.g. after the tenant approved a support ticket.
+//!
+//! Run with `cargo run --example saas`.
+
+use zorq_acl::authorizer::Authorizer;
+use zorq_acl::overlay::SessionOverlay;
+use zorq_acl::*;
+
+/// A tenant record as stored by the service.
+struct Tenant {
+    name:  String,
+    admin: String,
+    pro:   bool,
+} // struct Tenant
+
+/// Leaks name to obtain the `'static` lifetime required by the `Acl`.
+fn leak(name: &str) -> &'static str {
+    Box::leak(String::from(name).into_boxed_str())
+} // leak
+
+/// Builds the policy of the tenants.
+fn setup(tenants: &[Tenant]) -> Result<Acl, Error> {
+    let mut acl = Acl::new();
+
+    acl.add_role("member", vec![])?;
+    acl.add_role("support", vec![])?;
+    acl.add_resource("tenants", None)?;
+    acl.define_bundle("basic", &["view", "comment"])?;
+    acl.define_bundle("pro", &["view", "comment", "export"])?;
+
+    for tenant in tenants {
+        let root  = leak(&format!("tenant:{}", tenant.name));
+        let admin = leak(&tenant.admin);
+
+        acl.add_resource(root, Some("tenants"))?;
+        acl.add_resource(leak(&format!("{}/projects", root)), Some(root))?;
+        acl.add_resource(leak(&format!("{}/billing", root)), Some(root))?;
+        acl.add_role(admin, vec!["member"])?;
+        acl.allow(Some(admin), Some(root), Some(if tenant.pro { "pro" } else { "basic" }))?;
+        acl.allow(Some(admin), Some(root), Some("manage"))?;
+    } // for
+    // support sees the tenant list, nothing within
+    acl.allow(Some("support"), Some("tenants"), Some("list"))?;
+    acl.deny(Some("support"), Some("tenants"), Some("view"))?;
+    Ok(acl)
+} // setup
+
+fn tenants() -> Vec<Tenant> {
+    vec![
+        Tenant{name: String::from("acme"), admin: String::from("ada@acme"), pro: true},
+        Tenant{name: String::from("globex"), admin: String::from("gus@globex"), pro: false},
+    ]
+} // tenants
+
+fn main() -> Result<(), Error> {
+    env_logger::init();
+
+    let mut acl = setup(&tenants())?;
+
+    // a tenant admin only sees its own subtree
+    let view = acl.scoped_view("tenant:acme")?;
+
+    println!("{}", view.explain(Some("ada@acme"), Some("tenant:acme/projects"), Some("export")));
+    println!("{}", view.explain(Some("ada@acme"), Some("tenant:globex"), Some("view")));
+
+    // a support engineer gets temporary access to an approved ticket
+    let mut session = SessionOverlay::new(&acl);
+
+    session.allow(Some("support"), Some("tenant:globex"), Some("view"))?;
+    println!("{}", session.decide(Some("support"), Some("tenant:globex/projects"), Some("view")));
+    println!("{}", acl.decide(Some("support"), Some("tenant:globex/projects"), Some("view")));
+
+    // upgrading the basic plan applies to every tenant on it
+    acl.define_bundle("basic", &["view", "comment", "export"])?;
+    println!("{}", acl.decide(Some("gus@globex"), Some("tenant:globex/billing"), Some("export")));
+    Ok(())
+} // main
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn isolation() {
+        let mut acl = setup(&tenants()).unwrap();
+
+        assert!(acl.is_allowed(Some("ada@acme"), Some("tenant:acme/projects"), Some("export")));
+        assert!(acl.is_denied(Some("gus@globex"), Some("tenant:globex/projects"), Some("export")));
+        assert!(acl.is_denied(Some("ada@acme"), Some("tenant:globex"), Some("view")));
+        assert!(acl.scoped_view("tenant:acme").unwrap().decide(None, Some("tenant:globex"), None).is_err());
+
+        {
+            let mut session = SessionOverlay::new(&acl);
+
+            assert!(session.allow(Some("support"), Some("tenant:globex"), Some("view")).is_ok());
+            assert!(session.is_allowed(Some("support"), Some("tenant:globex/billing"), Some("view")));
+            assert!(session.is_denied(Some("support"), Some("tenant:acme"), Some("view")));
+        }
+        assert!(acl.is_denied(Some("support"), Some("tenant:globex/billing"), Some("view")));
+
+        assert!(acl.define_bundle("basic", &["view", "comment", "export"]).is_ok());
+        assert!(acl.is_allowed(Some("gus@globex"), Some("tenant:globex/projects"), Some("export")));
+    } // isolation
+
+} // mod tests
@@ -0,0 +1,13 @@
+//! Interleaves building, locking and querying a policy and checks the invariants of the `Acl`
+//! after each step.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zorq_acl::fuzzing::{run, steps};
+
+fuzz_target!(|data: &[u8]| {
+    if let Err(error) = run(&steps(data)) {
+        panic!("{}", error);
+    } // if
+});
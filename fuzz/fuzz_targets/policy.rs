@@ -0,0 +1,23 @@
+//! Loads arbitrary policy documents, which must either be rejected or yield an `Acl` satisfying
+//! its invariants and surviving an export round trip.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zorq_acl::fuzzing::check_invariants;
+use zorq_acl::Acl;
+
+fuzz_target!(|data: &[u8]| {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_)     => return,
+    }; // match
+
+    if let Ok(acl) = Acl::from_json(source) {
+        check_invariants(&acl).unwrap();
+
+        let exported = acl.to_json();
+
+        assert_eq!(Acl::from_json(&exported).map(|acl| acl.to_json()), Ok(exported));
+    } // if
+});
@@ -0,0 +1,597 @@
+//! Framework agnostic HTTP handlers for runtime policy management.
+//!
+//! `AdminApi` owns an `Acl` and maps requests, given by method, path and body, to responses with
+//! a status code and a JSON body. Embedders mount `AdminApi::handle` into the router of their web
+//! framework, e.g. as a catch-all route below `/admin/acl`, and take care of authentication.
+//!
+//! | Method | Path         | Description                                                      |
+//! |--------|--------------|------------------------------------------------------------------|
+//! | GET    | `/roles`     | lists roles with their parents                                   |
+//! | POST   | `/roles`     | adds a role: `{"name": "staff", "parents": ["guest"]}`           |
+//! | GET    | `/resources` | lists resources with their parent                                |
+//! | POST   | `/resources` | adds a resource: `{"name": "latest", "parent": "news"}`          |
+//! | GET    | `/rules`     | lists rules                                                      |
+//! | POST   | `/rules`     | sets a rule: `{"access": "allow", "role": "staff", ...}`         |
+//! | GET    | `/explain`   | explains a decision: `/explain?role=staff&privilege=view`        |
+//! | GET    | `/changes`   | lists the changes applied through the api, i.e. the diff         |
+//! | POST   | `/rollback`  | reverts the last change applied through the api                  |
+//!
+//! Names received by the api are leaked to obtain the `'static` lifetime required by the `Acl`.
+//!
+//! For HTTP caching embedders send `Acl::etag` as `ETag` header and pass the `If-None-Match`
+//! header to `AdminApi::handle_conditional`, which answers unchanged GET requests with 304.
+
+use crate::policy::intern;
+use crate::{Access, Acl, Error, Query, Rule};
+use log::{trace, warn};
+use serde_json::{json, Map, Value};
+
+
+// Response ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A response of the `AdminApi`. The body is always JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body:   String,
+} // struct Response
+
+impl Response {
+
+    fn json(status: u16, value: Value) -> Self {
+        Response{status, body: value.to_string()}
+    } // json
+
+    fn error(status: u16, message: &str) -> Self {
+        Response::json(status, json!({"error": message}))
+    } // error
+
+} // impl Response
+
+impl From<Error> for Response {
+
+    fn from(error: Error) -> Self {
+        let status = match error {
+            Error::DuplicateRole(_) | Error::DuplicateResource(_) | Error::Locked => 409,
+            Error::MissingRole(_) | Error::MissingParent(_) | Error::MissingResource(_)
+            | Error::MissingPrivilege(_) | Error::MissingRule(_)
+            | Error::MissingChange(_) | Error::MissingGroup(_)                    => 404,
+            Error::NotPermitted(_)                                                => 403,
+            _                                                                     => 400,
+        }; // match
+
+        Response::error(status, &error.to_string())
+    } // from
+
+} // impl From<Error> for Response
+
+
+// Change /////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A change applied through the `AdminApi`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Change {
+    AddRole(&'static str),
+    AddResource(&'static str),
+    SetRule{query: Query, access: Access, previous: Option<Rule>},
+} // enum Change
+
+impl Change {
+
+    fn to_json(self) -> Value {
+        match self {
+            Change::AddRole(name)     => json!({"change": "add_role", "name": name}),
+            Change::AddResource(name) => json!({"change": "add_resource", "name": name}),
+            Change::SetRule{query, access, previous} => json!({
+                "change":    "set_rule",
+                "access":    access_name(access),
+                "role":      query.role,
+                "resource":  query.resource,
+                "privilege": query.privilege,
+                "previous":  previous.map(|rule| access_name(rule.access())),
+            }),
+        } // match
+    } // to_json
+
+} // impl Change
+
+
+// AdminApi ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Handles administration requests for an `Acl`.
+pub struct AdminApi {
+    acl:     Acl,
+    changes: Vec<Change>,
+} // struct AdminApi
+
+impl AdminApi {
+
+    /// Creates a new `AdminApi` managing acl.
+    pub fn new(acl: Acl) -> Self {
+        AdminApi{acl, changes: vec![]}
+    } // new
+
+    /// Returns the managed `Acl`.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Returns the managed `Acl` and drops the change history.
+    pub fn into_inner(self) -> Acl {
+        self.acl
+    } // into_inner
+
+    /// Handles a request. The path may contain a query string.
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> Response {
+        trace!("handling {} {}", method, path);
+        let (path, query) = match path.find('?') {
+            Some(i) => (&path[..i], &path[i + 1..]),
+            None    => (path, ""),
+        }; // match
+
+        match (method, path.trim_end_matches('/')) {
+            ("GET", "/roles")      => self.list_roles(),
+            ("POST", "/roles")     => self.with_body(body, AdminApi::add_role),
+            ("GET", "/resources")  => self.list_resources(),
+            ("POST", "/resources") => self.with_body(body, AdminApi::add_resource),
+            ("GET", "/rules")      => self.list_rules(),
+            ("POST", "/rules")     => self.with_body(body, AdminApi::set_rule),
+            ("GET", "/explain")    => self.explain(query),
+            ("GET", "/changes")    => self.list_changes(),
+            ("POST", "/rollback")  => self.rollback(),
+            (_, "/roles") | (_, "/resources") | (_, "/rules") | (_, "/explain") | (_, "/changes")
+            | (_, "/rollback")     => Response::error(405, "method not allowed"),
+            _                      => Response::error(404, "not found"),
+        } // match
+    } // handle
+
+    /// Like `handle`, but answers a GET request with 304 and an empty body if if_none_match, the
+    /// value of the `If-None-Match` header, contains the etag of the managed `Acl`.
+    pub fn handle_conditional(&mut self, method: &str, path: &str, body: &str, if_none_match: Option<&str>) -> Response {
+        if method == "GET" {
+            let etag = self.acl.etag();
+
+            if if_none_match.is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*")) {
+                trace!("not modified: {}", etag);
+                return Response{status: 304, body: String::new()};
+            } // if
+        } // if
+        self.handle(method, path, body)
+    } // handle_conditional
+
+    fn with_body(&mut self, body: &str, f: fn(&mut Self, &Map<String, Value>) -> Result<Response, Response>) -> Response {
+        match serde_json::from_str::<Value>(body) {
+            Ok(Value::Object(map)) => f(self, &map).unwrap_or_else(|response| response),
+            Ok(_)                  => Response::error(400, "expected an object"),
+            Err(e)                 => Response::error(400, &e.to_string()),
+        } // match
+    } // with_body
+
+    fn list_roles(&self) -> Response {
+        let roles: Vec<Value> = self.acl.roles().map(|name| {
+            let parents = self.acl.get_role_parents(name).unwrap_or_default();
+
+            json!({"name": name, "parents": parents})
+        }).collect();
+
+        Response::json(200, Value::Array(roles))
+    } // list_roles
+
+    fn add_role(&mut self, body: &Map<String, Value>) -> Result<Response, Response> {
+        let name    = required(body, "name")?;
+        let parents = match body.get("parents") {
+            None | Some(Value::Null) => vec![],
+            Some(Value::Array(list)) => list.iter()
+                .map(|p| match p.as_str() {
+                    Some(p) => self.known_role(p).ok_or_else(|| Error::MissingParent(String::from(p)).into()),
+                    None    => Err(Response::error(400, "parents: expected strings")),
+                }) // map
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_)                  => return Err(Response::error(400, "parents: expected an array")),
+        }; // match
+
+        if self.known_role(name).is_some() {
+            return Err(Error::DuplicateRole(String::from(name)).into());
+        } // if
+
+        // names are interned once valid, rejected ones aren't leaked
+        self.acl.check_role_limits(name, &parents)?;
+
+        let name = intern(name);
+
+        self.acl.add_role(name, parents)?;
+        self.changes.push(Change::AddRole(name));
+        Ok(Response::json(201, json!({"name": name})))
+    } // add_role
+
+    fn list_resources(&self) -> Response {
+        let resources: Vec<Value> = self.acl.resources()
+            .map(|name| json!({"name": name, "parent": self.acl.get_resource_parent(name).unwrap_or(None)}))
+            .collect();
+
+        Response::json(200, Value::Array(resources))
+    } // list_resources
+
+    fn add_resource(&mut self, body: &Map<String, Value>) -> Result<Response, Response> {
+        let name   = required(body, "name")?;
+        let parent = match optional(body, "parent")? {
+            Some(p) => Some(self.known_resource(p).ok_or_else(|| Error::MissingParent(String::from(p)))?),
+            None    => None,
+        }; // match
+
+        if self.known_resource(name).is_some() {
+            return Err(Error::DuplicateResource(String::from(name)).into());
+        } // if
+
+        // names are interned once valid, rejected ones aren't leaked
+        self.acl.check_resource_limits(name, parent)?;
+
+        let name = intern(name);
+
+        self.acl.add_resource(name, parent)?;
+        self.changes.push(Change::AddResource(name));
+        Ok(Response::json(201, json!({"name": name})))
+    } // add_resource
+
+    fn list_rules(&self) -> Response {
+        let mut rules: Vec<(&Query, &Rule)> = self.acl.rules().map(|(query, rule, _)| (query, rule)).collect();
+
+        rules.sort_by_key(|(query, _)| (query.role, query.resource, query.privilege));
+
+        let rules: Vec<Value> = rules.into_iter().map(|(query, rule)| json!({
+            "access":    access_name(rule.access()),
+            "role":      query.role,
+            "resource":  query.resource,
+            "privilege": query.privilege,
+        })).collect();
+
+        Response::json(200, Value::Array(rules))
+    } // list_rules
+
+    fn set_rule(&mut self, body: &Map<String, Value>) -> Result<Response, Response> {
+        let access = match required(body, "access")? {
+            "allow" => Access::Allow,
+            "deny"  => Access::Deny,
+            _       => return Err(Response::error(400, "access: expected \"allow\" or \"deny\"")),
+        }; // match
+        let query  = Query{
+            role:      match optional(body, "role")? {
+                Some(name) => Some(self.known_role(name).ok_or_else(|| Error::MissingRole(String::from(name)))?),
+                None       => None,
+            }, // match
+            resource:  match optional(body, "resource")? {
+                Some(name) => Some(self.known_resource(name).ok_or_else(|| Error::MissingResource(String::from(name)))?),
+                None       => None,
+            }, // match
+            privilege: optional(body, "privilege")?.map(|name| self.known_privilege(name).unwrap_or_else(|| intern(name))),
+        }; // Query
+
+        if query == Query::ALL {
+            return Err(Response::error(400, "the catch-all rule cannot be changed"));
+        } // if
+
+        let previous = self.acl.rules.get(&query).copied();
+
+        self.acl.set_rule(query.role, query.resource, query.privilege, access)?;
+        self.changes.push(Change::SetRule{query, access, previous});
+        Ok(Response::json(if previous.is_some() { 200 } else { 201 }, json!({"rule": query.to_string()})))
+    } // set_rule
+
+    fn explain(&self, query: &str) -> Response {
+        let mut role      = None;
+        let mut resource  = None;
+        let mut privilege = None;
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = match pair.find('=') {
+                Some(i) => (&pair[..i], decode(&pair[i + 1..])),
+                None    => (pair, String::new()),
+            }; // match
+
+            match key {
+                "role"      => role      = Some(value),
+                "resource"  => resource  = Some(value),
+                "privilege" => privilege = Some(value),
+                _           => return Response::error(400, &format!("unknown parameter: {}", key)),
+            } // match
+        } // for
+
+        // only names known to the acl are considered, so that queries do not leak memory
+        let role = match role {
+            Some(name) => match self.known_role(&name) {
+                Some(name) => Some(name),
+                None       => return Response::from(Error::MissingRole(name)),
+            }, // Some
+            None       => None,
+        }; // match
+        let resource = match resource {
+            Some(name) => match self.known_resource(&name) {
+                Some(name) => Some(name),
+                None       => return Response::from(Error::MissingResource(name)),
+            }, // Some
+            None       => None,
+        }; // match
+        // privileges unknown to all rules are decided like any other unknown privilege
+        let known     = privilege.as_deref().and_then(|name| self.known_privilege(name));
+        let decision  = match (&privilege, known) {
+            (Some(_), None) => self.acl.decide(role, resource, Some("")),
+            _               => self.acl.decide(role, resource, known),
+        }; // match
+
+        let mut body  = json!({
+            "decision": access_name(decision.rule.access()),
+            "query":    format!("{}→{}: {}", role.unwrap_or("*"), resource.unwrap_or("*"),
+                privilege.as_deref().unwrap_or("*")),
+            "matched":  decision.matched.to_string(),
+            "bypass":   decision.bypass,
+        }); // body
+
+        if let Some(message) = self.acl.get_denial_message(&decision) {
+            body["message"] = json!(message);
+        } // if
+        Response::json(200, body)
+    } // explain
+
+    fn known_role(&self, name: &str) -> Option<&'static str> {
+        self.acl.roles.get_key_value(name).map(|(name, _)| *name)
+    } // known_role
+
+    fn known_resource(&self, name: &str) -> Option<&'static str> {
+        self.acl.resources.get_key_value(name).map(|(name, _)| *name)
+    } // known_resource
+
+    fn known_privilege(&self, name: &str) -> Option<&'static str> {
+        self.acl.rules.keys().filter_map(|query| query.privilege).find(|p| *p == name)
+    } // known_privilege
+
+    fn list_changes(&self) -> Response {
+        Response::json(200, Value::Array(self.changes.iter().map(|change| change.to_json()).collect()))
+    } // list_changes
+
+    fn rollback(&mut self) -> Response {
+        let change = match self.changes.last() {
+            Some(change) => *change,
+            None         => return Response::error(404, "no changes to roll back"),
+        }; // match
+
+        if self.acl.lock.is_some() {
+            return Response::from(Error::Locked);
+        } // if
+        match change {
+            Change::AddRole(name) => {
+                let referenced = self.acl.roles.values().any(|parents| parents.contains(&name))
+                    || self.acl.rules.keys().any(|query| query.role == Some(name))
+                    || self.acl.bypass.contains(name)
+                    || self.acl.default_role == Some(name);
+
+                if referenced {
+                    warn!("cannot roll back referenced role: {}", name);
+                    return Response::error(409, &format!("role is referenced: {}", name));
+                } // if
+                if let Err(error) = self.acl.purge_role(name) {
+                    return Response::from(error);
+                } // if
+            }, // Change::AddRole
+            Change::AddResource(name) => {
+                let referenced = self.acl.resources.values().any(|parent| *parent == Some(name))
+                    || self.acl.rules.keys().any(|query| query.resource == Some(name));
+
+                if referenced {
+                    warn!("cannot roll back referenced resource: {}", name);
+                    return Response::error(409, &format!("resource is referenced: {}", name));
+                } // if
+                if let Err(error) = self.acl.remove_resource(name) {
+                    return Response::from(error);
+                } // if
+            }, // Change::AddResource
+            Change::SetRule{query, previous, ..} => match previous {
+                Some(rule) => {
+                    self.acl.insert_rule(query, rule);
+                }, // Some
+                None       => {
+                    self.acl.remove_rule(&query);
+                    self.acl.meta.remove(&query);
+                }, // None
+            }, // Change::SetRule
+        } // match
+        self.changes.pop();
+        Response::json(200, json!({"reverted": change.to_json()}))
+    } // rollback
+
+} // impl AdminApi
+
+
+// Helpers ////////////////////////////////////////////////////////////////////////////////////////
+
+
+fn access_name(access: Access) -> &'static str {
+    match access {
+        Access::Allow => "allow",
+        Access::Deny  => "deny",
+    } // match
+} // access_name
+
+fn required<'a>(body: &'a Map<String, Value>, field: &str) -> Result<&'a str, Response> {
+    match body.get(field) {
+        Some(Value::String(s)) if !s.is_empty() => Ok(s),
+        _                                       => Err(Response::error(400, &format!("{}: expected a string", field))),
+    } // match
+} // required
+
+fn optional<'a>(body: &'a Map<String, Value>, field: &str) -> Result<Option<&'a str>, Response> {
+    match body.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s))   => Ok(Some(s)),
+        Some(_)                  => Err(Response::error(400, &format!("{}: expected a string or null", field))),
+    } // match
+} // optional
+
+/// Decodes a percent-encoded query string value.
+fn decode(value: &str) -> String {
+    let bytes   = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i   = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+                match hex {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }, // Some
+                    None       => out.push(b'%'),
+                } // match
+            }, // b'%'
+            b'+'                        => out.push(b' '),
+            byte                        => out.push(byte),
+        } // match
+        i += 1;
+    } // while
+    String::from_utf8_lossy(&out).into_owned()
+} // decode
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn setup() -> AdminApi {
+        let mut api = AdminApi::new(Acl::new());
+
+        assert_eq!(api.handle("POST", "/roles", r#"{"name": "guest"}"#).status, 201);
+        assert_eq!(api.handle("POST", "/roles", r#"{"name": "staff", "parents": ["guest"]}"#).status, 201);
+        assert_eq!(api.handle("POST", "/resources", r#"{"name": "news"}"#).status, 201);
+        assert_eq!(api.handle("POST", "/resources", r#"{"name": "latest", "parent": "news"}"#).status, 201);
+        assert_eq!(api.handle("POST", "/rules", r#"{"access": "allow", "role": "guest", "privilege": "view"}"#).status, 201);
+        api
+    } // setup
+
+    #[test]
+    fn manage() {
+        let mut api = setup();
+
+        assert_eq!(api.handle("GET", "/roles", "").body,
+            r#"[{"name":"guest","parents":[]},{"name":"staff","parents":["guest"]}]"#);
+        assert_eq!(api.handle("GET", "/resources", "").body,
+            r#"[{"name":"latest","parent":"news"},{"name":"news","parent":null}]"#);
+        assert_eq!(api.handle("GET", "/rules", "").body, concat!(
+            r#"[{"access":"deny","privilege":null,"resource":null,"role":null},"#,
+            r#"{"access":"allow","privilege":"view","resource":null,"role":"guest"}]"#));
+        assert!(api.acl().is_allowed(Some("staff"), Some("latest"), Some("view")));
+
+        assert_eq!(api.handle("POST", "/roles", r#"{"name": "guest"}"#).status, 409);
+        assert_eq!(api.handle("POST", "/roles", r#"{"name": "editor", "parents": ["nobody"]}"#).status, 404);
+        assert_eq!(api.handle("POST", "/rules", r#"{"access": "permit"}"#).status, 400);
+        assert_eq!(api.handle("POST", "/rules", r#"{"access": "deny"}"#).status, 400);
+        assert_eq!(api.handle("POST", "/rules", "[").status, 400);
+        assert_eq!(api.handle("DELETE", "/rules", "").status, 405);
+        assert_eq!(api.handle("GET", "/groups", "").status, 404);
+
+        // names rejected by limits aren't added
+        api.acl.set_limits(crate::limits::Limits{max_name_length: Some(8), ..api.acl().limits()});
+        assert_eq!(api.handle("POST", "/roles", r#"{"name": "marketing"}"#).status, 400);
+        assert_eq!(api.handle("POST", "/resources", r#"{"name": "marketing"}"#).status, 400);
+        assert_eq!(api.acl().roles().count(), 2);
+    } // manage
+
+    #[test]
+    fn explain() {
+        let mut api = setup();
+        let res = api.handle("GET", "/explain?role=staff&resource=latest&privilege=view", "");
+
+        assert_eq!(res.status, 200);
+        assert_eq!(res.body, concat!(
+            r#"{"bypass":false,"decision":"allow","matched":"guest→*: view","#,
+            r#""query":"staff→latest: view"}"#));
+
+        let res = api.handle("GET", "/explain?role=guest&privilege=edit%20news", "");
+
+        assert_eq!(res.body, concat!(
+            r#"{"bypass":false,"decision":"deny","matched":"*→*: *","#,
+            r#""query":"guest→*: edit news"}"#));
+        assert_eq!(api.handle("GET", "/explain?role=nobody", "").status, 404);
+        assert_eq!(api.handle("GET", "/explain?user=nobody", "").status, 400);
+
+        let mut acl = Acl::new();
+
+        assert!(acl.deny_with_message(None, None, None, "ask an administrator").is_ok());
+        assert_eq!(AdminApi::new(acl).handle("GET", "/explain", "").body, concat!(
+            r#"{"bypass":false,"decision":"deny","matched":"*→*: *","#,
+            r#""message":"ask an administrator","query":"*→*: *"}"#));
+    } // explain
+
+    #[test]
+    fn rollback() {
+        let mut api = setup();
+
+        assert_eq!(api.handle("POST", "/rules", r#"{"access": "deny", "role": "guest", "privilege": "view"}"#).status, 200);
+        assert!(api.acl().is_denied(Some("guest"), None, Some("view")));
+        assert_eq!(api.handle("GET", "/changes", "").body.matches("\"change\"").count(), 6);
+
+        assert_eq!(api.handle("POST", "/rollback", "").status, 200);
+        assert!(api.acl().is_allowed(Some("guest"), None, Some("view")));
+
+        assert_eq!(api.handle("POST", "/rollback", "").status, 200);
+        assert!(api.acl().is_denied(Some("guest"), None, Some("view")));
+
+        assert_eq!(api.handle("POST", "/rollback", "").status, 200);
+        assert!(!api.acl().has_resource("latest"));
+
+        assert_eq!(api.handle("POST", "/rollback", "").status, 200);
+        assert_eq!(api.handle("POST", "/rollback", "").status, 200);
+        assert!(!api.acl().has_role("staff"));
+        assert_eq!(api.handle("POST", "/rollback", "").status, 200);
+        assert_eq!(api.handle("POST", "/rollback", "").status, 404);
+        assert_eq!(api.acl().roles().count(), 0);
+        assert_eq!(api.acl().etag(), Acl::new().etag());
+    } // rollback
+
+    #[test]
+    fn conditional() {
+        let mut api = setup();
+        let etag    = api.acl().etag();
+
+        assert_eq!(api.handle_conditional("GET", "/rules", "", Some(&etag)).status, 304);
+        assert_eq!(api.handle_conditional("GET", "/rules", "", Some(&format!("\"0\", {}", etag))).status, 304);
+        assert_eq!(api.handle_conditional("GET", "/rules", "", Some("\"0\"")).status, 200);
+        assert_eq!(api.handle_conditional("GET", "/rules", "", None).status, 200);
+        assert_eq!(api.handle_conditional("POST", "/roles", r#"{"name": "editor"}"#, Some(&etag)).status, 201);
+        assert_eq!(api.handle_conditional("GET", "/roles", "", Some(&etag)).status, 200);
+    } // conditional
+
+    #[test]
+    fn referenced() {
+        let mut api = setup();
+
+        assert_eq!(api.handle("POST", "/roles", r#"{"name": "editor"}"#).status, 201);
+        assert!(api.acl.allow(Some("editor"), None, None).is_ok());
+        assert_eq!(api.handle("POST", "/rollback", "").status, 409);
+        assert!(api.acl().has_role("editor"));
+        assert_eq!(api.acl.remove_allow(Some("editor"), None, None), Ok(1));
+        assert!(api.acl.set_default_role("editor").is_ok());
+        assert_eq!(api.handle("POST", "/rollback", "").status, 409);
+        assert_eq!(api.acl().default_role(), Some("editor"));
+        assert_eq!(api.acl.unset_default_role(), Ok(true));
+        assert_eq!(api.handle("POST", "/rollback", "").status, 200);
+        assert!(!api.acl().has_role("editor"));
+
+        api.acl.lock();
+        assert_eq!(api.handle("POST", "/rollback", "").status, 409);
+        assert_eq!(api.handle("POST", "/rules", r#"{"access": "allow", "role": "staff"}"#).status, 409);
+        assert_eq!(decode("a%2Fb+c%"), "a/b c%");
+    } // referenced
+
+} // mod tests
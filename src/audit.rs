@@ -0,0 +1,424 @@
+//! Auditing of decisions.
+//!
+//! An `AuditSink` set by `set_audit_sink` receives an `AuditEvent` for each decision made by
+//! `decide`, `decide_for` and the methods based on them, e.g. `is_allowed`. The event holds the
+//! decision, the subject if known and the fingerprint of the policy, see module `etag`.
+//!
+//! With the `serde` feature events are `Serialize`. The `audit` feature adds `JsonLinesSink`,
+//! which writes one JSON object per event and line to a file, stdout or any writer:
+//!
+//! ```json
+//! {"timestamp":1700000000000,"subject":"sally","role":"staff","resource":"news","privilege":"edit",
+//!  "decision":"allow","matched":{"role":"staff","resource":null,"privilege":"edit"},"bypass":false,
+//!  "fingerprint":"4e1f6a0c2b9d3e57"}
+//! ```
+//!
+//! The timestamp is given in milliseconds since the UNIX epoch. The schema is stable, new fields
+//! may be added. A recorded log is replayed against a new policy version by `replay`, which
+//! reports every decision that would change.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::audit::AuditEvent;
+//! # use std::cell::RefCell;
+//! # use std::rc::Rc;
+//! let events = Rc::new(RefCell::new(vec![]));
+//! let sink   = Rc::clone(&events);
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.set_audit_sink(move |event: &AuditEvent| sink.borrow_mut().push(event.clone()));
+//!
+//! assert!(acl.is_denied(Some("staff"), None, Some("delete")));
+//! assert_eq!(events.borrow()[0].decision.to_string(), "DENY staff→*: delete");
+//! ```
+
+use crate::{Acl, Decision};
+use log::trace;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "audit")]
+use crate::policy::intern;
+#[cfg(feature = "audit")]
+use crate::{Access, Error};
+#[cfg(feature = "audit")]
+use serde_json::Value;
+#[cfg(feature = "audit")]
+use log::warn;
+#[cfg(feature = "audit")]
+use std::cell::RefCell;
+#[cfg(feature = "audit")]
+use std::collections::HashMap;
+#[cfg(feature = "audit")]
+use std::fmt;
+#[cfg(feature = "audit")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "audit")]
+use std::io::{self, BufRead, Stdout, Write};
+#[cfg(feature = "audit")]
+use std::path::Path;
+
+
+// AuditEvent /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A decision reported to the `AuditSink`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEvent {
+    /// when the decision has been made
+    pub timestamp:   SystemTime,
+    /// the subject the decision has been made for, see `decide_for`
+    pub subject:     Option<&'static str>,
+    /// the decision
+    pub decision:    Decision,
+    /// the fingerprint of the policy which made the decision
+    pub fingerprint: u64,
+} // struct AuditEvent
+
+impl AuditEvent {
+
+    /// Returns the timestamp in milliseconds since the UNIX epoch.
+    pub fn timestamp_millis(&self) -> u64 {
+        self.timestamp.duration_since(UNIX_EPOCH).map(|since| since.as_millis() as u64).unwrap_or(0)
+    } // timestamp_millis
+
+} // impl AuditEvent
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for AuditEvent {
+
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        /// The rule which made the decision.
+        #[derive(serde::Serialize)]
+        struct Matched {
+            role:      Option<&'static str>,
+            resource:  Option<&'static str>,
+            privilege: Option<&'static str>,
+        } // struct Matched
+
+        let query     = &self.decision.query;
+        let matched   = &self.decision.matched;
+        let mut event = serializer.serialize_struct("AuditEvent", 9)?;
+
+        event.serialize_field("timestamp", &self.timestamp_millis())?;
+        event.serialize_field("subject", &self.subject)?;
+        event.serialize_field("role", &query.role)?;
+        event.serialize_field("resource", &query.resource)?;
+        event.serialize_field("privilege", &query.privilege)?;
+        event.serialize_field("decision", if self.decision.is_allowed() { "allow" } else { "deny" })?;
+        event.serialize_field("matched", &Matched{role: matched.role, resource: matched.resource, privilege: matched.privilege})?;
+        event.serialize_field("bypass", &self.decision.bypass)?;
+        event.serialize_field("fingerprint", &format!("{:016x}", self.fingerprint))?;
+        event.end()
+    } // serialize
+
+} // impl serde::Serialize for AuditEvent
+
+
+// AuditSink //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Receives the decisions of an `Acl`.
+pub trait AuditSink {
+
+    /// Records event.
+    fn record(&self, event: &AuditEvent);
+
+} // trait AuditSink
+
+impl<F: Fn(&AuditEvent)> AuditSink for F {
+
+    fn record(&self, event: &AuditEvent) {
+        self(event)
+    } // record
+
+} // impl AuditSink for F
+
+impl Acl {
+
+    /// Sets the sink receiving the decisions. Replaces a previous sink.
+    pub fn set_audit_sink<S: AuditSink + 'static>(&mut self, sink: S) {
+        trace!("setting audit sink");
+        self.audit_sink = Some(Box::new(sink));
+    } // set_audit_sink
+
+    /// Removes the audit sink. Returns true if a sink has been removed.
+    pub fn unset_audit_sink(&mut self) -> bool {
+        self.audit_sink.take().is_some()
+    } // unset_audit_sink
+
+    /// Reports decision to the audit sink, if any.
+    pub(crate) fn audit(&self, subject: Option<&'static str>, decision: &Decision) {
+        if let Some(sink) = &self.audit_sink {
+            sink.record(&AuditEvent{
+                timestamp:   SystemTime::now(),
+                subject,
+                decision:    *decision,
+                fingerprint: self.fingerprint,
+            }); // AuditEvent
+        } // if
+    } // audit
+
+} // impl Acl
+
+
+// JsonLinesSink //////////////////////////////////////////////////////////////////////////////////
+
+
+/// Writes events as JSON lines. Failing writes are logged and otherwise ignored, so auditing
+/// never fails a decision.
+#[cfg(feature = "audit")]
+pub struct JsonLinesSink<W: Write> {
+    writer: RefCell<W>,
+} // struct JsonLinesSink
+
+#[cfg(feature = "audit")]
+impl<W: Write> JsonLinesSink<W> {
+
+    /// Creates a new `JsonLinesSink` writing to writer.
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink{writer: RefCell::new(writer)}
+    } // new
+
+    /// Returns the writer.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    } // into_inner
+
+} // impl JsonLinesSink
+
+#[cfg(feature = "audit")]
+impl JsonLinesSink<Stdout> {
+
+    /// Creates a new `JsonLinesSink` writing to stdout.
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    } // stdout
+
+} // impl JsonLinesSink<Stdout>
+
+#[cfg(feature = "audit")]
+impl JsonLinesSink<File> {
+
+    /// Creates a new `JsonLinesSink` appending to the file at path, which is created if missing.
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))?;
+
+        Ok(Self::new(file))
+    } // file
+
+} // impl JsonLinesSink<File>
+
+#[cfg(feature = "audit")]
+impl<W: Write> AuditSink for JsonLinesSink<W> {
+
+    fn record(&self, event: &AuditEvent) {
+        let mut writer = self.writer.borrow_mut();
+        let result     = serde_json::to_writer(&mut *writer, event)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"));
+
+        if let Err(e) = result {
+            warn!("failed to write audit event: {}", e);
+        } // if
+    } // record
+
+} // impl AuditSink for JsonLinesSink
+
+
+
+// Replay /////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A recorded decision which the replaying policy decides differently.
+#[cfg(feature = "audit")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecisionChange {
+    /// the line of the decision within the log, starting at 1
+    pub line:     usize,
+    /// the subject of the recorded decision
+    pub subject:  Option<String>,
+    /// the recorded access
+    pub recorded: Access,
+    /// the decision of the replaying policy
+    pub replayed: Decision,
+} // struct DecisionChange
+
+#[cfg(feature = "audit")]
+impl fmt::Display for DecisionChange {
+
+    /// Formats the change like `line 3: ALLOW → DENY staff→news: edit`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "line {}: {} → {}", self.line, self.recorded, self.replayed)
+    } // fmt
+
+} // impl fmt::Display for DecisionChange
+
+/// The outcome of replaying a decision log.
+#[cfg(feature = "audit")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayReport {
+    /// the number of replayed decisions
+    pub replayed: usize,
+    /// the decisions which changed in order of the log
+    pub changes:  Vec<DecisionChange>,
+} // struct ReplayReport
+
+/// Reads the events of a decision log written by `JsonLinesSink` with their line number, starting
+/// at 1, and recorded access. Empty lines are skipped.
+#[cfg(feature = "audit")]
+pub(crate) fn read_log<R: BufRead>(log: R) -> impl Iterator<Item = Result<(usize, Value, Access), Error>> {
+    log.lines().enumerate().filter_map(|(i, line)| {
+        let line = match line {
+            Ok(line) if line.trim().is_empty() => return None,
+            Ok(line)                           => line,
+            Err(e)                             => return Some(Err(Error::Io(e.to_string()))),
+        }; // match
+        let event: Value = match serde_json::from_str(&line) {
+            Ok(event) => event,
+            Err(e)    => return Some(Err(Error::Parse(format!("line {}: {}", i + 1, e)))),
+        }; // match
+
+        Some(match event["decision"].as_str() {
+            Some("allow") => Ok((i + 1, event, Access::Allow)),
+            Some("deny")  => Ok((i + 1, event, Access::Deny)),
+            _             => Err(Error::Parse(format!("line {}: expected \"allow\" or \"deny\" as decision", i + 1))),
+        }) // Some
+    }) // filter_map
+} // read_log
+
+#[cfg(feature = "audit")]
+impl Acl {
+
+    /// Replays a decision log written by `JsonLinesSink` and reports every decision this `Acl`
+    /// decides differently, e.g. to validate a refactored policy against real traffic. Replayed
+    /// decisions aren't audited. Names undefined in this `Acl` are leaked once per replay.
+    pub fn replay<R: BufRead>(&self, log: R) -> Result<ReplayReport, Error> {
+        let mut leaked = HashMap::new();
+        let mut report = ReplayReport::default();
+
+        for event in read_log(log) {
+            let (line, event, recorded) = event?;
+            let mut name     = |key: &str| event[key].as_str().map(|name| self.resolve_name(name, &mut leaked));
+            let role         = name("role");
+            let resource     = name("resource");
+            let privilege    = name("privilege");
+            let subject      = event["subject"].as_str();
+            let replayed     = match subject {
+                Some(subject) => self.evaluate_for(subject, role, resource, privilege),
+                None          => self.evaluate(role, resource, privilege),
+            }; // match
+
+            report.replayed += 1;
+            if replayed.rule.access() != recorded {
+                trace!("decision on line {} changed to {}", line, replayed);
+                report.changes.push(DecisionChange{line, subject: subject.map(String::from), recorded, replayed});
+            } // if
+        } // for
+        Ok(report)
+    } // replay
+
+    /// Returns the name defined in this `Acl` or leaks it once.
+    fn resolve_name(&self, name: &str, leaked: &mut HashMap<String, &'static str>) -> &'static str {
+        if let Some((name, _)) = self.roles.get_key_value(name) {
+            return name;
+        } // if
+        if let Some((name, _)) = self.resources.get_key_value(name) {
+            return name;
+        } // if
+        if let Some(name) = self.privileges.get(name) {
+            return name;
+        } // if
+        leaked.entry(String::from(name)).or_insert_with(|| intern(name))
+    } // resolve_name
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use test_env_log::test;
+
+    #[test]
+    fn audit() {
+        let events  = Rc::new(RefCell::new(vec![]));
+        let sink    = Rc::clone(&events);
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.allow(Some("staff"), None, Some("edit")).is_ok());
+        assert!(acl.deny_subject("sally", None, Some("edit")).is_ok());
+        acl.set_audit_sink(move |event: &AuditEvent| sink.borrow_mut().push(event.clone()));
+
+        assert!(acl.is_allowed(Some("staff"), None, Some("edit")));
+        assert!(acl.is_denied_for("sally", Some("staff"), None, Some("edit")));
+        assert!(acl.is_allowed_for("bob", Some("staff"), None, Some("edit")));
+        assert!(acl.unset_audit_sink());
+        assert!(acl.is_allowed(Some("staff"), None, Some("edit")));
+
+        let events = events.borrow();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].subject, None);
+        assert_eq!(events[1].subject, Some("sally"));
+        assert_eq!(events[1].decision.to_string(), "DENY staff→*: edit");
+        assert_eq!(events[2].subject, Some("bob"));
+        assert_eq!(events[2].fingerprint, acl.fingerprint());
+    } // audit
+
+    #[cfg(feature = "audit")]
+    #[test]
+    fn json_lines() {
+        let mut acl = Acl::new();
+        let sink    = Rc::new(JsonLinesSink::new(vec![]));
+        let shared  = Rc::clone(&sink);
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.allow(Some("staff"), None, Some("edit")).is_ok());
+        acl.set_audit_sink(move |event: &AuditEvent| shared.record(event));
+        assert!(acl.is_allowed(Some("staff"), Some("news"), Some("edit")));
+        assert!(acl.is_denied(None, None, None));
+        drop(acl);
+
+        let output = String::from_utf8(Rc::try_unwrap(sink).ok().unwrap().into_inner()).unwrap();
+        let lines: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["role"], "staff");
+        assert_eq!(lines[0]["decision"], "allow");
+        assert_eq!(lines[0]["matched"], serde_json::json!({"role": "staff", "resource": null, "privilege": "edit"}));
+        assert_eq!(lines[1]["decision"], "deny");
+        assert_eq!(lines[1]["fingerprint"].as_str().map(str::len), Some(16));
+        assert!(lines[1]["timestamp"].as_u64().unwrap() > 0);
+
+        // replay the log against a refactored policy
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.deny(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.allow_subject("sally", None, None).is_ok());
+
+        let log    = format!("{}\n{}", output, r#"{"subject": "sally", "role": "guest", "privilege": "view", "decision": "allow"}"#);
+        let report = acl.replay(log.as_bytes()).unwrap();
+
+        assert_eq!(report.replayed, 3);
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(report.changes[0].to_string(), "line 1: ALLOW → DENY staff→news: edit");
+        assert_eq!(acl.replay("{}".as_bytes()), Err(Error::Parse(String::from("line 1: expected \"allow\" or \"deny\" as decision"))));
+        assert!(acl.replay("[".as_bytes()).is_err());
+    } // json_lines
+
+} // mod tests
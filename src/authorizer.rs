@@ -0,0 +1,230 @@
+//! A common interface of the decision points.
+//!
+//! `Authorizer` is implemented by `Acl`, `ChainedAcl`, `RemoteAcl`, `CompiledAcl` and
+//! `ScopedView`, so application code may depend on the trait instead of a concrete policy. Tests
+//! may inject a mock instead of constructing a policy: an `Access` decides every query alike, e.g.
+//! `Access::Allow` permits everything, and a closure taking the `Query` decides scripted.
+//!
+//! `explain` returns a human readable explanation of the decision, e.g. for logs. Its format
+//! depends on the implementation.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Access, Query};
+//! # use zorq_acl::authorizer::Authorizer;
+//! fn may_publish(authorizer: &dyn Authorizer, role: &'static str) -> bool {
+//!     authorizer.is_allowed(Some(role), Some("news"), Some("publish"))
+//! }
+//!
+//! let scripted = |query: Query| if query.role == Some("editor") { Access::Allow } else { Access::Deny };
+//!
+//! assert!(may_publish(&Access::Allow, "guest"));
+//! assert!(may_publish(&scripted, "editor"));
+//! assert!(!may_publish(&scripted, "guest"));
+//! assert_eq!(scripted.explain(Some("guest"), None, Some("publish")), "DENY guest→*: publish");
+//! ```
+
+use crate::{Access, Acl, Decision, Privilege, Query, Resource, Role};
+use crate::chain::ChainedAcl;
+use crate::compiled::CompiledAcl;
+use crate::remote::{RemoteAcl, Transport};
+use crate::scope::ScopedView;
+
+
+// Authorizer /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Decides queries, see module `authorizer`.
+pub trait Authorizer {
+
+    /// Returns true if privilege is allowed for role on resource.
+    fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool;
+
+    /// Explains the decision of the query.
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String;
+
+    /// Returns true if privilege is denied for role on resource.
+    #[inline]
+    fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        !self.is_allowed(role, resource, privilege)
+    } // is_denied
+
+} // trait Authorizer
+
+/// Explains a decision by the deciding rule, like `DENY staff→latest: revise by DENY staff→news: *`.
+fn explain(decision: &Decision) -> String {
+    if decision.bypass {
+        return format!("{} by bypass role", decision);
+    } // if
+    format!("{} by {} {}", decision, decision.rule, decision.matched)
+} // explain
+
+impl Authorizer for Acl {
+
+    #[inline]
+    fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        Acl::is_allowed(self, role, resource, privilege)
+    } // is_allowed
+
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String {
+        explain(&self.decide(role, resource, privilege))
+    } // explain
+
+} // impl Authorizer for Acl
+
+impl Authorizer for ChainedAcl {
+
+    #[inline]
+    fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        ChainedAcl::is_allowed(self, role, resource, privilege)
+    } // is_allowed
+
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String {
+        explain(&self.decide(role, resource, privilege))
+    } // explain
+
+} // impl Authorizer for ChainedAcl
+
+impl<T: Transport> Authorizer for RemoteAcl<T> {
+
+    #[inline]
+    fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        RemoteAcl::is_allowed(self, role, resource, privilege)
+    } // is_allowed
+
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String {
+        let query = Query{resource, role, privilege};
+
+        match self.try_access(role, resource, privilege) {
+            Ok(access) => format!("{} {} by remote policy", access, query),
+            Err(e)     => format!("{} {} by failing closed: {}", Access::Deny, query, e),
+        } // match
+    } // explain
+
+} // impl Authorizer for RemoteAcl
+
+impl Authorizer for CompiledAcl<'_> {
+
+    #[inline]
+    fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        CompiledAcl::is_allowed(self, role, resource, privilege)
+    } // is_allowed
+
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String {
+        format!("{} {} by compiled policy", self.access(role, resource, privilege), Query{resource, role, privilege})
+    } // explain
+
+} // impl Authorizer for CompiledAcl
+
+impl Authorizer for ScopedView<'_> {
+
+    #[inline]
+    fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        ScopedView::is_allowed(self, role, resource, privilege)
+    } // is_allowed
+
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String {
+        match self.decide(role, resource, privilege) {
+            Ok(decision) => explain(&decision),
+            Err(e)       => format!("{} {} by {}", Access::Deny, Query{resource, role, privilege}, e),
+        } // match
+    } // explain
+
+} // impl Authorizer for ScopedView
+
+impl Authorizer for Access {
+
+    #[inline]
+    fn is_allowed(&self, _role: Role, _resource: Resource, _privilege: Privilege) -> bool {
+        *self == Access::Allow
+    } // is_allowed
+
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String {
+        format!("{} {}", self, Query{resource, role, privilege})
+    } // explain
+
+} // impl Authorizer for Access
+
+impl<F: Fn(Query) -> Access> Authorizer for F {
+
+    #[inline]
+    fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self(Query{resource, role, privilege}) == Access::Allow
+    } // is_allowed
+
+    fn explain(&self, role: Role, resource: Resource, privilege: Privilege) -> String {
+        let query = Query{resource, role, privilege};
+
+        format!("{} {}", self(query), query)
+    } // explain
+
+} // impl Authorizer for F
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Error;
+    use test_env_log::test;
+
+    fn setup() -> Acl {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), Some("news"), Some("view")).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        acl
+    } // setup
+
+    #[test]
+    fn authorizer() {
+        let acl      = setup();
+        let artifact = acl.compile().unwrap();
+        let compiled = CompiledAcl::from_bytes(&artifact).unwrap();
+        let remote   = RemoteAcl::new(|query: Query| Ok(setup().get_rule(query.role, query.resource, query.privilege).access()));
+        let view     = acl.scoped_view("latest").unwrap();
+        let chain    = setup().with_fallback(Acl::new());
+        let all: Vec<&dyn Authorizer> = vec![&acl, &compiled, &remote, &view, &chain];
+
+        for authorizer in all {
+            assert!(authorizer.is_allowed(Some("guest"), Some("latest"), Some("view")));
+            assert!(authorizer.is_denied(Some("guest"), Some("latest"), Some("edit")));
+        } // for
+
+        assert_eq!(acl.explain(Some("guest"), Some("latest"), Some("view")), "ALLOW guest→latest: view by ALLOW guest→news: view");
+        assert_eq!(acl.explain(Some("root"), None, None), "ALLOW root→*: * by bypass role");
+        assert_eq!(chain.explain(Some("guest"), None, None), "DENY guest→*: * by DENY *→*: *");
+        assert_eq!(compiled.explain(None, Some("news"), None), "DENY *→news: * by compiled policy");
+        assert_eq!(remote.explain(Some("guest"), Some("news"), Some("view")), "ALLOW guest→news: view by remote policy");
+        assert_eq!(view.explain(Some("guest"), Some("news"), Some("view")),
+            "DENY guest→news: view by Not permitted: resource news outside scope latest");
+
+        let down = RemoteAcl::new(|_: Query| Err(Error::Io(String::from("connection refused"))));
+
+        assert_eq!(down.explain(None, None, None), "DENY *→*: * by failing closed: I/O error: connection refused");
+    } // authorizer
+
+    #[test]
+    fn mock() {
+        let calls    = std::cell::Cell::new(0);
+        let scripted = |query: Query| {
+            calls.set(calls.get() + 1);
+            if query.privilege == Some("view") { Access::Allow } else { Access::Deny }
+        }; // scripted
+
+        assert!(Access::Allow.is_allowed(Some("anyone"), Some("anything"), None));
+        assert!(Access::Deny.is_denied(None, None, Some("view")));
+        assert_eq!(Access::Allow.explain(None, Some("news"), None), "ALLOW *→news: *");
+        assert!(scripted.is_allowed(Some("guest"), None, Some("view")));
+        assert!(scripted.is_denied(Some("guest"), None, Some("edit")));
+        assert_eq!(calls.get(), 2);
+    } // mock
+
+} // mod tests
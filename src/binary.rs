@@ -0,0 +1,324 @@
+//! Compact binary policies.
+//!
+//! Policies are encoded with bincode (feature `bincode`) or CBOR (feature `cbor`). The content
+//! mirrors the JSON policy document, see module `policy`, and is validated alike when loaded. An
+//! encoded policy starts with a header of the magic bytes `ZACL` and the format version, so
+//! readers reject policies of unknown versions with `Error::SchemaVersion` instead of misreading
+//! them. Policies of version 1, which lack conditions, environments, deny messages and the default
+//! role, are migrated when loaded.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//!
+//! # #[cfg(feature = "cbor")] {
+//! let acl = Acl::from_cbor(&acl.to_cbor()).unwrap();
+//!
+//! assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+//! # }
+//! ```
+
+use crate::policy::{export, load};
+use crate::{Acl, Error};
+use log::trace;
+use serde::{Deserialize, Serialize};
+
+/// The magic bytes starting an encoded policy.
+pub const MAGIC: &[u8; 4] = b"ZACL";
+
+/// The format version written into the header.
+pub const VERSION: u8 = 2;
+
+
+// Policy /////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Policy {
+    #[serde(default)]
+    version:      u64,
+    #[serde(default)]
+    roles:        Vec<Role>,
+    #[serde(default)]
+    resources:    Vec<Resource>,
+    #[serde(default)]
+    rules:        Vec<Rule>,
+    #[serde(default)]
+    bypass:       Vec<String>,
+    #[serde(default)]
+    default_role: Option<String>,
+} // struct Policy
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Role {
+    name:    String,
+    #[serde(default)]
+    parents: Vec<String>,
+} // struct Role
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Resource {
+    name:   String,
+    #[serde(default)]
+    parent: Option<String>,
+} // struct Resource
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Rule {
+    access:      String,
+    #[serde(default)]
+    role:        Option<String>,
+    #[serde(default)]
+    resource:    Option<String>,
+    #[serde(default)]
+    privilege:   Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    author:      Option<String>,
+    #[serde(default)]
+    ticket:      Option<String>,
+    #[serde(default)]
+    message:     Option<String>,
+    #[serde(default)]
+    condition:   Option<String>,
+    #[serde(default)]
+    environment: Option<String>,
+} // struct Rule
+
+/// The policy of format version 1. Decoded for migration only.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PolicyV1 {
+    roles:     Vec<Role>,
+    resources: Vec<Resource>,
+    rules:     Vec<RuleV1>,
+    bypass:    Vec<String>,
+} // struct PolicyV1
+
+/// A rule of format version 1.
+#[derive(Debug, Deserialize, Serialize)]
+struct RuleV1 {
+    access:      String,
+    role:        Option<String>,
+    resource:    Option<String>,
+    privilege:   Option<String>,
+    description: Option<String>,
+    author:      Option<String>,
+    ticket:      Option<String>,
+} // struct RuleV1
+
+impl From<PolicyV1> for Policy {
+
+    fn from(policy: PolicyV1) -> Self {
+        Policy{
+            version:      1,
+            roles:        policy.roles,
+            resources:    policy.resources,
+            rules:        policy.rules.into_iter().map(|rule| Rule{
+                access:      rule.access,
+                role:        rule.role,
+                resource:    rule.resource,
+                privilege:   rule.privilege,
+                description: rule.description,
+                author:      rule.author,
+                ticket:      rule.ticket,
+                message:     None,
+                condition:   None,
+                environment: None,
+            }).collect(),
+            bypass:       policy.bypass,
+            default_role: None,
+        } // Policy
+    } // from
+
+} // impl From<PolicyV1> for Policy
+
+impl Policy {
+
+    fn export(acl: &Acl) -> Self {
+        // the exported document always matches the policy
+        serde_json::from_value(export(acl)).unwrap_or_default()
+    } // export
+
+    fn load(&self) -> Result<Acl, Error> {
+        let value = serde_json::to_value(self).map_err(|e| Error::Parse(e.to_string()))?;
+
+        load(&value, None)
+    } // load
+
+} // impl Policy
+
+
+// Header /////////////////////////////////////////////////////////////////////////////////////////
+
+
+fn header() -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+
+    bytes.push(VERSION);
+    bytes
+} // header
+
+/// Returns the format version and the encoded policy following the header. Returns an error if
+/// the header is missing or of an unknown version.
+fn body(bytes: &[u8]) -> Result<(u8, &[u8]), Error> {
+    if bytes.len() <= MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(Error::Parse(String::from("missing policy header")));
+    } // if
+    match bytes[MAGIC.len()] {
+        0                            => Err(Error::Parse(String::from("invalid policy format version 0"))),
+        version if version > VERSION => Err(Error::SchemaVersion(u64::from(version))),
+        version                      => {
+            trace!("decoding policy of format version {}", version);
+            Ok((version, &bytes[MAGIC.len() + 1..]))
+        }, // version
+    } // match
+} // body
+
+impl Acl {
+
+    /// Exports the `Acl` as bincode encoded policy.
+    #[cfg(feature = "bincode")]
+    pub fn to_bincode(&self) -> Vec<u8> {
+        let mut bytes = header();
+
+        // serializing into a vector only fails for unsupported types
+        bincode::serialize_into(&mut bytes, &Policy::export(self)).unwrap_or_default();
+        bytes
+    } // to_bincode
+
+    /// Creates an `Acl` from a bincode encoded policy. Returns an error if the header or the
+    /// encoding is malformed or the policy is invalid.
+    #[cfg(feature = "bincode")]
+    pub fn from_bincode(bytes: &[u8]) -> Result<Acl, Error> {
+        let policy: Policy = match body(bytes)? {
+            (1, body) => bincode::deserialize::<PolicyV1>(body).map(Policy::from),
+            (_, body) => bincode::deserialize(body),
+        }.map_err(|e| Error::Parse(e.to_string()))?;
+
+        policy.load()
+    } // from_bincode
+
+    /// Exports the `Acl` as CBOR encoded policy.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut bytes = header();
+
+        // writing into a vector doesn't fail
+        ciborium::into_writer(&Policy::export(self), &mut bytes).unwrap_or_default();
+        bytes
+    } // to_cbor
+
+    /// Creates an `Acl` from a CBOR encoded policy. Returns an error if the header or the encoding
+    /// is malformed or the policy is invalid.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(bytes: &[u8]) -> Result<Acl, Error> {
+        let policy: Policy = match body(bytes)? {
+            (1, body) => ciborium::from_reader::<PolicyV1, _>(body).map(Policy::from),
+            (_, body) => ciborium::from_reader(body),
+        }.map_err(|e| Error::Parse(e.to_string()))?;
+
+        policy.load()
+    } // from_cbor
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn setup_acl() -> Acl {
+        Acl::from_json(r#"{
+            "roles":     [{"name": "guest"}, {"name": "staff", "parents": ["guest"]}, {"name": "root"}],
+            "resources": [{"name": "news"}, {"name": "latest", "parent": "news"}],
+            "rules":     [{"access": "allow", "role": "guest", "privilege": "view"},
+                          {"access": "allow", "role": "staff", "privilege": "edit", "condition": "owner"},
+                          {"access": "allow", "role": "staff", "privilege": "debug", "environment": "dev"},
+                          {"access": "deny", "role": "staff", "resource": "latest", "privilege": "revise", "ticket": "CR-42",
+                           "message": "ask an editor"}],
+            "bypass":    ["root"],
+            "default_role": "guest"
+        }"#).unwrap()
+    } // setup_acl
+
+    /// Encodes the policy of acl in format version 1.
+    fn encode_v1(acl: &Acl) -> PolicyV1 {
+        let policy = Policy::export(acl);
+
+        PolicyV1{
+            roles:     policy.roles,
+            resources: policy.resources,
+            rules:     policy.rules.into_iter().filter(|rule| rule.condition.is_none() && rule.environment.is_none()).map(|rule| RuleV1{
+                access:      rule.access,
+                role:        rule.role,
+                resource:    rule.resource,
+                privilege:   rule.privilege,
+                description: rule.description,
+                author:      rule.author,
+                ticket:      rule.ticket,
+            }).collect(),
+            bypass:    policy.bypass,
+        } // PolicyV1
+    } // encode_v1
+
+    #[test]
+    fn versioned_header() {
+        assert_eq!(body(b"ZACL\x01policy"), Ok((1, &b"policy"[..])));
+        assert_eq!(body(b"ZACL\x02policy"), Ok((2, &b"policy"[..])));
+        assert_eq!(body(b"ZACL\x03policy"), Err(Error::SchemaVersion(3)));
+        assert_eq!(body(b"ZACL\x00policy"), Err(Error::Parse(String::from("invalid policy format version 0"))));
+        assert_eq!(body(b"{}"), Err(Error::Parse(String::from("missing policy header"))));
+    } // versioned_header
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode() {
+        let acl   = setup_acl();
+        let bytes = acl.to_bincode();
+
+        assert!(bytes.len() < acl.to_json().len());
+        assert_eq!(Acl::from_bincode(&bytes).unwrap().to_json(), acl.to_json());
+        assert!(Acl::from_bincode(&bytes[..bytes.len() - 1]).is_err());
+
+        // version 1 lacks conditions, environments, deny messages and the default role
+        let mut bytes = MAGIC.to_vec();
+
+        bytes.push(1);
+        bincode::serialize_into(&mut bytes, &encode_v1(&acl)).unwrap();
+
+        let migrated = Acl::from_bincode(&bytes).unwrap();
+
+        assert!(migrated.is_denied(Some("staff"), Some("latest"), Some("revise")));
+        assert_eq!(migrated.default_role(), None);
+        assert!(migrated.get_rule_meta(Some("staff"), Some("latest"), Some("revise")).unwrap().message.is_none());
+    } // bincode
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor() {
+        let acl   = setup_acl();
+        let bytes = acl.to_cbor();
+
+        assert_eq!(Acl::from_cbor(&bytes).unwrap().to_json(), acl.to_json());
+        assert!(Acl::from_cbor(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Acl::from_cbor(&header()).is_err());
+
+        let mut bytes = MAGIC.to_vec();
+
+        bytes.push(1);
+        ciborium::into_writer(&encode_v1(&acl), &mut bytes).unwrap();
+        assert_eq!(Acl::from_cbor(&bytes).unwrap().get_rule_meta(Some("staff"), Some("latest"), Some("revise")).unwrap().ticket,
+            Some(String::from("CR-42")));
+    } // cbor
+
+} // mod tests
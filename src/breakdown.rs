@@ -0,0 +1,126 @@
+//! Permission breakdowns by ancestor.
+//!
+//! `role_permissions_breakdown` attributes the effective permissions of a role on a resource to
+//! the roles of its lineage: each privilege registered or named by rules or resource defaults, and
+//! the wildcard privilege, is decided like `decide` and credited to the role whose rule decides
+//! it. Rules for all roles, including the catch-all rule, are credited to the wildcard role, which
+//! follows the lineage. Reviewers thus see which inherited role is responsible for each grant and
+//! denial.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Access, Acl};
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("staff", vec!["guest"]).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//! acl.allow(Some("staff"), None, Some("edit")).unwrap();
+//!
+//! let breakdown = acl.role_permissions_breakdown("staff", None).unwrap();
+//!
+//! assert_eq!(breakdown[0].role, Some("staff"));
+//! assert_eq!(breakdown[0].privileges, vec![(Some("edit"), Access::Allow)]);
+//! assert_eq!(breakdown[1].privileges, vec![(Some("view"), Access::Allow)]);
+//! ```
+
+use crate::{Access, Acl, Error, Privilege, Resource, Role};
+use log::trace;
+
+
+// Contribution ///////////////////////////////////////////////////////////////////////////////////
+
+
+/// The privileges a role of a lineage decides, see `Acl::role_permissions_breakdown`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Contribution {
+    /// the ancestor, None for rules of all roles
+    pub role:       Role,
+    /// the decided privileges with their access, the wildcard privilege first
+    pub privileges: Vec<(Privilege, Access)>,
+} // struct Contribution
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Returns the contribution of each role in the lineage of role, in search order, followed by
+    /// the contribution of the wildcard role, see module `breakdown`. Roles which decide nothing
+    /// are included. Returns an error if role or resource is undefined.
+    pub fn role_permissions_breakdown(&self, role: &'static str, resource: Resource) -> Result<Vec<Contribution>, Error> {
+        trace!("breaking down permissions of {} on {:?}", role, resource);
+        if !self.roles.contains_key(role) {
+            return Err(Error::MissingRole(String::from(role)));
+        } // if
+        if let Some(name) = resource.filter(|name| !self.resources.contains_key(name)) {
+            return Err(Error::MissingResource(String::from(name)));
+        } // if
+
+        let privileges = self.known_privileges();
+        let mut breakdown: Vec<Contribution> = self.get_role_lineage(role).into_iter().map(Some)
+            .chain(Some(None))
+            .map(|role| Contribution{role, privileges: vec![]})
+            .collect();
+
+        for privilege in Some(None).into_iter().chain(privileges.iter().map(|name| Some(*name))) {
+            let (matched, rule) = self.effective(Some(role), resource, privilege);
+
+            if let Some(contribution) = breakdown.iter_mut().find(|contribution| contribution.role == matched.role) {
+                contribution.privileges.push((privilege, rule.access()));
+            } // if
+        } // for
+        Ok(breakdown)
+    } // role_permissions_breakdown
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn breakdown() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("member", vec![]).is_ok());
+        assert!(acl.add_role("editor", vec!["guest", "member"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("guest"), Some("news"), Some("delete")).is_ok());
+        assert!(acl.allow(Some("member"), Some("news"), Some("comment")).is_ok());
+        assert!(acl.deny(Some("member"), Some("news"), Some("delete")).is_ok());
+        assert!(acl.allow(Some("editor"), Some("news"), Some("edit")).is_ok());
+
+        assert_eq!(acl.role_permissions_breakdown("editor", Some("news")), Ok(vec![
+            Contribution{role: Some("editor"), privileges: vec![(Some("edit"), Access::Allow)]},
+            Contribution{role: Some("member"), privileges: vec![(Some("comment"), Access::Allow), (Some("delete"), Access::Deny)]},
+            Contribution{role: Some("guest"), privileges: vec![(Some("view"), Access::Allow)]},
+            Contribution{role: None, privileges: vec![(None, Access::Deny)]},
+        ]));
+
+        // the parent order decides which ancestor is credited
+        acl.set_parent_order(crate::ParentOrder::Fifo);
+        assert_eq!(acl.role_permissions_breakdown("editor", Some("news")).unwrap()[1], Contribution{
+            role:       Some("guest"),
+            privileges: vec![(Some("delete"), Access::Allow), (Some("view"), Access::Allow)],
+        });
+        assert_eq!(acl.role_permissions_breakdown("guest", None), Ok(vec![
+            Contribution{role: Some("guest"), privileges: vec![(Some("view"), Access::Allow)]},
+            Contribution{role: None, privileges: vec![(None, Access::Deny), (Some("comment"), Access::Deny),
+                (Some("delete"), Access::Deny), (Some("edit"), Access::Deny)]},
+        ]));
+
+        assert_eq!(acl.role_permissions_breakdown("nobody", None), Err(Error::MissingRole(String::from("nobody"))));
+        assert_eq!(acl.role_permissions_breakdown("guest", Some("blog")), Err(Error::MissingResource(String::from("blog"))));
+    } // breakdown
+
+} // mod tests
@@ -0,0 +1,347 @@
+//! Circuit breakers around role and resource providers.
+//!
+//! Providers backed by external services, e.g. an identity service, may be slow or unavailable.
+//! `GuardedRoleProvider` and `GuardedResourceProvider` wrap a fallible lookup in a
+//! `CircuitBreaker`: a lookup taking longer than the timeout or returning an error is a failure
+//! and retried up to `retries` times. After `threshold` consecutive failures the circuit opens and
+//! lookups fail immediately for the cool-down, afterwards a single trial lookup decides whether
+//! the circuit closes again.
+//!
+//! While a lookup fails, queries of the role or resource are decided by the fallback access of
+//! the breaker, i.e. they fail closed with `Access::Deny` or fail open with `Access::Allow`. Such
+//! decisions are not cached and neither is the failed lookup. Lookups are not interrupted, a
+//! lookup exceeding the timeout is waited for and its result discarded.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Acl, Error};
+//! # use zorq_acl::breaker::{BreakerConfig, GuardedRoleProvider};
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.allow(Some("staff"), None, Some("edit")).unwrap();
+//! acl.set_role_provider(GuardedRoleProvider::new(BreakerConfig::default(), |name: &'static str| {
+//!     // ask the identity service, which is down
+//!     Err(Error::Io(format!("identity service unavailable for {}", name)))
+//! }));
+//!
+//! assert!(acl.is_denied(Some("user:sally"), None, Some("edit")));
+//! ```
+
+use crate::provider::{ResourceProvider, RoleProvider};
+use crate::{Access, Error};
+use log::{trace, warn};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+
+// BreakerConfig //////////////////////////////////////////////////////////////////////////////////
+
+
+/// Configures a `CircuitBreaker`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BreakerConfig {
+    /// the time after which a call is a failure, None for no timeout
+    pub timeout:   Option<Duration>,
+    /// the number of retries of a failed call
+    pub retries:   u32,
+    /// the number of consecutive failed calls which open the circuit
+    pub threshold: u32,
+    /// the time the circuit stays open before a trial call
+    pub cool_down: Duration,
+    /// the access of queries decided while calls fail
+    pub fallback:  Access,
+} // struct BreakerConfig
+
+impl Default for BreakerConfig {
+
+    /// Returns a configuration without timeout and retries, which opens after 5 failures for 30
+    /// seconds and fails closed.
+    fn default() -> Self {
+        BreakerConfig{
+            timeout:   None,
+            retries:   0,
+            threshold: 5,
+            cool_down: Duration::from_secs(30),
+            fallback:  Access::Deny,
+        }
+    } // default
+
+} // impl Default for BreakerConfig
+
+
+// CircuitBreaker /////////////////////////////////////////////////////////////////////////////////
+
+
+/// Guards calls to an external service, see module `breaker`.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config:   BreakerConfig,
+    failures: Cell<u32>,
+    opened:   Cell<Option<Instant>>,
+} // struct CircuitBreaker
+
+impl CircuitBreaker {
+
+    /// Creates a closed circuit breaker.
+    pub fn new(config: BreakerConfig) -> Self {
+        CircuitBreaker{config, failures: Cell::new(0), opened: Cell::new(None)}
+    } // new
+
+    /// Returns the configuration.
+    #[inline]
+    pub fn config(&self) -> &BreakerConfig {
+        &self.config
+    } // config
+
+    /// Returns true if calls currently fail without being made, i.e. the circuit is open and the
+    /// cool-down has not passed.
+    pub fn is_open(&self) -> bool {
+        self.opened.get().is_some_and(|opened| opened.elapsed() < self.config.cool_down)
+    } // is_open
+
+    /// Calls f with retries. Returns its result, or None if all attempts failed or the circuit is
+    /// open.
+    pub fn call<T, F: Fn() -> Result<T, Error>>(&self, f: F) -> Option<T> {
+        if self.is_open() {
+            trace!("circuit open, skipping call");
+            return None;
+        } // if
+        // a trial call after the cool-down isn't retried
+        let attempts = if self.opened.get().is_some() { 1 } else { self.config.retries + 1 };
+
+        for attempt in 0..attempts {
+            let start  = Instant::now();
+            let result = f();
+
+            match result {
+                Ok(_) if self.config.timeout.is_some_and(|timeout| start.elapsed() > timeout) => {
+                    warn!("call timed out after {:?}, attempt {}", start.elapsed(), attempt + 1);
+                }, // Ok
+                Ok(value) => {
+                    self.failures.set(0);
+                    self.opened.set(None);
+                    return Some(value);
+                }, // Ok
+                Err(error) => warn!("call failed, attempt {}: {}", attempt + 1, error),
+            } // match
+        } // for
+
+        let failures = self.failures.get() + 1;
+
+        self.failures.set(failures);
+        if self.opened.get().is_some() || failures >= self.config.threshold {
+            warn!("opening circuit after {} failures", failures);
+            self.opened.set(Some(Instant::now()));
+        } // if
+        None
+    } // call
+
+} // impl CircuitBreaker
+
+
+// GuardedRoleProvider ////////////////////////////////////////////////////////////////////////////
+
+
+/// A role provider whose lookups are guarded by a circuit breaker.
+pub struct GuardedRoleProvider<F> {
+    breaker: CircuitBreaker,
+    lookup:  F,
+} // struct GuardedRoleProvider
+
+impl<F: Fn(&'static str) -> Result<Option<Vec<&'static str>>, Error>> GuardedRoleProvider<F> {
+
+    /// Guards lookup, which returns the parents of a role like `RoleProvider::parents`.
+    pub fn new(config: BreakerConfig, lookup: F) -> Self {
+        GuardedRoleProvider{breaker: CircuitBreaker::new(config), lookup}
+    } // new
+
+    /// Returns the circuit breaker.
+    #[inline]
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    } // breaker
+
+} // impl GuardedRoleProvider
+
+impl<F: Fn(&'static str) -> Result<Option<Vec<&'static str>>, Error>> RoleProvider for GuardedRoleProvider<F> {
+
+    fn parents(&self, role: &'static str) -> Option<Vec<&'static str>> {
+        self.try_parents(role).unwrap_or(None)
+    } // parents
+
+    fn try_parents(&self, role: &'static str) -> Result<Option<Vec<&'static str>>, Access> {
+        self.breaker.call(|| (self.lookup)(role)).ok_or(self.breaker.config.fallback)
+    } // try_parents
+
+} // impl RoleProvider for GuardedRoleProvider
+
+
+// GuardedResourceProvider ////////////////////////////////////////////////////////////////////////
+
+
+/// A resource provider whose lookups are guarded by a circuit breaker.
+pub struct GuardedResourceProvider<F> {
+    breaker: CircuitBreaker,
+    lookup:  F,
+} // struct GuardedResourceProvider
+
+impl<F: Fn(&'static str) -> Result<Option<Option<&'static str>>, Error>> GuardedResourceProvider<F> {
+
+    /// Guards lookup, which returns the parent of a resource like `ResourceProvider::parent`.
+    pub fn new(config: BreakerConfig, lookup: F) -> Self {
+        GuardedResourceProvider{breaker: CircuitBreaker::new(config), lookup}
+    } // new
+
+    /// Returns the circuit breaker.
+    #[inline]
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    } // breaker
+
+} // impl GuardedResourceProvider
+
+impl<F: Fn(&'static str) -> Result<Option<Option<&'static str>>, Error>> ResourceProvider for GuardedResourceProvider<F> {
+
+    fn parent(&self, resource: &'static str) -> Option<Option<&'static str>> {
+        self.try_parent(resource).unwrap_or(None)
+    } // parent
+
+    fn try_parent(&self, resource: &'static str) -> Result<Option<Option<&'static str>>, Access> {
+        self.breaker.call(|| (self.lookup)(resource)).ok_or(self.breaker.config.fallback)
+    } // try_parent
+
+} // impl ResourceProvider for GuardedResourceProvider
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Acl;
+    use std::rc::Rc;
+    use test_env_log::test;
+
+    fn failing(calls: &Cell<u32>) -> Result<u32, Error> {
+        calls.set(calls.get() + 1);
+        Err(Error::Io(String::from("unavailable")))
+    } // failing
+
+    #[test]
+    fn breaker() {
+        let calls   = Cell::new(0);
+        let breaker = CircuitBreaker::new(BreakerConfig{retries: 2, threshold: 2, cool_down: Duration::from_millis(50),
+            ..BreakerConfig::default()});
+
+        assert_eq!(breaker.call(|| failing(&calls)), None);
+        assert_eq!(calls.get(), 3);
+        assert!(!breaker.is_open());
+        assert_eq!(breaker.call(|| failing(&calls)), None);
+        assert_eq!(calls.get(), 6);
+        assert!(breaker.is_open());
+
+        // open circuits skip calls
+        assert_eq!(breaker.call(|| failing(&calls)), None);
+        assert_eq!(calls.get(), 6);
+
+        // a failed trial reopens the circuit, a successful one closes it
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!breaker.is_open());
+        assert_eq!(breaker.call(|| failing(&calls)), None);
+        assert_eq!(calls.get(), 7);
+        assert!(breaker.is_open());
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.call(|| Ok(1)), Some(1));
+        assert!(!breaker.is_open());
+        assert_eq!(breaker.call(|| failing(&calls)), None);
+        assert!(!breaker.is_open());
+    } // breaker
+
+    #[test]
+    fn timeout() {
+        let breaker = CircuitBreaker::new(BreakerConfig{timeout: Some(Duration::from_millis(10)), threshold: 1,
+            ..BreakerConfig::default()});
+
+        assert_eq!(breaker.call(|| Ok(1)), Some(1));
+        assert_eq!(breaker.call(|| {
+            std::thread::sleep(Duration::from_millis(20));
+            Ok(2)
+        }), None);
+        assert!(breaker.is_open());
+    } // timeout
+
+    #[test]
+    fn fallback() {
+        let up      = Rc::new(Cell::new(false));
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("documents", None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("documents"), Some("edit")).is_ok());
+        assert!(acl.deny(None, None, Some("delete")).is_ok());
+
+        let status = up.clone();
+        acl.set_role_provider(GuardedRoleProvider::new(BreakerConfig{threshold: 1, cool_down: Duration::from_secs(0),
+            ..BreakerConfig::default()}, move |name: &'static str| {
+            if !status.get() {
+                return Err(Error::Io(String::from("unavailable")));
+            } // if
+            Ok(if name.starts_with("user:") { Some(vec!["staff"]) } else { None })
+        }));
+
+        // fail closed, without caching the lookup or the decision
+        assert!(acl.is_denied(Some("user:sally"), Some("documents"), Some("edit")));
+        up.set(true);
+        assert!(acl.is_allowed(Some("user:sally"), Some("documents"), Some("edit")));
+        assert!(acl.is_denied(Some("user:sally"), Some("documents"), Some("delete")));
+
+        // fail open, resolved roles are cached
+        let status = up.clone();
+        acl.set_resource_provider(GuardedResourceProvider::new(BreakerConfig{fallback: Access::Allow,
+            ..BreakerConfig::default()}, move |name: &'static str| {
+            if !status.get() {
+                return Err(Error::Io(String::from("unavailable")));
+            } // if
+            Ok(if name.starts_with("document:") { Some(Some("documents")) } else { None })
+        }));
+        up.set(false);
+        assert!(acl.is_allowed(Some("user:sally"), Some("document:1"), Some("delete")));
+        up.set(true);
+        assert!(acl.is_denied(Some("user:sally"), Some("document:1"), Some("delete")));
+        assert!(acl.is_allowed(Some("user:sally"), Some("document:1"), Some("edit")));
+    } // fallback
+
+    #[test]
+    fn warm() {
+        let up      = Rc::new(Cell::new(false));
+        let status  = up.clone();
+        let edit    = crate::Query{resource: Some("documents"), role: Some("user:sally"), privilege: Some("edit")};
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("documents", None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("documents"), Some("edit")).is_ok());
+        assert!(acl.deny(None, Some("documents"), None).is_ok());
+        acl.set_role_provider(GuardedRoleProvider::new(BreakerConfig{threshold: 1, cool_down: Duration::from_secs(0),
+            ..BreakerConfig::default()}, move |name: &'static str| {
+            if !status.get() {
+                return Err(Error::Io(String::from("unavailable")));
+            } // if
+            Ok(if name.starts_with("user:") { Some(vec!["staff"]) } else { None })
+        }));
+        acl.lock();
+
+        // decisions searched while the provider fails aren't cached
+        assert_eq!(acl.warm_cache(vec![edit]), 0);
+        assert_eq!(acl.cache_stats().entries, 0);
+        up.set(true);
+        assert!(acl.is_allowed(Some("user:sally"), Some("documents"), Some("edit")));
+        acl.purge_cache();
+        assert_eq!(acl.warm_cache(vec![edit]), 1);
+        assert!(acl.is_allowed(Some("user:sally"), Some("documents"), Some("edit")));
+    } // warm
+
+} // mod tests
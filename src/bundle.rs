@@ -0,0 +1,279 @@
+//! Named privilege bundles.
+//!
+//! A bundle names a set of privileges, e.g. "editor-suite" for `edit`, `submit` and `revise`, so
+//! policy authors manage meaningful groupings instead of repeating long privilege lists. Setting
+//! or removing a rule for a bundle name, e.g. by `Acl::allow`, sets or removes the rules of its
+//! privileges. The bundle rule is remembered: redefining the bundle adds the rules of privileges
+//! joining it and removes the rules of privileges leaving it, unless another bundle rule for the
+//! same role and resource still includes them. Removing a bundle removes its rules.
+//!
+//! Bundles are expanded when rules are set, queries name privileges. The rules of a bundle are
+//! ordinary rules, e.g. they may be removed one by one, and rules defined for a bundled privilege
+//! directly are shared with the bundle. A bundle can't be named like a registered privilege or
+//! include bundles, and its rules can't be grouped, see module `group`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("editor", vec![]).unwrap();
+//! acl.add_resource("articles", None).unwrap();
+//! acl.define_bundle("editor-suite", &["edit", "submit"]).unwrap();
+//! acl.allow(Some("editor"), Some("articles"), Some("editor-suite")).unwrap();
+//! assert!(acl.is_allowed(Some("editor"), Some("articles"), Some("submit")));
+//!
+//! acl.define_bundle("editor-suite", &["edit", "revise"]).unwrap();
+//! assert!(acl.is_allowed(Some("editor"), Some("articles"), Some("revise")));
+//! assert!(acl.is_denied(Some("editor"), Some("articles"), Some("submit")));
+//! ```
+
+use crate::{Access, Acl, Error, Operation, Query, Resource, Role, Rule};
+use log::trace;
+
+impl Acl {
+
+    /// Defines the bundle of privileges by name, see module `bundle`. Replaces the privileges
+    /// defined before and updates the rules set for the bundle. Returns an error if name is a
+    /// registered privilege, a privilege is a bundle or unregistered while any are registered, see
+    /// module `domain`, or the `Acl` is locked.
+    pub fn define_bundle(&mut self, name: &'static str, privileges: &[&'static str]) -> Result<(), Error> {
+        trace!("defining bundle {} of {:?}", name, privileges);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if self.privileges.contains(name) {
+            return Err(Error::NotPermitted(format!("bundle named like privilege {}", name)));
+        } // if
+
+        let mut members: Vec<&'static str> = vec![];
+
+        for privilege in privileges {
+            if self.bundles.contains_key(privilege) || *privilege == name {
+                return Err(Error::NotPermitted(format!("bundle {} in bundle {}", privilege, name)));
+            } // if
+            self.check_privilege(None, Some(privilege))?;
+            if !members.contains(privilege) {
+                members.push(privilege);
+            } // if
+        } // for
+        self.sync_bundle(name, members)
+    } // define_bundle
+
+    /// Removes the bundle and its rules. Returns true if the bundle was defined. Returns an error
+    /// if the `Acl` is locked.
+    pub fn remove_bundle(&mut self, name: &str) -> Result<bool, Error> {
+        trace!("removing bundle {}", name);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let name = match self.bundles.get_key_value(name) {
+            Some((name, _)) => *name,
+            None            => return Ok(false),
+        }; // match
+
+        self.sync_bundle(name, vec![])?;
+        self.bundles.remove(name);
+        self.bundle_rules.retain(|query, _| query.privilege != Some(name));
+        Ok(true)
+    } // remove_bundle
+
+    /// Returns true if a bundle is defined by name.
+    #[inline]
+    pub fn has_bundle(&self, name: &str) -> bool {
+        self.bundles.contains_key(name)
+    } // has_bundle
+
+    /// Returns the privileges of the bundle in the order defined.
+    pub fn get_bundle(&self, name: &str) -> Option<&[&'static str]> {
+        self.bundles.get(name).map(Vec::as_slice)
+    } // get_bundle
+
+    /// Returns an iterator over the names of all bundles in ascending order.
+    pub fn bundles(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.bundles.keys().copied()
+    } // bundles
+
+    /// Returns the access set for the bundle for role on resource.
+    pub fn get_bundle_rule(&self, role: Role, resource: Resource, bundle: &'static str) -> Option<Access> {
+        self.bundle_rules.get(&Query{resource, role, privilege: Some(bundle)}).copied()
+    } // get_bundle_rule
+
+    /// Adds or removes the rules of the privileges of bundle like `set_rule_op`. Role and resource
+    /// are checked by the caller.
+    pub(crate) fn set_bundle_rule_op(&mut self, operation: Operation, role: Role, resource: Resource, bundle: &'static str, access: Access) -> Result<usize, Error> {
+        let members = self.bundles[bundle].clone();
+        let query   = Query{resource, role, privilege: Some(bundle)};
+
+        match operation {
+            Operation::Add    => {
+                let rules: Vec<Query> = members.iter().map(|privilege| Query{privilege: Some(privilege), ..query}).collect();
+
+                for privilege in &members {
+                    self.check_privilege(resource, Some(privilege))?;
+                } // for
+                self.check_rules_limits(&rules, 0)?;
+                for member in rules {
+                    self.insert_rule(member, Rule{acc: access, cond: None});
+                } // for
+                self.bundle_rules.insert(query, access);
+                Ok(members.len())
+            }, // Add
+            Operation::Remove => {
+                self.bundle_rules.retain(|other, other_access| *other_access != access
+                    || other.privilege != query.privilege
+                    || role.is_some_and(|role| other.role != Some(role))
+                    || resource.is_some_and(|resource| other.resource != Some(resource)));
+
+                let mut removed = 0;
+
+                for privilege in members {
+                    removed += self.set_rule_op(Operation::Remove, role, resource, Some(privilege), access)?;
+                } // for
+                Ok(removed)
+            }, // Remove
+        } // match
+    } // set_bundle_rule_op
+
+    /// Replaces the privileges of bundle by members and updates the rules set for the bundle.
+    fn sync_bundle(&mut self, name: &'static str, members: Vec<&'static str>) -> Result<(), Error> {
+        let previous = self.bundles.get(name).cloned().unwrap_or_default();
+        let rules: Vec<(Query, Access)> = self.bundle_rules.iter()
+            .filter(|(query, _)| query.privilege == Some(name))
+            .map(|(query, access)| (*query, *access))
+            .collect();
+        let joining: Vec<&'static str> = members.iter().copied().filter(|privilege| !previous.contains(privilege)).collect();
+
+        for (query, _) in &rules {
+            for privilege in &joining {
+                self.check_privilege(query.resource, Some(privilege))?;
+            } // for
+        } // for
+        let mut removed = vec![];
+        let mut added   = vec![];
+
+        for (query, access) in rules {
+            for privilege in &previous {
+                let member = Query{privilege: Some(privilege), ..query};
+
+                if members.contains(privilege) || self.rules.get(&member).is_none_or(|rule| rule.acc != access) {
+                    continue;
+                } // if
+                // keep rules still included by another bundle rule
+                let shared = self.bundle_rules.iter().any(|(other, other_access)| {
+                    other.privilege != Some(name) && other.role == query.role && other.resource == query.resource
+                        && *other_access == access
+                        && other.privilege.and_then(|bundle| self.bundles.get(bundle)).is_some_and(|bundle| bundle.contains(privilege))
+                }); // any
+
+                if !shared {
+                    removed.push(member);
+                } // if
+            } // for
+            for privilege in &joining {
+                added.push((Query{privilege: Some(privilege), ..query}, access));
+            } // for
+        } // for
+        let queries: Vec<Query> = added.iter().map(|(query, _)| *query).collect();
+
+        // all limits are checked before any rule changes
+        self.check_rules_limits(&queries, removed.len())?;
+        for member in &removed {
+            self.remove_rule(member);
+            self.meta.remove(member);
+        } // for
+        for (member, access) in added {
+            self.insert_rule(member, Rule{acc: access, cond: None});
+        } // for
+        self.bundles.insert(name, members);
+        Ok(())
+    } // sync_bundle
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn bundle() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("editor", vec![]).is_ok());
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_resource("articles", None).is_ok());
+        assert!(acl.add_resource("drafts", Some("articles")).is_ok());
+        assert!(acl.define_bundle("editor-suite", &["edit", "submit", "revise", "edit"]).is_ok());
+        assert!(acl.define_bundle("reader-suite", &["view", "revise"]).is_ok());
+        assert_eq!(acl.get_bundle("editor-suite"), Some(&["edit", "submit", "revise"][..]));
+
+        // rules for bundles are expanded
+        assert!(acl.allow(Some("editor"), Some("articles"), Some("editor-suite")).is_ok());
+        assert!(acl.allow(Some("editor"), Some("articles"), Some("reader-suite")).is_ok());
+        assert!(acl.deny(Some("editor"), Some("drafts"), Some("editor-suite")).is_ok());
+        assert!(acl.is_allowed(Some("editor"), Some("articles"), Some("submit")));
+        assert!(acl.is_denied(Some("editor"), Some("drafts"), Some("edit")));
+        assert!(acl.is_allowed(Some("editor"), Some("drafts"), Some("view")));
+        assert_eq!(acl.get_bundle_rule(Some("editor"), Some("drafts"), "editor-suite"), Some(Access::Deny));
+
+        // redefining keeps the rules in sync
+        assert!(acl.define_bundle("editor-suite", &["edit", "revise", "publish"]).is_ok());
+        assert!(acl.is_allowed(Some("editor"), Some("articles"), Some("publish")));
+        assert!(acl.is_denied(Some("editor"), Some("drafts"), Some("publish")));
+        assert!(acl.is_denied(Some("editor"), Some("articles"), Some("submit")));
+        assert!(acl.define_bundle("editor-suite", &["edit"]).is_ok());
+        assert!(acl.is_allowed(Some("editor"), Some("articles"), Some("revise")));
+        assert!(acl.is_allowed(Some("editor"), Some("drafts"), Some("revise")));
+
+        // removing rules for bundles and bundles
+        assert_eq!(acl.remove_deny(Some("editor"), None, Some("editor-suite")), Ok(1));
+        assert_eq!(acl.get_bundle_rule(Some("editor"), Some("drafts"), "editor-suite"), None);
+        assert!(acl.define_bundle("editor-suite", &["edit", "publish"]).is_ok());
+        assert!(acl.is_allowed(Some("editor"), Some("drafts"), Some("publish")));
+        assert_eq!(acl.remove_bundle("editor-suite"), Ok(true));
+        assert!(acl.is_denied(Some("editor"), Some("articles"), Some("edit")));
+        assert!(acl.is_allowed(Some("editor"), Some("articles"), Some("view")));
+        assert_eq!(acl.bundles().collect::<Vec<_>>(), vec!["reader-suite"]);
+        assert_eq!(acl.remove_bundle("editor-suite"), Ok(false));
+
+        assert!(acl.define_bundle("nested", &["reader-suite"]).is_err());
+        assert!(acl.group("beta", |group| group.allow(Some("guest"), None, Some("reader-suite"))).is_err());
+        acl.add_privilege("view");
+        assert_eq!(acl.define_bundle("viewer", &["view", "print"]), Err(Error::MissingPrivilege(String::from("print"))));
+        assert!(acl.define_bundle("view", &[]).is_err());
+        acl.lock();
+        assert_eq!(acl.define_bundle("viewer", &["view"]), Err(Error::Locked));
+    } // bundle
+
+    #[test]
+    fn limits() {
+        let mut acl = Acl::new();
+
+        acl.set_limits(crate::limits::Limits{max_rules: Some(3), ..Default::default()});
+        assert!(acl.add_role("editor", vec![]).is_ok());
+        assert!(acl.add_resource("articles", None).is_ok());
+        assert!(acl.define_bundle("suite", &["view", "edit"]).is_ok());
+        assert!(acl.define_bundle("wide", &["view", "edit", "print"]).is_ok());
+        assert!(acl.allow(Some("editor"), Some("articles"), Some("suite")).is_ok());
+
+        // all limits are checked before any rule changes
+        assert_eq!(acl.allow(Some("editor"), None, Some("wide")), Err(Error::TooManyRules(3)));
+        assert_eq!(acl.get_bundle_rule(Some("editor"), None, "wide"), None);
+        assert!(acl.is_denied(Some("editor"), None, Some("view")));
+        assert_eq!(acl.define_bundle("suite", &["view", "edit", "print", "share"]), Err(Error::TooManyRules(3)));
+        assert_eq!(acl.get_bundle("suite"), Some(&["view", "edit"][..]));
+        assert!(acl.is_denied(Some("editor"), Some("articles"), Some("print")));
+
+        // removed members make room for joining ones
+        assert!(acl.define_bundle("suite", &["view", "print", "share"]).is_ok());
+        assert!(acl.is_allowed(Some("editor"), Some("articles"), Some("share")));
+        assert!(acl.is_denied(Some("editor"), Some("articles"), Some("edit")));
+    } // limits
+
+} // mod tests
@@ -0,0 +1,390 @@
+//! Inspection of the decision cache.
+//!
+//! A locked `Acl` caches decisions which required a search by precedence, see `Acl::lock`.
+//! `cache_stats` reports the number of cached decisions, hits and misses since the `Acl` has been
+//! created and the number of decisions evicted by purging the cache. `cache_entries` lists the
+//! cached decisions, `purge_cache` empties the cache without unlocking the `Acl` and `evict_cache`
+//! drops only the decisions a changed rule may affect.
+//!
+//! After locking, `warm_cache` and `warm_cache_full` populate the cache in advance, so the first
+//! queries after a deployment don't pay for the search by precedence.
+//!
+//! Decisions are cached by the normalized query, see `Acl::normalize_query`, so that equivalent
+//! queries, e.g. without role and for the default role, share their cached decision.
+//!
+//! Every mutation which may change a decision advances the generation of the policy. Cached
+//! decisions are stamped with the generation they were made in and ignored once stale, so a
+//! mutation can't be served outdated decisions even if the cache isn't purged.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, None).unwrap();
+//! acl.lock();
+//!
+//! assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+//! assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+//!
+//! let stats = acl.cache_stats();
+//!
+//! assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 1));
+//! ```
+
+use crate::{Acl, Decision, Privilege, Query, Resource, Role, Rule};
+use log::trace;
+use std::collections::BTreeSet;
+
+
+// CacheStats /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Statistics of the decision cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// the number of cached decisions
+    pub entries:   usize,
+    /// the number of decisions served by the cache
+    pub hits:      u64,
+    /// the number of decisions missing the cache
+    pub misses:    u64,
+    /// the number of cached decisions dropped by purging the cache
+    pub evictions: u64,
+} // struct CacheStats
+
+impl CacheStats {
+
+    /// Returns the ratio of hits to lookups, or 0 without lookups.
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+
+        if lookups == 0 {
+            return 0.0;
+        } // if
+        self.hits as f64 / lookups as f64
+    } // hit_ratio
+
+} // impl CacheStats
+
+impl Acl {
+
+    /// Returns the statistics of the decision cache. The number of entries is 0 if the `Acl` is
+    /// unlocked.
+    pub fn cache_stats(&self) -> CacheStats {
+        let mut stats = self.cache_stats.get();
+
+        stats.entries = self.lock.as_ref()
+            .map(|cache| cache.borrow().values().filter(|entry| entry.2 == self.generation).count())
+            .unwrap_or(0);
+        stats
+    } // cache_stats
+
+    /// Resets the hits, misses and evictions of the statistics.
+    pub fn reset_cache_stats(&self) {
+        self.cache_stats.set(CacheStats::default());
+    } // reset_cache_stats
+
+    /// Returns the generation of the policy, which advances with every mutation which may change
+    /// a decision. Generations of different `Acl`s aren't comparable, see `fingerprint` instead.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    } // generation
+
+    /// Advances the generation of the policy, so all cached decisions are stale.
+    pub(crate) fn advance_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    } // advance_generation
+
+    /// Returns the cached decision of query, unless it is stale.
+    pub(crate) fn cached(&self, query: &Query) -> Option<(Query, Rule)> {
+        let cache = self.lock.as_ref()?.borrow();
+
+        cache.get(query)
+            .filter(|(_, _, generation)| *generation == self.generation)
+            .map(|(matched, rule, _)| (*matched, *rule))
+    } // cached
+
+    /// Caches the decision of query stamped with the current generation.
+    pub(crate) fn cache_decision(&self, query: Query, matched: Query, rule: Rule) {
+        if let Some(cache) = &self.lock {
+            cache.borrow_mut().insert(query, (matched, rule, self.generation));
+        } // if
+    } // cache_decision
+
+    /// Returns the cached decisions which aren't stale in arbitrary order.
+    pub fn cache_entries(&self) -> impl Iterator<Item = Decision> {
+        let entries: Vec<Decision> = match &self.lock {
+            Some(cache) => cache.borrow().iter()
+                .filter(|(_, (_, _, generation))| *generation == self.generation)
+                .map(|(query, (matched, rule, _))| Decision{query: *query, matched: *matched, rule: *rule, bypass: false})
+                .collect(),
+            None        => vec![],
+        }; // match
+
+        entries.into_iter()
+    } // cache_entries
+
+    /// Purges the cache without unlocking the `Acl`.
+    pub fn purge_cache(&self) {
+        if let Some(cache) = &self.lock {
+            let mut cache = cache.borrow_mut();
+            let mut stats = self.cache_stats.get();
+
+            trace!("purging {} cached decisions", cache.len());
+            stats.evictions += cache.len() as u64;
+            self.cache_stats.set(stats);
+            cache.clear();
+        } // if
+    } // purge_cache
+
+    /// Evicts the cached decisions a rule for role on resource to privilege may change, i.e. of
+    /// queries whose role and resource lineage contain role and resource, wildcards match all.
+    /// Returns the number of decisions evicted.
+    pub fn evict_cache(&self, role: Role, resource: Resource, privilege: Privilege) -> usize {
+        let cache = match &self.lock {
+            Some(cache) => cache,
+            None        => return 0,
+        }; // match
+        let affects = |query: &Query| {
+            let role_affected      = role.is_none_or(|name| query.role.is_some_and(|queried| self.get_role_lineage(queried).contains(&name)));
+            let resource_affected  = resource.is_none_or(|name| query.resource.is_some_and(|queried| self.get_resource_lineage(queried).contains(&name)));
+            // privilege specific denies also decide queries of all privileges in compat mode
+            let privilege_affected = privilege.is_none() || query.privilege.is_none() || query.privilege == privilege;
+
+            role_affected && resource_affected && privilege_affected
+        }; // affects
+        let mut cache = cache.borrow_mut();
+        let before    = cache.len();
+
+        cache.retain(|query, _| !affects(query));
+
+        let evicted   = before - cache.len();
+        let mut stats = self.cache_stats.get();
+
+        trace!("evicted {} cached decisions for {:?} on {:?} to {:?}", evicted, role, resource, privilege);
+        stats.evictions += evicted as u64;
+        self.cache_stats.set(stats);
+        evicted
+    } // evict_cache
+
+    /// Caches the decisions of queries. Returns the number of decisions cached, which is 0 if
+    /// the `Acl` is unlocked. Queries decided without a search by precedence, e.g. of bypass roles
+    /// or matching a rule directly, aren't cached. Queries without role are cached for the default
+    /// role, if set, like `decide` does. Warming doesn't count as hits or misses.
+    pub fn warm_cache<I: IntoIterator<Item = Query>>(&self, queries: I) -> usize {
+        if self.lock.is_none() {
+            return 0;
+        } // if
+        let mut warmed = 0;
+
+        self.warming.set(true);
+        for query in queries.into_iter().map(|query| self.normalize_query(query)) {
+            // decided like `decide`, which caches only decisions searched by precedence
+            if self.cached(&query).is_none() {
+                self.evaluate(query.role, query.resource, query.privilege);
+                if self.cached(&query).is_some() {
+                    warmed += 1;
+                } // if
+            } // if
+        } // for
+        self.warming.set(false);
+        trace!("warmed cache with {} decisions", warmed);
+        warmed
+    } // warm_cache
+
+    /// Caches the decisions of all combinations of defined roles, resources and privileges, each
+    /// including the wildcard. Privileges are the registered ones and those named by rules or
+    /// resource defaults. Returns the number of decisions cached.
+    pub fn warm_cache_full(&self) -> usize {
+        let mut privileges: BTreeSet<Option<&'static str>> = self.known_privileges().into_iter().map(Some).collect();
+
+        privileges.insert(None);
+
+        let resources: Vec<Option<&'static str>> = self.resources.keys().map(|name| Some(*name)).chain(Some(None)).collect();
+        let mut queries = vec![];
+
+        for role in self.roles.keys().map(|name| Some(*name)).chain(Some(None)) {
+            for resource in &resources {
+                for privilege in &privileges {
+                    queries.push(Query{resource: *resource, role, privilege: *privilege});
+                } // for
+            } // for
+        } // for
+        self.warm_cache(queries)
+    } // warm_cache_full
+
+    /// Counts a lookup of the cache.
+    pub(crate) fn count_cache(&self, hit: bool) {
+        // lookups while warming aren't queries
+        if self.warming.get() {
+            return;
+        } // if
+        let mut stats = self.cache_stats.get();
+
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        } // else
+        self.cache_stats.set(stats);
+        #[cfg(feature = "metrics")]
+        crate::metrics::cache(hit);
+    } // count_cache
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn stats() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        assert_eq!(acl.cache_stats(), CacheStats::default());
+
+        acl.lock();
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+        assert!(acl.is_denied(Some("staff"), None, Some("edit")));
+        assert_eq!(acl.cache_stats(), CacheStats{entries: 1, hits: 1, misses: 2, evictions: 0});
+        assert!((acl.cache_stats().hit_ratio() - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(acl.cache_entries().map(|decision| decision.to_string()).collect::<Vec<_>>(), vec!["ALLOW staff→*: view"]);
+        assert_eq!(acl.cache_entries().next().unwrap().matched.role, Some("guest"));
+
+        // purging keeps the acl locked
+        acl.purge_cache();
+        assert_eq!(acl.cache_stats(), CacheStats{entries: 0, hits: 1, misses: 2, evictions: 1});
+        assert!(acl.deny(Some("staff"), None, None).is_err());
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        acl.unlock();
+        assert_eq!(acl.cache_stats().evictions, 2);
+        acl.reset_cache_stats();
+        assert_eq!(acl.cache_stats(), CacheStats::default());
+    } // stats
+
+    #[test]
+    fn generation() {
+        let mut acl    = Acl::new();
+        let generation = acl.generation();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.generation() > generation);
+
+        // decisions of a previous generation are stale
+        acl.lock();
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("view")));
+        assert_eq!(acl.cache_stats().entries, 1);
+        assert!(acl.add_resource("news", None).is_ok());
+        assert_eq!(acl.cache_stats().entries, 0);
+        assert_eq!(acl.cache_entries().count(), 0);
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("view")));
+        assert_eq!(acl.cache_stats(), CacheStats{entries: 1, hits: 0, misses: 2, evictions: 0});
+
+        let generation = acl.generation();
+
+        acl.set_parent_order(crate::ParentOrder::DenyFirst);
+        assert_eq!(acl.generation(), generation + 1);
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("view")));
+    } // generation
+
+    #[test]
+    fn warm() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+
+        let staff = Query{resource: None, role: Some("staff"), privilege: Some("view")};
+
+        assert_eq!(acl.warm_cache(vec![staff]), 0);
+        acl.lock();
+        assert_eq!(acl.warm_cache(vec![staff, staff, Query{resource: None, role: Some("root"), privilege: None}]), 1);
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        assert_eq!(acl.cache_stats(), CacheStats{entries: 1, hits: 1, misses: 0, evictions: 0});
+
+        // warmed decisions equal the searched ones
+        acl.purge_cache();
+        assert_eq!(acl.warm_cache_full(), 3);
+
+        let warmed: Vec<Decision> = acl.cache_entries().collect();
+
+        acl.purge_cache();
+        for decision in warmed {
+            assert_eq!(acl.decide(decision.query.role, decision.query.resource, decision.query.privilege), decision);
+        } // for
+        assert_eq!(acl.cache_stats().hits, 1);
+
+        // queries without role are cached for the default role
+        acl.unlock();
+        assert!(acl.set_default_role("staff").is_ok());
+        acl.lock();
+        assert_eq!(acl.warm_cache(vec![Query{resource: None, role: None, privilege: Some("view")}]), 1);
+        assert_eq!(acl.cache_entries().map(|decision| decision.to_string()).collect::<Vec<_>>(), vec!["ALLOW staff→*: view"]);
+        assert!(acl.is_allowed(None, None, Some("view")));
+        assert_eq!(acl.cache_stats().entries, 1);
+    } // warm
+
+    #[test]
+    fn evict() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert_eq!(acl.evict_cache(None, None, None), 0);
+        acl.lock();
+        assert_eq!(acl.warm_cache_full(), 5);
+
+        // a rule for guest on news may change decisions of staff on latest, but not on the wildcard
+        assert_eq!(acl.evict_cache(Some("guest"), Some("news"), Some("edit")), 0);
+        assert_eq!(acl.evict_cache(Some("guest"), Some("news"), Some("view")), 4);
+        assert_eq!(acl.cache_entries().map(|decision| decision.to_string()).collect::<Vec<_>>(), vec!["ALLOW staff→*: view"]);
+        assert_eq!(acl.evict_cache(None, None, None), 1);
+        assert_eq!(acl.cache_stats(), CacheStats{entries: 0, hits: 0, misses: 0, evictions: 5});
+    } // evict
+
+    #[test]
+    fn normalize() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+
+        let anonymous = Query{resource: Some("news"), role: None, privilege: Some("view")};
+
+        assert_eq!(acl.normalize_query(anonymous), anonymous);
+        assert!(acl.set_default_role("guest").is_ok());
+        assert_eq!(acl.normalize_query(anonymous), Query{role: Some("guest"), ..anonymous});
+
+        // anonymous queries share the cached decision of the default role
+        acl.lock();
+        assert!(acl.is_allowed(None, Some("news"), Some("view")));
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("view")));
+        assert_eq!(acl.cache_stats(), CacheStats{entries: 1, hits: 1, misses: 1, evictions: 0});
+    } // normalize
+
+} // mod tests
@@ -0,0 +1,151 @@
+//! Chained policies.
+//!
+//! A `ChainedAcl` holds layered policies, e.g. the application policy, the organization policy
+//! and the global defaults. A query is answered by the first policy with a rule matching the
+//! query. If a policy resolves the query to its catch-all rule only, the query is retried against
+//! the next policy. If no policy matches, the catch-all rule of the last policy decides.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut application  = Acl::new();
+//! let mut organization = Acl::new();
+//!
+//! application.add_role("staff", vec![]).unwrap();
+//! application.deny(Some("staff"), None, Some("delete")).unwrap();
+//! organization.add_role("staff", vec![]).unwrap();
+//! organization.allow(Some("staff"), None, None).unwrap();
+//!
+//! let chain = application.with_fallback(organization);
+//!
+//! assert!(chain.is_denied (Some("staff"), None, Some("delete")));
+//! assert!(chain.is_allowed(Some("staff"), None, Some("view")));
+//! ```
+
+use crate::{Acl, Decision, Privilege, Query, Resource, Role};
+use log::trace;
+
+
+// ChainedAcl /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Holds policies in order of precedence. Queries are retried against the next policy if a policy
+/// resolves them to its catch-all rule only.
+pub struct ChainedAcl {
+    acls: Vec<Acl>,
+} // struct ChainedAcl
+
+impl ChainedAcl {
+
+    /// Creates a new `ChainedAcl` holding the policies in order of precedence. An empty chain
+    /// denies access.
+    pub fn new(acls: Vec<Acl>) -> Self {
+        ChainedAcl{acls}
+    } // new
+
+    /// Appends a policy with the lowest precedence.
+    pub fn with_fallback(mut self, acl: Acl) -> Self {
+        self.acls.push(acl);
+        self
+    } // with_fallback
+
+    /// Returns the policies in order of precedence.
+    #[inline]
+    pub fn acls(&self) -> &[Acl] {
+        &self.acls
+    } // acls
+
+    /// Returns the policies in order of precedence for modification.
+    #[inline]
+    pub fn acls_mut(&mut self) -> &mut [Acl] {
+        &mut self.acls
+    } // acls_mut
+
+    /// Returns the decision of the first policy with a rule matching the query, or the catch-all
+    /// decision of the last policy.
+    pub fn decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        let mut last = None;
+
+        for (i, acl) in self.acls.iter().enumerate() {
+            let decision = acl.decide(role, resource, privilege);
+
+            if decision.bypass || decision.matched != Query::ALL {
+                trace!("chained policy {} decided {}", i, decision);
+                return decision;
+            } // if
+            last = Some(decision);
+        } // for
+        last.unwrap_or_else(|| Acl::new().decide(role, resource, privilege))
+    } // decide
+
+    /// Returns true if privilege is allowed for role on resource.
+    #[inline]
+    pub fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_allowed()
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource.
+    #[inline]
+    pub fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_denied()
+    } // is_denied
+
+    /// Returns the policies in order of precedence.
+    pub fn into_inner(self) -> Vec<Acl> {
+        self.acls
+    } // into_inner
+
+} // impl ChainedAcl
+
+impl Acl {
+
+    /// Chains this policy with a fallback policy, which answers the queries this policy resolves
+    /// to its catch-all rule only.
+    pub fn with_fallback(self, acl: Acl) -> ChainedAcl {
+        ChainedAcl::new(vec![self, acl])
+    } // with_fallback
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn fallback() {
+        let mut application  = Acl::new();
+        let mut organization = Acl::new();
+        let mut global       = Acl::new();
+
+        for acl in [&mut application, &mut organization, &mut global].iter_mut() {
+            assert!(acl.add_role("guest", vec![]).is_ok());
+            assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+            assert!(acl.add_resource("news", None).is_ok());
+        } // for
+        assert!(application.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(organization.deny(Some("staff"), None, None).is_ok());
+        assert!(organization.allow(Some("guest"), None, Some("view")).is_ok());
+        global.set_laminas_compat(true);
+        assert!(global.allow(None, None, None).is_ok());
+
+        let chain = application.with_fallback(organization).with_fallback(global);
+
+        assert_eq!(chain.decide(Some("staff"), Some("news"), Some("edit")).to_string(), "ALLOW staff→news: edit");
+        assert_eq!(chain.decide(Some("staff"), Some("news"), Some("view")).matched.to_string(), "staff→*: *");
+        assert!(chain.is_allowed(Some("guest"), Some("news"), Some("view")));
+
+        // resolved by the catch-all rule of the last policy
+        assert_eq!(chain.decide(Some("guest"), Some("news"), Some("edit")).matched, Query::ALL);
+        assert!(chain.is_allowed(Some("guest"), Some("news"), Some("edit")));
+
+        assert!(ChainedAcl::new(vec![]).is_denied(None, None, None));
+        assert_eq!(chain.into_inner().len(), 3);
+    } // fallback
+
+} // mod tests
@@ -0,0 +1,171 @@
+//! Queries for several roles at once.
+//!
+//! A user usually holds several roles. `decide_any` decides a query for each role and combines
+//! the decisions by the `RoleCombination` of the `Acl`:
+//!
+//! * `AnyAllows` allows if any role is allowed,
+//! * `DenyOverrides`, the default, allows if any role is allowed, unless a deny rule defined for
+//!   one of the roles or its ancestors applies. Deny rules for all roles, including the catch-all
+//!   rule, don't override.
+//!
+//! A bypass role is allowed by either combination. Without roles the query is decided like a
+//! query without role, i.e. for the default role.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::combine::RoleCombination;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("author", vec![]).unwrap();
+//! acl.add_role("suspended", vec![]).unwrap();
+//! acl.allow(Some("author"), None, Some("publish")).unwrap();
+//! acl.deny(Some("suspended"), None, Some("publish")).unwrap();
+//!
+//! assert!(acl.is_any_allowed(&["author"], None, Some("publish")));
+//! assert!(acl.is_any_denied(&["author", "suspended"], None, Some("publish")));
+//!
+//! acl.set_role_combination(RoleCombination::AnyAllows);
+//! assert!(acl.is_any_allowed(&["author", "suspended"], None, Some("publish")));
+//! ```
+
+use crate::{Access, Acl, Decision, Privilege, Resource};
+use crate::etag::Item;
+use log::trace;
+
+
+// RoleCombination ////////////////////////////////////////////////////////////////////////////////
+
+
+/// Combines the decisions for several roles, see module `combine`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoleCombination {
+    /// allowed if any role is allowed
+    AnyAllows,
+    /// allowed if any role is allowed, unless a deny rule of any role or its ancestors applies
+    #[default]
+    DenyOverrides,
+} // enum RoleCombination
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Sets how the decisions of queries for several roles are combined, see module `combine`.
+    pub fn set_role_combination(&mut self, combination: RoleCombination) {
+        trace!("setting role combination to {:?}", combination);
+        // the default combination doesn't contribute to the fingerprint
+        if self.role_combination != RoleCombination::default() {
+            self.track(Item::RoleCombination(self.role_combination), false);
+        } // if
+        if combination != RoleCombination::default() {
+            self.track(Item::RoleCombination(combination), true);
+        } // if
+        self.role_combination = combination;
+    } // set_role_combination
+
+    /// Returns how the decisions of queries for several roles are combined.
+    #[inline]
+    pub fn role_combination(&self) -> RoleCombination {
+        self.role_combination
+    } // role_combination
+
+    /// Decides the query for each of roles and returns the deciding decision, see module
+    /// `combine`: the first bypass, the first overriding deny, the first allow or else the
+    /// decision for the first role. Only the returned decision is reported to the audit sink.
+    pub fn decide_any(&self, roles: &[&'static str], resource: Resource, privilege: Privilege) -> Decision {
+        trace!("getting rule for any of {:?} on {:?} to {:?}", roles, resource, privilege);
+        #[cfg(feature = "metrics")]
+        let start     = std::time::Instant::now();
+        #[cfg(feature = "otel")]
+        let span      = crate::otel::start(None, roles.first().copied(), resource, privilege);
+        let decisions: Vec<Decision> = roles.iter()
+            .map(|role| self.evaluate(Some(role), resource, privilege))
+            .collect();
+        let overrides = |decision: &&Decision| self.role_combination == RoleCombination::DenyOverrides
+            && decision.is_denied() && decision.matched.role.is_some();
+        let decision  = decisions.iter().find(|decision| decision.bypass)
+            .or_else(|| decisions.iter().find(overrides))
+            .or_else(|| decisions.iter().find(|decision| decision.rule.access() == Access::Allow))
+            .or_else(|| decisions.first())
+            .copied()
+            .unwrap_or_else(|| self.evaluate(None, resource, privilege));
+
+        self.count_hit(&decision);
+        #[cfg(feature = "metrics")]
+        crate::metrics::decision(&decision, start);
+        #[cfg(feature = "otel")]
+        crate::otel::end(span, &decision, self.fingerprint);
+        self.audit(None, &decision);
+        decision
+    } // decide_any
+
+    /// Returns true if privilege on resource is allowed for the combination of roles, see
+    /// `decide_any`.
+    #[inline]
+    pub fn is_any_allowed(&self, roles: &[&'static str], resource: Resource, privilege: Privilege) -> bool {
+        self.decide_any(roles, resource, privilege).is_allowed()
+    } // is_any_allowed
+
+    /// Returns true if privilege on resource is denied for the combination of roles, see
+    /// `decide_any`.
+    #[inline]
+    pub fn is_any_denied(&self, roles: &[&'static str], resource: Resource, privilege: Privilege) -> bool {
+        self.decide_any(roles, resource, privilege).is_denied()
+    } // is_any_denied
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Query;
+    use test_env_log::test;
+
+    #[test]
+    fn combination() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("author", vec!["guest"]).is_ok());
+        assert!(acl.add_role("suspended", vec![]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("author"), Some("news"), None).is_ok());
+        assert!(acl.deny(Some("suspended"), Some("news"), Some("edit")).is_ok());
+        assert!(acl.deny(None, Some("news"), Some("delete")).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+
+        assert_eq!(acl.role_combination(), RoleCombination::DenyOverrides);
+        assert!(acl.is_any_allowed(&["suspended", "author"], Some("news"), Some("view")));
+        assert_eq!(acl.decide_any(&["author", "suspended"], Some("news"), Some("edit")).matched,
+            Query{resource: Some("news"), role: Some("suspended"), privilege: Some("edit")});
+        assert!(acl.is_any_denied(&["author", "suspended"], Some("news"), Some("edit")));
+
+        // deny rules for all roles don't override, neither do inherited allows
+        assert!(acl.is_denied(Some("suspended"), Some("news"), Some("delete")));
+        assert!(acl.is_any_allowed(&["suspended", "author"], Some("news"), Some("delete")));
+        assert!(acl.is_any_allowed(&["author", "suspended"], None, Some("view")));
+
+        // bypass roles win, no roles decide for the default role
+        assert!(acl.is_any_allowed(&["suspended", "root"], Some("news"), Some("edit")));
+        assert!(acl.is_any_denied(&[], None, Some("view")));
+        assert!(acl.set_default_role("guest").is_ok());
+        assert!(acl.is_any_allowed(&[], None, Some("view")));
+        assert_eq!(acl.decide_any(&["suspended"], Some("news"), Some("view")).query.role, Some("suspended"));
+
+        acl.set_role_combination(RoleCombination::AnyAllows);
+        assert_eq!(acl.decide_any(&["suspended", "author"], Some("news"), Some("edit")).matched,
+            Query{resource: Some("news"), role: Some("author"), privilege: None});
+        assert!(acl.is_any_denied(&["suspended"], Some("news"), Some("edit")));
+    } // combination
+
+} // mod tests
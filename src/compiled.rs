@@ -0,0 +1,529 @@
+//! Compiled read-only policies.
+//!
+//! `Acl::compile` transforms a policy into a compact binary artifact, e.g. at build time from a
+//! policy document. A `CompiledAcl` decides queries directly on the bytes of the artifact, which
+//! may be a memory-mapped file or `include_bytes!`, so loading only checks the header and the size
+//! of the tables and takes constant time regardless of the size of the policy.
+//!
+//! The artifact consists of a header, a sorted table of interned names, the roles with their
+//! flattened lineage in search order, the resources with their flattened lineage and the rules
+//! sorted by resource, role and privilege. All integers are little endian `u32`. Queries look up
+//! names and rules by binary search and follow the order of precedence of `Acl::get_rule` with
+//! the parent order of the compiled `Acl`.
+//!
+//! Only the rules in effect are compiled: roles and resources resolved by providers, subject
+//! overrides and the cache are not part of the artifact. Policies with conditional rules or in
+//! laminas compatibility mode can't be compiled, since their decisions depend on code, neither can
+//! policies with rules with priority or resource defaults.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::compiled::CompiledAcl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("staff", vec!["guest"]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.allow(Some("guest"), Some("news"), Some("view")).unwrap();
+//!
+//! let artifact = acl.compile().unwrap();
+//! let compiled = CompiledAcl::from_bytes(&artifact).unwrap();
+//!
+//! assert!(compiled.is_allowed(Some("staff"), Some("news"), Some("view")));
+//! assert!(compiled.is_denied(Some("staff"), Some("news"), Some("edit")));
+//! ```
+
+use crate::{Access, Acl, Error, ParentOrder, Query};
+use log::{trace, warn};
+use std::collections::BTreeSet;
+
+/// The magic bytes starting a compiled policy.
+pub const MAGIC: &[u8; 4] = b"ZACC";
+
+/// The format version written into the header.
+pub const VERSION: u8 = 1;
+
+/// The symbol of wildcards and of no default role.
+const NONE: u32 = u32::MAX;
+
+/// The size of the header: magic, version, parent order, catch-all access, a reserved byte, the
+/// default role and the number of symbols, roles, resources, lineage entries and rules.
+const HEADER: usize = 8 + 6 * 4;
+
+/// The sizes of the entries of the role, resource and rule tables.
+const ROLE:     usize = 16;
+const RESOURCE: usize = 12;
+const RULE:     usize = 16;
+
+
+// CompiledAcl ////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A read-only policy deciding queries on a compiled artifact, see module `compiled`.
+#[derive(Clone, Copy, Debug)]
+pub struct CompiledAcl<'a> {
+    bytes:        &'a [u8],
+    order:        ParentOrder,
+    catch_all:    Access,
+    default_role: u32,
+    symbols:      Table,
+    roles:        Table,
+    resources:    Table,
+    lineage:      Table,
+    rules:        Table,
+    strings:      usize,
+} // struct CompiledAcl
+
+/// The offset and number of entries of a table.
+#[derive(Clone, Copy, Debug)]
+struct Table {
+    start: usize,
+    len:   usize,
+} // struct Table
+
+impl<'a> CompiledAcl<'a> {
+
+    /// Loads a compiled policy. Returns an error if the header is malformed, the artifact is of
+    /// another version or its size doesn't match the tables.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        trace!("loading compiled policy of {} bytes", bytes.len());
+        if bytes.len() < HEADER || &bytes[..4] != MAGIC {
+            return Err(Error::Parse(String::from("not a compiled policy")));
+        } // if
+        match bytes[4] {
+            0                    => return Err(Error::Parse(String::from("invalid compiled policy version 0"))),
+            version if version > VERSION => {
+                warn!("unsupported compiled policy version {}", version);
+                return Err(Error::SchemaVersion(u64::from(version)));
+            }, // version
+            _                    => (),
+        } // match
+
+        let order     = match bytes[5] {
+            0 => ParentOrder::Lifo,
+            1 => ParentOrder::Fifo,
+            2 => ParentOrder::DenyFirst,
+            n => return Err(Error::Parse(format!("invalid parent order {}", n))),
+        }; // match
+        let catch_all = match bytes[6] {
+            0 => Access::Deny,
+            1 => Access::Allow,
+            n => return Err(Error::Parse(format!("invalid access {}", n))),
+        }; // match
+        let count     = |i: usize| read(bytes, 8 + 4 * i) as usize;
+        let mut end   = HEADER;
+        let mut table = |len: usize, size: usize| {
+            let table = Table{start: end, len};
+
+            end = end.saturating_add(len.saturating_mul(size));
+            table
+        }; // table
+        // the symbol table holds the end of the last name as well
+        let symbols   = Table{len: count(1), ..table(count(1).saturating_add(1), 4)};
+        let roles     = table(count(2), ROLE);
+        let resources = table(count(3), RESOURCE);
+        let lineage   = table(count(4), 4);
+        let rules     = table(count(5), RULE);
+        let strings   = end;
+        let mut acl   = CompiledAcl{bytes, order, catch_all, default_role: read(bytes, 8), symbols, roles, resources, lineage, rules, strings};
+
+        if strings > bytes.len() || strings.saturating_add(acl.offset(acl.symbols.len)) != bytes.len() {
+            return Err(Error::Parse(String::from("truncated compiled policy")));
+        } // if
+        if acl.role(acl.default_role).is_none() {
+            acl.default_role = NONE;
+        } // if
+        Ok(acl)
+    } // from_bytes
+
+    /// Returns the order in which the parents of a role are searched, as compiled.
+    #[inline]
+    pub fn parent_order(&self) -> ParentOrder {
+        self.order
+    } // parent_order
+
+    /// Returns true if role is defined.
+    pub fn has_role(&self, name: &str) -> bool {
+        self.symbol(name).and_then(|symbol| self.role(symbol)).is_some()
+    } // has_role
+
+    /// Returns true if resource is defined.
+    pub fn has_resource(&self, name: &str) -> bool {
+        self.symbol(name).and_then(|symbol| self.resource(symbol)).is_some()
+    } // has_resource
+
+    /// Returns the access granted to role on resource for privilege, like `Acl::get_rule`.
+    pub fn access(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> Access {
+        trace!("getting compiled rule for {:?} on {:?} to {:?}", role, resource, privilege);
+        // roles and resources which aren't defined have no lineage
+        let role      = match role {
+            Some(name) => Some(self.symbol(name).and_then(|symbol| self.role(symbol))),
+            None       => self.role(self.default_role).map(Some),
+        }; // match
+        let privilege = privilege.map(|name| self.symbol(name));
+
+        if let Some(Some(entry)) = role {
+            if read(self.bytes, entry + 12) & 1 == 1 {
+                trace!("    bypass role");
+                return Access::Allow;
+            } // if
+        } // if
+        let roles     = role.map(|entry| entry.map_or((0, 0), |entry| self.slice(entry)));
+
+        if let Some(entry) = resource.and_then(|name| self.symbol(name)).and_then(|symbol| self.resource(symbol)) {
+            let (start, len) = self.slice(entry);
+
+            for i in start..start + len {
+                if let Some(access) = self.query_roles(self.entry(self.lineage, i, 4), roles, privilege) {
+                    return access;
+                } // if
+            } // for
+        } // if
+        self.query_roles(NONE, roles, privilege).unwrap_or(self.catch_all)
+    } // access
+
+    /// Returns true if privilege is allowed for role on resource.
+    #[inline]
+    pub fn is_allowed(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> bool {
+        self.access(role, resource, privilege) == Access::Allow
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource.
+    #[inline]
+    pub fn is_denied(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> bool {
+        self.access(role, resource, privilege) == Access::Deny
+    } // is_denied
+
+    /// Searches the lineage of roles, given by the start and length within the lineage table, and
+    /// the wildcard role, see `Acl::query_roles`.
+    fn query_roles(&self, resource: u32, roles: Option<(usize, usize)>, privilege: Option<Option<u32>>) -> Option<Access> {
+        if let Some((start, len)) = roles {
+            let mut allowed = None;
+
+            for i in 0..len {
+                if let Some(access) = self.query_privileges(resource, self.entry(self.lineage, start + i, 4), privilege) {
+                    // an inherited allow rule is kept until no ancestor denies
+                    if self.order != ParentOrder::DenyFirst || i == 0 || access == Access::Deny {
+                        return Some(access);
+                    } // if
+                    allowed = allowed.or(Some(access));
+                } // if let
+            } // for
+            if allowed.is_some() {
+                return allowed;
+            } // if
+        } // if let
+        self.query_privileges(resource, NONE, privilege)
+    } // query_roles
+
+    /// Looks up the rule for privilege, then for the wildcard privilege, see
+    /// `Acl::query_privileges`. An unknown privilege only matches the wildcard privilege.
+    fn query_privileges(&self, resource: u32, role: u32, privilege: Option<Option<u32>>) -> Option<Access> {
+        if let Some(Some(symbol)) = privilege {
+            if let Some(access) = self.rule(resource, role, symbol) {
+                return Some(access);
+            } // if
+        } // if
+        if resource != NONE || role != NONE {
+            return self.rule(resource, role, NONE);
+        } // if
+        None
+    } // query_privileges
+
+    /// Returns the access of the rule for resource, role and privilege.
+    fn rule(&self, resource: u32, role: u32, privilege: u32) -> Option<Access> {
+        let key   = (resource, role, privilege);
+        let found = self.search(self.rules, RULE, |entry| {
+            (read(self.bytes, entry), read(self.bytes, entry + 4), read(self.bytes, entry + 8)).cmp(&key)
+        })?;
+
+        Some(if read(self.bytes, found + 12) == 1 { Access::Allow } else { Access::Deny })
+    } // rule
+
+    /// Returns the symbol of name.
+    fn symbol(&self, name: &str) -> Option<u32> {
+        let mut low  = 0;
+        let mut high = self.symbols.len;
+
+        while low < high {
+            let mid  = low + (high - low) / 2;
+            let text = self.bytes.get(self.strings + self.offset(mid)..self.strings + self.offset(mid + 1)).unwrap_or_default();
+
+            match text.cmp(name.as_bytes()) {
+                std::cmp::Ordering::Less    => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal   => return Some(mid as u32),
+            } // match
+        } // while
+        None
+    } // symbol
+
+    /// Returns the position of the role entry of symbol.
+    fn role(&self, symbol: u32) -> Option<usize> {
+        self.search(self.roles, ROLE, |entry| read(self.bytes, entry).cmp(&symbol))
+    } // role
+
+    /// Returns the position of the resource entry of symbol.
+    fn resource(&self, symbol: u32) -> Option<usize> {
+        self.search(self.resources, RESOURCE, |entry| read(self.bytes, entry).cmp(&symbol))
+    } // resource
+
+    /// Returns the start and length of the lineage of the role or resource entry.
+    fn slice(&self, entry: usize) -> (usize, usize) {
+        let start = read(self.bytes, entry + 4) as usize;
+        let len   = read(self.bytes, entry + 8) as usize;
+
+        // lineages reaching beyond the table are cut short
+        (start.min(self.lineage.len), len.min(self.lineage.len - start.min(self.lineage.len)))
+    } // slice
+
+    /// Returns the offset of the symbol within the strings.
+    #[inline]
+    fn offset(&self, symbol: usize) -> usize {
+        self.entry(self.symbols, symbol, 4) as usize
+    } // offset
+
+    /// Returns the first integer of entry i of table.
+    #[inline]
+    fn entry(&self, table: Table, i: usize, size: usize) -> u32 {
+        read(self.bytes, table.start + i * size)
+    } // entry
+
+    /// Returns the position of the entry of the sorted table for which compare returns equal.
+    fn search<F: Fn(usize) -> std::cmp::Ordering>(&self, table: Table, size: usize, compare: F) -> Option<usize> {
+        let mut low  = 0;
+        let mut high = table.len;
+
+        while low < high {
+            let mid   = low + (high - low) / 2;
+            let entry = table.start + mid * size;
+
+            match compare(entry) {
+                std::cmp::Ordering::Less    => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal   => return Some(entry),
+            } // match
+        } // while
+        None
+    } // search
+
+} // impl CompiledAcl
+
+/// Reads the integer at position, or `NONE` beyond the end of bytes.
+#[inline]
+fn read(bytes: &[u8], position: usize) -> u32 {
+    match bytes.get(position..position.saturating_add(4)) {
+        Some(&[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]),
+        _                   => NONE,
+    } // match
+} // read
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Compiles the rules in effect into an artifact for `CompiledAcl`, see module `compiled`.
+    /// Returns an error if the `Acl` has conditional rules, rules with priority or resource defaults,
+    /// or is in laminas compatibility mode.
+    pub fn compile(&self) -> Result<Vec<u8>, Error> {
+        trace!("compiling policy");
+        if self.compat {
+            return Err(Error::NotPermitted(String::from("compiling in laminas compatibility mode")));
+        } // if
+        if let Some((query, _)) = self.rules.iter().find(|(_, rule)| rule.condition().is_some()) {
+            return Err(Error::NotPermitted(format!("compiling conditional rule {}", query)));
+        } // if
+        if let Some(query) = self.priorities.keys().min() {
+            return Err(Error::NotPermitted(format!("compiling rule with priority {}", query)));
+        } // if
+        if let Some((resource, privilege)) = self.resource_defaults.keys().min() {
+            return Err(Error::NotPermitted(format!("compiling default of {} on {}", privilege, resource)));
+        } // if
+
+        let names: BTreeSet<&'static str> = self.roles.keys().chain(self.resources.keys()).copied()
+            .chain(self.rules.keys().filter_map(|query| query.privilege))
+            .collect();
+        let names: Vec<&'static str>      = names.into_iter().collect();
+        let symbol = |name: Option<&'static str>| name.and_then(|name| names.binary_search(&name).ok()).map_or(NONE, |i| i as u32);
+
+        let mut lineage   = vec![];
+        let mut roles     = vec![];
+        let mut resources = vec![];
+
+        // names are sorted, so are roles and resources by symbol
+        for name in self.roles.keys() {
+            let ancestors = self.get_role_lineage(name);
+
+            roles.push([symbol(Some(name)), lineage.len() as u32, ancestors.len() as u32, self.bypass.contains(name) as u32]);
+            lineage.extend(ancestors.into_iter().map(|name| symbol(Some(name))));
+        } // for
+        for name in self.resources.keys() {
+            let ancestors = self.get_resource_lineage(name);
+
+            resources.push([symbol(Some(name)), lineage.len() as u32, ancestors.len() as u32]);
+            lineage.extend(ancestors.into_iter().map(|name| symbol(Some(name))));
+        } // for
+
+        let mut rules: Vec<[u32; 4]> = self.rules.iter()
+            .filter(|(query, _)| **query != Query::ALL)
+            .map(|(query, rule)| [symbol(query.resource), symbol(query.role), symbol(query.privilege), (rule.access() == Access::Allow) as u32])
+            .collect();
+
+        rules.sort_unstable();
+
+        let order     = match self.parent_order {
+            ParentOrder::Lifo      => 0,
+            ParentOrder::Fifo      => 1,
+            ParentOrder::DenyFirst => 2,
+        }; // match
+        let catch_all = (self.rules[&Query::ALL].access() == Access::Allow) as u8;
+        let mut bytes = MAGIC.to_vec();
+
+        bytes.extend_from_slice(&[VERSION, order, catch_all, 0]);
+        for count in [symbol(self.default_role), names.len() as u32, roles.len() as u32, resources.len() as u32, lineage.len() as u32, rules.len() as u32] {
+            bytes.extend_from_slice(&count.to_le_bytes());
+        } // for
+
+        let mut offset = 0u32;
+
+        for name in names.iter().map(|name| name.len() as u32).chain(Some(0)) {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            offset += name;
+        } // for
+        let tables = roles.iter().flatten()
+            .chain(resources.iter().flatten())
+            .chain(lineage.iter())
+            .chain(rules.iter().flatten());
+
+        for value in tables {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        } // for
+        for name in &names {
+            bytes.extend_from_slice(name.as_bytes());
+        } // for
+        trace!("compiled {} rules into {} bytes", rules.len(), bytes.len());
+        Ok(bytes)
+    } // compile
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn setup() -> Acl {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("member", vec![]).is_ok());
+        assert!(acl.add_role("editor", vec!["guest", "member"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.add_resource("archive", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.deny(Some("guest"), Some("archive"), None).is_ok());
+        assert!(acl.allow(Some("member"), Some("news"), None).is_ok());
+        assert!(acl.deny(Some("member"), Some("latest"), Some("delete")).is_ok());
+        assert!(acl.allow(Some("editor"), Some("latest"), Some("publish")).is_ok());
+        assert!(acl.deny(None, Some("news"), Some("purge")).is_ok());
+        assert!(acl.allow(None, Some("latest"), None).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        acl
+    } // setup
+
+    #[test]
+    fn compile() {
+        let roles      = [None, Some("guest"), Some("member"), Some("editor"), Some("root"), Some("nobody"), Some("view")];
+        let resources  = [None, Some("news"), Some("latest"), Some("archive"), Some("unknown"), Some("editor")];
+        let privileges = [None, Some("view"), Some("delete"), Some("publish"), Some("purge"), Some("unknown"), Some("news")];
+
+        for order in [ParentOrder::Lifo, ParentOrder::Fifo, ParentOrder::DenyFirst] {
+            for default_role in [None, Some("guest")] {
+                let mut acl = setup();
+
+                acl.set_parent_order(order);
+                if let Some(name) = default_role {
+                    assert!(acl.set_default_role(name).is_ok());
+                } // if
+
+                let artifact = acl.compile().unwrap();
+                let compiled = CompiledAcl::from_bytes(&artifact).unwrap();
+
+                assert_eq!(compiled.parent_order(), order);
+                for role in &roles {
+                    for resource in &resources {
+                        for privilege in &privileges {
+                            assert_eq!(compiled.access(*role, *resource, *privilege), acl.get_rule(*role, *resource, *privilege).access(),
+                                "{:?} on {:?} to {:?} in {:?}", role, resource, privilege, order);
+                        } // for
+                    } // for
+                } // for
+            } // for
+        } // for
+
+        let acl      = setup();
+        let artifact = acl.compile().unwrap();
+        let compiled = CompiledAcl::from_bytes(&artifact).unwrap();
+
+        assert!(compiled.has_role("editor"));
+        assert!(!compiled.has_role("news"));
+        assert!(compiled.has_resource("archive"));
+        assert!(!compiled.has_resource("view"));
+
+        let artifact = Acl::new().compile().unwrap();
+
+        assert_eq!(artifact.len(), HEADER + 4);
+        assert!(CompiledAcl::from_bytes(&artifact).unwrap().is_denied(None, None, None));
+    } // compile
+
+    #[test]
+    fn invalid() {
+        let artifact = setup().compile().unwrap();
+        let error    = |bytes: &[u8]| CompiledAcl::from_bytes(bytes).err();
+
+        assert_eq!(error(b"ZAC"), Some(Error::Parse(String::from("not a compiled policy"))));
+        assert_eq!(error(&artifact[..artifact.len() - 1]), Some(Error::Parse(String::from("truncated compiled policy"))));
+        assert_eq!(error(&[&artifact[..], b"x"].concat()), Some(Error::Parse(String::from("truncated compiled policy"))));
+
+        let mut newer = artifact.clone();
+
+        newer[4] = VERSION + 1;
+        assert_eq!(error(&newer), Some(Error::SchemaVersion(u64::from(VERSION) + 1)));
+
+        // garbage doesn't panic
+        let mut garbage = artifact.clone();
+
+        for byte in garbage[HEADER..].iter_mut() {
+            *byte = byte.wrapping_mul(31).wrapping_add(7);
+        } // for
+        if let Ok(compiled) = CompiledAcl::from_bytes(&garbage) {
+            let _ = compiled.access(Some("editor"), Some("latest"), Some("view"));
+        } // if
+
+        let mut acl = setup();
+
+        assert!(acl.allow_if(Some("guest"), Some("news"), Some("comment"), "owner").is_ok());
+        assert_eq!(acl.compile(), Err(Error::NotPermitted(String::from("compiling conditional rule guest→news: comment"))));
+
+        let mut acl = setup();
+
+        assert!(acl.set_rule_priority(None, Some("news"), Some("purge"), 1).is_ok());
+        assert_eq!(acl.compile(), Err(Error::NotPermitted(String::from("compiling rule with priority *→news: purge"))));
+
+        let mut acl = setup();
+
+        assert!(acl.set_resource_default("news", "view", Access::Allow).is_ok());
+        assert_eq!(acl.compile(), Err(Error::NotPermitted(String::from("compiling default of view on news"))));
+    } // invalid
+
+} // mod tests
@@ -0,0 +1,465 @@
+//! Conditional rules.
+//!
+//! A rule may be bound to an assertion, which is evaluated with the queried role, resource and
+//! privilege whenever the rule matches a query. The rule applies only if the assertion holds,
+//! otherwise the search continues with less specific rules as if the rule wasn't defined. Like in
+//! laminas, an assertion can consult state the `Acl` doesn't know about, e.g. the time of day or a
+//! maintenance mode.
+//!
+//! Assertions are registered by name with `add_assertion`, rules refer to them by name via
+//! `allow_if` and `deny_if`, so policy documents can declare conditional rules before the
+//! assertions are registered. `allow_assert` and `deny_assert` attach an assertion to a single
+//! rule instead. It's registered under a reserved anonymous name, displayed as `<closure>`, and
+//! unregistered once the rule is replaced or removed. A rule bound to an unregistered assertion
+//! fails closed: an allow rule doesn't apply, a deny rule does. Decisions which evaluated any
+//! assertion aren't cached by a locked `Acl`. The catch-all rule can't be conditional.
+//!
+//! Assertions performing I/O, e.g. looking up the owner of a row in a database, implement
+//! `AsyncAssertion` and are registered with `add_async_assertion`. Only `decide_async` and
+//! `is_allowed_async` await them: whenever a decision reaches a rule bound to an async assertion
+//! not awaited yet, the assertion is awaited and the query decided again with its result. All
+//! other queries can't await, so they treat async assertions like unregistered ones and fail
+//! closed.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Acl, Query};
+//! # use std::cell::Cell;
+//! # use std::rc::Rc;
+//! let maintenance = Rc::new(Cell::new(false));
+//! let flag        = Rc::clone(&maintenance);
+//! let mut acl     = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.allow(Some("staff"), None, None).unwrap();
+//! acl.add_assertion("maintenance", move |_: &Acl, _: &Query| flag.get());
+//! acl.deny_if(Some("staff"), None, Some("edit"), "maintenance").unwrap();
+//!
+//! assert!(acl.is_allowed(Some("staff"), None, Some("edit")));
+//! maintenance.set(true);
+//! assert!(acl.is_denied(Some("staff"), None, Some("edit")));
+//! assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+//! ```
+
+use crate::{Access, Acl, Decision, Error, Privilege, Query, Resource, Role, Rule};
+use log::{trace, warn};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, PoisonError};
+
+/// The prefix of the names of assertions attached to single rules, see `Acl::set_asserted_rule`.
+pub const CLOSURE: &str = "<closure>";
+
+/// Returns the anonymous name numbered id. Names are allocated once and shared by all `Acl`s.
+fn closure_name(id: usize) -> &'static str {
+    static NAMES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    let mut names = NAMES.lock().unwrap_or_else(PoisonError::into_inner);
+
+    while names.len() <= id {
+        let name = format!("{}#{}", CLOSURE, names.len());
+
+        names.push(Box::leak(name.into_boxed_str()));
+    } // while
+    names[id]
+} // closure_name
+
+
+// Assertion //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Decides whether a conditional rule applies to a query.
+pub trait Assertion {
+
+    /// Returns true if the rule applies to the queried role, resource and privilege.
+    fn assert(&self, acl: &Acl, query: &Query) -> bool;
+
+} // trait Assertion
+
+impl<F: Fn(&Acl, &Query) -> bool> Assertion for F {
+
+    fn assert(&self, acl: &Acl, query: &Query) -> bool {
+        self(acl, query)
+    } // assert
+
+} // impl Assertion for F
+
+/// Decides asynchronously whether a conditional rule applies to a query, see `Acl::decide_async`.
+pub trait AsyncAssertion {
+
+    /// Returns a future resolving to true if the rule applies to the queried role, resource and
+    /// privilege.
+    fn assert<'a>(&'a self, acl: &'a Acl, query: &'a Query) -> Pin<Box<dyn Future<Output = bool> + 'a>>;
+
+} // trait AsyncAssertion
+
+impl<F, T> AsyncAssertion for F
+where
+    F: Fn(&Acl, &Query) -> T,
+    T: Future<Output = bool> + 'static,
+{
+    fn assert<'a>(&'a self, acl: &'a Acl, query: &'a Query) -> Pin<Box<dyn Future<Output = bool> + 'a>> {
+        Box::pin(self(acl, query))
+    } // assert
+
+} // impl AsyncAssertion for F
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Registers assertion by name. Replaces a previous assertion of the same name. Purges the
+    /// cache, which may hold decisions of rules bound to an unregistered assertion. Names starting
+    /// with `CLOSURE` are reserved for `set_asserted_rule`.
+    pub fn add_assertion<A: Assertion + 'static>(&mut self, name: &'static str, assertion: A) {
+        trace!("adding assertion {}", name);
+        self.async_assertions.remove(name);
+        self.assertions.insert(name, Box::new(assertion));
+        self.advance_generation();
+        self.purge_cache();
+    } // add_assertion
+
+    /// Like `add_assertion`, but registers an assertion awaited by `decide_async`. Other queries
+    /// fail closed on rules bound to it.
+    pub fn add_async_assertion<A: AsyncAssertion + 'static>(&mut self, name: &'static str, assertion: A) {
+        trace!("adding async assertion {}", name);
+        self.assertions.remove(name);
+        self.async_assertions.insert(name, Box::new(assertion));
+        self.advance_generation();
+        self.purge_cache();
+    } // add_async_assertion
+
+    /// Returns true if an assertion, async or not, is registered by name.
+    #[inline]
+    pub fn has_assertion(&self, name: &str) -> bool {
+        self.assertions.contains_key(name) || self.async_assertions.contains_key(name)
+    } // has_assertion
+
+    /// Like `set_rule`, but the rule only applies if the assertion registered by name holds.
+    /// Returns an error for the catch-all rule.
+    pub fn set_conditional_rule(&mut self, role: Role, resource: Resource, privilege: Privilege, access: Access, assertion: &'static str) -> Result<(), Error> {
+        trace!("setting {} rule for {:?} on {:?} with {:?} privilege if {}", access, role, resource, privilege, assertion);
+        let query = Query{resource, role, privilege};
+
+        if query == Query::ALL {
+            return Err(Error::NotPermitted(format!("conditional rule {}", query)));
+        } // if
+        self.set_rule(role, resource, privilege, access)?;
+        self.insert_rule(query, Rule{acc: access, cond: Some(assertion)});
+        Ok(())
+    } // set_conditional_rule
+
+    /// Allows privilege for role on resource if the assertion registered by name holds.
+    #[inline]
+    pub fn allow_if(&mut self, role: Role, resource: Resource, privilege: Privilege, assertion: &'static str) -> Result<(), Error> {
+        self.set_conditional_rule(role, resource, privilege, Access::Allow, assertion)
+    } // allow_if
+
+    /// Denies privilege for role on resource if the assertion registered by name holds.
+    #[inline]
+    pub fn deny_if(&mut self, role: Role, resource: Resource, privilege: Privilege, assertion: &'static str) -> Result<(), Error> {
+        self.set_conditional_rule(role, resource, privilege, Access::Deny, assertion)
+    } // deny_if
+
+    /// Like `set_conditional_rule`, but binds the rule to assertion, which is registered under an
+    /// anonymous name starting with `CLOSURE` until the rule is replaced or removed. Returns an
+    /// error for the catch-all rule.
+    pub fn set_asserted_rule<A: Assertion + 'static>(&mut self, role: Role, resource: Resource, privilege: Privilege, access: Access, assertion: A) -> Result<(), Error> {
+        let mut id = 0;
+
+        // names of released assertions are reused, so only as many are allocated as rules coexist
+        while self.closures.contains_key(closure_name(id)) || self.has_assertion(closure_name(id)) {
+            id += 1;
+        } // while
+        let name = closure_name(id);
+
+        self.set_conditional_rule(role, resource, privilege, access, name)?;
+        self.add_assertion(name, assertion);
+        Ok(())
+    } // set_asserted_rule
+
+    /// Allows privilege for role on resource if assertion holds.
+    #[inline]
+    pub fn allow_assert<A: Assertion + 'static>(&mut self, role: Role, resource: Resource, privilege: Privilege, assertion: A) -> Result<(), Error> {
+        self.set_asserted_rule(role, resource, privilege, Access::Allow, assertion)
+    } // allow_assert
+
+    /// Denies privilege for role on resource if assertion holds.
+    #[inline]
+    pub fn deny_assert<A: Assertion + 'static>(&mut self, role: Role, resource: Resource, privilege: Privilege, assertion: A) -> Result<(), Error> {
+        self.set_asserted_rule(role, resource, privilege, Access::Deny, assertion)
+    } // deny_assert
+
+    /// Counts a reference to the anonymous assertion of rule, which is in effect or set aside by a
+    /// group or environment.
+    pub(crate) fn retain_assertion(&mut self, rule: Option<Rule>) {
+        if let Some(name) = rule.and_then(|rule| rule.cond).filter(|name| name.starts_with(CLOSURE)) {
+            *self.closures.entry(name).or_insert(0) += 1;
+        } // if
+    } // retain_assertion
+
+    /// Drops a reference to the anonymous assertion of rule and unregisters it once no rule in
+    /// effect or set aside is bound to it.
+    pub(crate) fn release_assertion(&mut self, rule: Option<Rule>) {
+        if let Some(name) = rule.and_then(|rule| rule.cond).filter(|name| name.starts_with(CLOSURE)) {
+            match self.closures.get_mut(name) {
+                Some(count) if *count > 1 => *count -= 1,
+                _                         => {
+                    trace!("releasing assertion {}", name);
+                    self.closures.remove(name);
+                    self.assertions.remove(name);
+                },
+            } // match
+        } // if
+    } // release_assertion
+
+    /// Returns true if rule applies to query. Sets conditional if an assertion has been evaluated.
+    pub(crate) fn holds(&self, rule: &Rule, query: &Query, conditional: &Cell<bool>) -> bool {
+        let name = match rule.cond {
+            Some(name) => name,
+            None       => return true,
+        }; // match
+
+        conditional.set(true);
+        if let Some(assertion) = self.assertions.get(name) {
+            return assertion.assert(self, query);
+        } // if
+        let awaited = self.awaited.borrow();
+
+        match (self.async_assertions.contains_key(name), awaited.as_ref()) {
+            (false, _)            => warn!("missing assertion {} of rule for {}", name, query),
+            (true, None)          => warn!("can't await assertion {} of rule for {} synchronously", name, query),
+            (true, Some(awaited)) => match awaited.get(name) {
+                Some(holds) => return *holds,
+                // the query is decided again once the assertion is awaited, see decide_async
+                None        => if self.awaiting.get().is_none() {
+                    self.awaiting.set(Some((name, *query)));
+                }, // None
+            }, // Some
+        } // match
+        rule.acc == Access::Deny
+    } // holds
+
+    /// Like `decide`, but awaits the async assertions of the rules the decision reaches, see
+    /// module `condition`. Each assertion is awaited at most once per query.
+    pub async fn decide_async(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        let mut awaited = HashMap::new();
+
+        while let Some((name, query)) = self.pending(role, resource, privilege, &mut awaited) {
+            if let Some(assertion) = self.async_assertions.get(name) {
+                trace!("awaiting assertion {} for {}", name, query);
+                awaited.insert(name, assertion.assert(self, &query).await);
+            } // if
+        } // while
+        self.awaited.replace(Some(awaited));
+
+        let decision = self.decide(role, resource, privilege);
+
+        self.awaited.replace(None);
+        self.awaiting.set(None);
+        decision
+    } // decide_async
+
+    /// Returns true if privilege is allowed for role on resource, see `decide_async`.
+    #[inline]
+    pub async fn is_allowed_async(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide_async(role, resource, privilege).await.rule.acc == Access::Allow
+    } // is_allowed_async
+
+    /// Returns true if privilege is denied for role on resource, see `decide_async`.
+    #[inline]
+    pub async fn is_denied_async(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide_async(role, resource, privilege).await.rule.acc == Access::Deny
+    } // is_denied_async
+
+    /// Decides the query with the async assertions awaited so far and returns the first one
+    /// reached without a result, with the query to await it for.
+    fn pending(&self, role: Role, resource: Resource, privilege: Privilege, awaited: &mut HashMap<&'static str, bool>) -> Option<(&'static str, Query)> {
+        self.awaited.replace(Some(std::mem::take(awaited)));
+        self.awaiting.set(None);
+        self.evaluate(role, resource, privilege);
+        *awaited = self.awaited.replace(None).unwrap_or_default();
+        self.awaiting.take()
+    } // pending
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn owner(_: &Acl, query: &Query) -> bool {
+        query.role == Some("alice")
+    } // owner
+
+    #[test]
+    fn conditional() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_role("alice", vec!["staff"]).is_ok());
+        assert!(acl.add_role("bob", vec!["staff"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.deny(Some("staff"), None, Some("edit")).is_ok());
+        assert!(acl.allow_if(Some("staff"), Some("news"), Some("edit"), "owner").is_ok());
+        assert!(acl.deny_if(Some("staff"), None, Some("view"), "owner").is_ok());
+
+        // unregistered assertions fail closed
+        assert!(!acl.has_assertion("owner"));
+        assert!(acl.is_denied(Some("alice"), Some("latest"), Some("edit")));
+        assert!(acl.is_denied(Some("bob"), Some("latest"), Some("view")));
+
+        acl.add_assertion("owner", owner);
+        assert!(acl.has_assertion("owner"));
+        assert!(acl.is_allowed(Some("alice"), Some("latest"), Some("edit")));
+        assert!(acl.is_denied (Some("bob"), Some("latest"), Some("edit")));
+        assert!(acl.is_denied (Some("alice"), Some("latest"), Some("view")));
+
+        let decision = acl.decide(Some("alice"), Some("latest"), Some("edit"));
+
+        assert_eq!(decision.rule.condition(), Some("owner"));
+        assert_eq!(decision.to_string(), "ALLOW IF owner alice→latest: edit");
+        assert_eq!(decision.matched, Query{resource: Some("news"), role: Some("staff"), privilege: Some("edit")});
+        assert_eq!(acl.decide(Some("bob"), Some("latest"), Some("edit")).matched, Query{resource: None, role: Some("staff"), privilege: Some("edit")});
+
+        // decisions of conditional rules aren't cached
+        acl.lock();
+        assert!(acl.is_allowed(Some("alice"), Some("news"), Some("edit")));
+        assert!(acl.is_denied (Some("bob"), Some("news"), Some("edit")));
+        assert_eq!(acl.cache_stats().entries, 0);
+        acl.unlock();
+
+        // the rule is unconditional once set again
+        assert!(acl.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(acl.is_allowed(Some("bob"), Some("latest"), Some("edit")));
+        assert_eq!(acl.allow_if(None, None, None, "owner"), Err(Error::NotPermitted(String::from("conditional rule *→*: *"))));
+        assert!(acl.allow_if(Some("staff"), Some("unknown"), None, "owner").is_err());
+    } // conditional
+
+    #[test]
+    fn asserted() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow_assert(Some("staff"), Some("news"), None, |_: &Acl, query: &Query| query.privilege != Some("delete")).is_ok());
+        assert!(acl.deny_assert(Some("staff"), None, Some("view"), owner).is_ok());
+
+        assert!(acl.is_allowed(Some("staff"), Some("news"), Some("edit")));
+        assert!(acl.is_denied(Some("staff"), Some("news"), Some("delete")));
+        assert!(!acl.has_assertion("staff→news: *"));
+        assert_eq!(acl.assertions.len(), 2);
+        assert_eq!(acl.decide(Some("staff"), Some("news"), Some("edit")).to_string(), "ALLOW IF <closure> staff→news: edit");
+
+        // replacing the assertion of a rule unregisters the previous one
+        assert!(acl.allow_assert(Some("staff"), Some("news"), None, |_: &Acl, _: &Query| false).is_ok());
+        assert!(acl.is_denied(Some("staff"), Some("news"), Some("edit")));
+        assert_eq!(acl.assertions.len(), 2);
+        assert_eq!(acl.deny_assert(None, None, None, owner), Err(Error::NotPermitted(String::from("conditional rule *→*: *"))));
+        assert_eq!(acl.assertions.len(), 2);
+
+        // as does replacing or removing the rule
+        assert!(acl.allow(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.is_allowed(Some("staff"), Some("news"), Some("delete")));
+        assert_eq!(acl.assertions.len(), 1);
+        assert!(acl.revoke(Some("staff"), None, Some("view")));
+        assert!(acl.assertions.is_empty());
+        assert!(acl.closures.is_empty());
+    } // asserted
+
+    #[test]
+    fn asserted_aside() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow_assert(Some("staff"), Some("news"), None, |_: &Acl, query: &Query| query.privilege != Some("delete")).is_ok());
+
+        // a group replacing the rule sets it aside with its assertion
+        assert!(acl.group("beta", |group| group.deny(Some("staff"), Some("news"), None)).is_ok());
+        assert!(acl.is_denied(Some("staff"), Some("news"), Some("edit")));
+        assert!(acl.allow_assert(Some("staff"), None, Some("view"), |_: &Acl, _: &Query| false).is_ok());
+        assert_eq!(acl.assertions.len(), 2);
+        assert_eq!(acl.disable_group("beta"), Ok(true));
+        assert!(acl.is_allowed(Some("staff"), Some("news"), Some("edit")));
+        assert!(acl.is_denied(Some("staff"), Some("news"), Some("delete")));
+        assert!(acl.is_denied(Some("staff"), None, Some("view")));
+
+        // and back again, also for asserted rules of the group and environments
+        assert!(acl.group("beta", |group| group.allow(Some("staff"), Some("news"), Some("edit"))).is_ok());
+        assert_eq!(acl.enable_group("beta"), Ok(true));
+        assert_eq!(acl.disable_group("beta"), Ok(true));
+        assert!(acl.is_denied(Some("staff"), Some("news"), Some("delete")));
+        assert!(acl.allow_in("dev", Some("staff"), None, Some("view")).is_ok());
+        acl.set_environment(Some("dev"));
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        acl.set_environment(None);
+        assert!(acl.is_denied(Some("staff"), None, Some("view")));
+        assert!(acl.allow_assert(Some("staff"), None, Some("view"), |_: &Acl, _: &Query| true).is_ok());
+        acl.set_environment(Some("dev"));
+        acl.set_environment(None);
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        assert_eq!(acl.assertions.len(), 2);
+
+        // removing the resource releases the rules set aside
+        assert!(acl.remove_resource("news").is_ok());
+        assert_eq!(acl.assertions.len(), 1);
+        assert_eq!(acl.closures.len(), 1);
+    } // asserted_aside
+
+    #[test]
+    fn awaited() {
+        use futures::executor::block_on;
+        use std::rc::Rc;
+
+        let lookups = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&lookups);
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_role("alice", vec!["staff"]).is_ok());
+        assert!(acl.add_role("bob", vec!["staff"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("staff"), None, Some("view")).is_ok());
+        assert!(acl.allow_if(Some("staff"), Some("news"), Some("edit"), "owner").is_ok());
+        assert!(acl.deny_if(Some("staff"), Some("news"), Some("view"), "banned").is_ok());
+        acl.add_async_assertion("owner", move |_: &Acl, query: &Query| {
+            let role = query.role;
+
+            counter.set(counter.get() + 1);
+            async move { role == Some("alice") }
+        });
+        acl.add_async_assertion("banned", |_: &Acl, query: &Query| futures::future::ready(query.role == Some("bob")));
+        assert!(acl.has_assertion("owner"));
+
+        block_on(async {
+            assert!(acl.is_allowed_async(Some("alice"), Some("news"), Some("edit")).await);
+            assert!(acl.is_denied_async(Some("bob"), Some("news"), Some("edit")).await);
+            assert!(acl.is_allowed_async(Some("alice"), Some("news"), Some("view")).await);
+            assert!(acl.is_denied_async(Some("bob"), Some("news"), Some("view")).await);
+            assert_eq!(acl.decide_async(Some("alice"), Some("news"), Some("edit")).await.rule.condition(), Some("owner"));
+        }); // block_on
+        assert_eq!(lookups.get(), 3);
+
+        // synchronous queries fail closed
+        assert!(acl.is_denied(Some("alice"), Some("news"), Some("edit")));
+        assert!(acl.is_denied(Some("alice"), Some("news"), Some("view")));
+
+        // replacing by a synchronous assertion
+        acl.add_assertion("owner", owner);
+        assert!(acl.is_allowed(Some("alice"), Some("news"), Some("edit")));
+        assert_eq!(lookups.get(), 3);
+    } // awaited
+
+} // mod tests
@@ -0,0 +1,203 @@
+//! Default access of resources per privilege.
+//!
+//! A resource may define the default access of a privilege for its subtree, e.g. `view` is allowed
+//! on the public subtree. Defaults form a layer between the rules and the catch-all rule: if no
+//! rule matches a query, the default of the privilege defined by the resource or its nearest
+//! ancestor decides, before falling through to the catch-all rule. Any rule matching the query
+//! wins over a default, including rules for all resources.
+//!
+//! A decision by a default is reported like a rule for all roles on the resource defining it.
+//! Queries for the wildcard privilege or resource aren't decided by defaults.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Access, Acl};
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("suspended", vec![]).unwrap();
+//! acl.add_resource("public", None).unwrap();
+//! acl.add_resource("blog", Some("public")).unwrap();
+//! acl.deny(Some("suspended"), None, Some("view")).unwrap();
+//! acl.set_resource_default("public", "view", Access::Allow).unwrap();
+//!
+//! assert!(acl.is_allowed(Some("guest"), Some("blog"), Some("view")));
+//! assert!(acl.is_denied(Some("suspended"), Some("blog"), Some("view")));
+//! assert!(acl.is_denied(Some("guest"), Some("blog"), Some("edit")));
+//! ```
+
+use crate::{Access, Acl, Error, Privilege, Query, Resource};
+use crate::etag::Item;
+use log::trace;
+
+impl Acl {
+
+    /// Sets the default access of privilege on the subtree of resource, see module `defaults`.
+    /// Replaces the default set before. Returns an error if resource is undefined, privilege isn't
+    /// registered while any are, see module `domain`, or the `Acl` is locked.
+    pub fn set_resource_default(&mut self, resource: &'static str, privilege: &'static str, access: Access) -> Result<(), Error> {
+        trace!("setting default {} of {} on {}", access, privilege, resource);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if !self.resources.contains_key(resource) {
+            return Err(Error::MissingResource(String::from(resource)));
+        } // if
+        self.check_privilege(Some(resource), Some(privilege))?;
+        if let Some(previous) = self.resource_defaults.insert((resource, privilege), access) {
+            self.track(Item::Default(resource, privilege, previous), false);
+        } // if
+        self.track(Item::Default(resource, privilege, access), true);
+        Ok(())
+    } // set_resource_default
+
+    /// Removes the default access of privilege on resource. Returns the access removed. Returns an
+    /// error if the `Acl` is locked.
+    pub fn remove_resource_default(&mut self, resource: &'static str, privilege: &'static str) -> Result<Option<Access>, Error> {
+        trace!("removing default of {} on {}", privilege, resource);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let removed = self.resource_defaults.remove(&(resource, privilege));
+
+        if let Some(access) = removed {
+            self.track(Item::Default(resource, privilege, access), false);
+        } // if
+        Ok(removed)
+    } // remove_resource_default
+
+    /// Returns the default access of privilege defined by resource itself.
+    #[inline]
+    pub fn get_resource_default(&self, resource: &'static str, privilege: &'static str) -> Option<Access> {
+        self.resource_defaults.get(&(resource, privilege)).copied()
+    } // get_resource_default
+
+    /// Returns the defaults defined by resource ordered by privilege.
+    pub fn resource_defaults(&self, resource: &'static str) -> Vec<(&'static str, Access)> {
+        let mut defaults: Vec<(&'static str, Access)> = self.resource_defaults.iter()
+            .filter(|((name, _), _)| *name == resource)
+            .map(|((_, privilege), access)| (*privilege, *access))
+            .collect();
+
+        defaults.sort_unstable_by_key(|(privilege, _)| *privilege);
+        defaults
+    } // resource_defaults
+
+    /// Returns the default deciding privilege on resource and the query it is reported as, see
+    /// module `defaults`.
+    pub(crate) fn query_defaults(&self, resource: Resource, privilege: Privilege) -> Option<(Query, Access)> {
+        let (resource, privilege) = match (resource, privilege) {
+            (Some(resource), Some(privilege)) if !self.resource_defaults.is_empty() => (resource, privilege),
+            _                                                                       => return None,
+        }; // match
+
+        self.iter_resource_lineage(resource).find_map(|name| {
+            let access = self.resource_defaults.get(&(name, privilege))?;
+
+            Some((Query{resource: Some(name), role: None, privilege: Some(privilege)}, *access))
+        }) // find_map
+    } // query_defaults
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn defaults() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("public", None).is_ok());
+        assert!(acl.add_resource("blog", Some("public")).is_ok());
+        assert!(acl.add_resource("drafts", Some("blog")).is_ok());
+        assert!(acl.add_resource("internal", None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("drafts"), None).is_ok());
+        assert!(acl.deny(Some("guest"), None, Some("comment")).is_ok());
+        assert!(acl.set_resource_default("public", "view", Access::Allow).is_ok());
+        assert!(acl.set_resource_default("public", "comment", Access::Allow).is_ok());
+        assert!(acl.set_resource_default("drafts", "view", Access::Deny).is_ok());
+
+        // the nearest default decides if no rule matches
+        assert_eq!(acl.decide(Some("guest"), Some("blog"), Some("view")).matched,
+            Query{resource: Some("public"), role: None, privilege: Some("view")});
+        assert!(acl.is_allowed(None, Some("blog"), Some("view")));
+        assert!(acl.is_denied(Some("guest"), Some("drafts"), Some("view")));
+        assert!(acl.is_allowed(Some("staff"), Some("drafts"), Some("view")));
+        assert!(acl.is_denied(Some("guest"), Some("blog"), Some("comment")));
+        assert!(acl.is_allowed(None, Some("blog"), Some("comment")));
+
+        // other privileges, resources and wildcards fall through to the catch-all rule
+        assert!(acl.is_denied(Some("guest"), Some("blog"), Some("edit")));
+        assert!(acl.is_denied(Some("guest"), Some("internal"), Some("view")));
+        assert!(acl.is_denied(Some("guest"), Some("blog"), None));
+        assert!(acl.is_denied(Some("guest"), None, Some("view")));
+
+        assert_eq!(acl.get_resource_default("public", "view"), Some(Access::Allow));
+        assert_eq!(acl.get_resource_default("blog", "view"), None);
+        assert_eq!(acl.resource_defaults("public"), vec![("comment", Access::Allow), ("view", Access::Allow)]);
+        assert_eq!(acl.remove_resource_default("drafts", "view"), Ok(Some(Access::Deny)));
+        assert!(acl.is_allowed(Some("guest"), Some("drafts"), Some("view")));
+
+        // defaults are part of the fingerprint
+        let etag = acl.etag();
+
+        assert!(acl.set_resource_default("blog", "view", Access::Deny).is_ok());
+        assert_ne!(acl.etag(), etag);
+        assert!(acl.set_resource_default("blog", "view", Access::Allow).is_ok());
+        assert_ne!(acl.etag(), etag);
+        assert_eq!(acl.remove_resource_default("blog", "view"), Ok(Some(Access::Allow)));
+        assert_eq!(acl.etag(), etag);
+        assert!(acl.set_resource_default("internal", "view", Access::Deny).is_ok());
+        assert!(acl.remove_resource("internal").is_ok());
+        assert!(acl.add_resource("internal", None).is_ok());
+        assert_eq!(acl.etag(), etag);
+
+        assert_eq!(acl.set_resource_default("nothing", "view", Access::Allow), Err(Error::MissingResource(String::from("nothing"))));
+        acl.add_privilege("view");
+        assert_eq!(acl.set_resource_default("public", "edit", Access::Allow), Err(Error::MissingPrivilege(String::from("edit"))));
+        acl.lock();
+        assert_eq!(acl.set_resource_default("public", "view", Access::Deny), Err(Error::Locked));
+        assert!(acl.is_allowed(Some("guest"), Some("drafts"), Some("view")));
+    } // defaults
+
+    #[test]
+    fn views() {
+        use crate::shard::ShardedAcl;
+
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("public", None).is_ok());
+        assert!(acl.add_resource("blog", Some("public")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("blog"), Some("edit")).is_ok());
+        assert!(acl.set_resource_default("public", "view", Access::Allow).is_ok());
+
+        // residual policies, shards and reports decide privileges named by defaults only
+        let residual    = acl.specialize("staff").unwrap();
+        let report      = acl.to_markdown_report();
+        let mut sharded = ShardedAcl::new(acl, 4);
+
+        assert!(sharded.add_role("user:sally", vec!["staff"]).is_ok());
+        for resource in [None, Some("public"), Some("blog")] {
+            for privilege in [None, Some("view"), Some("edit"), Some("share")] {
+                let decision = sharded.base().decide(Some("staff"), resource, privilege);
+
+                assert_eq!(sharded.base().effective(Some("staff"), resource, privilege), (decision.matched, decision.rule));
+                assert_eq!(residual.is_allowed(None, resource, privilege), decision.is_allowed(), "{}", decision);
+                assert_eq!(sharded.decide(Some("user:sally"), resource, privilege).rule, decision.rule, "{}", decision);
+            } // for
+        } // for
+        assert!(report.contains("### staff\n\n| Resource | * | edit | view |\n| --- | --- | --- | --- |\n\
+            | * | deny | deny | deny |\n| public | deny | deny | allow |\n| blog | deny | allow | allow |\n"));
+    } // views
+
+} // mod tests
@@ -0,0 +1,301 @@
+//! Delegation of permissions.
+//!
+//! A role may delegate a subset of its own effective permissions to another role. The `Acl`
+//! verifies that the grantor actually holds the delegated privileges and tracks the rules defined
+//! by each delegation, so delegations can be revoked individually or en masse. Revoking a
+//! delegation also revokes the delegations transferring its privileges further.
+//!
+//! Every delegation expires. Expired delegations are revoked by `sweep_expired_delegations`,
+//! which should be called periodically.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::delegation::Constraints;
+//! # use std::time::{Duration, SystemTime};
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("editor", vec![]).unwrap();
+//! acl.add_role("intern", vec![]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.allow(Some("editor"), Some("news"), Some("publish")).unwrap();
+//!
+//! // cover for me while I'm on vacation
+//! let vacation = Constraints::until(SystemTime::now() + Duration::from_secs(14 * 24 * 3600));
+//! let id       = acl.delegate("editor", "intern", Some("news"), &["publish"], vacation).unwrap();
+//!
+//! assert!(acl.is_allowed(Some("intern"), Some("news"), Some("publish")));
+//! assert!(acl.delegate("intern", "editor", Some("news"), &["delete"], vacation).is_err());
+//!
+//! acl.revoke_delegation(id).unwrap();
+//! assert!(acl.is_denied(Some("intern"), Some("news"), Some("publish")));
+//! ```
+
+use crate::{Access, Acl, Error, Query, Resource, Rule};
+use log::{trace, warn};
+use std::time::SystemTime;
+
+
+// Delegation /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Constraints of a delegation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Constraints {
+    /// the point in time the delegation expires
+    pub expires:      SystemTime,
+    /// the delegate may delegate the privileges further
+    pub transferable: bool,
+} // struct Constraints
+
+impl Constraints {
+
+    /// Creates constraints for a delegation expiring at the given point in time, which may not be
+    /// transferred.
+    pub fn until(expires: SystemTime) -> Self {
+        Constraints{expires, transferable: false}
+    } // until
+
+    /// Allows the delegate to delegate the privileges further.
+    pub fn transferable(mut self) -> Self {
+        self.transferable = true;
+        self
+    } // transferable
+
+} // impl Constraints
+
+/// Permissions delegated from one role to another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delegation {
+    /// the id returned by `Acl::delegate`
+    pub id:          u64,
+    /// the role which delegated its permissions
+    pub from:        &'static str,
+    /// the role the permissions have been delegated to
+    pub to:          &'static str,
+    /// the resource or None for all resources
+    pub resource:    Resource,
+    /// the privileges, empty for all privileges
+    pub privileges:  Vec<&'static str>,
+    /// the constraints
+    pub constraints: Constraints,
+    /// the rules defined by this delegation
+    rules:           Vec<Query>,
+    /// the delegations the grantor holds privileges by
+    sources:         Vec<u64>,
+} // struct Delegation
+
+impl Acl {
+
+    /// Delegates privileges on resource from role `from` to role `to`. An empty list of privileges
+    /// delegates all privileges. Each privilege must be allowed for `from` and, if it has been
+    /// delegated to `from` itself, the delegation must be transferable. A transferred delegation
+    /// expires no later than the delegations it is transferred from. Rules explicitly defined for
+    /// `to` are kept. Returns the id of the delegation.
+    pub fn delegate(&mut self, from: &'static str, to: &'static str, resource: Resource, privileges: &[&'static str], mut constraints: Constraints) -> Result<u64, Error> {
+        trace!("delegating {:?} on {:?} from {} to {}", privileges, resource, from, to);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        for name in [from, to].iter() {
+            if !self.roles.contains_key(name) {
+                return Err(Error::MissingRole(String::from(*name)));
+            } // if
+        } // for
+        if let Some(name) = resource {
+            if !self.resources.contains_key(name) {
+                return Err(Error::MissingResource(String::from(name)));
+            } // if
+        } // if
+
+        let queries: Vec<Query> = if privileges.is_empty() {
+            vec![Query{resource, role: Some(to), privilege: None}]
+        } else {
+            privileges.iter().map(|privilege| Query{resource, role: Some(to), privilege: Some(privilege)}).collect()
+        }; // if
+
+        let mut sources = vec![];
+
+        // the grantor must hold every delegated privilege
+        for query in &queries {
+            let decision = self.evaluate(Some(from), resource, query.privilege);
+
+            if decision.is_denied() {
+                warn!("{} may not delegate {}", from, decision.query);
+                return Err(Error::NotPermitted(decision.query.to_string()));
+            } // if
+            let delegated = self.delegations.iter().find(|delegation| delegation.rules.contains(&decision.matched));
+
+            if let Some(delegation) = delegated {
+                if !delegation.constraints.transferable {
+                    warn!("{} may not transfer {}", from, decision.query);
+                    return Err(Error::NotPermitted(decision.query.to_string()));
+                } // if
+                constraints.expires = constraints.expires.min(delegation.constraints.expires);
+                sources.push(delegation.id);
+            } // if
+        } // for
+
+        let mut rules = vec![];
+
+        for query in queries {
+            if !self.rules.contains_key(&query) {
+                self.insert_rule(query, Rule{acc: Access::Allow, cond: None});
+                rules.push(query);
+            } // if
+        } // for
+        self.next_delegation += 1;
+        self.delegations.push(Delegation{
+            id:          self.next_delegation,
+            from,
+            to,
+            resource,
+            privileges:  privileges.to_vec(),
+            constraints,
+            rules,
+            sources,
+        }); // Delegation
+        Ok(self.next_delegation)
+    } // delegate
+
+    /// Returns an iterator over all delegations in order of creation.
+    pub fn delegations(&self) -> impl Iterator<Item = &Delegation> {
+        self.delegations.iter()
+    } // delegations
+
+    /// Revokes the delegation with id and the delegations transferring its privileges and removes
+    /// their rules. Returns true if the delegation existed. Returns an error if the `Acl` is
+    /// locked.
+    pub fn revoke_delegation(&mut self, id: u64) -> Result<bool, Error> {
+        Ok(self.revoke_delegations_if(|delegation| delegation.id == id)? > 0)
+    } // revoke_delegation
+
+    /// Revokes all delegations granted by role and the delegations transferring their privileges
+    /// and removes their rules. Returns the number of delegations granted by role. Returns an
+    /// error if the `Acl` is locked.
+    pub fn revoke_delegations(&mut self, from: &'static str) -> Result<usize, Error> {
+        self.revoke_delegations_if(|delegation| delegation.from == from)
+    } // revoke_delegations
+
+    /// Revokes all delegations expired by now and the delegations transferring their privileges.
+    /// Returns the number of expired delegations. Returns an error if the `Acl` is locked.
+    pub fn sweep_expired_delegations(&mut self) -> Result<usize, Error> {
+        let now = SystemTime::now();
+
+        trace!("sweeping expired delegations");
+        self.revoke_delegations_if(|delegation| delegation.constraints.expires <= now)
+    } // sweep_expired_delegations
+
+    pub(crate) fn revoke_delegations_if<F: Fn(&Delegation) -> bool>(&mut self, filter: F) -> Result<usize, Error> {
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let mut revoked: Vec<u64> = self.delegations.iter().filter(|delegation| filter(delegation))
+            .map(|delegation| delegation.id).collect();
+        let count = revoked.len();
+
+        // delegations are transferred after their sources have been created
+        for delegation in &self.delegations {
+            if delegation.sources.iter().any(|id| revoked.contains(id)) && !revoked.contains(&delegation.id) {
+                revoked.push(delegation.id);
+            } // if
+        } // for
+        let (revoked, kept): (Vec<Delegation>, Vec<Delegation>) = self.delegations.drain(..)
+            .partition(|delegation| revoked.contains(&delegation.id));
+
+        self.delegations = kept;
+        for delegation in &revoked {
+            trace!("revoking delegation {} from {} to {}", delegation.id, delegation.from, delegation.to);
+            for query in &delegation.rules {
+                self.remove_rule(query);
+                self.meta.remove(query);
+            } // for
+        } // for
+        Ok(count)
+    } // revoke_delegations_if
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::time::Duration;
+    use test_env_log::test;
+
+    fn in_days(days: u64) -> SystemTime {
+        SystemTime::now() + Duration::from_secs(days * 24 * 3600)
+    } // in_days
+
+    #[test]
+    fn delegate() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("editor", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_role("intern", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("editor"), Some("news"), None).is_ok());
+        assert!(acl.deny(Some("intern"), Some("news"), Some("delete")).is_ok());
+
+        let week   = Constraints::until(in_days(7));
+        let first  = acl.delegate("editor", "staff", Some("news"), &["publish", "delete"], week.transferable()).unwrap();
+        let second = acl.delegate("editor", "intern", Some("news"), &["delete"], week).unwrap();
+
+        assert!(acl.is_allowed(Some("staff"), Some("news"), Some("publish")));
+        assert!(acl.is_denied (Some("staff"), Some("news"), Some("edit")));
+
+        // explicit rules are kept
+        assert!(acl.is_denied(Some("intern"), Some("news"), Some("delete")));
+        assert_eq!(acl.delegations().map(|delegation| delegation.id).collect::<Vec<_>>(), vec![first, second]);
+
+        // grantors must hold the privileges, transferring requires a transferable delegation
+        assert_eq!(acl.delegate("staff", "intern", Some("news"), &["edit"], week),
+            Err(Error::NotPermitted(String::from("staff→news: edit"))));
+        assert!(acl.delegate("staff", "intern", Some("news"), &["publish"], week).is_ok());
+        assert!(acl.is_allowed(Some("intern"), Some("news"), Some("publish")));
+        assert_eq!(acl.delegate("intern", "staff", Some("news"), &["publish"], week),
+            Err(Error::NotPermitted(String::from("intern→news: publish"))));
+        assert_eq!(acl.delegate("admin", "staff", None, &[], week), Err(Error::MissingRole(String::from("admin"))));
+
+        assert_eq!(acl.revoke_delegations("editor"), Ok(2));
+        assert_eq!(acl.delegations().count(), 0);
+        assert!(acl.is_denied (Some("staff"), Some("news"), Some("publish")));
+        assert!(acl.is_denied (Some("intern"), Some("news"), Some("publish")));
+        assert!(acl.is_denied (Some("intern"), Some("news"), Some("delete")));
+        assert_eq!(acl.revoke_delegation(first), Ok(false));
+
+        acl.lock();
+        assert_eq!(acl.revoke_delegations("staff"), Err(Error::Locked));
+    } // delegate
+
+    #[test]
+    fn expiry() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("editor", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_role("intern", vec![]).is_ok());
+        assert!(acl.allow(Some("editor"), None, None).is_ok());
+
+        let past   = SystemTime::now() - Duration::from_secs(1);
+        let first  = acl.delegate("editor", "staff", None, &["edit"], Constraints::until(in_days(1)).transferable()).unwrap();
+        let second = acl.delegate("staff", "intern", None, &["edit"], Constraints::until(in_days(30))).unwrap();
+
+        // transferred delegations don't outlive their source
+        let expires: Vec<SystemTime> = acl.delegations().map(|delegation| delegation.constraints.expires).collect();
+
+        assert_eq!(expires[0], expires[1]);
+        assert_eq!(acl.sweep_expired_delegations(), Ok(0));
+        assert!(acl.delegate("editor", "staff", None, &["view"], Constraints::until(past)).is_ok());
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        assert_eq!(acl.sweep_expired_delegations(), Ok(1));
+        assert!(acl.is_denied(Some("staff"), None, Some("view")));
+        assert_eq!(acl.delegations().map(|delegation| delegation.id).collect::<Vec<_>>(), vec![first, second]);
+    } // expiry
+
+} // mod tests
@@ -0,0 +1,457 @@
+//! Roles and resources as domain types.
+//!
+//! Domain types, e.g. users or documents, implement `AclRole` or `AclResource` to be queried
+//! directly. Their ids are resolved to the roles and resources defined in the `Acl`. An undefined
+//! id is treated like an unknown role or resource, i.e. only wildcard rules apply.
+//!
+//! With the `derive` feature the traits can be derived. The id is either taken from the field
+//! annotated with `#[acl(id)]`, which must implement `AsRef<str>`, or fixed by `#[acl(id = "...")]`
+//! on the type.
+//!
+//! Privileges can be declared as an enum implementing `AclPrivilege`. Once registered with
+//! `register_privileges` or `add_privilege`, rules for unregistered privileges are rejected with
+//! `Error::MissingPrivilege`, unless `set_lenient_privileges` keeps accepting them while existing
+//! policies are migrated. The derive maps each
+//! unit variant to its snake case name, e.g. `PublishDraft` to `publish_draft`, unless renamed by
+//! `#[acl(id = "...")]` on the variant, and converts the enum into a privilege for all queries.
+//! Labels and descriptions for UIs are given by `#[acl(label = "...", description = "...")]` on
+//! the variant, see module `privileges`:
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::domain::AclPrivilege;
+//! #[derive(Clone, Copy)]
+//! enum Privilege {
+//!     View,
+//!     Edit,
+//! }
+//!
+//! impl AclPrivilege for Privilege {
+//!     const ALL: &'static [Self] = &[Privilege::View, Privilege::Edit];
+//!
+//!     fn privilege_id(&self) -> &'static str {
+//!         match self {
+//!             Privilege::View => "view",
+//!             Privilege::Edit => "edit",
+//!         }
+//!     }
+//!
+//!     fn label(&self) -> Option<&'static str> {
+//!         match self {
+//!             Privilege::View => Some("View"),
+//!             Privilege::Edit => Some("Edit"),
+//!         }
+//!     }
+//! }
+//!
+//! let mut acl = Acl::new();
+//!
+//! acl.register_privileges::<Privilege>();
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, Some(Privilege::View.privilege_id())).unwrap();
+//!
+//! assert!(acl.allow(Some("guest"), None, Some("veiw")).is_err());
+//! assert_eq!(acl.get_privilege_label("edit"), "Edit");
+//! ```
+//!
+//! Handlers are guarded by the `#[require_privilege]` attribute. It takes the `Acl` and the role
+//! of the caller from a parameter implementing `AclContext`, named `ctx` unless given by
+//! `context = "..."`, and returns early with `AccessDenied`, converted by `From`, if access is
+//! denied:
+//!
+//! ```ignore
+//! #[require_privilege(resource = "newsletter", privilege = "publish")]
+//! fn publish(ctx: &Request, issue: u32) -> Result<(), AppError> {
+//!     // only reached if the role of the request may publish the newsletter
+//! }
+//! ```
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::domain::{AclResource, AclRole};
+//! struct User {
+//!     role: String,
+//! }
+//!
+//! impl AclRole for User {
+//!     fn role_id(&self) -> &str {
+//!         &self.role
+//!     }
+//! }
+//!
+//! struct Newsletter;
+//!
+//! impl AclResource for Newsletter {
+//!     fn resource_id(&self) -> &str {
+//!         "newsletter"
+//!     }
+//! }
+//!
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("marketing", vec![]).unwrap();
+//! acl.add_resource("newsletter", None).unwrap();
+//! acl.allow(Some("marketing"), Some("newsletter"), Some("publish")).unwrap();
+//!
+//! let user = User{role: String::from("marketing")};
+//!
+//! assert!(acl.is_allowed_with(&user, &Newsletter, Some("publish")));
+//! ```
+//!
+//! Rules are defined for domain types by `allow_with` and `deny_with`, None being the wildcard.
+//! Names stay strings within the `Acl`, so domain types of any shape share one policy: an enum of
+//! roles or resources implementing the traits rules out misspelled variants at compile time. Only
+//! the variant is checked, the string returned by `role_id` or `resource_id` isn't: a misspelled
+//! id is an undefined role or resource at runtime.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::domain::{AclResource, AclRole};
+//! enum Role {
+//!     Editor,
+//! }
+//!
+//! impl AclRole for Role {
+//!     fn role_id(&self) -> &str {
+//!         match self {
+//!             Role::Editor => "editor",
+//!         }
+//!     }
+//! }
+//!
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("editor", vec![]).unwrap();
+//! acl.allow_with(Some(&Role::Editor), None::<&dyn AclResource>, Some("publish")).unwrap();
+//!
+//! assert!(acl.is_allowed(Some("editor"), None, Some("publish")));
+//! ```
+//!
+//! A misspelled role doesn't compile:
+//!
+//! ```compile_fail
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::domain::{AclResource, AclRole};
+//! # enum Role {
+//! #     Editor,
+//! # }
+//! # impl AclRole for Role {
+//! #     fn role_id(&self) -> &str {
+//! #         match self {
+//! #             Role::Editor => "editor",
+//! #         }
+//! #     }
+//! # }
+//! let mut acl = Acl::new();
+//!
+//! acl.allow_with(Some(&Role::Editr), None::<&dyn AclResource>, Some("publish")).unwrap();
+//! ```
+
+use crate::{Access, Acl, Decision, Error, Privilege, Resource};
+use crate::privileges::PrivilegeInfo;
+use log::{debug, trace, warn};
+use std::fmt;
+
+#[cfg(feature = "derive")]
+pub use zorq_acl_derive::{require_privilege, AclPrivilege, AclResource, AclRole};
+
+/// A domain type acting as role.
+pub trait AclRole {
+
+    /// Returns the id of the role.
+    fn role_id(&self) -> &str;
+
+} // trait AclRole
+
+/// A domain type acting as resource.
+pub trait AclResource {
+
+    /// Returns the id of the resource.
+    fn resource_id(&self) -> &str;
+
+} // trait AclResource
+
+/// An enum of privileges.
+pub trait AclPrivilege: Copy + 'static {
+
+    /// All privileges.
+    const ALL: &'static [Self];
+
+    /// Returns the name of the privilege.
+    fn privilege_id(&self) -> &'static str;
+
+    /// Returns the privilege named id.
+    fn from_privilege_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|privilege| privilege.privilege_id() == id)
+    } // from_privilege_id
+
+    /// Returns a human-readable label of the privilege, see module `privileges`.
+    fn label(&self) -> Option<&'static str> {
+        None
+    } // label
+
+    /// Returns a human-readable description of the privilege, see module `privileges`.
+    fn description(&self) -> Option<&'static str> {
+        None
+    } // description
+
+} // trait AclPrivilege
+
+/// The context of a guarded handler, see `#[require_privilege]`.
+pub trait AclContext {
+
+    /// Returns the `Acl` to query.
+    fn acl(&self) -> &Acl;
+
+    /// Returns the id of the role of the caller or None if it has none.
+    fn role_id(&self) -> Option<&str>;
+
+    /// Returns an error unless privilege on resource is allowed for the role of the caller.
+    fn require(&self, resource: Resource, privilege: Privilege) -> Result<(), AccessDenied> {
+        let acl      = self.acl();
+        let role     = self.role_id().and_then(|id| acl.roles.get_key_value(id)).map(|(name, _)| *name);
+        let decision = acl.decide(role, resource, privilege);
+
+        if decision.is_denied() {
+            debug!("access denied: {}", decision);
+            return Err(AccessDenied{decision: Box::new(decision)});
+        } // if
+        Ok(())
+    } // require
+
+} // trait AclContext
+
+/// The error returned by a guarded handler if access is denied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessDenied {
+    /// the denying decision, boxed to keep results small
+    pub decision: Box<Decision>,
+} // struct AccessDenied
+
+impl fmt::Display for AccessDenied {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "Access denied: {}", self.decision)
+    } // fmt
+
+} // impl fmt::Display for AccessDenied
+
+impl Acl {
+
+    /// Registers all privileges of P with their labels and descriptions. Once any privilege is
+    /// registered, rules may only be defined for registered privileges.
+    pub fn register_privileges<P: AclPrivilege>(&mut self) {
+        for privilege in P::ALL {
+            trace!("registering privilege {}", privilege.privilege_id());
+            self.privileges.insert(privilege.privilege_id());
+            if privilege.label().is_some() || privilege.description().is_some() {
+                self.privilege_info.insert(privilege.privilege_id(), PrivilegeInfo{
+                    label:       privilege.label().map(String::from),
+                    description: privilege.description().map(String::from),
+                }); // PrivilegeInfo
+            } // if
+        } // for
+    } // register_privileges
+
+    /// Registers the privilege name. Once any privilege is registered, rules may only be defined
+    /// for registered privileges.
+    pub fn add_privilege(&mut self, name: &'static str) {
+        trace!("registering privilege {}", name);
+        self.privileges.insert(name);
+    } // add_privilege
+
+    /// Accepts rules for unregistered privileges with a warning if enabled, like before any
+    /// privilege was registered. This is meant for migrating existing policies, it's disabled by
+    /// default. Privileges declared per resource are still checked, see module `privileges`.
+    #[inline]
+    pub fn set_lenient_privileges(&mut self, enabled: bool) {
+        trace!("setting lenient privileges to {}", enabled);
+        self.lenient_privileges = enabled;
+    } // set_lenient_privileges
+
+    /// Returns true if rules for unregistered privileges are accepted.
+    #[inline]
+    pub fn is_lenient_privileges(&self) -> bool {
+        self.lenient_privileges
+    } // is_lenient_privileges
+
+    /// Returns true if the privilege name is registered.
+    #[inline]
+    pub fn has_privilege(&self, name: &str) -> bool {
+        self.privileges.contains(name)
+    } // has_privilege
+
+    /// Returns an iterator over the registered privileges.
+    pub fn privileges(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.privileges.iter().copied()
+    } // privileges
+
+    /// Returns an error if privileges are registered and privilege isn't one of them, unless
+    /// lenient, or if privileges are declared for resource and privilege isn't one of them, see
+    /// module `privileges`.
+    pub(crate) fn check_privilege(&self, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        match (resource, privilege) {
+            (_, Some(name)) if !self.lenient_privileges && !self.is_registered(name) =>
+                Err(Error::MissingPrivilege(String::from(name))),
+            (Some(resource), Some(name)) if !self.is_privilege_declared(resource, name) =>
+                Err(Error::MissingPrivilege(format!("{} on {}", name, resource))),
+            (_, Some(name)) if !self.is_registered(name) => {
+                warn!("accepting unregistered privilege: {}", name);
+                Ok(())
+            }, // lenient
+            _ => Ok(()),
+        } // match
+    } // check_privilege
+
+    /// Returns true if no privileges are registered or name is one of them.
+    fn is_registered(&self, name: &'static str) -> bool {
+        self.privileges.is_empty() || self.privileges.contains(name)
+    } // is_registered
+
+    /// Like `decide`, but takes domain types as role and resource.
+    pub fn decide_with<R: AclRole + ?Sized, S: AclResource + ?Sized>(&self, role: &R, resource: &S, privilege: Privilege) -> Decision {
+        let role     = self.roles.get_key_value(role.role_id()).map(|(name, _)| *name);
+        let resource = self.resources.get_key_value(resource.resource_id()).map(|(name, _)| *name);
+
+        self.decide(role, resource, privilege)
+    } // decide_with
+
+    /// Returns true if privilege is allowed for the domain role on the domain resource.
+    #[inline]
+    pub fn is_allowed_with<R: AclRole + ?Sized, S: AclResource + ?Sized>(&self, role: &R, resource: &S, privilege: Privilege) -> bool {
+        self.decide_with(role, resource, privilege).is_allowed()
+    } // is_allowed_with
+
+    /// Returns true if privilege is denied for the domain role on the domain resource.
+    #[inline]
+    pub fn is_denied_with<R: AclRole + ?Sized, S: AclResource + ?Sized>(&self, role: &R, resource: &S, privilege: Privilege) -> bool {
+        self.decide_with(role, resource, privilege).is_denied()
+    } // is_denied_with
+
+    /// Like `set_rule`, but takes domain types as role and resource. None is the wildcard like in
+    /// `set_rule`, e.g. `None::<&dyn AclRole>`. Returns an error if their ids aren't defined.
+    pub fn set_rule_with<R: AclRole + ?Sized, S: AclResource + ?Sized>(&mut self, role: Option<&R>, resource: Option<&S>, privilege: Privilege, access: Access) -> Result<(), Error> {
+        let role     = role.map(|role| self.roles.get_key_value(role.role_id()).map(|(name, _)| *name)
+            .ok_or_else(|| Error::MissingRole(String::from(role.role_id())))).transpose()?;
+        let resource = resource.map(|resource| self.resources.get_key_value(resource.resource_id()).map(|(name, _)| *name)
+            .ok_or_else(|| Error::MissingResource(String::from(resource.resource_id())))).transpose()?;
+
+        self.set_rule(role, resource, privilege, access)
+    } // set_rule_with
+
+    /// Allows privilege for the domain role on the domain resource, see `set_rule_with`.
+    #[inline]
+    pub fn allow_with<R: AclRole + ?Sized, S: AclResource + ?Sized>(&mut self, role: Option<&R>, resource: Option<&S>, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule_with(role, resource, privilege, Access::Allow)
+    } // allow_with
+
+    /// Denies privilege for the domain role on the domain resource, see `set_rule_with`.
+    #[inline]
+    pub fn deny_with<R: AclRole + ?Sized, S: AclResource + ?Sized>(&mut self, role: Option<&R>, resource: Option<&S>, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule_with(role, resource, privilege, Access::Deny)
+    } // deny_with
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Privilege {
+        View,
+        Edit,
+    } // enum Privilege
+
+    impl AclPrivilege for Privilege {
+
+        const ALL: &'static [Self] = &[Privilege::View, Privilege::Edit];
+
+        fn privilege_id(&self) -> &'static str {
+            match self {
+                Privilege::View => "view",
+                Privilege::Edit => "edit",
+            } // match
+        } // privilege_id
+
+    } // impl AclPrivilege for Privilege
+
+    impl AclRole for str {
+
+        fn role_id(&self) -> &str {
+            self
+        } // role_id
+
+    } // impl AclRole for str
+
+    impl AclResource for str {
+
+        fn resource_id(&self) -> &str {
+            self
+        } // resource_id
+
+    } // impl AclResource for str
+
+    #[test]
+    fn domain() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("report", None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("report"), Some("edit")).is_ok());
+
+        let role = String::from("staff");
+
+        assert!(acl.is_allowed_with(role.as_str(), "report", Some("edit")));
+        assert!(acl.is_denied_with ("guest", "report", Some("edit")));
+        assert!(acl.deny_with(Some(role.as_str()), Some("report"), Some("edit")).is_ok());
+        assert!(acl.is_denied_with(role.as_str(), "report", Some("edit")));
+        assert_eq!(acl.allow_with(Some("guest"), Some("report"), None), Err(Error::MissingRole(String::from("guest"))));
+        assert_eq!(acl.allow_with(Some("staff"), Some("archive"), None), Err(Error::MissingResource(String::from("archive"))));
+        assert!(acl.allow_with(Some("staff"), Some("report"), Some("edit")).is_ok());
+
+        // wildcards
+        assert!(acl.allow_with(None::<&dyn AclRole>, Some("report"), Some("view")).is_ok());
+        assert!(acl.is_allowed_with("guest", "report", Some("view")));
+        assert!(acl.deny_with(Some("staff"), None::<&str>, Some("print")).is_ok());
+        assert_eq!(acl.decide_with(role.as_str(), "report", Some("print")).matched, crate::Query{resource: None, role: Some("staff"), privilege: Some("print")});
+
+        acl.register_privileges::<Privilege>();
+        assert_eq!(Privilege::from_privilege_id("edit"), Some(Privilege::Edit));
+        assert!(acl.deny(Some("staff"), None, Some("view")).is_ok());
+        assert_eq!(acl.deny(Some("staff"), None, Some("publish")), Err(Error::MissingPrivilege(String::from("publish"))));
+        assert!(crate::overlay::SessionOverlay::new(&acl).allow(None, None, Some("publish")).is_err());
+    } // domain
+
+    #[test]
+    fn registered() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource_with_privileges("report", None, &["view", "publish"]).is_ok());
+        assert!(acl.allow(Some("staff"), None, Some("print")).is_ok());
+
+        acl.add_privilege("view");
+        assert!(acl.has_privilege("view"));
+        assert_eq!(acl.allow(Some("staff"), None, Some("print")), Err(Error::MissingPrivilege(String::from("print"))));
+
+        // lenient privileges are accepted unless undeclared on the resource
+        acl.set_lenient_privileges(true);
+        assert!(acl.is_lenient_privileges());
+        assert!(acl.allow(Some("staff"), None, Some("print")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("report"), Some("publish")).is_ok());
+        assert_eq!(acl.allow(Some("staff"), Some("report"), Some("print")), Err(Error::MissingPrivilege(String::from("print on report"))));
+        assert!(acl.is_allowed(Some("staff"), Some("report"), Some("publish")));
+    } // registered
+
+} // mod tests
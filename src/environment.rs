@@ -0,0 +1,187 @@
+//! Environment-scoped rules.
+//!
+//! Rules may be scoped to an environment, e.g. "dev", "staging" or "prod", so a single policy can
+//! contain environment-specific exceptions. The `Acl` is configured with its active environment,
+//! rules of other environments are ignored. The rules of the active environment replace the rules
+//! defined for the same queries without environment, and changes to those queries apply to the
+//! active environment. No environment is active by default. Unlike rule groups, environments may
+//! share queries, but a query of an environment can't belong to a group, see module `group`.
+//!
+//! Switching the environment doesn't define new rules, so it's permitted while the `Acl` is locked
+//! and purges the cache.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.add_resource("database", None).unwrap();
+//! acl.deny(Some("staff"), Some("database"), None).unwrap();
+//! acl.allow_in("dev", Some("staff"), Some("database"), None).unwrap();
+//!
+//! assert!(acl.is_denied(Some("staff"), Some("database"), Some("drop")));
+//! acl.set_environment(Some("dev"));
+//! assert!(acl.is_allowed(Some("staff"), Some("database"), Some("drop")));
+//! acl.set_environment(Some("prod"));
+//! assert!(acl.is_denied(Some("staff"), Some("database"), Some("drop")));
+//! ```
+
+use crate::{Access, Acl, Error, Privilege, Query, Resource, Role};
+use crate::group::Group;
+use log::trace;
+
+impl Acl {
+
+    /// Returns the active environment.
+    #[inline]
+    pub fn environment(&self) -> Option<&'static str> {
+        self.environment
+    } // environment
+
+    /// Activates the rules of environment and ignores the rules of all other environments. None
+    /// ignores all environment-scoped rules.
+    pub fn set_environment(&mut self, environment: Option<&'static str>) {
+        trace!("setting environment {:?}", environment);
+        if environment == self.environment {
+            return;
+        } // if
+        for name in [self.environment, environment].iter().flatten().copied() {
+            if let Some(mut group) = self.environments.remove(name) {
+                self.toggle_group(&mut group);
+                self.environments.insert(name, group);
+            } // if
+        } // for
+        self.environment = environment;
+        self.purge_cache();
+    } // set_environment
+
+    /// Returns an iterator over all environments with rules in ascending order.
+    pub fn environments(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.environments.keys().copied()
+    } // environments
+
+    /// Sets a rule which only applies in environment, see `set_rule`. Returns an error for the
+    /// catch-all rule and for queries of a group.
+    #[inline]
+    pub fn set_environment_rule(&mut self, environment: &'static str, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
+        self.set_scoped_rule(environment, Query{resource, role, privilege}, |acl| acl.set_rule(role, resource, privilege, access))
+    } // set_environment_rule
+
+    /// Allows privilege for role on resource in environment.
+    #[inline]
+    pub fn allow_in(&mut self, environment: &'static str, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_environment_rule(environment, role, resource, privilege, Access::Allow)
+    } // allow_in
+
+    /// Denies privilege for role on resource in environment.
+    #[inline]
+    pub fn deny_in(&mut self, environment: &'static str, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_environment_rule(environment, role, resource, privilege, Access::Deny)
+    } // deny_in
+
+    /// Sets the rule for query of environment with set.
+    pub(crate) fn set_scoped_rule<F>(&mut self, environment: &'static str, query: Query, set: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Acl) -> Result<(), Error>
+    {
+        trace!("setting rule for {} in environment {}", query, environment);
+        if query == Query::ALL {
+            return Err(Error::NotPermitted(format!("environment-scoped rule {}", query)));
+        } // if
+        if let Some((name, _)) = self.groups.iter().find(|(_, group)| group.aside.contains_key(&query)) {
+            return Err(Error::NotPermitted(format!("rule {} of group {}", query, name)));
+        } // if
+
+        let mut group = self.environments.remove(environment)
+            .unwrap_or_else(|| Group{enabled: self.environment == Some(environment), ..Group::default()});
+        let result    = self.set_grouped_rule(&mut group, query, set);
+
+        if !group.aside.is_empty() {
+            self.environments.insert(environment, group);
+        } // if
+        result
+    } // set_scoped_rule
+
+    /// Returns all rules except the catch-all rule with the environment they are scoped to,
+    /// regardless of the active environment, ordered by query and environment.
+    #[cfg(feature = "json")]
+    pub(crate) fn scoped_rules(&self) -> Vec<(Query, crate::Rule, Option<&'static str>)> {
+        let active    = self.environment.and_then(|name| self.environments.get(name));
+        let mut rules = vec![];
+
+        for (query, rule) in self.rules.iter().filter(|(query, _)| **query != Query::ALL) {
+            // the unscoped rules replaced by the active environment are set aside
+            match active.and_then(|group| group.aside.get(query)) {
+                Some(aside) => rules.extend(aside.map(|rule| (*query, rule, None))),
+                None        => rules.push((*query, *rule, None)),
+            } // match
+        } // for
+        for (name, group) in &self.environments {
+            for (query, aside) in &group.aside {
+                let rule = if group.enabled { self.rules.get(query).copied() } else { *aside };
+
+                rules.extend(rule.map(|rule| (*query, rule, Some(*name))));
+            } // for
+        } // for
+        rules.sort_by_key(|(query, _, environment)| (*query, *environment));
+        rules
+    } // scoped_rules
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn environment() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("database", None).is_ok());
+        assert!(acl.deny(Some("staff"), Some("database"), None).is_ok());
+        assert!(acl.allow_in("dev", Some("staff"), Some("database"), None).is_ok());
+        assert!(acl.allow_in("staging", Some("staff"), Some("database"), Some("read")).is_ok());
+        assert!(acl.allow_in("staging", Some("staff"), Some("database"), None).is_ok());
+        assert!(acl.deny_in("staging", Some("staff"), Some("database"), None).is_ok());
+        assert_eq!(acl.environment(), None);
+        assert_eq!(acl.environments().collect::<Vec<_>>(), vec!["dev", "staging"]);
+        assert!(acl.is_denied(Some("staff"), Some("database"), Some("read")));
+
+        acl.lock();
+        acl.set_environment(Some("staging"));
+        assert_eq!(acl.environment(), Some("staging"));
+        assert!(acl.is_allowed(Some("staff"), Some("database"), Some("read")));
+        assert!(acl.is_denied (Some("staff"), Some("database"), Some("write")));
+        acl.set_environment(Some("dev"));
+        assert!(acl.is_allowed(Some("staff"), Some("database"), Some("write")));
+        acl.set_environment(None);
+        assert!(acl.is_denied(Some("staff"), Some("database"), Some("read")));
+        acl.unlock();
+
+        // rules set while the environment is active
+        acl.set_environment(Some("dev"));
+        assert!(acl.allow_in("dev", Some("staff"), None, None).is_ok());
+        assert!(acl.allow_in("prod", Some("staff"), None, Some("read")).is_ok());
+        assert!(acl.is_allowed(Some("staff"), None, Some("write")));
+        assert!(acl.is_allowed(Some("staff"), None, Some("read")));
+        acl.set_environment(Some("prod"));
+        assert!(acl.is_denied (Some("staff"), None, Some("write")));
+        assert!(acl.is_allowed(Some("staff"), None, Some("read")));
+
+        assert!(acl.allow_in("dev", None, None, None).is_err());
+        assert!(acl.group("beta", |group| group.allow(Some("staff"), None, None)).is_err());
+        assert!(acl.group("beta", |group| group.allow(None, Some("database"), None)).is_ok());
+        assert_eq!(acl.allow_in("dev", None, Some("database"), None),
+            Err(Error::NotPermitted(String::from("rule *→database: * of group beta"))));
+        assert!(acl.allow_in("dev", Some("nobody"), None, None).is_err());
+    } // environment
+
+} // mod tests
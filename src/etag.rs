@@ -0,0 +1,268 @@
+//! Policy fingerprints for change detection.
+//!
+//! The fingerprint is a hash over the roles, resources, rules, rule priorities, resource defaults,
+//! subject overrides, quota limits, bypass roles and default role of an `Acl`, and over the parent
+//! order, the role combination and the laminas compatibility mode unless they are the default.
+//! The usage of quotas isn't part of the policy. The fingerprint is independent of the order of
+//! definition and stable across processes and platforms, so replicas holding the same policy
+//! report the same fingerprint. Each mutation updates the fingerprint incrementally, reading it is
+//! free. `etag` formats the fingerprint as HTTP entity tag.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut primary = Acl::new();
+//! let mut replica = Acl::new();
+//!
+//! primary.add_role("guest", vec![]).unwrap();
+//! primary.add_role("staff", vec![]).unwrap();
+//! replica.add_role("staff", vec![]).unwrap();
+//! replica.add_role("guest", vec![]).unwrap();
+//! assert_eq!(primary.etag(), replica.etag());
+//!
+//! primary.allow(Some("guest"), None, Some("view")).unwrap();
+//! assert_ne!(primary.etag(), replica.etag());
+//! ```
+
+use crate::{Access, Acl, ParentOrder, Query, Rule};
+use crate::combine::RoleCombination;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME:  u64 = 0x0000_0100_0000_01b3;
+
+/// A part of the policy contributing to the fingerprint.
+pub(crate) enum Item<'a> {
+    /// a role with its parents in search order
+    Role(&'static str, &'a [&'static str]),
+    Resource(&'static str, Option<&'static str>),
+    Rule(&'a Query, Rule),
+    Priority(&'a Query, i32),
+    /// the default access of a privilege on a resource
+    Default(&'static str, &'static str, Access),
+    /// an override of a subject
+    Subject(&'static str, &'a Query, Rule),
+    /// the limit of the quota of a rule
+    Quota(&'a Query, u32),
+    Bypass(&'static str),
+    DefaultRole(&'static str),
+    /// a parent order other than the default
+    ParentOrder(ParentOrder),
+    /// a role combination other than the default
+    RoleCombination(RoleCombination),
+    /// the enabled laminas compatibility mode
+    Compat,
+} // enum Item
+
+impl<'a> Item<'a> {
+
+    /// Returns the FNV-1a hash of the canonical form of the item.
+    fn hash(&self) -> u64 {
+        let canonical = match self {
+            Item::Role(name, parents)              => format!("role {:?} {:?}", name, parents),
+            Item::Resource(name, parent)           => format!("resource {:?} {:?}", name, parent),
+            Item::Rule(query, rule)                => format!("rule {} {:?} {:?} {:?}", rule, query.role, query.resource, query.privilege),
+            Item::Priority(query, value)           => format!("priority {} {:?} {:?} {:?}", value, query.role, query.resource, query.privilege),
+            Item::Default(name, privilege, access) => format!("default {} {:?} {:?}", access, name, privilege),
+            Item::Subject(subject, query, rule)    => format!("subject {} {:?} {:?} {:?}", rule, subject, query.resource, query.privilege),
+            Item::Quota(query, limit)              => format!("quota {} {:?} {:?} {:?}", limit, query.role, query.resource, query.privilege),
+            Item::Bypass(name)                     => format!("bypass {:?}", name),
+            Item::DefaultRole(name)                => format!("default role {:?}", name),
+            Item::ParentOrder(order)               => format!("parent order {:?}", order),
+            Item::RoleCombination(combination)     => format!("role combination {:?}", combination),
+            Item::Compat                           => String::from("laminas compat"),
+        }; // match
+
+        fnv1a(canonical.as_bytes())
+    } // hash
+
+} // impl Item
+
+/// Returns the FNV-1a hash of bytes, which is stable across processes and platforms.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME))
+} // fnv1a
+
+impl Acl {
+
+    /// Returns the fingerprint of the policy.
+    #[inline]
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    } // fingerprint
+
+    /// Returns the fingerprint of the policy as strong HTTP entity tag, e.g. for `If-None-Match`.
+    pub fn etag(&self) -> String {
+        format!("\"{:016x}\"", self.fingerprint)
+    } // etag
+
+    /// Adds or removes item to or from the fingerprint. Items are combined by wrapping addition, so
+    /// the order of mutations doesn't matter. Advances the generation, see module `cache`.
+    pub(crate) fn track(&mut self, item: Item, added: bool) {
+        let hash = item.hash();
+
+        self.advance_generation();
+
+        self.fingerprint = if added {
+            self.fingerprint.wrapping_add(hash)
+        } else {
+            self.fingerprint.wrapping_sub(hash)
+        }; // if
+    } // track
+
+    /// Inserts a rule and tracks the change. Returns the replaced rule, whose anonymous assertion
+    /// is released.
+    pub(crate) fn insert_rule(&mut self, query: Query, rule: Rule) -> Option<Rule> {
+        let previous = self.rules.insert(query, rule);
+
+        self.role_rules.insert((query.role, query));
+        self.retain_assertion(Some(rule));
+
+        if let Some(previous) = previous {
+            self.track(Item::Rule(&query, previous), false);
+            self.release_assertion(Some(previous));
+        } // if
+        self.track(Item::Rule(&query, rule), true);
+        previous
+    } // insert_rule
+
+    /// Removes a rule with its priority, quota and anonymous assertion and tracks the change.
+    /// Returns the removed rule.
+    pub(crate) fn remove_rule(&mut self, query: &Query) -> Option<Rule> {
+        let removed = self.rules.remove(query);
+
+        if let Some(rule) = removed {
+            self.role_rules.remove(&(query.role, *query));
+            if let Some(priority) = self.priorities.remove(query) {
+                self.track(Item::Priority(query, priority), false);
+            } // if
+            if let Some(quota) = self.quotas.remove(query) {
+                self.track(Item::Quota(query, quota.limit), false);
+            } // if
+            self.track(Item::Rule(query, rule), false);
+            self.release_assertion(Some(rule));
+        } // if
+        removed
+    } // remove_rule
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Access;
+    use test_env_log::test;
+
+    #[test]
+    fn fingerprint() {
+        let mut acl   = Acl::new();
+        let empty     = acl.etag();
+
+        assert_eq!(empty, Acl::new().etag());
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(acl.set_bypass_role("guest").is_ok());
+
+        let mut other = Acl::new();
+
+        assert!(other.add_resource("news", None).is_ok());
+        assert!(other.add_role("guest", vec![]).is_ok());
+        assert!(other.add_role("staff", vec!["guest"]).is_ok());
+        assert!(other.set_bypass_role("guest").is_ok());
+        assert!(other.deny(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert_ne!(acl.fingerprint(), other.fingerprint());
+        assert!(other.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert_eq!(acl.fingerprint(), other.fingerprint());
+
+        // reverting a change restores the fingerprint
+        let etag = acl.etag();
+
+        assert!(acl.allow_all("guest").is_ok());
+        assert!(acl.unset_bypass_role("guest").unwrap());
+        assert_ne!(acl.etag(), etag);
+        assert!(acl.revoke_all("guest").unwrap());
+        assert!(acl.set_bypass_role("guest").is_ok());
+        assert_eq!(acl.etag(), etag);
+        assert!(acl.set_rule_priority(Some("staff"), Some("news"), Some("edit"), 1).is_ok());
+        assert_ne!(acl.etag(), etag);
+        assert_eq!(acl.clear_rule_priority(Some("staff"), Some("news"), Some("edit")), Ok(Some(1)));
+        assert_eq!(acl.etag(), etag);
+        assert!(acl.deny_subject("sally", Some("news"), Some("edit")).is_ok());
+        assert!(acl.allow_subject("sally", Some("news"), Some("edit")).is_ok());
+        assert_ne!(acl.etag(), etag);
+        assert_eq!(acl.revoke_subject("sally", Some("news"), Some("edit")), Ok(true));
+        assert!(acl.deny_subject("sally", None, None).is_ok());
+        assert_eq!(acl.clear_subject("sally"), Ok(true));
+        assert!(acl.set_quota(Some("staff"), Some("news"), Some("edit"), 5).is_ok());
+        assert_ne!(acl.etag(), etag);
+        assert!(acl.consume(Some("staff"), Some("news"), Some("edit")));
+        assert_eq!(acl.unset_quota(Some("staff"), Some("news"), Some("edit")), Ok(true));
+        assert_eq!(acl.etag(), etag);
+        acl.set_parent_order(ParentOrder::Fifo);
+        assert_ne!(acl.etag(), etag);
+        acl.set_role_combination(RoleCombination::AnyAllows);
+        acl.set_parent_order(ParentOrder::DenyFirst);
+        acl.set_laminas_compat(true);
+        acl.set_laminas_compat(true);
+        assert_ne!(acl.etag(), etag);
+        acl.set_laminas_compat(false);
+        acl.set_parent_order(ParentOrder::default());
+        assert_ne!(acl.etag(), etag);
+        acl.set_role_combination(RoleCombination::default());
+        assert_eq!(acl.etag(), etag);
+        assert!(acl.set_rule_priority(Some("staff"), Some("news"), Some("edit"), 2).is_ok());
+        acl.set_parent_order(ParentOrder::Fifo);
+        assert!(acl.allow_subject("sally", Some("news"), None).is_ok());
+        assert!(acl.set_quota(Some("staff"), Some("news"), Some("edit"), 5).is_ok());
+        assert_eq!(acl.remove_allow(Some("staff"), None, None), Ok(1));
+        assert_ne!(acl.etag(), etag);
+
+        // the fingerprint equals a fresh computation
+        let mut fresh = 0u64;
+
+        for (query, rule, _) in acl.rules() {
+            fresh = fresh.wrapping_add(Item::Rule(query, *rule).hash());
+        } // for
+        for (name, parents) in &acl.roles {
+            fresh = fresh.wrapping_add(Item::Role(name, parents).hash());
+        } // for
+        for (name, parent) in &acl.resources {
+            fresh = fresh.wrapping_add(Item::Resource(name, *parent).hash());
+        } // for
+        for (query, priority) in &acl.priorities {
+            fresh = fresh.wrapping_add(Item::Priority(query, *priority).hash());
+        } // for
+        for ((name, privilege), access) in &acl.resource_defaults {
+            fresh = fresh.wrapping_add(Item::Default(name, privilege, *access).hash());
+        } // for
+        for (subject, rules) in &acl.subjects {
+            for (query, rule) in rules {
+                fresh = fresh.wrapping_add(Item::Subject(subject, query, *rule).hash());
+            } // for
+        } // for
+        for (query, quota) in &acl.quotas {
+            fresh = fresh.wrapping_add(Item::Quota(query, quota.limit).hash());
+        } // for
+        for name in &acl.bypass {
+            fresh = fresh.wrapping_add(Item::Bypass(name).hash());
+        } // for
+        if acl.parent_order != ParentOrder::default() {
+            fresh = fresh.wrapping_add(Item::ParentOrder(acl.parent_order).hash());
+        } // if
+        if acl.role_combination != RoleCombination::default() {
+            fresh = fresh.wrapping_add(Item::RoleCombination(acl.role_combination).hash());
+        } // if
+        if acl.compat {
+            fresh = fresh.wrapping_add(Item::Compat.hash());
+        } // if
+        assert_eq!(acl.fingerprint(), fresh);
+        assert_eq!(Item::Rule(&Query::ALL, Rule{acc: Access::Deny, cond: None}).hash(), 0x1d41_cf2c_1e2d_436f);
+    } // fingerprint
+
+} // mod tests
@@ -0,0 +1,299 @@
+//! Interactive HTML policy explorer.
+//!
+//! `to_html_explorer` exports a snapshot of the policy as a single self-contained HTML file for
+//! security reviews. The file embeds the policy as JSON together with a small viewer, so reviewers
+//! browse the role hierarchy and the resource tree and check queries in the browser without any
+//! server or network access.
+//!
+//! Checks look up decisions computed by the `Acl` while exporting, so they follow the order of
+//! precedence exactly and show the deciding rule. Decisions are exported for all defined roles and
+//! the wildcard role, all resources and the wildcard resource and all privileges registered or
+//! named by rules or resource defaults; other privileges are decided like any privilege not named
+//! at all. The file grows with the product of these, so export large policies per domain.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//!
+//! let html = acl.to_html_explorer("Policy review");
+//!
+//! assert!(html.starts_with("<!DOCTYPE html>"));
+//! assert!(html.contains("<title>Policy review</title>"));
+//! ```
+
+use crate::{Acl, Privilege, Query, Resource, Role};
+use log::trace;
+use serde_json::{json, Map, Value};
+
+/// The viewer, `{{title}}` and `{{policy}}` are replaced while exporting.
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+body { font-family: sans-serif; margin: 0; display: flex; min-height: 100vh; color: #222; }
+nav { width: 18rem; padding: 1rem; background: #f4f4f4; border-right: 1px solid #ddd; overflow: auto; }
+main { flex: 1; padding: 1rem 2rem; }
+h1 { font-size: 1.4rem; } h2 { font-size: 1.1rem; margin-top: 1.5rem; }
+ul { padding-left: 1.2rem; } li { cursor: pointer; margin: 0.15rem 0; }
+li.selected > span { font-weight: bold; text-decoration: underline; }
+table { border-collapse: collapse; margin-top: 0.5rem; }
+td, th { border: 1px solid #ccc; padding: 0.2rem 0.6rem; text-align: left; }
+.allow { background: #dff5df; } .deny { background: #f8dddd; }
+#result { margin-top: 1rem; padding: 0.6rem; border-radius: 4px; }
+</style>
+</head>
+<body>
+<nav>
+<h2>Roles</h2>
+<ul id="roles"></ul>
+<h2>Resources</h2>
+<ul id="resources"></ul>
+</nav>
+<main>
+<h1>{{title}}</h1>
+<form id="check">
+<select id="role"></select>
+<select id="resource"></select>
+<input id="privilege" list="privileges" placeholder="* (any privilege)">
+<datalist id="privileges"></datalist>
+<button type="submit">Check</button>
+</form>
+<div id="result"></div>
+<div id="details"></div>
+</main>
+<script type="application/json" id="policy">{{policy}}</script>
+<script>
+"use strict";
+const policy = JSON.parse(document.getElementById("policy").textContent);
+const key = (name) => name === null ? "*" : name;
+const element = (tag, text, cls) => {
+    const e = document.createElement(tag);
+    if (text !== undefined) e.textContent = text;
+    if (cls) e.className = cls;
+    return e;
+};
+const option = (select, value, text) => {
+    const o = element("option", text);
+    o.value = value;
+    select.appendChild(o);
+};
+const rule = (q) => `${key(q.role)}→${key(q.resource)}: ${key(q.privilege)}`;
+
+function check(role, resource, privilege) {
+    const cells = policy.decisions[role][resource];
+    return cells[privilege] || cells["?"];
+}
+
+function showResult(role, resource, privilege) {
+    const decision = check(role, resource, privilege || "*");
+    const result = document.getElementById("result");
+    result.className = decision.access;
+    result.textContent = `${decision.access.toUpperCase()} ${role}→${resource}: ${privilege || "*"}, decided by ${rule(decision.matched)}`;
+}
+
+function showRole(name) {
+    const role = policy.roles.find((r) => r.name === name);
+    const details = document.getElementById("details");
+    details.innerHTML = "";
+    details.appendChild(element("h2", `Role ${name}${role.bypass ? " (bypass)" : ""}`));
+    details.appendChild(element("p", `Parents: ${role.parents.join(", ") || "none"}. Lineage: ${role.lineage.join(" → ")}.`));
+    const table = element("table");
+    const head = element("tr");
+    head.appendChild(element("th", "Resource"));
+    for (const privilege of ["*"].concat(policy.privileges)) {
+        head.appendChild(element("th", policy.labels[privilege] || privilege));
+    }
+    table.appendChild(head);
+    for (const resource of ["*"].concat(policy.resources.map((r) => r.name))) {
+        const row = element("tr");
+        row.appendChild(element("td", resource));
+        for (const privilege of ["*"].concat(policy.privileges)) {
+            const decision = check(name, resource, privilege);
+            const cell = element("td", decision.access, decision.access);
+            cell.title = rule(decision.matched);
+            row.appendChild(cell);
+        }
+        table.appendChild(row);
+    }
+    details.appendChild(table);
+    document.getElementById("role").value = name;
+}
+
+function select(list, item) {
+    for (const e of document.querySelectorAll(`#${list} li`)) e.classList.remove("selected");
+    item.classList.add("selected");
+}
+
+const roles = document.getElementById("roles");
+const roleSelect = document.getElementById("role");
+option(roleSelect, "*", "* (any role)");
+for (const role of policy.roles) {
+    const item = element("li");
+    item.appendChild(element("span", role.name));
+    if (role.parents.length) item.appendChild(element("small", ` ← ${role.parents.join(", ")}`));
+    item.onclick = () => { select("roles", item); showRole(role.name); };
+    roles.appendChild(item);
+    option(roleSelect, role.name, role.name);
+}
+
+const resources = document.getElementById("resources");
+const resourceSelect = document.getElementById("resource");
+option(resourceSelect, "*", "* (any resource)");
+for (const resource of policy.resources) {
+    const item = element("li");
+    item.style.marginLeft = `${resource.depth}rem`;
+    item.appendChild(element("span", resource.name));
+    item.onclick = () => { select("resources", item); resourceSelect.value = resource.name; };
+    resources.appendChild(item);
+    option(resourceSelect, resource.name, " ".repeat(2 * resource.depth) + resource.name);
+}
+
+const privileges = document.getElementById("privileges");
+for (const privilege of policy.privileges) option(privileges, privilege, policy.labels[privilege]);
+
+document.getElementById("check").onsubmit = (event) => {
+    event.preventDefault();
+    showResult(roleSelect.value, resourceSelect.value, document.getElementById("privilege").value.trim());
+};
+</script>
+</body>
+</html>
+"#;
+
+/// Escapes text for HTML content.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+} // escape
+
+/// Converts a query to JSON, wildcards as null.
+fn query(query: &Query) -> Value {
+    json!({"role": query.role, "resource": query.resource, "privilege": query.privilege})
+} // query
+
+impl Acl {
+
+    /// Returns the explorer of the policy titled title, see module `explorer`.
+    pub fn to_html_explorer(&self, title: &str) -> String {
+        trace!("exporting explorer of {} roles and {} resources", self.roles.len(), self.resources.len());
+        let tree = self.resource_tree();
+        let privileges = self.known_privileges();
+        let roles: Vec<Role>        = Some(None).into_iter().chain(self.roles.keys().map(|name| Some(*name))).collect();
+        let rows: Vec<Resource>     = Some(None).into_iter().chain(tree.iter().map(|(_, name)| Some(*name))).collect();
+        let columns: Vec<Privilege> = Some(None).into_iter().chain(privileges.iter().map(|name| Some(*name))).collect();
+        let mut decisions = Map::new();
+
+        for role in &roles {
+            let mut resources = Map::new();
+
+            for resource in &rows {
+                let mut cells = Map::new();
+
+                for privilege in &columns {
+                    let (matched, rule) = self.effective(*role, *resource, *privilege);
+
+                    cells.insert(String::from(privilege.unwrap_or("*")), json!({
+                        "access":  rule.acc.to_string().to_lowercase(),
+                        "matched": query(&matched),
+                    }));
+                } // for
+                // privileges not named by any rule are decided alike, e.g. like the empty one
+                let (matched, rule) = self.effective(*role, *resource, Some(""));
+
+                cells.insert(String::from("?"), json!({
+                    "access":  rule.acc.to_string().to_lowercase(),
+                    "matched": query(&matched),
+                }));
+                resources.insert(String::from(resource.unwrap_or("*")), Value::Object(cells));
+            } // for
+            decisions.insert(String::from(role.unwrap_or("*")), Value::Object(resources));
+        } // for
+
+        let policy = json!({
+            "roles": self.roles.keys().map(|name| json!({
+                "name":    name,
+                "parents": self.get_role_parents(name).unwrap_or_default(),
+                "lineage": self.get_role_lineage(name),
+                "bypass":  self.bypass.contains(name),
+            })).collect::<Vec<_>>(),
+            "resources": tree.iter().map(|(depth, name)| json!({
+                "name":   name,
+                "parent": self.resources[name],
+                "depth":  depth,
+            })).collect::<Vec<_>>(),
+            "privileges": privileges,
+            "labels": privileges.iter()
+                .filter_map(|name| self.privilege_info.get(name).and_then(|info| info.label.as_ref()).map(|label| (name.to_string(), json!(label))))
+                .collect::<Map<_, _>>(),
+            "decisions": decisions,
+        });
+
+        // the script element ends at the first "</"
+        TEMPLATE
+            .replace("{{title}}", &escape(title))
+            .replace("{{policy}}", &policy.to_string().replace("</", "<\\/"))
+    } // to_html_explorer
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn embedded(html: &str) -> Value {
+        let start = html.find(r#"id="policy">"#).unwrap() + 12;
+        let end   = start + html[start..].find("</script>").unwrap();
+
+        serde_json::from_str(&html[start..end]).unwrap()
+    } // embedded
+
+    #[test]
+    fn explorer() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), Some("</script>")).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        assert!(acl.describe_privilege("view", Some("View"), None).is_ok());
+
+        let html   = acl.to_html_explorer("<Review>");
+        let policy = embedded(&html);
+
+        assert!(html.contains("<title>&lt;Review&gt;</title>"));
+        assert_eq!(html.matches("</script>").count(), 2);
+        assert_eq!(policy["roles"][1]["lineage"], json!(["root"]));
+        assert_eq!(policy["roles"][2]["parents"], json!(["guest"]));
+        assert_eq!(policy["resources"][1], json!({"name": "latest", "parent": "news", "depth": 1}));
+        assert_eq!(policy["privileges"], json!(["</script>", "view"]));
+        assert_eq!(policy["labels"], json!({"view": "View"}));
+
+        // decisions equal those of the acl
+        let decisions = &policy["decisions"];
+
+        assert_eq!(decisions["staff"]["news"]["view"]["access"], "allow");
+        assert_eq!(decisions["staff"]["news"]["view"]["matched"], json!({"role": "guest", "resource": null, "privilege": "view"}));
+        assert_eq!(decisions["staff"]["latest"]["view"]["access"], "deny");
+        assert_eq!(decisions["staff"]["latest"]["?"]["access"], "deny");
+        assert_eq!(decisions["guest"]["*"]["?"]["access"], "deny");
+        assert_eq!(decisions["root"]["latest"]["?"]["access"], "allow");
+        assert_eq!(decisions["*"]["news"]["view"]["matched"], json!({"role": null, "resource": null, "privilege": null}));
+    } // explorer
+
+} // mod tests
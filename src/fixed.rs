@@ -0,0 +1,272 @@
+//! Fixed policies built at compile time.
+//!
+//! A `StaticAcl` is a table of roles, resources and rules borrowed from static slices. It is
+//! constructed by `const fn`s, so a fixed policy can be a `static` without any startup cost or
+//! heap allocation. Queries follow the order of precedence of `Acl::get_rule` by scanning the
+//! tables, which suits small policies like those of firmware or command line tools.
+//!
+//! Parents must be declared before their children and roles must not be cyclic. A rule for
+//! `Query::ALL` replaces the catch-all rule, which denies by default.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::fixed::{StaticAcl, StaticRule};
+//! static POLICY: StaticAcl = StaticAcl::new(
+//!     &[("guest", &[]), ("operator", &["guest"])],
+//!     &[("config", None), ("network", Some("config"))],
+//!     &[
+//!         StaticRule::allow(Some("guest"),    None,            Some("view")),
+//!         StaticRule::allow(Some("operator"), Some("config"),  None),
+//!         StaticRule::deny (Some("operator"), Some("network"), Some("reset")),
+//!     ],
+//! );
+//!
+//! assert!(POLICY.is_allowed(Some("operator"), Some("network"), Some("view")));
+//! assert!(POLICY.is_allowed(Some("operator"), Some("network"), Some("edit")));
+//! assert!(POLICY.is_denied (Some("operator"), Some("network"), Some("reset")));
+//! assert!(POLICY.is_denied (Some("guest"),    Some("config"),  Some("edit")));
+//! ```
+
+use crate::{Access, Acl, Decision, Error, Privilege, Query, Resource, Role, Rule};
+use log::trace;
+
+
+// StaticRule /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A rule of a `StaticAcl`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StaticRule {
+    query: Query,
+    rule:  Rule,
+} // struct StaticRule
+
+impl StaticRule {
+
+    /// Creates a rule granting access.
+    pub const fn allow(role: Role, resource: Resource, privilege: Privilege) -> Self {
+        StaticRule{query: Query{resource, role, privilege}, rule: Rule{acc: Access::Allow, cond: None}}
+    } // allow
+
+    /// Creates a rule denying access.
+    pub const fn deny(role: Role, resource: Resource, privilege: Privilege) -> Self {
+        StaticRule{query: Query{resource, role, privilege}, rule: Rule{acc: Access::Deny, cond: None}}
+    } // deny
+
+    /// Returns the role, resource and privilege of the rule.
+    #[inline]
+    pub fn query(&self) -> &Query {
+        &self.query
+    } // query
+
+    /// Returns the granted access.
+    #[inline]
+    pub fn access(&self) -> Access {
+        self.rule.acc
+    } // access
+
+} // impl StaticRule
+
+
+// StaticAcl //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A fixed policy borrowed from static tables.
+#[derive(Clone, Copy, Debug)]
+pub struct StaticAcl {
+    roles:     &'static [(&'static str, &'static [&'static str])],
+    resources: &'static [(&'static str, Option<&'static str>)],
+    rules:     &'static [StaticRule],
+} // struct StaticAcl
+
+impl StaticAcl {
+
+    /// Creates a new `StaticAcl` from roles with their parents in order of declaration, resources
+    /// with their parent and rules.
+    pub const fn new(
+        roles:     &'static [(&'static str, &'static [&'static str])],
+        resources: &'static [(&'static str, Option<&'static str>)],
+        rules:     &'static [StaticRule],
+    ) -> Self {
+        StaticAcl{roles, resources, rules}
+    } // new
+
+    /// Returns true if role is defined.
+    pub fn has_role(&self, name: &str) -> bool {
+        self.roles.iter().any(|(role, _)| *role == name)
+    } // has_role
+
+    /// Returns true if resource is defined.
+    pub fn has_resource(&self, name: &str) -> bool {
+        self.resources.iter().any(|(resource, _)| *resource == name)
+    } // has_resource
+
+    /// Returns the rules.
+    #[inline]
+    pub fn rules(&self) -> &'static [StaticRule] {
+        self.rules
+    } // rules
+
+    /// Decides the query like `Acl::decide`.
+    pub fn decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        trace!("getting static rule for {:?} on {:?} to {:?}", role, resource, privilege);
+        let query = Query{resource, role, privilege};
+        let role  = role.filter(|name| self.has_role(name));
+        let mut current = resource.filter(|name| self.has_resource(name));
+
+        // specific resources in lineage, bounded in case of cyclic tables
+        for _ in 0..self.resources.len() {
+            let name = match current {
+                Some(name) => name,
+                None       => break,
+            }; // match
+
+            if let Some(found) = self.query_roles(Some(name), role, privilege) {
+                return Decision{query, matched: found.query, rule: found.rule, bypass: false};
+            } // if let
+            current = self.resources.iter().find(|(resource, _)| *resource == name).and_then(|(_, parent)| *parent);
+        } // for
+        // wildcard resource
+        if let Some(found) = self.query_roles(None, role, privilege) {
+            return Decision{query, matched: found.query, rule: found.rule, bypass: false};
+        } // if let
+
+        let rule = self.find(Query::ALL).map(|found| found.rule).unwrap_or(Rule{acc: Access::Deny, cond: None});
+
+        trace!("    matching catch-all");
+        Decision{query, matched: Query::ALL, rule, bypass: false}
+    } // decide
+
+    /// Returns true if privilege is allowed for role on resource.
+    #[inline]
+    pub fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_allowed()
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource.
+    #[inline]
+    pub fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_denied()
+    } // is_denied
+
+    /// Builds an `Acl` from the tables, e.g. to extend a fixed policy at runtime.
+    pub fn to_acl(&self) -> Result<Acl, Error> {
+        let mut acl = Acl::new();
+
+        for (name, parent) in self.resources {
+            acl.add_resource(name, *parent)?;
+        } // for
+        for (name, parents) in self.roles {
+            acl.add_role(name, parents.to_vec())?;
+        } // for
+        for rule in self.rules {
+            if rule.query == Query::ALL {
+                acl.insert_rule(Query::ALL, rule.rule);
+            } else {
+                acl.set_rule(rule.query.role, rule.query.resource, rule.query.privilege, rule.rule.acc)?;
+            } // else
+        } // for
+        Ok(acl)
+    } // to_acl
+
+    fn find(&self, query: Query) -> Option<&StaticRule> {
+        self.rules.iter().find(|rule| rule.query == query)
+    } // find
+
+    fn query_privileges(&self, resource: Resource, role: Role, privilege: Privilege) -> Option<&StaticRule> {
+        // query specific privilege
+        if privilege.is_some() {
+            if let Some(found) = self.find(Query{resource, role, privilege}) {
+                return Some(found);
+            } // if let
+        } // if
+        // query wildcard privilege if query isn't equal to Query::ALL
+        if resource.is_some() || role.is_some() {
+            return self.find(Query{resource, role, privilege: None});
+        } // if
+        None
+    } // query_privileges
+
+    fn query_lineage(&self, resource: Resource, role: &'static str, privilege: Privilege, depth: usize) -> Option<&StaticRule> {
+        // a lineage can't be longer than the table unless roles are cyclic
+        if depth > self.roles.len() {
+            return None;
+        } // if
+        if let Some(found) = self.query_privileges(resource, Some(role), privilege) {
+            return Some(found);
+        } // if let
+
+        let parents = self.roles.iter().find(|(name, _)| *name == role).map(|(_, parents)| *parents).unwrap_or(&[]);
+
+        // like in `Acl`, the last declared parent is searched first
+        for parent in parents.iter().rev() {
+            if let Some(found) = self.query_lineage(resource, parent, privilege, depth + 1) {
+                return Some(found);
+            } // if let
+        } // for
+        None
+    } // query_lineage
+
+    fn query_roles(&self, resource: Resource, role: Role, privilege: Privilege) -> Option<&StaticRule> {
+        // specific roles in lineage
+        if let Some(name) = role {
+            if let Some(found) = self.query_lineage(resource, name, privilege, 0) {
+                return Some(found);
+            } // if let
+        } // if let
+        // wildcard role
+        self.query_privileges(resource, None, privilege)
+    } // query_roles
+
+} // impl StaticAcl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    static POLICY: StaticAcl = StaticAcl::new(
+        &[("guest", &[]), ("staff", &["guest"]), ("editor", &["staff"]), ("publisher", &["guest", "editor"])],
+        &[("news", None), ("latest", Some("news")), ("announcement", Some("news"))],
+        &[
+            StaticRule::allow(Some("guest"),     None,                 Some("view")),
+            StaticRule::allow(Some("staff"),     None,                 Some("edit")),
+            StaticRule::deny (Some("guest"),     Some("news"),         None),
+            StaticRule::allow(Some("editor"),    Some("announcement"), None),
+            StaticRule::deny (Some("staff"),     Some("latest"),       Some("edit")),
+        ],
+    );
+
+    #[test]
+    fn decide() {
+        assert!(POLICY.is_allowed(Some("guest"), None, Some("view")));
+        assert!(POLICY.is_denied (Some("guest"), Some("latest"), Some("view")));
+        assert!(POLICY.is_denied (Some("publisher"), Some("latest"), Some("edit")));
+        assert!(POLICY.is_allowed(Some("publisher"), Some("announcement"), Some("edit")));
+        assert!(POLICY.is_denied (Some("unknown"), None, Some("view")));
+        assert!(POLICY.is_denied (None, None, None));
+        assert_eq!(POLICY.decide(Some("publisher"), Some("latest"), Some("edit")).matched.to_string(), "staff→latest: edit");
+
+        // the static policy decides like the dynamic one
+        let acl = POLICY.to_acl().unwrap();
+
+        for role in &[None, Some("guest"), Some("staff"), Some("editor"), Some("publisher")] {
+            for resource in &[None, Some("news"), Some("latest"), Some("announcement")] {
+                for privilege in &[None, Some("view"), Some("edit")] {
+                    assert_eq!(POLICY.decide(*role, *resource, *privilege), acl.decide(*role, *resource, *privilege));
+                } // for
+            } // for
+        } // for
+
+        static OPEN: StaticAcl = StaticAcl::new(&[], &[], &[StaticRule::allow(None, None, None)]);
+
+        assert!(OPEN.is_allowed(None, None, Some("view")));
+        assert!(OPEN.to_acl().unwrap().is_allowed(None, None, Some("view")));
+    } // decide
+
+} // mod tests
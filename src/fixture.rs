@@ -0,0 +1,242 @@
+//! Policy test fixtures for downstream services.
+//!
+//! `Acl::fixture` exports the policy together with a sample of queries and their decisions as a
+//! `Fixture`. Services deciding against a remote policy decision point, see module `remote`, ship
+//! the fixture with their tests and replay it offline: `Fixture::acl` stands in for the remote
+//! policy, e.g. behind a mock transport, and `Fixture::replay` checks any `Authorizer` against
+//! the recorded decisions, reporting all mismatches at once.
+//!
+//! The sample is drawn evenly and deterministically from all queries of the defined roles, the
+//! defined resources and the privileges registered or named by rules or resource defaults, each
+//! including the wildcard, so regenerating an unchanged policy yields the same fixture. Decisions
+//! are recorded as made by the exporting `Acl`; assertions aren't exported with the policy, so the
+//! decisions of conditional rules are only reproduced by authorizers evaluating the same
+//! assertions.
+//!
+//! The fixture is a JSON document with the version `fixture`, the policy document `policy` and
+//! the `cases`, e.g. `{"role": "guest", "resource": null, "privilege": "view", "expected": "allow"}`,
+//! where null is a wildcard.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Access, Acl};
+//! # use zorq_acl::fixture::Fixture;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.allow(Some("guest"), Some("news"), Some("view")).unwrap();
+//!
+//! let json    = acl.fixture(100).unwrap().to_json();
+//! let fixture = Fixture::from_json(&json).unwrap();
+//!
+//! assert_eq!(fixture.replay(fixture.acl()), Ok(8));
+//! assert!(fixture.replay(&Access::Deny).is_err());
+//! ```
+
+use crate::authorizer::Authorizer;
+use crate::policy::{export, intern, load};
+use crate::{Access, Acl, Error, Privilege, Query, Resource, Role, SchemaError};
+use log::{trace, warn};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+/// The version of the fixture format.
+pub const FIXTURE_VERSION: u64 = 1;
+
+
+// Case ///////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A query with its recorded decision.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Case {
+    /// the queried role, resource and privilege
+    pub query:    Query,
+    /// the access decided
+    pub expected: Access,
+} // struct Case
+
+
+// Fixture ////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A policy with a sample of recorded decisions, see module `fixture`.
+pub struct Fixture {
+    acl:   Acl,
+    cases: Vec<Case>,
+} // struct Fixture
+
+impl Fixture {
+
+    /// Reads a fixture from JSON. Returns an error if it is malformed, of a newer version or
+    /// holds an invalid policy.
+    pub fn from_json(source: &str) -> Result<Fixture, Error> {
+        let value: Value = serde_json::from_str(source).map_err(|e| Error::Parse(e.to_string()))?;
+
+        match value.get("fixture").and_then(Value::as_u64) {
+            Some(version) if version > FIXTURE_VERSION => return Err(Error::SchemaVersion(version)),
+            Some(_)                                    => (),
+            None                                       =>
+                return Err(Error::Schema(vec![SchemaError::new("fixture", "expected a version number")])),
+        } // match
+
+        let acl        = load(&value["policy"], Some("fixture"))?;
+        let mut errors = vec![];
+        let mut cases  = vec![];
+        let name       = |case: &Value, field: &str| case.get(field).and_then(Value::as_str).map(intern);
+
+        for (i, case) in value["cases"].as_array().map(Vec::as_slice).unwrap_or_default().iter().enumerate() {
+            let expected = match case.get("expected").and_then(Value::as_str) {
+                Some("allow") => Access::Allow,
+                Some("deny")  => Access::Deny,
+                _             => {
+                    errors.push(SchemaError::new(&format!("cases[{}].expected", i), "expected \"allow\" or \"deny\""));
+                    continue;
+                }, // _
+            }; // match
+
+            cases.push(Case{query: Query{resource: name(case, "resource"), role: name(case, "role"), privilege: name(case, "privilege")}, expected});
+        } // for
+        if !errors.is_empty() {
+            warn!("invalid fixture with {} problems", errors.len());
+            return Err(Error::Schema(errors));
+        } // if
+        Ok(Fixture{acl, cases})
+    } // from_json
+
+    /// Writes the fixture as JSON.
+    pub fn to_json(&self) -> String {
+        let cases: Vec<Value> = self.cases.iter().map(|case| json!({
+            "role":      case.query.role,
+            "resource":  case.query.resource,
+            "privilege": case.query.privilege,
+            "expected":  case.expected.to_string().to_lowercase(),
+        })).collect();
+
+        json!({"fixture": FIXTURE_VERSION, "policy": export(&self.acl), "cases": cases}).to_string()
+    } // to_json
+
+    /// Returns the policy of the fixture.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Returns the recorded cases.
+    #[inline]
+    pub fn cases(&self) -> &[Case] {
+        &self.cases
+    } // cases
+
+    /// Replays all cases against authorizer. Returns the number of cases decided as recorded, or
+    /// the report of all cases decided otherwise.
+    pub fn replay(&self, authorizer: &dyn Authorizer) -> Result<usize, String> {
+        trace!("replaying {} cases", self.cases.len());
+        let mut report = String::new();
+        let mut failed = 0;
+
+        for (i, case) in self.cases.iter().enumerate() {
+            let query = case.query;
+
+            if authorizer.is_allowed(query.role, query.resource, query.privilege) == (case.expected == Access::Allow) {
+                continue;
+            } // if
+            failed += 1;
+            let _ = writeln!(report, "\ncases[{}]: {} expected {}, got {}", i, query, case.expected,
+                authorizer.explain(query.role, query.resource, query.privilege));
+        } // for
+        if failed > 0 {
+            warn!("{} of {} cases failed", failed, self.cases.len());
+            return Err(format!("{} of {} cases failed\n{}", failed, self.cases.len(), report));
+        } // if
+        Ok(self.cases.len())
+    } // replay
+
+} // impl Fixture
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Exports the policy with a sample of at most max_cases decisions, see module `fixture`.
+    /// Returns an error if the exported policy can't be loaded, e.g. if it exceeds the default
+    /// limits, see module `limits`.
+    pub fn fixture(&self, max_cases: usize) -> Result<Fixture, Error> {
+        let privileges = self.known_privileges();
+        let roles: Vec<Role>           = Some(None).into_iter().chain(self.roles.keys().map(|name| Some(*name))).collect();
+        let resources: Vec<Resource>   = Some(None).into_iter().chain(self.resources.keys().map(|name| Some(*name))).collect();
+        let privileges: Vec<Privilege> = Some(None).into_iter().chain(privileges.into_iter().map(Some)).collect();
+        let total                      = roles.len() * resources.len() * privileges.len();
+        let count                      = total.min(max_cases);
+
+        trace!("sampling {} of {} queries", count, total);
+        let cases = (0..count).map(|i| {
+            // spread evenly over all queries
+            let index     = i * total / count;
+            let privilege = privileges[index % privileges.len()];
+            let resource  = resources[index / privileges.len() % resources.len()];
+            let role      = roles[index / privileges.len() / resources.len()];
+
+            Case{query: Query{resource, role, privilege}, expected: self.evaluate(role, resource, privilege).rule.access()}
+        }).collect();
+
+        Ok(Fixture{acl: load(&export(self), Some("fixture"))?, cases})
+    } // fixture
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn fixture() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("delete")).is_ok());
+
+        // 3 roles, 3 resources and 3 privileges
+        let all     = acl.fixture(usize::MAX).unwrap();
+        let sampled = acl.fixture(10).unwrap();
+
+        assert_eq!(all.cases().len(), 27);
+        assert_eq!(all.cases()[26], Case{query: Query{resource: Some("news"), role: Some("staff"), privilege: Some("view")}, expected: Access::Allow});
+        assert_eq!(sampled.cases().len(), 10);
+        assert_eq!(sampled.cases()[1].query, all.cases()[2].query);
+        assert_eq!(sampled.to_json(), acl.fixture(10).unwrap().to_json());
+        assert!(acl.fixture(0).unwrap().cases().is_empty());
+
+        // the fixture replays against the policy and other authorizers
+        let fixture = Fixture::from_json(&all.to_json()).unwrap();
+
+        assert_eq!(fixture.cases(), all.cases());
+        assert_eq!(fixture.replay(fixture.acl()), Ok(27));
+        assert_eq!(fixture.replay(&acl), Ok(27));
+        assert!(acl.deny(Some("staff"), Some("news"), Some("view")).is_ok());
+        let report = fixture.replay(&acl).unwrap_err();
+
+        assert!(report.starts_with("2 of 27 cases failed\n"));
+        assert!(report.contains("\ncases[26]: staff→news: view expected ALLOW, got DENY staff→news: view by DENY staff→news: view\n"));
+
+        assert_eq!(Fixture::from_json(r#"{"fixture": 2}"#).err(), Some(Error::SchemaVersion(2)));
+        assert_eq!(Fixture::from_json(r#"{"fixture": 1, "policy": {}, "cases": [{"role": "guest", "expected": "yes"}]}"#).err(),
+            Some(Error::Schema(vec![SchemaError::new("cases[0].expected", "expected \"allow\" or \"deny\"")])));
+        assert!(Fixture::from_json(r#"{"policy": {}}"#).is_err());
+    } // fixture
+
+} // mod tests
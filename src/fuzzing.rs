@@ -0,0 +1,223 @@
+//! Fuzzing support.
+//!
+//! With the `arbitrary` feature, sequences of `Step`s building and querying a policy implement
+//! `arbitrary::Arbitrary`. `run` applies them to a new `Acl` and checks the internal invariants
+//! after each step: parents and rules only reference defined roles and resources, the catch-all
+//! rule exists, and cached as well as reported decisions equal an uncached search by precedence.
+//! Names are drawn from a small pool shared by roles, resources and privileges, so that steps
+//! interact and also fail in interesting ways. The cargo-fuzz targets live in `fuzz/`:
+//!
+//! ```text
+//! cargo +nightly fuzz run operations
+//! ```
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::fuzzing::{run, Name, Step};
+//! let steps = vec![
+//!     Step::AddRole(Name("guest"), vec![]),
+//!     Step::Allow(Some(Name("guest")), None, Some(Name("view"))),
+//!     Step::Lock,
+//!     Step::Decide(Some(Name("guest")), Some(Name("news")), Some(Name("view"))),
+//! ];
+//!
+//! assert_eq!(run(&steps), Ok(()));
+//! ```
+
+use crate::{Acl, Decision, ParentOrder, Query};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use log::trace;
+
+
+// Steps //////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The pool of names.
+pub const NAMES: &[&str] = &["guest", "staff", "editor", "admin", "news", "latest", "blog", "view", "edit", "publish"];
+
+/// A name of the pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Name(pub &'static str);
+
+impl<'a> Arbitrary<'a> for Name {
+
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Name(u.choose(NAMES)?))
+    } // arbitrary
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (1, Some(1))
+    } // size_hint
+
+} // impl Arbitrary for Name
+
+/// A wildcard or a name of the pool.
+pub type Wildcard = Option<Name>;
+
+/// An operation on the `Acl`.
+#[derive(Arbitrary, Clone, Debug, PartialEq)]
+pub enum Step {
+    AddRole(Name, Vec<Name>),
+    AddResource(Name, Wildcard),
+    Allow(Wildcard, Wildcard, Wildcard),
+    Deny(Wildcard, Wildcard, Wildcard),
+    RemoveAllow(Wildcard, Wildcard, Wildcard),
+    RemoveDeny(Wildcard, Wildcard, Wildcard),
+    RevokeAll(Name),
+    SetBypassRole(Name),
+    UnsetBypassRole(Name),
+    SetParentOrder(u8),
+    Lock,
+    Unlock,
+    PurgeCache,
+    WarmCache,
+    Decide(Wildcard, Wildcard, Wildcard),
+} // enum Step
+
+fn name(name: Wildcard) -> Option<&'static str> {
+    name.map(|Name(name)| name)
+} // name
+
+/// Applies step to acl. Errors of the `Acl` are expected and ignored.
+pub fn apply(acl: &mut Acl, step: &Step) -> std::result::Result<(), String> {
+    trace!("applying {:?}", step);
+    match *step {
+        Step::AddRole(Name(role), ref parents) => {
+            let _ = acl.add_role(role, parents.iter().map(|Name(parent)| *parent).collect());
+        }, // Step::AddRole
+        Step::AddResource(Name(resource), parent) => {
+            let _ = acl.add_resource(resource, name(parent));
+        }, // Step::AddResource
+        Step::Allow(role, resource, privilege)       => { let _ = acl.allow(name(role), name(resource), name(privilege)); },
+        Step::Deny(role, resource, privilege)        => { let _ = acl.deny(name(role), name(resource), name(privilege)); },
+        Step::RemoveAllow(role, resource, privilege) => { let _ = acl.remove_allow(name(role), name(resource), name(privilege)); },
+        Step::RemoveDeny(role, resource, privilege)  => { let _ = acl.remove_deny(name(role), name(resource), name(privilege)); },
+        Step::RevokeAll(Name(role))       => { let _ = acl.revoke_all(role); },
+        Step::SetBypassRole(Name(role))   => { let _ = acl.set_bypass_role(role); },
+        Step::UnsetBypassRole(Name(role)) => { let _ = acl.unset_bypass_role(role); },
+        Step::SetParentOrder(order)       => acl.set_parent_order(match order % 3 {
+            0 => ParentOrder::Lifo,
+            1 => ParentOrder::Fifo,
+            _ => ParentOrder::DenyFirst,
+        }), // Step::SetParentOrder
+        Step::Lock       => acl.lock(),
+        Step::Unlock     => acl.unlock(),
+        Step::PurgeCache => acl.purge_cache(),
+        Step::WarmCache  => { acl.warm_cache_full(); },
+        Step::Decide(role, resource, privilege) => {
+            let decision = acl.decide(name(role), name(resource), name(privilege));
+
+            check_decision(acl, &decision)?;
+        }, // Step::Decide
+    } // match
+    Ok(())
+} // apply
+
+/// Returns an error if decision differs from an uncached search by precedence.
+fn check_decision(acl: &Acl, decision: &Decision) -> std::result::Result<(), String> {
+    let Query{role, resource, privilege} = decision.query;
+    let (matched, rule) = acl.effective(role, resource, privilege);
+
+    if (matched, rule) != (decision.matched, decision.rule) {
+        return Err(format!("{} decided by {} {}, but searched {} {}", decision.query, decision.rule, decision.matched, rule, matched));
+    } // if
+    Ok(())
+} // check_decision
+
+/// Returns an error describing the first broken invariant of acl.
+pub fn check_invariants(acl: &Acl) -> std::result::Result<(), String> {
+    if !acl.rules.contains_key(&Query::ALL) {
+        return Err(String::from("missing catch-all rule"));
+    } // if
+    for (role, parents) in &acl.roles {
+        if let Some(parent) = parents.iter().find(|parent| !acl.roles.contains_key(*parent)) {
+            return Err(format!("role {} has dangling parent {}", role, parent));
+        } // if
+    } // for
+    for (resource, parent) in &acl.resources {
+        if let Some(parent) = parent.filter(|parent| !acl.resources.contains_key(parent)) {
+            return Err(format!("resource {} has dangling parent {}", resource, parent));
+        } // if
+    } // for
+    for query in acl.rules.keys() {
+        let dangling = query.role.map(|role| !acl.roles.contains_key(role)).unwrap_or(false)
+            || query.resource.map(|resource| !acl.resources.contains_key(resource)).unwrap_or(false);
+
+        if dangling {
+            return Err(format!("rule {} is dangling", query));
+        } // if
+    } // for
+    for decision in acl.cache_entries() {
+        check_decision(acl, &decision).map_err(|error| format!("cache inconsistent: {}", error))?;
+    } // for
+    Ok(())
+} // check_invariants
+
+/// Returns the steps encoded by bytes, as many as the bytes suffice for.
+pub fn steps(bytes: &[u8]) -> Vec<Step> {
+    let mut u     = Unstructured::new(bytes);
+    let mut steps = vec![];
+
+    while !u.is_empty() {
+        match Step::arbitrary(&mut u) {
+            Ok(step) => steps.push(step),
+            Err(_)   => break,
+        } // match
+    } // while
+    steps
+} // steps
+
+/// Applies steps to a new `Acl` and checks its invariants after each step. Returns an error
+/// naming the step which broke an invariant.
+pub fn run(steps: &[Step]) -> std::result::Result<(), String> {
+    let mut acl = Acl::new();
+
+    for (i, step) in steps.iter().enumerate() {
+        apply(&mut acl, step)
+            .and_then(|_| check_invariants(&acl))
+            .map_err(|error| format!("step {} {:?}: {}", i, step, error))?;
+    } // for
+    Ok(())
+} // run
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn interleaved() {
+        // a cheap pseudo random generator replaces the fuzzer
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut bytes      = vec![0u8; 2048];
+
+        for _ in 0..64 {
+            for byte in bytes.iter_mut() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *byte = state as u8;
+            } // for
+
+            assert_eq!(run(&steps(&bytes)), Ok(()));
+        } // for
+    } // interleaved
+
+    #[test]
+    fn invariants() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(check_invariants(&acl).is_ok());
+
+        acl.roles.remove("guest");
+        assert_eq!(check_invariants(&acl), Err(String::from("role staff has dangling parent guest")));
+    } // invariants
+
+} // mod tests
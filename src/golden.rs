@@ -0,0 +1,315 @@
+//! Golden-file policy tests.
+//!
+//! A golden test is a directory holding a policy document, `policy.json` or with feature `yaml`
+//! `policy.yaml`, and a table of expected decisions, `expectations.toml`. A missing role, resource
+//! or privilege of an expectation is a wildcard, `expected` is `allow` or `deny`.
+//!
+//! ```toml
+//! [[expect]]
+//! role      = "guest"
+//! resource  = "news"
+//! privilege = "view"
+//! expected  = "allow"
+//! ```
+//!
+//! The expectations are validated as a whole when loaded: roles and resources must be defined by
+//! the policy. Running the test checks every expectation and reports all failures at once, each
+//! with the line of the expectation and the explanation of the decision, see module `testing`. A
+//! single test function thus covers a whole table, e.g. `assert_golden("tests/golden/news")`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::golden::GoldenTest;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//!
+//! let test = GoldenTest::new(acl, "[[expect]]\nrole = \"guest\"\nprivilege = \"view\"\nexpected = \"allow\"\n").unwrap();
+//!
+//! assert_eq!(test.run(), Ok(1));
+//! ```
+
+use crate::testing::explain;
+use crate::{Access, Acl, Error, Query, SchemaError};
+use log::{trace, warn};
+use serde::Deserialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use toml::Spanned;
+
+/// The name of the expectations file of a golden test directory.
+pub const EXPECTATIONS: &str = "expectations.toml";
+
+
+// Expectation ////////////////////////////////////////////////////////////////////////////////////
+
+
+/// An expected decision.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Expectation {
+    /// the queried role, resource and privilege
+    pub query:    Query,
+    /// the expected access
+    pub expected: Access,
+    /// the line of the expectation within its file
+    pub line:     usize,
+} // struct Expectation
+
+#[derive(Debug, Deserialize)]
+struct File {
+    #[serde(default)]
+    expect: Vec<Spanned<Entry>>,
+} // struct File
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    role:      Option<String>,
+    resource:  Option<String>,
+    privilege: Option<String>,
+    expected:  String,
+} // struct Entry
+
+
+// GoldenTest /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A policy with a table of expected decisions.
+pub struct GoldenTest {
+    acl:          Acl,
+    source:       String,
+    expectations: Vec<Expectation>,
+} // struct GoldenTest
+
+impl GoldenTest {
+
+    /// Creates a golden test of acl from the source of an expectations file. Returns an error if
+    /// the file is malformed or names undefined roles or resources.
+    pub fn new(acl: Acl, expectations: &str) -> Result<GoldenTest, Error> {
+        Self::parse(acl, EXPECTATIONS, expectations)
+    } // new
+
+    /// Loads a golden test from a directory, see module `golden`.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<GoldenTest, Error> {
+        let dir = dir.as_ref();
+
+        trace!("loading golden test {}", dir.display());
+        #[cfg(feature = "yaml")]
+        {
+            let path = dir.join("policy.yaml");
+
+            if path.exists() {
+                return Self::load_with(Acl::load_yaml(path)?, dir);
+            } // if
+        }
+        Self::load_with(Acl::load_json(dir.join("policy.json"))?, dir)
+    } // load
+
+    /// Returns the `Acl` under test.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Returns the expectations in file order.
+    #[inline]
+    pub fn expectations(&self) -> &[Expectation] {
+        &self.expectations
+    } // expectations
+
+    /// Checks all expectations. Returns the number of expectations met, or the report of all
+    /// failed expectations.
+    pub fn run(&self) -> Result<usize, String> {
+        let mut report = String::new();
+        let mut failed = 0;
+
+        for expectation in &self.expectations {
+            let query    = expectation.query;
+            let decision = self.acl.evaluate(query.role, query.resource, query.privilege);
+
+            if decision.rule.access() == expectation.expected {
+                continue;
+            } // if
+            failed += 1;
+            let _ = writeln!(report, "\n{}:{}: {} expected {}, got {}",
+                self.source, expectation.line, query, expectation.expected, decision.rule.access());
+            for line in explain(&self.acl, query.role, query.resource, query.privilege).lines() {
+                let _ = writeln!(report, "    {}", line);
+            } // for
+        } // for
+        if failed > 0 {
+            warn!("{} of {} expectations failed", failed, self.expectations.len());
+            return Err(format!("{} of {} expectations failed\n{}", failed, self.expectations.len(), report));
+        } // if
+        Ok(self.expectations.len())
+    } // run
+
+    /// Loads the expectations of dir for acl.
+    fn load_with(acl: Acl, dir: &Path) -> Result<GoldenTest, Error> {
+        let path   = dir.join(EXPECTATIONS);
+        let source = fs::read_to_string(&path).map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))?;
+
+        Self::parse(acl, &path.display().to_string(), &source)
+    } // load_with
+
+    /// Parses the expectations named name from source.
+    fn parse(acl: Acl, name: &str, source: &str) -> Result<GoldenTest, Error> {
+        let file: File = toml::from_str(source).map_err(|e| Error::Parse(format!("{}: {}", name, e)))?;
+        let mut errors       = vec![];
+        let mut expectations = vec![];
+
+        for (i, entry) in file.expect.iter().enumerate() {
+            let path     = format!("expect[{}]", i);
+            let line     = source[..entry.span().start].matches('\n').count() + 1;
+            let entry    = entry.get_ref();
+            let expected = match entry.expected.to_ascii_lowercase().as_str() {
+                "allow" => Access::Allow,
+                "deny"  => Access::Deny,
+                _       => {
+                    errors.push(SchemaError::at(Some(name), &format!("{}.expected", path),
+                        &format!("expected \"allow\" or \"deny\", found \"{}\"", entry.expected)));
+                    continue;
+                }, // _
+            }; // match
+            let role     = match entry.role.as_deref() {
+                Some(role) => match acl.roles.get_key_value(role) {
+                    Some((role, _)) => Some(*role),
+                    None            => {
+                        errors.push(SchemaError::at(Some(name), &format!("{}.role", path), &format!("unknown role \"{}\"", role)));
+                        continue;
+                    }, // None
+                }, // Some
+                None       => None,
+            }; // match
+            let resource = match entry.resource.as_deref() {
+                Some(resource) => match acl.resources.get_key_value(resource) {
+                    Some((resource, _)) => Some(*resource),
+                    None                => {
+                        errors.push(SchemaError::at(Some(name), &format!("{}.resource", path), &format!("unknown resource \"{}\"", resource)));
+                        continue;
+                    }, // None
+                }, // Some
+                None           => None,
+            }; // match
+            // privileges unknown to the acl are leaked, which is fine for tests
+            let privilege = entry.privilege.as_deref().map(|privilege| acl.privileges.get(privilege).copied()
+                .or_else(|| acl.rules.keys().filter_map(|query| query.privilege).find(|known| *known == privilege))
+                .unwrap_or_else(|| Box::leak(String::from(privilege).into_boxed_str())));
+
+            expectations.push(Expectation{query: Query{resource, role, privilege}, expected, line});
+        } // for
+        if !errors.is_empty() {
+            warn!("invalid expectations {} with {} problems", name, errors.len());
+            return Err(Error::Schema(errors));
+        } // if
+        Ok(GoldenTest{acl, source: String::from(name), expectations})
+    } // parse
+
+} // impl GoldenTest
+
+/// Loads the golden test of dir and panics with the report of all failed expectations, see module
+/// `golden`.
+pub fn assert_golden<P: AsRef<Path>>(dir: P) {
+    let dir = dir.as_ref();
+
+    match GoldenTest::load(dir) {
+        Ok(test)   => if let Err(report) = test.run() {
+            panic!("golden test {} failed: {}", dir.display(), report);
+        }, // Ok
+        Err(error) => panic!("golden test {} can't be loaded: {}", dir.display(), error),
+    } // match
+} // assert_golden
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    const EXPECT: &str = r#"
+[[expect]]
+role      = "guest"
+resource  = "news"
+privilege = "view"
+expected  = "allow"
+
+[[expect]]
+role      = "staff"
+resource  = "latest"
+privilege = "edit"
+expected  = "Allow"
+
+[[expect]]
+privilege = "view"
+expected  = "deny"
+"#;
+
+    fn setup() -> Acl {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("edit")).is_ok());
+        acl
+    } // setup
+
+    #[test]
+    fn run() {
+        let test = GoldenTest::new(setup(), EXPECT).unwrap();
+
+        assert_eq!(test.expectations().len(), 3);
+        assert_eq!(test.expectations()[2], Expectation{query: Query{resource: None, role: None, privilege: Some("view")},
+            expected: Access::Deny, line: 14});
+        assert_eq!(test.run(), Err(String::from(concat!(
+            "1 of 3 expectations failed\n",
+            "\n",
+            "expectations.toml:8: staff→latest: edit expected ALLOW, got DENY\n",
+            "    decision: DENY staff→latest: edit\n",
+            "    matched:  DENY staff→latest: edit\n",
+            "    roles:     staff, guest\n",
+            "    resources: latest, news\n",
+            "    rules in order of precedence:\n",
+            "      => DENY staff→latest: edit\n",
+            "         DENY *→*: *\n",
+        ))));
+
+        let mut acl = setup();
+
+        assert!(acl.allow(Some("staff"), Some("latest"), None).is_ok());
+        assert!(acl.remove_deny(Some("staff"), Some("latest"), Some("edit")).is_ok());
+        assert_eq!(GoldenTest::new(acl, EXPECT).unwrap().run(), Ok(3));
+        assert_eq!(GoldenTest::new(setup(), "").unwrap().run(), Ok(0));
+    } // run
+
+    #[test]
+    fn invalid() {
+        let errors: Vec<String> = match GoldenTest::new(setup(), concat!(
+            "[[expect]]\nrole = \"nobody\"\nexpected = \"allow\"\n",
+            "[[expect]]\nresource = \"nothing\"\nexpected = \"deny\"\n",
+            "[[expect]]\nexpected = \"maybe\"\n",
+        )).map(|test| test.expectations.len()) {
+            Err(Error::Schema(errors)) => errors.iter().map(|e| e.to_string()).collect(),
+            other                      => panic!("unexpected result {:?}", other),
+        }; // match
+
+        assert_eq!(errors, vec![
+            "expectations.toml: expect[0].role: unknown role \"nobody\"",
+            "expectations.toml: expect[1].resource: unknown resource \"nothing\"",
+            "expectations.toml: expect[2].expected: expected \"allow\" or \"deny\", found \"maybe\"",
+        ]);
+        assert!(matches!(GoldenTest::new(setup(), "[[expect]]\nrole = \"guest\"\n").err(), Some(Error::Parse(_))));
+        assert!(matches!(GoldenTest::new(setup(), "[[expect]]\nexpected = \"allow\"\nrule = 1\n").err(), Some(Error::Parse(_))));
+        assert!(matches!(GoldenTest::load("missing").err(), Some(Error::Io(_))));
+    } // invalid
+
+} // mod tests
@@ -0,0 +1,225 @@
+//! GraphQL field-level authorization for async-graphql.
+//!
+//! A `FieldMap` maps GraphQL type and field names to a resource and privilege. Since an `Acl`
+//! can't be shared across threads, it isn't consulted during field resolution. Instead
+//! `Acl::authorize_fields` decides all mapped fields for the role of a request at once. The
+//! resulting `FieldGrants` are added to the request data and looked up by a `FieldGuard` per
+//! field resolution. Fields guarded but not mapped are denied.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::graphql::FieldMap;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_resource("user", None).unwrap();
+//! acl.allow(Some("guest"), Some("user"), Some("view")).unwrap();
+//!
+//! let mut map = FieldMap::new();
+//!
+//! map.map("User", "name",  Some("user"), Some("view"));
+//! map.map("User", "email", Some("user"), Some("view_private"));
+//!
+//! let grants = acl.authorize_fields(Some("guest"), &map);
+//!
+//! assert!( grants.is_allowed("User", "name"));
+//! assert!(!grants.is_allowed("User", "email"));
+//! ```
+//!
+//! Guards are attached to fields like `#[graphql(guard = "FieldGuard::new(\"User\", \"email\")")]`
+//! and the grants are added to each request by `Request::data(grants)`.
+
+use crate::{Acl, Privilege, Resource, Role};
+use async_graphql::{Context, Error, Guard, Result};
+use log::trace;
+use std::collections::{HashMap, HashSet};
+
+
+// FieldMap ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Maps GraphQL type and field names to a resource and privilege.
+#[derive(Clone, Debug, Default)]
+pub struct FieldMap {
+    fields: HashMap<&'static str, HashMap<&'static str, (Resource, Privilege)>>,
+} // struct FieldMap
+
+impl FieldMap {
+
+    /// Creates a new, empty `FieldMap`.
+    pub fn new() -> Self {
+        FieldMap{fields: HashMap::new()}
+    } // new
+
+    /// Maps field of type to privilege on resource. Replaces a previous mapping.
+    pub fn map(&mut self, type_name: &'static str, field: &'static str, resource: Resource, privilege: Privilege) -> &mut Self {
+        self.fields.entry(type_name).or_default().insert(field, (resource, privilege));
+        self
+    } // map
+
+    /// Returns the resource and privilege field of type is mapped to.
+    pub fn get(&self, type_name: &str, field: &str) -> Option<(Resource, Privilege)> {
+        self.fields.get(type_name).and_then(|fields| fields.get(field)).copied()
+    } // get
+
+} // impl FieldMap
+
+
+// FieldGrants ////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The fields allowed for a role, see `Acl::authorize_fields`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldGrants {
+    allowed: HashMap<&'static str, HashSet<&'static str>>,
+} // struct FieldGrants
+
+impl FieldGrants {
+
+    /// Returns true if field of type is allowed.
+    pub fn is_allowed(&self, type_name: &str, field: &str) -> bool {
+        self.allowed.get(type_name).is_some_and(|fields| fields.contains(field))
+    } // is_allowed
+
+} // impl FieldGrants
+
+impl Acl {
+
+    /// Decides all fields mapped by map for role. Each distinct resource and privilege is decided
+    /// once, no matter how many fields are mapped to it.
+    pub fn authorize_fields(&self, role: Role, map: &FieldMap) -> FieldGrants {
+        trace!("authorizing fields of {} types for {:?}", map.fields.len(), role);
+        let mut decided: HashMap<(Resource, Privilege), bool> = HashMap::new();
+        let mut allowed: HashMap<&'static str, HashSet<&'static str>> = HashMap::new();
+
+        for (type_name, fields) in &map.fields {
+            for (field, (resource, privilege)) in fields {
+                let is_allowed = *decided.entry((*resource, *privilege))
+                    .or_insert_with(|| self.is_allowed(role, *resource, *privilege));
+
+                if is_allowed {
+                    allowed.entry(type_name).or_default().insert(field);
+                } // if
+            } // for
+        } // for
+        FieldGrants{allowed}
+    } // authorize_fields
+
+} // impl Acl
+
+
+// FieldGuard /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Guards the resolution of a field by the `FieldGrants` of the request.
+#[derive(Clone, Copy, Debug)]
+pub struct FieldGuard {
+    type_name: &'static str,
+    field:     &'static str,
+} // struct FieldGuard
+
+impl FieldGuard {
+
+    /// Creates a new `FieldGuard` for field of type.
+    pub fn new(type_name: &'static str, field: &'static str) -> Self {
+        FieldGuard{type_name, field}
+    } // new
+
+} // impl FieldGuard
+
+impl Guard for FieldGuard {
+
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let grants = ctx.data::<FieldGrants>()?;
+
+        if grants.is_allowed(self.type_name, self.field) {
+            Ok(())
+        } else {
+            Err(Error::new(format!("Access denied: {}.{}", self.type_name, self.field)))
+        } // else
+    } // check
+
+} // impl Guard for FieldGuard
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Request, Schema};
+    use futures::executor::block_on;
+    use test_env_log::test;
+
+    struct User;
+
+    #[Object]
+    impl User {
+
+        #[graphql(guard = "FieldGuard::new(\"User\", \"name\")")]
+        async fn name(&self) -> &str {
+            "sally"
+        } // name
+
+        #[graphql(guard = "FieldGuard::new(\"User\", \"email\")")]
+        async fn email(&self) -> &str {
+            "sally@example.com"
+        } // email
+
+        #[graphql(guard = "FieldGuard::new(\"User\", \"phone\")")]
+        async fn phone(&self) -> &str {
+            "555-0100"
+        } // phone
+
+    } // impl User
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+
+        async fn user(&self) -> User {
+            User
+        } // user
+
+    } // impl Query
+
+    #[test]
+    fn guard() {
+        let mut acl = Acl::new();
+        let mut map = FieldMap::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("user", None).is_ok());
+        assert!(acl.allow(Some("guest"), Some("user"), Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("user"), Some("view_private")).is_ok());
+        map.map("User", "name",  Some("user"), Some("view"))
+           .map("User", "email", Some("user"), Some("view_private"));
+        assert_eq!(map.get("User", "email"), Some((Some("user"), Some("view_private"))));
+        assert_eq!(map.get("User", "phone"), None);
+
+        let schema  = Schema::new(Query, EmptyMutation, EmptySubscription);
+        let execute = |role| {
+            let request = Request::new("{ user { name email } }").data(acl.authorize_fields(role, &map));
+
+            block_on(schema.execute(request))
+        }; // execute
+
+        let staff = execute(Some("staff"));
+        let guest = execute(Some("guest"));
+
+        assert!(staff.errors.is_empty());
+        assert_eq!(staff.data.to_string(), r#"{user: {name: "sally", email: "sally@example.com"}}"#);
+        assert_eq!(guest.errors[0].message, "Access denied: User.email");
+
+        // unmapped fields are denied
+        let response = block_on(schema.execute(Request::new("{ user { phone } }").data(acl.authorize_fields(Some("staff"), &map))));
+
+        assert_eq!(response.errors[0].message, "Access denied: User.phone");
+    } // guard
+
+} // mod tests
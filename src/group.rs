@@ -0,0 +1,296 @@
+//! Named rule groups.
+//!
+//! Rules may be defined in a named group, e.g. to gate a whole permission set behind a feature
+//! flag. A group is enabled or disabled atomically at runtime without losing its rules. While a
+//! group is enabled, its rules replace the rules defined for the same queries outside of the
+//! group, and changes to those queries apply to the group. While it is disabled, its rules are set
+//! aside and the replaced rules apply again. Groups are enabled when first defined. A query
+//! belongs to at most one group and to no environment, see module `environment`. The catch-all
+//! rule can't be grouped.
+//!
+//! Toggling a group doesn't define new rules, so it's permitted while the `Acl` is locked and
+//! purges the cache.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.add_resource("reports", None).unwrap();
+//! acl.group("beta-features", |group| {
+//!     group.allow(Some("staff"), Some("reports"), Some("export"))?;
+//!     group.allow(Some("staff"), Some("reports"), Some("share"))
+//! }).unwrap();
+//!
+//! assert!(acl.is_allowed(Some("staff"), Some("reports"), Some("export")));
+//! acl.disable_group("beta-features").unwrap();
+//! assert!(acl.is_denied(Some("staff"), Some("reports"), Some("export")));
+//! assert!(acl.is_denied(Some("staff"), Some("reports"), Some("share")));
+//! ```
+
+use crate::{Access, Acl, Error, Privilege, Query, Resource, Role, Rule};
+use log::trace;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+
+
+// Group //////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The queries of a group and the rules set aside for them. Also used for environments, see
+/// module `environment`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Group {
+    pub enabled: bool,
+    /// the replaced rules if enabled, the rules of the group if disabled
+    pub aside:   BTreeMap<Query, Option<Rule>>,
+} // struct Group
+
+
+// RuleGroup //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Defines the rules of a named group, see `Acl::group`.
+pub struct RuleGroup<'a> {
+    acl:  &'a mut Acl,
+    name: &'static str,
+} // struct RuleGroup
+
+impl<'a> RuleGroup<'a> {
+
+    /// Returns the name of the group.
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    } // name
+
+    /// Sets a rule of the group like `Acl::set_rule`. Returns an error for the catch-all rule and
+    /// for queries of another group or an environment.
+    pub fn set_rule(&mut self, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
+        trace!("setting {} rule of group {} for {:?} on {:?} with {:?} privilege", access, self.name, role, resource, privilege);
+        let query = Query{resource, role, privilege};
+
+        if query == Query::ALL {
+            return Err(Error::NotPermitted(format!("grouped rule {}", query)));
+        } // if
+        if let Some((other, _)) = self.acl.groups.iter().find(|(other, group)| **other != self.name && group.aside.contains_key(&query)) {
+            return Err(Error::NotPermitted(format!("rule {} of group {}", query, other)));
+        } // if
+        if let Some((env, _)) = self.acl.environments.iter().find(|(_, group)| group.aside.contains_key(&query)) {
+            return Err(Error::NotPermitted(format!("rule {} of environment {}", query, env)));
+        } // if
+
+        let mut group = self.acl.groups.remove(self.name).unwrap_or_default();
+        let result    = self.acl.set_grouped_rule(&mut group, query, |acl| acl.set_rule(role, resource, privilege, access));
+
+        self.acl.groups.insert(self.name, group);
+        result
+    } // set_rule
+
+    /// Allows privilege for role on resource while the group is enabled.
+    #[inline]
+    pub fn allow(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(role, resource, privilege, Access::Allow)
+    } // allow
+
+    /// Denies privilege for role on resource while the group is enabled.
+    #[inline]
+    pub fn deny(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(role, resource, privilege, Access::Deny)
+    } // deny
+
+} // impl RuleGroup
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Defines rules of the group by name, creating an enabled group if it doesn't exist. Rules
+    /// set by define before an error remain set.
+    pub fn group<F>(&mut self, name: &'static str, define: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut RuleGroup) -> Result<(), Error>
+    {
+        trace!("defining group {}", name);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        self.groups.entry(name).or_insert_with(|| Group{enabled: true, aside: BTreeMap::new()});
+        define(&mut RuleGroup{acl: self, name})
+    } // group
+
+    /// Returns true if a group is defined by name.
+    #[inline]
+    pub fn has_group(&self, name: &str) -> bool {
+        self.groups.contains_key(name)
+    } // has_group
+
+    /// Returns an iterator over the names of all groups in ascending order.
+    pub fn groups(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.groups.keys().copied()
+    } // groups
+
+    /// Returns true if the group is enabled. Returns an error if the group is undefined.
+    pub fn is_group_enabled(&self, name: &str) -> Result<bool, Error> {
+        match self.groups.get(name) {
+            Some(group) => Ok(group.enabled),
+            None        => Err(Error::MissingGroup(String::from(name))),
+        } // match
+    } // is_group_enabled
+
+    /// Enables or disables the group. Returns true if the state changed. Returns an error if the
+    /// group is undefined.
+    pub fn set_group_enabled(&mut self, name: &str, enabled: bool) -> Result<bool, Error> {
+        trace!("setting group {} enabled to {}", name, enabled);
+        let (name, mut group) = match self.groups.remove_entry(name) {
+            Some(entry) => entry,
+            None        => return Err(Error::MissingGroup(String::from(name))),
+        }; // match
+
+        let changed = group.enabled != enabled;
+
+        if changed {
+            self.toggle_group(&mut group);
+            self.purge_cache();
+        } // if
+        self.groups.insert(name, group);
+        Ok(changed)
+    } // set_group_enabled
+
+    /// Sets the rule for query of group with set. The rule of a disabled group is set aside right
+    /// away and the replaced rule restored.
+    pub(crate) fn set_grouped_rule<F>(&mut self, group: &mut Group, query: Query, set: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Acl) -> Result<(), Error>
+    {
+        if let Some(bundle) = query.privilege.filter(|name| self.bundles.contains_key(name)) {
+            return Err(Error::NotPermitted(format!("grouped rule for bundle {}", bundle)));
+        } // if
+        let previous = self.rules.get(&query).copied();
+
+        // the previous rule keeps its assertion until it's set aside or restored
+        self.retain_assertion(previous);
+        if let Err(err) = set(self) {
+            self.release_assertion(previous);
+            return Err(err);
+        } // if
+        if group.enabled {
+            match group.aside.entry(query) {
+                Entry::Vacant(entry) => { entry.insert(previous); },
+                Entry::Occupied(_)   => self.release_assertion(previous),
+            } // match
+        } else {
+            let current = self.rules.get(&query).copied();
+
+            self.retain_assertion(current);
+            if let Some(replaced) = group.aside.insert(query, current) {
+                self.release_assertion(replaced);
+            } // if
+            match previous {
+                Some(previous) => self.insert_rule(query, previous),
+                None           => self.remove_rule(&query),
+            }; // match
+            self.release_assertion(previous);
+        } // if
+        Ok(())
+    } // set_grouped_rule
+
+    /// Swaps the rules set aside by group with the rules in effect and flips its state.
+    pub(crate) fn toggle_group(&mut self, group: &mut Group) {
+        for (query, aside) in group.aside.iter_mut() {
+            let current = self.rules.get(query).copied();
+
+            // retained before it's removed, so it doesn't lose its assertion
+            self.retain_assertion(current);
+            self.remove_rule(query);
+            if let Some(rule) = aside.take() {
+                self.insert_rule(*query, rule);
+                self.release_assertion(Some(rule));
+            } // if
+            *aside = current;
+        } // for
+        group.enabled = !group.enabled;
+    } // toggle_group
+
+    /// Enables the group. Returns true if it was disabled.
+    #[inline]
+    pub fn enable_group(&mut self, name: &str) -> Result<bool, Error> {
+        self.set_group_enabled(name, true)
+    } // enable_group
+
+    /// Disables the group. Returns true if it was enabled.
+    #[inline]
+    pub fn disable_group(&mut self, name: &str) -> Result<bool, Error> {
+        self.set_group_enabled(name, false)
+    } // disable_group
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn toggle() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("reports", None).is_ok());
+        assert!(acl.deny(Some("staff"), Some("reports"), Some("share")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("reports"), Some("view")).is_ok());
+
+        let etag = acl.etag();
+
+        assert!(acl.group("beta", |group| {
+            group.allow(Some("staff"), Some("reports"), Some("export"))?;
+            group.allow(Some("staff"), Some("reports"), Some("share"))
+        }).is_ok());
+        assert_eq!(acl.groups().collect::<Vec<_>>(), vec!["beta"]);
+        assert_eq!(acl.is_group_enabled("beta"), Ok(true));
+        assert!(acl.is_allowed(Some("staff"), Some("reports"), Some("export")));
+        assert!(acl.is_allowed(Some("staff"), Some("reports"), Some("share")));
+
+        // disabling restores the replaced rules, also while locked
+        acl.lock();
+        assert!(acl.is_allowed(Some("staff"), Some("reports"), Some("export")));
+        assert_eq!(acl.disable_group("beta"), Ok(true));
+        assert_eq!(acl.disable_group("beta"), Ok(false));
+        assert!(acl.is_denied(Some("staff"), Some("reports"), Some("export")));
+        assert!(acl.is_denied(Some("staff"), Some("reports"), Some("share")));
+        assert!(acl.is_allowed(Some("staff"), Some("reports"), Some("view")));
+        assert_eq!(acl.etag(), etag);
+        assert_eq!(acl.group("beta", |_| Ok(())), Err(Error::Locked));
+        acl.unlock();
+
+        // rules defined while disabled are set aside
+        assert!(acl.group("beta", |group| group.deny(Some("staff"), Some("reports"), Some("view"))).is_ok());
+        assert!(acl.is_allowed(Some("staff"), Some("reports"), Some("view")));
+        assert_eq!(acl.enable_group("beta"), Ok(true));
+        assert!(acl.is_denied(Some("staff"), Some("reports"), Some("view")));
+        assert!(acl.is_allowed(Some("staff"), Some("reports"), Some("share")));
+
+        // changes while enabled apply to the group
+        assert!(acl.deny(Some("staff"), Some("reports"), Some("export")).is_ok());
+        assert_eq!(acl.disable_group("beta"), Ok(true));
+        assert!(acl.allow(Some("staff"), Some("reports"), Some("export")).is_ok());
+        assert_eq!(acl.enable_group("beta"), Ok(true));
+        assert!(acl.is_denied(Some("staff"), Some("reports"), Some("export")));
+
+        assert_eq!(acl.group("alpha", |group| group.allow(Some("staff"), Some("reports"), Some("share"))),
+            Err(Error::NotPermitted(String::from("rule staff→reports: share of group beta"))));
+        assert!(acl.group("alpha", |group| group.allow(None, None, None)).is_err());
+        assert!(acl.group("alpha", |group| group.allow(Some("nobody"), None, None)).is_err());
+        assert_eq!(acl.enable_group("gamma"), Err(Error::MissingGroup(String::from("gamma"))));
+        assert!(acl.has_group("alpha"));
+    } // toggle
+
+} // mod tests
@@ -0,0 +1,170 @@
+//! Diagnostics of pathological hierarchies.
+//!
+//! Imported hierarchies, e.g. enterprise org charts, may hold structures which make queries slow:
+//! deep resource chains, roles with hundreds of parents or diamond inheritance, where many roles
+//! share ancestors, so that lineages span most of the roles. `Acl::hierarchy_report` finds the
+//! worst of each and estimates the worst-case cost of a query which isn't answered by the cache.
+//! Locking an `Acl` whose estimate exceeds `COST_WARNING` logs the report as a warning.
+//!
+//! The estimate counts the rule lookups of the search in order of precedence: each resource of
+//! the deepest resource lineage and the wildcard, combined with each role of the largest role
+//! lineage and the wildcard, for the queried and the wildcard privilege. The limits `max_parents`
+//! and `max_lineage` reject such structures while they are defined, see module `limits`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("employee", vec![]).unwrap();
+//! acl.add_role("engineering", vec!["employee"]).unwrap();
+//! acl.add_role("sales", vec!["employee"]).unwrap();
+//! acl.add_role("solutions", vec!["engineering", "sales"]).unwrap();
+//! acl.add_resource("wiki", None).unwrap();
+//!
+//! let report = acl.hierarchy_report();
+//!
+//! assert_eq!(report.largest_lineage, Some(("solutions", 4)));
+//! assert_eq!(report.diamonds, 1);
+//! assert_eq!(report.query_cost, 20);
+//! ```
+
+use crate::Acl;
+use log::warn;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The estimated query cost above which locking logs a warning.
+pub const COST_WARNING: usize = 10_000;
+
+
+// HierarchyReport ////////////////////////////////////////////////////////////////////////////////
+
+
+/// The worst structures of the role and resource hierarchies, see module `hierarchy`. Ties are
+/// reported by the name first in lexical order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HierarchyReport {
+    /// the resource with the most ancestors and their number
+    pub deepest_resource: Option<(&'static str, usize)>,
+    /// the role with the most parents and their number
+    pub most_parents:     Option<(&'static str, usize)>,
+    /// the role with the largest lineage and the number of roles in it, including itself
+    pub largest_lineage:  Option<(&'static str, usize)>,
+    /// the number of roles inheriting an ancestor through more than one parent
+    pub diamonds:         usize,
+    /// the estimated number of rule lookups of a query in the worst case
+    pub query_cost:       usize,
+} // struct HierarchyReport
+
+impl fmt::Display for HierarchyReport {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if let Some((name, count)) = self.deepest_resource {
+            writeln!(f, "deepest resource: {} with {} ancestors", name, count)?;
+        } // if
+        if let Some((name, count)) = self.most_parents {
+            writeln!(f, "most parents: {} with {} parents", name, count)?;
+        } // if
+        if let Some((name, count)) = self.largest_lineage {
+            writeln!(f, "largest lineage: {} with {} roles", name, count)?;
+        } // if
+        writeln!(f, "diamonds: {} roles", self.diamonds)?;
+        write!(f, "worst-case query cost: {} rule lookups", self.query_cost)
+    } // fmt
+
+} // impl fmt::Display for HierarchyReport
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Returns the diagnostics of the defined hierarchies, see module `hierarchy`.
+    pub fn hierarchy_report(&self) -> HierarchyReport {
+        let mut report = HierarchyReport::default();
+        // keeps the first name of the maximum
+        let max        = |current: &mut Option<(&'static str, usize)>, name: &'static str, count: usize| {
+            if current.is_none_or(|(_, max)| count > max) {
+                *current = Some((name, count));
+            } // if
+        }; // max
+
+        for name in self.resources.keys() {
+            max(&mut report.deepest_resource, name, self.iter_resource_lineage(name).count() - 1);
+        } // for
+        for (name, parents) in &self.roles {
+            let lineage: BTreeSet<&'static str> = self.iter_role_lineage(name).collect();
+            let inherited = parents.iter().map(|parent| self.iter_role_lineage(parent).count()).sum::<usize>();
+
+            max(&mut report.most_parents, name, parents.len());
+            max(&mut report.largest_lineage, name, lineage.len());
+            // without diamonds, the lineages of the parents are disjoint
+            if inherited > lineage.len() - 1 {
+                report.diamonds += 1;
+            } // if
+        } // for
+
+        let roles     = report.largest_lineage.map_or(0, |(_, count)| count) + 1;
+        let resources = report.deepest_resource.map_or(0, |(_, count)| count + 1) + 1;
+
+        report.query_cost = roles * resources * 2;
+        report
+    } // hierarchy_report
+
+    /// Logs the hierarchy report if the estimated query cost exceeds `COST_WARNING`.
+    pub(crate) fn warn_pathological(&self) {
+        let report = self.hierarchy_report();
+
+        if report.query_cost > COST_WARNING {
+            warn!("pathological hierarchies:\n{}", report);
+        } // if
+    } // warn_pathological
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn report() {
+        let mut acl = Acl::new();
+
+        assert_eq!(acl.hierarchy_report(), HierarchyReport{query_cost: 2, ..HierarchyReport::default()});
+
+        // a chain of resources and a wide diamond of roles
+        assert!(acl.add_resource("r0", None).is_ok());
+        for (name, parent) in [("r1", "r0"), ("r2", "r1"), ("r3", "r2")] {
+            assert!(acl.add_resource(name, Some(parent)).is_ok());
+        } // for
+        assert!(acl.add_role("all", vec![]).is_ok());
+        for name in ["a", "b", "c"] {
+            assert!(acl.add_role(name, vec!["all"]).is_ok());
+        } // for
+        assert!(acl.add_role("abc", vec!["a", "b", "c"]).is_ok());
+        assert!(acl.add_role("x", vec!["abc"]).is_ok());
+        assert!(acl.add_role("y", vec!["a", "x"]).is_ok());
+
+        let report = acl.hierarchy_report();
+
+        assert_eq!(report.deepest_resource, Some(("r3", 3)));
+        assert_eq!(report.most_parents, Some(("abc", 3)));
+        assert_eq!(report.largest_lineage, Some(("y", 7)));
+        assert_eq!(report.diamonds, 2);
+        assert_eq!(report.query_cost, 8 * 5 * 2);
+        assert_eq!(report.to_string(), "deepest resource: r3 with 3 ancestors\n\
+            most parents: abc with 3 parents\n\
+            largest lineage: y with 7 roles\n\
+            diamonds: 2 roles\n\
+            worst-case query cost: 80 rule lookups");
+    } // report
+
+} // mod tests
@@ -0,0 +1,137 @@
+//! Per-rule hit counters.
+//!
+//! Once enabled by `set_rule_hits`, the `Acl` counts how many times each rule has been the
+//! deciding match of `decide`, `decide_for` and the methods based on them. Rules which are never
+//! hit under real traffic are candidates for removal. Decisions of bypass roles and of subject
+//! overrides don't count.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//! acl.set_rule_hits(true);
+//!
+//! assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+//! assert!(acl.is_denied(Some("guest"), None, Some("edit")));
+//! assert_eq!(acl.rule_hits(Some("guest"), None, Some("view")), 1);
+//! assert_eq!(acl.rule_hits(None, None, None), 1);
+//! ```
+
+use crate::{Acl, Decision, Privilege, Query, Resource, Role};
+use log::trace;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+impl Acl {
+
+    /// Enables or disables counting of rule hits. Disabling drops the counts.
+    pub fn set_rule_hits(&mut self, enabled: bool) {
+        trace!("setting rule hits to {}", enabled);
+        if !enabled {
+            self.rule_hits = None;
+        } else if self.rule_hits.is_none() {
+            self.rule_hits = Some(RefCell::new(HashMap::new()));
+        } // else if
+    } // set_rule_hits
+
+    /// Returns true if rule hits are counted.
+    #[inline]
+    pub fn is_counting_rule_hits(&self) -> bool {
+        self.rule_hits.is_some()
+    } // is_counting_rule_hits
+
+    /// Returns how many times the rule defined for role on resource to privilege has decided a
+    /// query. Returns 0 if the rule is undefined or hits aren't counted.
+    pub fn rule_hits(&self, role: Role, resource: Resource, privilege: Privilege) -> u64 {
+        let query = Query{resource, role, privilege};
+
+        match &self.rule_hits {
+            Some(hits) if self.rules.contains_key(&query) => hits.borrow().get(&query).copied().unwrap_or(0),
+            _                                             => 0,
+        } // match
+    } // rule_hits
+
+    /// Returns all defined rules with their hits, including rules never hit, ordered by role,
+    /// resource and privilege. Returns an empty vector if hits aren't counted.
+    pub fn rule_hit_counts(&self) -> Vec<(Query, u64)> {
+        let hits = match &self.rule_hits {
+            Some(hits) => hits.borrow(),
+            None       => return vec![],
+        }; // match
+        let mut counts: Vec<(Query, u64)> = self.rules.keys()
+            .map(|query| (*query, hits.get(query).copied().unwrap_or(0)))
+            .collect();
+
+        counts.sort_by_key(|(query, _)| (query.role, query.resource, query.privilege));
+        counts
+    } // rule_hit_counts
+
+    /// Resets all hits to 0.
+    pub fn reset_rule_hits(&self) {
+        if let Some(hits) = &self.rule_hits {
+            hits.borrow_mut().clear();
+        } // if
+    } // reset_rule_hits
+
+    /// Counts the deciding rule of decision.
+    pub(crate) fn count_hit(&self, decision: &Decision) {
+        if let Some(hits) = &self.rule_hits {
+            if !decision.bypass {
+                *hits.borrow_mut().entry(decision.matched).or_insert(0) += 1;
+            } // if
+        } // if
+    } // count_hit
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn hits() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        assert!(acl.deny_subject("sally", None, Some("view")).is_ok());
+        assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+        assert_eq!(acl.rule_hit_counts(), vec![]);
+
+        acl.set_rule_hits(true);
+        acl.lock();
+        for _ in 0..2 {
+            assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+            assert!(acl.is_denied(Some("staff"), Some("news"), Some("view")));
+        } // for
+        assert!(acl.is_allowed(Some("root"), Some("news"), None));
+        assert!(acl.is_denied_for("sally", Some("guest"), None, Some("view")));
+        assert!(acl.is_allowed_for("bob", Some("guest"), None, Some("view")));
+        assert_eq!(acl.rule_hits(Some("guest"), None, Some("view")), 3);
+        assert_eq!(acl.rule_hit_counts(), vec![
+            (Query::ALL, 0),
+            (Query{resource: None, role: Some("guest"), privilege: Some("view")}, 3),
+            (Query{resource: Some("news"), role: Some("staff"), privilege: None}, 2),
+        ]);
+
+        acl.reset_rule_hits();
+        assert_eq!(acl.rule_hits(Some("guest"), None, Some("view")), 0);
+        acl.set_rule_hits(false);
+        assert!(!acl.is_counting_rule_hits());
+    } // hits
+
+} // mod tests
@@ -0,0 +1,136 @@
+//! Error recovery of importers.
+//!
+//! Importers of policy documents, permission matrices and `ls -l` listings take an `ErrorMode`
+//! deciding how invalid entries are handled:
+//!
+//! * `FailFast` stops at the first problem and reports it alone,
+//! * `CollectAll` validates everything and reports all problems at once without importing
+//!   anything, which is what the importers without mode do,
+//! * `SkipInvalid` imports the valid entries and reports the skipped ones in an `ImportReport`.
+//!
+//! Skipping an entry skips the entries depending on it, e.g. the rules of a skipped role. Problems
+//! which don't concern a single entry, e.g. a malformed file or a locked `Acl`, always fail the
+//! import.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::import::ErrorMode;
+//! let mut acl = Acl::new();
+//! let report  = acl.import_ls_with("/srv", "\
+//! total 8
+//! drwxr-x--- 2 alice staff 4096 Mar  1 12:00 docs
+//! -rw-r--r-- alice staff 220 Mar  1 12:00 notes.txt", ErrorMode::SkipInvalid).unwrap();
+//!
+//! assert_eq!(report.imported, 1);
+//! assert_eq!(report.skipped[0].to_string(), "3: Parse error: invalid listing: -rw-r--r-- alice staff 220 Mar  1 12:00 notes.txt");
+//! assert!(acl.has_resource("/srv/docs"));
+//! assert!(acl.import_ls_with("/srv", "-rw-r--r-- alice staff", ErrorMode::CollectAll).is_err());
+//! ```
+
+use crate::{Error, SchemaError};
+use log::warn;
+
+
+// ErrorMode //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Handles invalid entries while importing, see module `import`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorMode {
+    /// stop at the first problem
+    FailFast,
+    /// report all problems and import nothing
+    CollectAll,
+    /// import the valid entries and report the invalid ones
+    SkipInvalid,
+} // enum ErrorMode
+
+impl Default for ErrorMode {
+
+    /// Returns `CollectAll`.
+    fn default() -> Self {
+        ErrorMode::CollectAll
+    } // default
+
+} // impl Default for ErrorMode
+
+impl ErrorMode {
+
+    /// Records a problem. Returns an error if failing fast.
+    pub(crate) fn reject(self, errors: &mut Vec<SchemaError>, error: SchemaError) -> Result<(), Error> {
+        if self == ErrorMode::FailFast {
+            warn!("import failed: {}", error);
+            return Err(Error::Schema(vec![error]));
+        } // if
+        errors.push(error);
+        Ok(())
+    } // reject
+
+    /// Returns the problems found to be reported as skipped entries, or an error unless invalid
+    /// entries are skipped.
+    pub(crate) fn check(self, mut errors: Vec<SchemaError>) -> Result<Vec<SchemaError>, Error> {
+        if errors.is_empty() || self == ErrorMode::SkipInvalid {
+            return Ok(errors);
+        } // if
+        warn!("import failed with {} problems", errors.len());
+        if self == ErrorMode::FailFast {
+            errors.truncate(1);
+        } // if
+        Err(Error::Schema(errors))
+    } // check
+
+} // impl ErrorMode
+
+
+// ImportReport ///////////////////////////////////////////////////////////////////////////////////
+
+
+/// The outcome of an import.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    /// the number of entries imported
+    pub imported: usize,
+    /// the problems of the skipped entries
+    pub skipped:  Vec<SchemaError>,
+} // struct ImportReport
+
+impl ImportReport {
+
+    /// Returns true if no entry has been skipped.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    } // is_complete
+
+} // impl ImportReport
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn mode() {
+        let problems   = || vec![SchemaError::new("1:2", "first"), SchemaError::new("2:2", "second")];
+        let mut errors = vec![];
+
+        assert_eq!(ErrorMode::default(), ErrorMode::CollectAll);
+        assert_eq!(ErrorMode::FailFast.reject(&mut errors, SchemaError::new("1:2", "first")),
+            Err(Error::Schema(vec![SchemaError::new("1:2", "first")])));
+        assert!(ErrorMode::SkipInvalid.reject(&mut errors, SchemaError::new("1:2", "first")).is_ok());
+        assert_eq!(errors.len(), 1);
+
+        assert_eq!(ErrorMode::FailFast.check(problems()), Err(Error::Schema(vec![SchemaError::new("1:2", "first")])));
+        assert_eq!(ErrorMode::CollectAll.check(problems()), Err(Error::Schema(problems())));
+        assert_eq!(ErrorMode::SkipInvalid.check(problems()), Ok(problems()));
+        assert_eq!(ErrorMode::CollectAll.check(vec![]), Ok(vec![]));
+        assert!(ImportReport::default().is_complete());
+    } // mode
+
+} // mod tests
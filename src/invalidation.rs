@@ -0,0 +1,317 @@
+//! Cache invalidation of locked replicas.
+//!
+//! Processes holding a locked `Acl` replica of a central policy serve cached decisions. An
+//! `Invalidator` owns the central `Acl` and publishes a small JSON message over a `Channel` for
+//! each mutation, e.g. over Redis pub/sub with the `redis` feature. Rule changes carry the key of
+//! the rule and its new access, so a replica applies the rule without unlocking and evicts only
+//! the cached decisions the rule may affect, see `Acl::evict_cache`. Any other change, e.g. a new
+//! role, asks replicas to reload the policy.
+//!
+//! Messages are neither sequenced nor acknowledged. Replicas which must not miss a change replicate
+//! through module `sync` instead.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Access, Acl};
+//! # use zorq_acl::invalidation::{Invalidated, Invalidator};
+//! # use std::sync::mpsc::channel;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.deny(Some("guest"), None, None).unwrap();
+//!
+//! let mut replica          = Acl::from_json(&acl.to_json()).unwrap();
+//! let (sender, receiver)   = channel();
+//! let mut invalidator      = Invalidator::new(acl, sender);
+//!
+//! replica.lock();
+//! assert!(replica.is_denied(Some("guest"), None, Some("view")));
+//!
+//! invalidator.set_rule(Some("guest"), None, Some("view"), Access::Allow).unwrap();
+//! for message in receiver.try_iter() {
+//!     assert_eq!(replica.receive_invalidation(&message), Ok(Invalidated::Evicted(1)));
+//! }
+//! assert!(replica.is_allowed(Some("guest"), None, Some("view")));
+//! ```
+
+use crate::policy::intern;
+use crate::sync::Channel;
+use crate::{Access, Acl, Error, Privilege, Query, Resource, Role, Rule, SchemaError};
+use log::{trace, warn};
+use serde_json::{json, Value};
+
+
+// Invalidation ///////////////////////////////////////////////////////////////////////////////////
+
+
+/// A message published on a change of the central `Acl`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Invalidation {
+    /// the rule has been set to access or removed if None
+    Rule{role: Option<String>, resource: Option<String>, privilege: Option<String>, access: Option<Access>},
+    /// the policy has changed otherwise, replicas must reload it
+    Reload,
+} // enum Invalidation
+
+impl Invalidation {
+
+    /// Serializes the invalidation as JSON message.
+    pub fn to_message(&self) -> String {
+        match self {
+            Invalidation::Rule{role, resource, privilege, access} => json!({
+                "rule":   {"role": role, "resource": resource, "privilege": privilege},
+                "access": access.map(|access| access.to_string().to_lowercase()),
+            }), // Invalidation::Rule
+            Invalidation::Reload => json!({"reload": true}),
+        }.to_string()
+    } // to_message
+
+    /// Deserializes an invalidation from a JSON message.
+    pub fn from_message(message: &str) -> Result<Invalidation, Error> {
+        let value: Value = serde_json::from_str(message).map_err(|e| Error::Parse(e.to_string()))?;
+
+        if value["reload"] == Value::Bool(true) {
+            return Ok(Invalidation::Reload);
+        } // if
+        let rule   = value["rule"].as_object()
+            .ok_or_else(|| Error::Schema(vec![SchemaError::new("rule", "expected an object")]))?;
+        let name   = |key: &str| match &rule.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(name)) => Ok(Some(name.clone())),
+            Some(_)                   => Err(Error::Schema(vec![SchemaError::new(&format!("rule.{}", key), "expected a string or null")])),
+        }; // name
+        let access = match value["access"].as_str() {
+            None          if value["access"].is_null() => None,
+            Some("allow") => Some(Access::Allow),
+            Some("deny")  => Some(Access::Deny),
+            _             => return Err(Error::Schema(vec![SchemaError::new("access", "expected \"allow\", \"deny\" or null")])),
+        }; // match
+
+        Ok(Invalidation::Rule{role: name("role")?, resource: name("resource")?, privilege: name("privilege")?, access})
+    } // from_message
+
+} // impl Invalidation
+
+/// The outcome of receiving an invalidation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Invalidated {
+    /// the rule has been applied and this many cached decisions have been evicted
+    Evicted(usize),
+    /// the replica is outdated and must be reloaded
+    ReloadRequired,
+} // enum Invalidated
+
+impl Acl {
+
+    /// Applies a received invalidation message, even if this `Acl` is locked. Returns
+    /// `ReloadRequired` if the message asks for a reload or the rule names an undefined role or
+    /// resource.
+    pub fn receive_invalidation(&mut self, message: &str) -> Result<Invalidated, Error> {
+        let (role, resource, privilege, access) = match Invalidation::from_message(message)? {
+            Invalidation::Reload => return Ok(Invalidated::ReloadRequired),
+            Invalidation::Rule{role, resource, privilege, access} => (role, resource, privilege, access),
+        }; // match
+        let role      = match role.as_deref().map(|name| self.roles.get_key_value(name)) {
+            Some(None)  => return Ok(Invalidated::ReloadRequired),
+            known       => known.flatten().map(|(name, _)| *name),
+        }; // match
+        let resource  = match resource.as_deref().map(|name| self.resources.get_key_value(name)) {
+            Some(None)  => return Ok(Invalidated::ReloadRequired),
+            known       => known.flatten().map(|(name, _)| *name),
+        }; // match
+        let privilege = privilege.as_deref().map(|name| self.privileges.get(name).copied()
+            .or_else(|| self.rules.keys().filter_map(|query| query.privilege).find(|known| *known == name))
+            .unwrap_or_else(|| intern(name)));
+        let query     = Query{resource, role, privilege};
+
+        trace!("invalidating rule {} with {:?}", query, access);
+        match access {
+            Some(access)                => { self.insert_rule(query, Rule{acc: access, cond: None}); },
+            // the catch-all rule is reset instead of removed
+            None if query == Query::ALL => { self.insert_rule(query, Rule{acc: Access::Deny, cond: None}); },
+            None                        => {
+                self.remove_rule(&query);
+                self.meta.remove(&query);
+            }, // None
+        } // match
+        Ok(Invalidated::Evicted(self.evict_cache(role, resource, privilege)))
+    } // receive_invalidation
+
+} // impl Acl
+
+
+// Invalidator ////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Owns the central `Acl` and publishes invalidations of its changes.
+pub struct Invalidator<C: Channel> {
+    acl:     Acl,
+    channel: C,
+} // struct Invalidator
+
+impl<C: Channel> Invalidator<C> {
+
+    /// Creates a new `Invalidator`.
+    pub fn new(acl: Acl, channel: C) -> Self {
+        Invalidator{acl, channel}
+    } // new
+
+    /// Returns the central `Acl`.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Sets the rule and publishes its invalidation, see `Acl::set_rule`.
+    pub fn set_rule(&mut self, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
+        self.acl.set_rule(role, resource, privilege, access)?;
+        self.publish(&Invalidation::Rule{
+            role:      role.map(String::from),
+            resource:  resource.map(String::from),
+            privilege: privilege.map(String::from),
+            access:    Some(access),
+        }) // Invalidation::Rule
+    } // set_rule
+
+    /// Removes the rule for role on resource to privilege, wildcards only match wildcards, and
+    /// publishes its invalidation. Returns false if no such rule is defined.
+    pub fn remove_rule(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<bool, Error> {
+        let query = Query{resource, role, privilege};
+
+        if self.acl.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let removed = if query == Query::ALL {
+            self.acl.insert_rule(query, Rule{acc: Access::Deny, cond: None}) != Some(Rule{acc: Access::Deny, cond: None})
+        } else {
+            self.acl.meta.remove(&query);
+            self.acl.remove_rule(&query).is_some()
+        }; // else
+
+        if removed {
+            self.publish(&Invalidation::Rule{
+                role:      role.map(String::from),
+                resource:  resource.map(String::from),
+                privilege: privilege.map(String::from),
+                access:    None,
+            })?; // Invalidation::Rule
+        } // if
+        Ok(removed)
+    } // remove_rule
+
+    /// Changes the central `Acl` otherwise and asks replicas to reload, unless change fails.
+    pub fn update<F: FnOnce(&mut Acl) -> Result<(), Error>>(&mut self, change: F) -> Result<(), Error> {
+        change(&mut self.acl)?;
+        self.publish(&Invalidation::Reload)
+    } // update
+
+    fn publish(&mut self, invalidation: &Invalidation) -> Result<(), Error> {
+        self.channel.send(invalidation.to_message()).map_err(|e| {
+            warn!("failed to publish invalidation: {}", e);
+            e
+        }) // map_err
+    } // publish
+
+} // impl Invalidator
+
+
+// Redis //////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Publishes messages to a Redis pub/sub channel. Replicas subscribe to the channel, e.g. by
+/// `Connection::as_pubsub`, and pass the payloads to `Acl::receive_invalidation`.
+#[cfg(feature = "redis")]
+pub struct RedisChannel {
+    connection: redis::Connection,
+    channel:    String,
+} // struct RedisChannel
+
+#[cfg(feature = "redis")]
+impl RedisChannel {
+
+    /// Creates a new `RedisChannel` publishing to channel.
+    pub fn new(connection: redis::Connection, channel: &str) -> Self {
+        RedisChannel{connection, channel: String::from(channel)}
+    } // new
+
+} // impl RedisChannel
+
+#[cfg(feature = "redis")]
+impl Channel for RedisChannel {
+
+    fn send(&mut self, message: String) -> Result<(), Error> {
+        redis::cmd("PUBLISH").arg(&self.channel).arg(message)
+            .query::<i64>(&mut self.connection)
+            .map(|_| ())
+            .map_err(|e| Error::Io(e.to_string()))
+    } // send
+
+} // impl Channel for RedisChannel
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use test_env_log::test;
+
+    fn setup() -> (Invalidator<Sender<String>>, Receiver<String>, Acl) {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+
+        let mut replica    = Acl::from_json(&acl.to_json()).unwrap();
+        let (sender, receiver) = channel();
+
+        replica.lock();
+        assert_eq!(replica.warm_cache_full(), 5);
+        (Invalidator::new(acl, sender), receiver, replica)
+    } // setup
+
+    #[test]
+    fn message() {
+        let rule = Invalidation::Rule{role: Some(String::from("guest")), resource: None, privilege: Some(String::from("view")), access: Some(Access::Deny)};
+
+        assert_eq!(rule.to_message(), r#"{"access":"deny","rule":{"privilege":"view","resource":null,"role":"guest"}}"#);
+        assert_eq!(Invalidation::from_message(&rule.to_message()), Ok(rule));
+        assert_eq!(Invalidation::from_message(&Invalidation::Reload.to_message()), Ok(Invalidation::Reload));
+        assert_eq!(Invalidation::from_message(r#"{"rule":{}}"#), Ok(Invalidation::Rule{role: None, resource: None, privilege: None, access: None}));
+        assert!(Invalidation::from_message(r#"{"rule":{},"access":"grant"}"#).is_err());
+        assert!(Invalidation::from_message(r#"{"rule":{"role":1}}"#).is_err());
+        assert!(Invalidation::from_message("[]").is_err());
+    } // message
+
+    #[test]
+    fn invalidate() {
+        let (mut invalidator, receiver, mut replica) = setup();
+
+        assert!(invalidator.set_rule(Some("staff"), Some("news"), Some("view"), Access::Deny).is_ok());
+        assert!(invalidator.set_rule(Some("staff"), None, None, Access::Allow).is_ok());
+        assert_eq!(invalidator.remove_rule(Some("staff"), None, None), Ok(true));
+        assert_eq!(invalidator.remove_rule(Some("staff"), None, None), Ok(false));
+
+        let received: Vec<Invalidated> = receiver.try_iter().map(|message| replica.receive_invalidation(&message).unwrap()).collect();
+
+        // the deny of staff on news evicts staff on news and latest, the wildcard rule all of staff
+        assert_eq!(received, vec![Invalidated::Evicted(2), Invalidated::Evicted(1), Invalidated::Evicted(0)]);
+        assert_eq!(replica.rules().count(), invalidator.acl().rules().count());
+        assert!(replica.is_denied(Some("staff"), Some("latest"), Some("view")));
+        assert!(replica.is_allowed(Some("guest"), Some("latest"), Some("view")));
+
+        // structural changes and unknown names require a reload
+        assert!(invalidator.update(|acl| acl.add_role("editor", vec!["staff"])).is_ok());
+        assert!(invalidator.update(|acl| acl.add_role("editor", vec![])).is_err());
+        assert!(invalidator.set_rule(Some("editor"), None, Some("publish"), Access::Allow).is_ok());
+        assert_eq!(receiver.try_iter().map(|message| replica.receive_invalidation(&message)).collect::<Vec<_>>(),
+            vec![Ok(Invalidated::ReloadRequired), Ok(Invalidated::ReloadRequired)]);
+    } // invalidate
+
+} // mod tests
@@ -0,0 +1,230 @@
+//! Self-tests of critical invariants.
+//!
+//! An `Invariant` is a decision which must hold for a policy, e.g. "admin can manage users" or
+//! "guest cannot delete anything". `Acl::assert_invariants` checks invariants, e.g. at startup or
+//! after reloading the policy, and reports every violated invariant, so that a catastrophic
+//! mistake is caught before traffic is served.
+//!
+//! A `None` role, resource or privilege of an invariant means any: the invariant must hold for the
+//! wildcard and for every defined role, every defined resource and every privilege registered or
+//! named by rules or resource defaults respectively. An invariant naming an undefined role or
+//! resource is violated, e.g. if a role has been renamed. Checking doesn't report to the audit
+//! sink or count rule hits.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::invariant::Invariant;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("admin", vec![]).unwrap();
+//! acl.add_resource("users", None).unwrap();
+//! acl.allow(Some("admin"), Some("users"), None).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//!
+//! let invariants = [
+//!     Invariant::allow("admin can manage users", Some("admin"), Some("users"), Some("manage")),
+//!     Invariant::deny("guest cannot delete anything", Some("guest"), None, Some("delete")),
+//! ];
+//!
+//! assert!(acl.assert_invariants(&invariants).is_ok());
+//!
+//! acl.allow(Some("guest"), Some("users"), None).unwrap();
+//! let violations = acl.assert_invariants(&invariants).unwrap_err();
+//!
+//! assert_eq!(violations[0].to_string(), "guest cannot delete anything: ALLOW guest→users: delete by ALLOW guest→users: *");
+//! ```
+
+use crate::{Access, Acl, Decision, Error, Privilege, Query, Resource, Role};
+use log::{trace, warn};
+use std::fmt;
+
+
+// Invariant //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A decision which must hold, see module `invariant`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Invariant {
+    /// the description reported if violated
+    pub description: String,
+    /// the role, resource and privilege, None for any
+    pub query:       Query,
+    /// the access which must be decided
+    pub expected:    Access,
+} // struct Invariant
+
+impl Invariant {
+
+    /// Creates an invariant which holds if privilege is allowed for role on resource.
+    pub fn allow(description: &str, role: Role, resource: Resource, privilege: Privilege) -> Self {
+        Invariant{description: String::from(description), query: Query{resource, role, privilege}, expected: Access::Allow}
+    } // allow
+
+    /// Creates an invariant which holds if privilege is denied for role on resource.
+    pub fn deny(description: &str, role: Role, resource: Resource, privilege: Privilege) -> Self {
+        Invariant{description: String::from(description), query: Query{resource, role, privilege}, expected: Access::Deny}
+    } // deny
+
+} // impl Invariant
+
+
+// Violation //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A violated invariant.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// the invariant names an undefined role or resource
+    Undefined{
+        /// the description of the invariant
+        invariant: String,
+        /// the missing role or resource
+        error:     Error,
+    }, // Undefined
+    /// a query has been decided otherwise, the first such query is reported
+    Decided{
+        /// the description of the invariant
+        invariant: String,
+        /// the decision contradicting the invariant
+        decision:  Decision,
+    }, // Decided
+} // enum Violation
+
+impl Violation {
+
+    /// Returns the description of the violated invariant.
+    pub fn invariant(&self) -> &str {
+        match self {
+            Violation::Undefined{invariant, ..} => invariant,
+            Violation::Decided{invariant, ..}   => invariant,
+        } // match
+    } // invariant
+
+} // impl Violation
+
+impl fmt::Display for Violation {
+
+    /// Formats the violation like `guest cannot delete: ALLOW guest→news: delete by ALLOW *→news: *`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Violation::Undefined{invariant, error} => write!(f, "{}: {}", invariant, error),
+            Violation::Decided{invariant, decision} if decision.bypass =>
+                write!(f, "{}: {} by bypass role", invariant, decision),
+            Violation::Decided{invariant, decision} =>
+                write!(f, "{}: {} by {} {}", invariant, decision, decision.rule, decision.matched),
+        } // match
+    } // fmt
+
+} // impl fmt::Display for Violation
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Checks the invariants, see module `invariant`. Returns the violations in the order of the
+    /// invariants, at most one per invariant.
+    pub fn assert_invariants(&self, invariants: &[Invariant]) -> Result<(), Vec<Violation>> {
+        trace!("asserting {} invariants", invariants.len());
+        let privileges = self.known_privileges();
+        let any        = |name: Option<&'static str>, names: &mut dyn Iterator<Item = &'static str>| match name {
+            Some(name) => vec![Some(name)],
+            None       => Some(None).into_iter().chain(names.map(Some)).collect::<Vec<_>>(),
+        }; // any
+        let mut violations = vec![];
+
+        for invariant in invariants {
+            let query = invariant.query;
+            let error = match (query.role, query.resource) {
+                (Some(role), _) if !self.roles.contains_key(role)             => Some(Error::MissingRole(String::from(role))),
+                (_, Some(resource)) if !self.resources.contains_key(resource) => Some(Error::MissingResource(String::from(resource))),
+                _                                                             => None,
+            }; // match
+
+            if let Some(error) = error {
+                warn!("invariant {} violated: {}", invariant.description, error);
+                violations.push(Violation::Undefined{invariant: invariant.description.clone(), error});
+                continue;
+            } // if
+
+            let roles     = any(query.role, &mut self.roles.keys().copied());
+            let resources = any(query.resource, &mut self.resources.keys().copied());
+
+            'queries: for privilege in any(query.privilege, &mut privileges.iter().copied()) {
+                for role in &roles {
+                    for resource in &resources {
+                        let decision = self.evaluate(*role, *resource, privilege);
+
+                        if decision.rule.access() != invariant.expected {
+                            warn!("invariant {} violated by {}", invariant.description, decision);
+                            violations.push(Violation::Decided{invariant: invariant.description.clone(), decision});
+                            break 'queries;
+                        } // if
+                    } // for
+                } // for
+            } // for
+        } // for
+        if violations.is_empty() {
+            return Ok(());
+        } // if
+        Err(violations)
+    } // assert_invariants
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn invariants() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("latest"), None).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("purge")).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        acl.set_rule_hits(true);
+
+        let invariants = [
+            Invariant::allow("everyone may view", None, None, Some("view")),
+            Invariant::allow("staff may edit the latest news", Some("staff"), Some("latest"), Some("edit")),
+            Invariant::deny("guests may only view", Some("guest"), None, None),
+            Invariant::deny("nobody but root may purge", None, None, Some("purge")),
+            Invariant::allow("editors may edit", Some("editor"), None, Some("edit")),
+            Invariant::deny("staff may not edit the archive", Some("staff"), Some("archive"), Some("edit")),
+        ];
+        let violations = acl.assert_invariants(&invariants).unwrap_err();
+
+        assert_eq!(violations.iter().map(Violation::invariant).collect::<Vec<_>>(),
+            vec!["everyone may view", "guests may only view", "nobody but root may purge", "editors may edit", "staff may not edit the archive"]);
+        assert_eq!(violations[0].to_string(), "everyone may view: DENY *→*: view by DENY *→*: *");
+        assert_eq!(violations[2].to_string(), "nobody but root may purge: ALLOW root→*: purge by bypass role");
+        assert_eq!(violations[3], Violation::Undefined{invariant: String::from("editors may edit"), error: Error::MissingRole(String::from("editor"))});
+        assert_eq!(violations[4].to_string(), "staff may not edit the archive: Missing resource: archive");
+        match &violations[1] {
+            Violation::Decided{decision, ..} => assert_eq!(decision.query, Query{resource: None, role: Some("guest"), privilege: Some("view")}),
+            violation                        => panic!("unexpected violation {:?}", violation),
+        } // match
+
+        // checks aren't counted as hits
+        assert!(acl.rule_hit_counts().iter().all(|(_, hits)| *hits == 0));
+        assert!(acl.assert_invariants(&invariants[1..2]).is_ok());
+        assert!(acl.assert_invariants(&[]).is_ok());
+    } // invariants
+
+} // mod tests
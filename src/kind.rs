@@ -0,0 +1,184 @@
+//! Kinds and metadata of resources.
+//!
+//! A resource may carry a kind, e.g. "document", "folder" or "api-endpoint", and arbitrary
+//! metadata as key-value pairs, e.g. for exporters and permission-management UIs to tell resource
+//! types apart. Neither is inherited by descendants. Assertions may consult them through the `Acl`
+//! they are evaluated with, `OfKind` is an assertion holding for resources of a kind.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::kind::OfKind;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.add_resource("shared", None).unwrap();
+//! acl.add_resource("handbook", Some("shared")).unwrap();
+//! acl.set_resource_kind("shared", Some("folder")).unwrap();
+//! acl.set_resource_kind("handbook", Some("document")).unwrap();
+//! acl.set_resource_meta("handbook", "owner", Some("hr")).unwrap();
+//!
+//! acl.add_assertion("document", OfKind("document"));
+//! acl.allow_if(Some("staff"), Some("shared"), Some("print"), "document").unwrap();
+//!
+//! assert!(acl.is_allowed(Some("staff"), Some("handbook"), Some("print")));
+//! assert!(acl.is_denied(Some("staff"), Some("shared"), Some("print")));
+//! assert_eq!(acl.resources_of_kind("document").collect::<Vec<_>>(), vec!["handbook"]);
+//! assert_eq!(acl.get_resource_meta("handbook", "owner"), Some("hr"));
+//! ```
+
+use crate::{Acl, Error, Query};
+use crate::condition::Assertion;
+use log::trace;
+use std::collections::BTreeMap;
+
+
+// ResourceInfo ///////////////////////////////////////////////////////////////////////////////////
+
+
+/// The kind and metadata of a resource.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceInfo {
+    /// a type like "document"
+    pub kind: Option<String>,
+    /// arbitrary metadata ordered by key
+    pub meta: BTreeMap<String, String>,
+} // struct ResourceInfo
+
+
+// OfKind /////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// An assertion holding if the queried resource is of the kind, see module `kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OfKind(pub &'static str);
+
+impl Assertion for OfKind {
+
+    fn assert(&self, acl: &Acl, query: &Query) -> bool {
+        query.resource.and_then(|name| acl.get_resource_kind(name)) == Some(self.0)
+    } // assert
+
+} // impl Assertion for OfKind
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Sets the kind of resource, None removes it. Returns an error if resource is undefined.
+    pub fn set_resource_kind(&mut self, name: &'static str, kind: Option<&str>) -> Result<(), Error> {
+        trace!("setting kind of resource {} to {:?}", name, kind);
+        let info = self.resource_info_mut(name)?;
+
+        info.kind = kind.map(String::from);
+        self.prune_resource_info(name);
+        Ok(())
+    } // set_resource_kind
+
+    /// Sets the metadata of resource by key, None removes it. Returns an error if resource is
+    /// undefined.
+    pub fn set_resource_meta(&mut self, name: &'static str, key: &str, value: Option<&str>) -> Result<(), Error> {
+        trace!("setting metadata {} of resource {} to {:?}", key, name, value);
+        let info = self.resource_info_mut(name)?;
+
+        match value {
+            Some(value) => info.meta.insert(String::from(key), String::from(value)),
+            None        => info.meta.remove(key),
+        }; // match
+        self.prune_resource_info(name);
+        Ok(())
+    } // set_resource_meta
+
+    /// Returns the kind and metadata of resource or None if it has neither.
+    #[inline]
+    pub fn get_resource_info(&self, name: &str) -> Option<&ResourceInfo> {
+        self.resource_info.get(name)
+    } // get_resource_info
+
+    /// Returns the kind of resource.
+    pub fn get_resource_kind(&self, name: &str) -> Option<&str> {
+        self.resource_info.get(name).and_then(|info| info.kind.as_deref())
+    } // get_resource_kind
+
+    /// Returns the metadata of resource by key.
+    pub fn get_resource_meta(&self, name: &str, key: &str) -> Option<&str> {
+        self.resource_info.get(name).and_then(|info| info.meta.get(key)).map(String::as_str)
+    } // get_resource_meta
+
+    /// Returns an iterator over the defined resources of kind in lexical order.
+    pub fn resources_of_kind<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'static str> + 'a {
+        self.resources().filter(move |name| self.get_resource_kind(name) == Some(kind))
+    } // resources_of_kind
+
+    /// Returns the info of resource for modification. Returns an error if resource is undefined.
+    fn resource_info_mut(&mut self, name: &'static str) -> Result<&mut ResourceInfo, Error> {
+        if !self.resources.contains_key(name) {
+            return Err(Error::MissingResource(String::from(name)));
+        } // if
+        Ok(self.resource_info.entry(name).or_default())
+    } // resource_info_mut
+
+    /// Drops the info of resource if it is empty.
+    fn prune_resource_info(&mut self, name: &'static str) {
+        if self.resource_info.get(name).is_some_and(|info| *info == ResourceInfo::default()) {
+            self.resource_info.remove(name);
+        } // if
+    } // prune_resource_info
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn kind() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("api", None).is_ok());
+        assert!(acl.add_resource("users", Some("api")).is_ok());
+        assert!(acl.add_resource("orders", Some("api")).is_ok());
+        assert!(acl.set_resource_kind("users", Some("api-endpoint")).is_ok());
+        assert!(acl.set_resource_kind("orders", Some("api-endpoint")).is_ok());
+        assert!(acl.set_resource_meta("orders", "method", Some("POST")).is_ok());
+        assert!(acl.set_resource_meta("orders", "version", Some("2")).is_ok());
+
+        assert_eq!(acl.get_resource_kind("users"), Some("api-endpoint"));
+        assert_eq!(acl.get_resource_kind("api"), None);
+        assert_eq!(acl.resources_of_kind("api-endpoint").collect::<Vec<_>>(), vec!["orders", "users"]);
+        assert_eq!(acl.get_resource_info("orders").unwrap().meta.keys().collect::<Vec<_>>(), vec!["method", "version"]);
+        assert_eq!(acl.get_resource_meta("orders", "method"), Some("POST"));
+        assert_eq!(acl.get_resource_meta("users", "method"), None);
+
+        // assertions may consult kinds and metadata
+        acl.add_assertion("endpoint", OfKind("api-endpoint"));
+        acl.add_assertion("read-only", |acl: &Acl, query: &Query| {
+            query.resource.and_then(|name| acl.get_resource_meta(name, "method")).is_none_or(|method| method == "GET")
+        });
+        assert!(acl.allow_if(Some("staff"), Some("api"), Some("call"), "endpoint").is_ok());
+        assert!(acl.deny_if(Some("staff"), Some("api"), Some("cache"), "read-only").is_ok());
+        assert!(acl.allow(Some("staff"), Some("api"), None).is_ok());
+        assert!(acl.is_allowed(Some("staff"), Some("users"), Some("call")));
+        assert!(acl.is_denied(Some("staff"), Some("users"), Some("cache")));
+        assert!(acl.is_allowed(Some("staff"), Some("orders"), Some("cache")));
+
+        // empty info is dropped
+        assert!(acl.set_resource_kind("users", None).is_ok());
+        assert_eq!(acl.get_resource_info("users"), None);
+        assert!(acl.set_resource_meta("orders", "method", None).is_ok());
+        assert!(acl.set_resource_meta("orders", "version", None).is_ok());
+        assert!(acl.set_resource_kind("orders", None).is_ok());
+        assert_eq!(acl.get_resource_info("orders"), None);
+        assert_eq!(acl.set_resource_kind("nothing", Some("folder")), Err(Error::MissingResource(String::from("nothing"))));
+    } // kind
+
+} // mod tests
@@ -7,12 +7,26 @@
 //! 
 //! # What is missing from the original implementation?
 //! 
-//! * Removing access control. This will be implemented in a future version by a `revoke` method.
-//! * Ownership assertions and the role and resource interfaces. Ownership assertion may be
-//! implemented by traits defining the role and resource interface and by extending the api in
-//! the future.
+//! * Ownership assertions and the role and resource interfaces. Rules can be bound to assertions,
+//!   see module `condition`, but assertions receive the queried names instead of role and
+//!   resource objects.
 //! * Expression assertions. This may be implemented in a future version.
-//! 
+//!
+//! # What behaves differently?
+//!
+//! Some behaviors intentionally differ from the original implementation. The conformance test
+//! suite in `tests/laminas.rs` covers them. Calling `set_laminas_compat(true)` restores the
+//! original behavior where possible:
+//!
+//! * The catch-all rule is fixed and denies access. Setting a rule for `None` role, resource and
+//!   privilege is ignored. In compatibility mode it replaces the catch-all rule, like calling
+//!   `allow()` or `deny()` without arguments does.
+//! * A `None` privilege in a query is a wildcard and matches the rules defined for all privileges.
+//!   In compatibility mode it queries all privileges: access is denied if any privilege specific
+//!   deny rule applies to the role and resource. These queries are not cached.
+//! * Querying an unknown role or resource does not fail, only the rules defined for wildcards
+//!   apply. This is not affected by the compatibility mode.
+//!
 //! # Introduction
 //! 
 //! In general an appilcation can utilize ACLs to allow or deny access to resources by requesting
@@ -96,6 +110,14 @@
 //! > *LIFO Order for Role Queries*:
 //! > When specifying multiple parents for a role, keep in mind that the last parent listed is the first
 //! > one searched for rules applicable to an authorization query.
+//!
+//! The order is configurable by `set_parent_order`. With `ParentOrder::Fifo` the first parent
+//! listed is searched first and "someUser" is denied access. With `ParentOrder::DenyFirst` parents
+//! are searched in the order listed, but a deny rule inherited from any parent wins over inherited
+//! allow rules, so "someUser" is denied access as well. Rules defined for "someUser" itself take
+//! precedence in every order. Single queries may override the order by `decide_with_options`, so
+//! security-critical checks can use `ParentOrder::DenyFirst` while other checks keep the order of
+//! the `Acl`.
 //! 
 //! # Creating the Access Control List
 //! 
@@ -380,12 +402,102 @@
 //! assert!( acl.is_denied (Some("admin"), Some("anouncement"), Some("archive")));
 //! ```
 
+#[cfg(feature = "admin")]
+pub mod admin;
+pub mod audit;
+pub mod authorizer;
+#[cfg(any(feature = "bincode", feature = "cbor"))]
+pub mod binary;
+pub mod breakdown;
+pub mod breaker;
+pub mod bundle;
+pub mod cache;
+pub mod chain;
+pub mod combine;
+pub mod compiled;
+pub mod condition;
+pub mod defaults;
+pub mod delegation;
+pub mod domain;
+pub mod environment;
+pub mod etag;
+#[cfg(feature = "json")]
+pub mod explorer;
+pub mod fixed;
+#[cfg(feature = "json")]
+pub mod fixture;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+#[cfg(feature = "golden")]
+pub mod golden;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod group;
+pub mod hierarchy;
+pub mod hits;
+pub mod import;
+pub mod invariant;
+pub mod kind;
+pub mod limits;
+#[cfg(feature = "csv")]
+pub mod matrix;
+#[cfg(feature = "json")]
+pub mod invalidation;
+pub mod listing;
+pub mod manage;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "audit")]
+pub mod mining;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod overlay;
+#[cfg(feature = "json")]
+pub mod policy;
+pub mod priority;
+pub mod privileges;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod provider;
+pub mod quota;
+pub mod remote;
+pub mod report;
+pub mod rls;
+pub mod scope;
+#[cfg(any(feature = "actix", feature = "axum"))]
+pub mod session;
+pub mod shadow;
+pub mod shard;
+pub mod specialize;
+pub mod subject;
+#[cfg(feature = "test-util")]
+pub mod testing;
+#[cfg(feature = "json")]
+pub mod sync;
+pub mod unix;
+#[cfg(feature = "json")]
+pub mod workflow;
+
+use audit::AuditSink;
+use cache::CacheStats;
+use combine::RoleCombination;
+use condition::{Assertion, AsyncAssertion, CLOSURE};
+use delegation::Delegation;
+use etag::Item;
+use group::Group;
+use kind::ResourceInfo;
+use limits::Limits;
 use log::{trace, warn};
-use std::cell::RefCell;
+use privileges::PrivilegeInfo;
+use provider::{ResourceProvider, RoleProvider};
+use quota::Quota;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::hash::Hash;
-use std::ops::Index;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Bound, Index};
+use std::time::SystemTime;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 
 // Helper types ///////////////////////////////////////////////////////////////////////////////////
@@ -393,8 +505,10 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 
 type Resource   = Option<&'static str>;
 type Role       = Option<&'static str>;
-type Roles      = Option<Vec<&'static str>>;
+type Roles<'l>  = Option<&'l [&'static str]>;
 type Privilege  = Option<&'static str>;
+/// cached decisions by query, stamped with the generation they were made in
+type Cache      = HashMap<Query, (Query, Rule, u64)>;
 
 /// Allow or deny access.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -403,22 +517,142 @@ pub enum Access {
     Deny
 } // enum Access
 
+impl fmt::Display for Access {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Access::Allow => write!(f, "ALLOW"),
+            Access::Deny  => write!(f, "DENY"),
+        } // match
+    } // fmt
+
+} // impl fmt::Display for Access
+
+/// Adds or removes rules, see `Acl::set_rule_op`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Remove
+} // enum Operation
+
+/// The order in which the parents of a role are searched, see `Acl::set_parent_order`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParentOrder {
+    /// the last declared parent is searched first, like in laminas
+    #[default]
+    Lifo,
+    /// the first declared parent is searched first
+    Fifo,
+    /// the first declared parent is searched first, but a deny rule inherited from any ancestor
+    /// overrides allow rules inherited from other ancestors
+    DenyFirst,
+} // enum ParentOrder
+
+/// Options of a single query, see `Acl::decide_with_options`. The default options decide like
+/// `Acl::decide`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// the order in which the parents of a role are searched instead of the order of the `Acl`,
+    /// e.g. `ParentOrder::DenyFirst` for security-critical checks
+    pub parent_order: Option<ParentOrder>,
+} // struct QueryOptions
+
 /// Defines if a privilege is allowed or denied for a role on a resource. The selective parameters
 /// are in decending order of precedence: resource, role and privilege.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Rule {
     // the granted access: allow or deny
-    acc: Access,
+    acc:  Access,
+    // the name of the assertion which must hold for the rule to apply, see module `condition`
+    cond: Option<&'static str>,
 } // struct Rule
 
+impl Rule {
+
+    /// Returns the granted access.
+    #[inline]
+    pub fn access(&self) -> Access {
+        self.acc
+    } // access
+
+    /// Returns the name of the assertion which must hold for the rule to apply, if conditional.
+    #[inline]
+    pub fn condition(&self) -> Option<&'static str> {
+        self.cond
+    } // condition
+
+} // impl Rule
+
+impl fmt::Display for Rule {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.cond {
+            Some(name) if name.starts_with(CLOSURE) => write!(f, "{} IF {}", self.acc, CLOSURE),
+            Some(name)                              => write!(f, "{} IF {}", self.acc, name),
+            None                                    => self.acc.fmt(f),
+        } // match
+    } // fmt
+
+} // impl fmt::Display for Rule
+
+
+/// Optional metadata attached to a rule to make it traceable, e.g. to a change request.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RuleMeta {
+    /// free-form description of the rule
+    pub description: Option<String>,
+    /// author of the rule
+    pub author:      Option<String>,
+    /// creation time of the rule
+    pub created_at:  Option<SystemTime>,
+    /// reference to a ticket or change request
+    pub ticket:      Option<String>,
+    /// origin of the rule if it has been imported
+    pub provenance:  Option<Provenance>,
+    /// explanation shown to users denied by the rule, e.g. "archiving is disabled"
+    pub message:     Option<String>,
+} // struct RuleMeta
+
+/// Records where an imported rule has been defined.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Provenance {
+    /// name of the imported file or source
+    pub file:     String,
+    /// line within the source, starting at 1
+    pub line:     Option<usize>,
+    /// position within the line or the index of the rule within the source
+    pub position: Option<usize>,
+    /// identifier of the import batch
+    pub batch:    Option<String>,
+} // struct Provenance
+
+impl fmt::Display for Provenance {
+
+    /// Formats the provenance like `policy.json:12:3 (batch 7)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        } // if
+        if let Some(position) = self.position {
+            write!(f, ":{}", position)?;
+        } // if
+        if let Some(batch) = &self.batch {
+            write!(f, " (batch {})", batch)?;
+        } // if
+        Ok(())
+    } // fmt
+
+} // impl fmt::Display for Provenance
+
 
 // Query //////////////////////////////////////////////////////////////////////////////////////////
 
 
 /// Defines the parameters to query a rule for. A None value for a parameter declares a wildcard
-/// placeholder.
-#[derive(Debug, Eq, Hash, PartialEq)]
-struct Query {
+/// placeholder. Queries are ordered by resource, role and privilege, wildcards first.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Query {
     pub resource:  Option<&'static str>,
     pub role:      Option<&'static str>,
     pub privilege: Option<&'static str>,
@@ -427,10 +661,65 @@ struct Query {
 impl Query {
 
     /// This defines the catch all criteria. A rule for this query is always defined in an Acl.
-    const ALL: Query = Query{resource: None, role: None, privilege: None};
+    pub const ALL: Query = Query{resource: None, role: None, privilege: None};
 
 } // impl Query
 
+impl fmt::Display for Query {
+
+    /// Formats the query as `role→resource: privilege`. Wildcards are shown as `*`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}→{}: {}",
+            self.role.unwrap_or("*"),
+            self.resource.unwrap_or("*"),
+            self.privilege.unwrap_or("*"))
+    } // fmt
+
+} // impl fmt::Display for Query
+
+
+// Decision ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The outcome of a query. Holds the queried parameters, the parameters of the rule which decided
+/// the query and the rule itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decision {
+    /// the queried role, resource and privilege
+    pub query:   Query,
+    /// the role, resource and privilege of the deciding rule
+    pub matched: Query,
+    /// the deciding rule
+    pub rule:    Rule,
+    /// true if the queried role is a bypass role and rule evaluation has been skipped
+    pub bypass:  bool,
+} // struct Decision
+
+impl Decision {
+
+    /// Returns true if the decision allows access.
+    #[inline]
+    pub fn is_allowed(&self) -> bool {
+        self.rule.acc == Access::Allow
+    } // is_allowed
+
+    /// Returns true if the decision denies access.
+    #[inline]
+    pub fn is_denied(&self) -> bool {
+        self.rule.acc == Access::Deny
+    } // is_denied
+
+} // impl Decision
+
+impl fmt::Display for Decision {
+
+    /// Formats the decision like `ALLOW staff→latest: revise`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{} {}", self.rule, self.query)
+    } // fmt
+
+} // impl fmt::Display for Decision
+
 
 // Acl ////////////////////////////////////////////////////////////////////////////////////////////
 
@@ -439,10 +728,51 @@ impl Query {
 /// privileges are not automatically defined upon rule definition, but must be declared beforehand.
 /// A catch-all rule is predefined and denies access. This is like a drop-policy on firewalls.
 pub struct Acl {
-    resources:  BTreeMap<&'static str, Option<&'static str>>,
-    roles:      BTreeMap<&'static str, Vec<&'static str>>,
-    rules:      HashMap<Query, Rule>,
-    lock:       Option<RefCell<HashMap<Query, Rule>>>,
+    resources:           BTreeMap<&'static str, Option<&'static str>>,
+    roles:               BTreeMap<&'static str, Vec<&'static str>>,
+    rules:               BTreeMap<Query, Rule>,
+    role_rules:          BTreeSet<(Role, Query)>,
+    meta:                HashMap<Query, RuleMeta>,
+    priorities:          HashMap<Query, i32>,
+    resource_defaults:   HashMap<(&'static str, &'static str), Access>,
+    resource_info:       HashMap<&'static str, ResourceInfo>,
+    bypass:              BTreeSet<&'static str>,
+    privileges:          BTreeSet<&'static str>,
+    resource_privileges: HashMap<&'static str, Vec<&'static str>>,
+    privilege_info:      HashMap<&'static str, PrivilegeInfo>,
+    bundles:             BTreeMap<&'static str, Vec<&'static str>>,
+    bundle_rules:        BTreeMap<Query, Access>,
+    assertions:          HashMap<&'static str, Box<dyn Assertion>>,
+    async_assertions:    HashMap<&'static str, Box<dyn AsyncAssertion>>,
+    closures:            HashMap<&'static str, usize>,
+    awaited:             RefCell<Option<HashMap<&'static str, bool>>>,
+    awaiting:            Cell<Option<(&'static str, Query)>>,
+    subjects:            HashMap<&'static str, BTreeMap<Query, Rule>>,
+    delegations:         Vec<Delegation>,
+    next_delegation:     u64,
+    quotas:              HashMap<Query, Quota>,
+    audit_sink:          Option<Box<dyn AuditSink>>,
+    fingerprint:         u64,
+    compat:              bool,
+    lenient_privileges:  bool,
+    parent_order:        ParentOrder,
+    role_combination:    RoleCombination,
+    role_provider:       Option<Box<dyn RoleProvider>>,
+    provided_roles:      RefCell<HashMap<&'static str, Option<Vec<&'static str>>>>,
+    resource_provider:   Option<Box<dyn ResourceProvider>>,
+    provided_resources:  RefCell<HashMap<&'static str, Option<Option<&'static str>>>>,
+    provider_failure:    Cell<Option<Access>>,
+    lock:                Option<RefCell<Cache>>,
+    generation:          u64,
+    cache_stats:         Cell<CacheStats>,
+    warming:             Cell<bool>,
+    rule_hits:           Option<RefCell<HashMap<Query, u64>>>,
+    scratch:             RefCell<Vec<&'static str>>,
+    groups:              BTreeMap<&'static str, Group>,
+    environments:        BTreeMap<&'static str, Group>,
+    environment:         Option<&'static str>,
+    default_role:        Option<&'static str>,
+    limits:              Limits,
 } // Acl
 
 impl Acl {
@@ -455,13 +785,54 @@ impl Acl {
     pub fn new() -> Self {
         trace!("creating new acl");
         let mut acl = Acl{
-            resources:  BTreeMap::new(),
-            roles:      BTreeMap::new(),
-            rules:      HashMap::new(),
-            lock:       None,
+            resources:           BTreeMap::new(),
+            roles:               BTreeMap::new(),
+            rules:               BTreeMap::new(),
+            role_rules:          BTreeSet::new(),
+            meta:                HashMap::new(),
+            priorities:          HashMap::new(),
+            resource_defaults:   HashMap::new(),
+            resource_info:       HashMap::new(),
+            bypass:              BTreeSet::new(),
+            privileges:          BTreeSet::new(),
+            resource_privileges: HashMap::new(),
+            privilege_info:      HashMap::new(),
+            bundles:             BTreeMap::new(),
+            bundle_rules:        BTreeMap::new(),
+            assertions:          HashMap::new(),
+            async_assertions:    HashMap::new(),
+            closures:            HashMap::new(),
+            awaited:             RefCell::new(None),
+            awaiting:            Cell::new(None),
+            subjects:            HashMap::new(),
+            delegations:         vec![],
+            next_delegation:     0,
+            quotas:              HashMap::new(),
+            audit_sink:          None,
+            fingerprint:         0,
+            compat:              false,
+            lenient_privileges:  false,
+            parent_order:        ParentOrder::Lifo,
+            role_combination:    RoleCombination::default(),
+            role_provider:       None,
+            provided_roles:      RefCell::new(HashMap::new()),
+            resource_provider:   None,
+            provided_resources:  RefCell::new(HashMap::new()),
+            provider_failure:    Cell::new(None),
+            lock:                None,
+            generation:          0,
+            cache_stats:         Cell::new(CacheStats::default()),
+            warming:             Cell::new(false),
+            rule_hits:           None,
+            scratch:             RefCell::new(vec![]),
+            groups:              BTreeMap::new(),
+            environments:        BTreeMap::new(),
+            environment:         None,
+            default_role:        None,
+            limits:              Limits::default(),
         }; // Acl
 
-        acl.rules.insert(Query::ALL, Rule{acc: Access::Deny});
+        acl.insert_rule(Query::ALL, Rule{acc: Access::Deny, cond: None});
         acl
     } // new
 
@@ -469,18 +840,64 @@ impl Acl {
     /// rule queries.
     pub fn lock(&mut self) {
         if self.lock.is_none() {
-            self.lock = Some(RefCell::new(HashMap::new()))
+            self.lock = Some(RefCell::new(HashMap::new()));
+            self.warn_pathological();
         } // if
+        #[cfg(feature = "metrics")]
+        self.record_policy_metrics();
     } // lock
 
     /// Unlock opens the `Acl` to define new rules and purges and disables the cache.
     pub fn unlock(&mut self) {
         if self.lock.is_some() {
+            self.purge_cache();
             self.lock = None
         } // if
     } // unlock
 
-    /// Adds a new resource. Returns an error if resource is already defined or parent is unknown.
+    /// Enables or disables the laminas compatibility mode. See the crate documentation for the
+    /// behaviors that change. Purges the cache.
+    pub fn set_laminas_compat(&mut self, enabled: bool) {
+        trace!("setting laminas compatibility mode to {}", enabled);
+        if self.compat != enabled {
+            self.track(Item::Compat, enabled);
+        } else {
+            self.advance_generation();
+        } // else
+        self.compat = enabled;
+        self.purge_cache();
+    } // set_laminas_compat
+
+    /// Returns true if the laminas compatibility mode is enabled.
+    #[inline]
+    pub fn is_laminas_compat(&self) -> bool {
+        self.compat
+    } // is_laminas_compat
+
+    /// Sets the order in which the parents of a role are searched for rules. Purges the cache.
+    pub fn set_parent_order(&mut self, order: ParentOrder) {
+        trace!("setting parent order to {:?}", order);
+        // the default order doesn't contribute to the fingerprint
+        if self.parent_order != ParentOrder::default() {
+            self.track(Item::ParentOrder(self.parent_order), false);
+        } // if
+        if order != ParentOrder::default() {
+            self.track(Item::ParentOrder(order), true);
+        } else if self.parent_order == order {
+            self.advance_generation();
+        } // else if
+        self.parent_order = order;
+        self.purge_cache();
+    } // set_parent_order
+
+    /// Returns the order in which the parents of a role are searched.
+    #[inline]
+    pub fn parent_order(&self) -> ParentOrder {
+        self.parent_order
+    } // parent_order
+
+    /// Adds a new resource. Returns an error if resource is already defined, parent is unknown or a
+    /// limit is exceeded, see module `limits`.
     pub fn add_resource(&mut self, name: &'static str, parent: Option<&'static str>) -> Result<(), Error> {
         trace!("adding resource {} with parent {:?}", name, parent);
         if self.resources.contains_key(name) {
@@ -493,7 +910,9 @@ impl Acl {
                 return Err(Error::MissingParent(String::from(name)))
             } // if
         } // if
+        self.check_resource_limits(name, parent)?;
         self.resources.insert(name, parent);
+        self.track(Item::Resource(name, parent), true);
         Ok(())
     } // add_resource
 
@@ -503,6 +922,11 @@ impl Acl {
         self.resources.contains_key(name)
     } // has_resource
 
+    /// Returns an iterator over the names of all defined resources in lexical order.
+    pub fn resources(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.resources.keys().copied()
+    } // resources
+
     /// Returns the parent of resource or None. Returns an error if resource is undefined.
     pub fn get_resource_parent(&self, name: &'static str) -> Result<Option<&'static str>, Error> {
         trace!("getting resource parent for: {}", name);
@@ -516,25 +940,24 @@ impl Acl {
     /// Returns the ancestors prefixed with the resource. Returns an empty vector if resource is undefined.
     pub fn get_resource_lineage(&self, name: &'static str) -> Vec<&'static str> {
         trace!("getting resource lineage for: {}", name);
-        match self.resources.get(name) {
-            None         => vec![],
-            Some(parent) => {
-                let mut v = vec![name];
-                let mut i = parent;
-
-                loop {
-                    if let Some(name) = i {
-                        v.push(name);
-                        i = self.resources.get(name).unwrap();
-                    } else {
-                        break
-                    } // else
-                } // loop
-                v
-            }, // Some
-        } // match
+        self.iter_resource_lineage(name).collect()
     } // get_resource_lineage
 
+    /// Returns an iterator over the ancestors prefixed with the resource, which resolves parents
+    /// as they are reached. Yields nothing if resource is undefined. Only lineages of provided
+    /// resources allocate.
+    pub fn iter_resource_lineage(&self, name: &'static str) -> impl Iterator<Item = &'static str> + '_ {
+        let mut provided = vec![];
+
+        std::iter::successors(self.lookup_resource(name).map(|_| name), move |name| {
+            // resolved parents may be unknown or cyclic, defined ones are neither
+            if !self.resources.contains_key(name) {
+                provided.push(*name);
+            } // if
+            self.lookup_resource(name).flatten().filter(|parent| !provided.contains(parent))
+        }) // successors
+    } // iter_resource_lineage
+
     /// Returns the ancestors of the resource. Returns an empty vector if resource is undefined.
     pub fn get_resource_ancestors(&self, name: &'static str) -> Vec<&'static str> {
         trace!("getting resource ancestors for: {}", name);
@@ -547,27 +970,142 @@ impl Acl {
         } // else
     } // get_resource_ancestors
 
-    /// Adds a new role. Returns an error if role is already defined or parent is unknown.
+    /// Removes a resource without children together with every rule, override, delegation,
+    /// quota, default, declared privilege and metadata referencing it. Returns an error if
+    /// resource is undefined or has children, see `remove_resource_recursive`, or if the `Acl` is
+    /// locked.
+    pub fn remove_resource(&mut self, name: &'static str) -> Result<(), Error> {
+        trace!("removing resource {}", name);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if !self.resources.contains_key(name) {
+            return Err(Error::MissingResource(String::from(name)));
+        } // if
+        if let Some((child, _)) = self.resources.iter().find(|(_, parent)| **parent == Some(name)) {
+            warn!("removing resource {} with child {}", name, child);
+            return Err(Error::NotPermitted(format!("removing resource {} with children", name)));
+        } // if
+        self.purge_resource(name)
+    } // remove_resource
+
+    /// Removes a resource with all its descendants like `remove_resource`. Returns the removed
+    /// resources, descendants before their ancestors.
+    pub fn remove_resource_recursive(&mut self, name: &'static str) -> Result<Vec<&'static str>, Error> {
+        trace!("removing resource {} recursively", name);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if !self.resources.contains_key(name) {
+            return Err(Error::MissingResource(String::from(name)));
+        } // if
+        let mut removed: Vec<(usize, &'static str)> = self.resources.keys()
+            .filter_map(|other| self.iter_resource_lineage(other).position(|ancestor| ancestor == name).map(|depth| (depth, *other)))
+            .collect();
+
+        // the deepest first
+        removed.sort_by(|(a, _), (b, _)| b.cmp(a));
+        for (_, other) in &removed {
+            self.purge_resource(other)?;
+        } // for
+        Ok(removed.into_iter().map(|(_, other)| other).collect())
+    } // remove_resource_recursive
+
+    /// Removes the resource and everything referencing it, except its children.
+    fn purge_resource(&mut self, name: &'static str) -> Result<(), Error> {
+        self.revoke_delegations_if(|delegation| delegation.resource == Some(name))?;
+        self.purge_rules_if(|query| query.resource == Some(name));
+        for (privilege, access) in self.resource_defaults(name) {
+            self.resource_defaults.remove(&(name, privilege));
+            self.track(Item::Default(name, privilege, access), false);
+        } // for
+        self.resource_privileges.remove(name);
+        self.resource_info.remove(name);
+        if let Some(parent) = self.resources.remove(name) {
+            self.track(Item::Resource(name, parent), false);
+        } // if
+        Ok(())
+    } // purge_resource
+
+    /// Removes the role and everything referencing it, except its children, like `purge_resource`.
+    /// Used to roll back added roles, see module `admin`.
+    #[cfg(feature = "admin")]
+    pub(crate) fn purge_role(&mut self, name: &'static str) -> Result<(), Error> {
+        self.revoke_delegations_if(|delegation| delegation.from == name || delegation.to == name)?;
+        self.purge_rules_if(|query| query.role == Some(name));
+        self.unset_bypass_role(name)?;
+        if self.default_role == Some(name) {
+            self.unset_default_role()?;
+        } // if
+        if let Some(parents) = self.roles.remove(name) {
+            self.track(Item::Role(name, &parents), false);
+        } // if
+        Ok(())
+    } // purge_role
+
+    /// Removes the rules, overrides, quotas and bundle and subject rules of the queries matching
+    /// references.
+    fn purge_rules_if<F: Fn(&Query) -> bool>(&mut self, references: F) {
+        let rules: Vec<Query> = self.rules.keys().copied().filter(|query| references(query)).collect();
+
+        for query in &rules {
+            self.remove_rule(query);
+            self.meta.remove(query);
+        } // for
+        let mut released = vec![];
+
+        for group in self.groups.values_mut().chain(self.environments.values_mut()) {
+            released.extend(group.aside.iter().filter(|(query, _)| references(query)).map(|(_, rule)| *rule));
+            group.aside.retain(|query, _| !references(query));
+        } // for
+        for rule in released {
+            self.release_assertion(rule);
+        } // for
+        self.bundle_rules.retain(|query, _| !references(query));
+
+        let quotas: Vec<(Query, u32)> = self.quotas.iter()
+            .filter(|(query, _)| references(query))
+            .map(|(query, quota)| (*query, quota.limit))
+            .collect();
+
+        for (query, limit) in &quotas {
+            self.quotas.remove(query);
+            self.track(Item::Quota(query, *limit), false);
+        } // for
+        let overrides: Vec<(&'static str, Query, Rule)> = self.subjects.iter()
+            .flat_map(|(subject, rules)| rules.iter().map(move |(query, rule)| (*subject, *query, *rule)))
+            .filter(|(_, query, _)| references(query))
+            .collect();
+
+        for (subject, query, rule) in &overrides {
+            if let Some(rules) = self.subjects.get_mut(subject) {
+                rules.remove(query);
+            } // if
+            self.track(Item::Subject(subject, query, *rule), false);
+        } // for
+        self.subjects.retain(|_, rules| !rules.is_empty());
+    } // purge_rules_if
+
+    /// Adds a new role. Returns an error if role is already defined, parent is unknown or a limit
+    /// is exceeded, see module `limits`.
     pub fn add_role(&mut self, name: &'static str, parents: Vec<&'static str>) -> Result<(), Error> {
         trace!("adding role {} with parents {:?}", name, parents);
         if self.roles.contains_key(name) {
             warn!("adding duplicate role: {}", name);
             return Err(Error::DuplicateRole(String::from(name)));
         } // if
-        if parents.len() > 0 {
-            let mut reversed = parents.clone();
+        let mut reversed = parents.clone();
 
-            for name in parents {
-                if !self.roles.contains_key(name) {
-                    warn!("missing parent for new role: {}", name);
-                    return Err(Error::MissingParent(String::from(name)))
-                } // if
-            } // for
-            reversed.reverse();
-            self.roles.insert(name, reversed);
-        } else {
-            self.roles.insert(name, vec![]);
-        } // else
+        for name in parents {
+            if !self.roles.contains_key(name) {
+                warn!("missing parent for new role: {}", name);
+                return Err(Error::MissingParent(String::from(name)))
+            } // if
+        } // for
+        self.check_role_limits(name, &reversed)?;
+        reversed.reverse();
+        self.track(Item::Role(name, &reversed), true);
+        self.roles.insert(name, reversed);
         Ok(())
     } // add_role
 
@@ -577,48 +1115,80 @@ impl Acl {
         self.roles.contains_key(name)
     } // has_role
 
-    /// Returns the parent of role or None. Returns an error if role is undefined.
+    /// Returns an iterator over the names of all defined roles in lexical order.
+    pub fn roles(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.roles.keys().copied()
+    } // roles
+
+    /// Returns the parents of role in the order they have been declared. Returns an error if role
+    /// is undefined.
     pub fn get_role_parents(&self, name: &'static str) -> Result<Vec<&'static str>, Error> {
         trace!("getting role parents for: {}", name);
-        if let Some(parent) = self.roles.get(name) {
-            return Ok(parent.to_vec())
+        if let Some(parents) = self.roles.get(name) {
+            // parents are stored in search order
+            return Ok(parents.iter().rev().copied().collect())
         } // if
         warn!("missing role while getting parents: {}", name);
         Err(Error::MissingRole(String::from(name)))
     } // get_role_parents
 
-    fn iter_roles(&self, roles: &Vec<&'static str>, seen: &mut HashSet<&'static str>, lineage: &mut Vec<&'static str>) {
-        for role in roles {
-            // only add this role if we haven't seen it already
-            if !seen.contains(role) {
-                seen.insert(role);
-                lineage.push(role);
-            } // if
-            if let Some(parents) = self.roles.get(role) {
-                if parents.len() > 0 {
-                    self.iter_roles(parents, seen, lineage);
-                } // if
+    /// Appends the lineage of role to lineage, which is empty or holds the lineage of another
+    /// role. Lineages are short, so lineage itself tracks the roles seen. Returns false if role is
+    /// undefined.
+    fn extend_role_lineage(&self, name: &'static str, order: ParentOrder, lineage: &mut Vec<&'static str>) -> bool {
+        let parents = match self.role_parents(name) {
+            Some(parents) => parents,
+            None          => return false,
+        }; // match
+
+        lineage.push(name);
+        for i in 0..parents.len() {
+            // parents are stored in LIFO order
+            let parent = match order {
+                ParentOrder::Lifo => parents[i],
+                _                 => parents[parents.len() - 1 - i],
+            }; // match
+
+            // only add this role and its ancestors if we haven't seen it already, ancestors of
+            // provided roles may be cyclic
+            if !lineage.contains(&parent) && !self.extend_role_lineage(parent, order, lineage) {
+                lineage.push(parent);
             } // if
         } // for
-    } // iter_roles
+        true
+    } // extend_role_lineage
 
-    /// Returns the ancestors prefixed with the role. Returns an empty vector if role is undefined.
+    /// Returns the ancestors prefixed with the role in search order, see `set_parent_order`.
+    /// Returns an empty vector if role is undefined.
     pub fn get_role_lineage(&self, name: &'static str) -> Vec<&'static str> {
         trace!("getting role lineage for: {}", name);
-        match self.roles.get(name) {
-            None         => vec![],
-            Some(parents) => {
-                let mut seen    = HashSet::new();
-                let mut lineage = vec![name];
+        let mut lineage = vec![];
 
-                if parents.len() > 0 {
-                    self.iter_roles(parents, &mut seen, &mut lineage);
-                } // if
-                lineage
-            }, // Some
-        } // match
+        self.extend_role_lineage(name, self.parent_order, &mut lineage);
+        lineage
     } // get_role_lineage
 
+    /// Returns an iterator over the ancestors prefixed with the role in search order, which
+    /// resolves parents as they are reached. Yields nothing if role is undefined.
+    pub fn iter_role_lineage(&self, name: &'static str) -> impl Iterator<Item = &'static str> + '_ {
+        let parents = self.role_parents(name);
+
+        RoleLineage{
+            acl:   self,
+            first: parents.as_ref().map(|_| name),
+            seen:  vec![],
+            stack: parents.into_iter().map(|parents| (parents, 0)).collect(),
+        } // RoleLineage
+    } // iter_role_lineage
+
+    /// Returns the parents of role in LIFO order, borrowed if role is defined.
+    fn role_parents(&self, name: &'static str) -> Option<Cow<'_, [&'static str]>> {
+        match self.roles.get(name) {
+            Some(parents) => Some(Cow::Borrowed(parents)),
+            None          => self.lookup_role(name).map(Cow::Owned),
+        } // match
+    } // role_parents
+
     /// Returns the ancestors of the role. Returns an empty vector if role is undefined.
     pub fn get_role_ancestors(&self, name: &'static str) -> Vec<&'static str> {
         trace!("getting role ancestors for: {}", name);
@@ -655,60 +1225,369 @@ impl Acl {
         self.get_rule(role, resource, privilege).acc == Access::Deny
     } // is_denied
 
+    /// Allows all privileges for role on all resources. This is a shortcut for
+    /// `allow(Some(role), None, None)`. Returns an error if role is undefined.
     #[inline]
-    fn get_one_rule(&self, role: Role, resource: Resource, privilege: Privilege) -> Option<&Rule> {
-        trace!("getting one rule for {:?} on {:?} to {:?}", role, resource, privilege);
-        self.rules.get(&Query{resource, role, privilege})
-    } // get_one_rule
+    pub fn allow_all(&mut self, role: &'static str) -> Result<(), Error> {
+        self.set_rule(Some(role), None, None, Access::Allow)
+    } // allow_all
 
-    fn query_privileges(&self, resource: &Resource, role: &Role, privilege: &Privilege) -> Option<&Rule> {
+    /// Denies all privileges for role on all resources. This is a shortcut for
+    /// `deny(Some(role), None, None)`. Returns an error if role is undefined.
+    #[inline]
+    pub fn deny_all(&mut self, role: &'static str) -> Result<(), Error> {
+        self.set_rule(Some(role), None, None, Access::Deny)
+    } // deny_all
+
+    /// Removes exactly the rule defined for role on resource to privilege, whatever its access or
+    /// assertion, so queries fall back to inherited rules, defaults and the catch-all rule. Unlike
+    /// `remove_allow` and `remove_deny`, None is a wildcard like in `set_rule`. Revoking the
+    /// catch-all rule resets it to deny. Revoking a bundle revokes the rules of its privileges,
+    /// see module `bundle`. Returns true if a rule has been removed or reset.
+    ///
+    /// Revoking doesn't define new rules, so it's permitted while the `Acl` is locked, e.g. to
+    /// withdraw access in an emergency. Decisions cached before are discarded.
+    pub fn revoke(&mut self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        trace!("revoking rule for {:?} on {:?} with {:?} privilege", role, resource, privilege);
+        let query = Query{resource, role, privilege};
+
+        // bundles expand to the rules of their privileges like in `set_rule_op`
+        if let Some(bundle) = privilege.filter(|name| self.bundles.contains_key(name)) {
+            let mut revoked = self.bundle_rules.remove(&query).is_some();
+
+            for member in self.bundles[bundle].clone() {
+                revoked |= self.revoke(role, resource, Some(member));
+            } // for
+            return revoked;
+        } // if
+
+        self.meta.remove(&query);
+        if query == Query::ALL {
+            if self.rules[&Query::ALL].acc == Access::Deny {
+                return false;
+            } // if
+            self.insert_rule(Query::ALL, Rule{acc: Access::Deny, cond: None});
+            return true;
+        } // if
+        self.remove_rule(&query).is_some()
+    } // revoke
+
+    /// Removes the role-level wildcard rule defined by `allow_all` or `deny_all`. Returns true if
+    /// a rule has been removed. Returns an error if role is undefined or the `Acl` is locked.
+    pub fn revoke_all(&mut self, role: &'static str) -> Result<bool, Error> {
+        trace!("revoking wildcard rule for {}", role);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if !self.roles.contains_key(role) {
+            return Err(Error::MissingRole(String::from(role)));
+        } // if
+        let query = Query{resource: None, role: Some(role), privilege: None};
+
+        self.meta.remove(&query);
+        Ok(self.remove_rule(&query).is_some())
+    } // revoke_all
+
+    /// Attaches metadata to the rule defined for role on resource to privilege. Replaces metadata
+    /// attached before. Returns an error if no such rule is defined.
+    pub fn set_rule_meta(&mut self, role: Role, resource: Resource, privilege: Privilege, meta: RuleMeta) -> Result<(), Error> {
+        trace!("setting rule meta for {:?} on {:?} to {:?}", role, resource, privilege);
+        let query = Query{resource, role, privilege};
+
+        if !self.rules.contains_key(&query) {
+            warn!("missing rule while setting meta: {}", query);
+            return Err(Error::MissingRule(query.to_string()));
+        } // if
+        self.meta.insert(query, meta);
+        Ok(())
+    } // set_rule_meta
+
+    /// Returns the metadata attached to the rule defined for role on resource to privilege.
+    #[inline]
+    pub fn get_rule_meta(&self, role: Role, resource: Resource, privilege: Privilege) -> Option<&RuleMeta> {
+        self.meta.get(&Query{resource, role, privilege})
+    } // get_rule_meta
+
+    /// Returns the metadata attached to the rule which made the decision.
+    #[inline]
+    pub fn get_decision_meta(&self, decision: &Decision) -> Option<&RuleMeta> {
+        self.meta.get(&decision.matched)
+    } // get_decision_meta
+
+    /// Denies privilege for role on resource and attaches message explaining the denial to the
+    /// rule, keeping other metadata. For the catch-all rule only the message is set. Returns an
+    /// error if role, resource or privilege is undefined.
+    pub fn deny_with_message(&mut self, role: Role, resource: Resource, privilege: Privilege, message: &str) -> Result<(), Error> {
+        self.deny(role, resource, privilege)?;
+        self.meta.entry(Query{resource, role, privilege}).or_default().message = Some(String::from(message));
+        Ok(())
+    } // deny_with_message
+
+    /// Returns the message explaining a denial, if the decision denies access and the deciding
+    /// rule carries a message.
+    pub fn get_denial_message(&self, decision: &Decision) -> Option<&str> {
+        if decision.is_allowed() {
+            return None;
+        } // if
+        self.get_decision_meta(decision).and_then(|meta| meta.message.as_deref())
+    } // get_denial_message
+
+    /// Returns the origin of the rule defined for role on resource to privilege, if the rule has
+    /// been imported.
+    #[inline]
+    pub fn get_rule_provenance(&self, role: Role, resource: Resource, privilege: Privilege) -> Option<&Provenance> {
+        self.get_rule_meta(role, resource, privilege).and_then(|meta| meta.provenance.as_ref())
+    } // get_rule_provenance
+
+    /// Returns an iterator over all defined rules including the catch-all rule and their metadata.
+    /// The rules are ordered by query, so the catch-all rule comes first.
+    pub fn rules(&self) -> impl Iterator<Item = (&Query, &Rule, Option<&RuleMeta>)> {
+        self.rules.iter().map(move |(query, rule)| (query, rule, self.meta.get(query)))
+    } // rules
+
+    /// Returns an iterator over the rules on resources whose name starts with prefix and their
+    /// metadata, ordered by query. Rules on all resources aren't included.
+    pub fn rules_by_resource_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a Query, &'a Rule, Option<&'a RuleMeta>)> {
+        let start = self.resources.range::<str, _>((Bound::Included(prefix), Bound::Unbounded)).next().map(|(name, _)| *name);
+
+        start.into_iter()
+            .flat_map(move |name| self.rules.range(Query{resource: Some(name), role: None, privilege: None}..))
+            .take_while(move |(query, _)| query.resource.is_some_and(|name| name.starts_with(prefix)))
+            .map(move |(query, rule)| (query, rule, self.meta.get(query)))
+    } // rules_by_resource_prefix
+
+    /// Returns an iterator over the rules for roles whose name starts with prefix and their
+    /// metadata, ordered by role and query. Rules for all roles aren't included.
+    pub fn rules_by_role_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a Query, &'a Rule, Option<&'a RuleMeta>)> {
+        let start = self.roles.range::<str, _>((Bound::Included(prefix), Bound::Unbounded)).next().map(|(name, _)| *name);
+
+        start.into_iter()
+            .flat_map(move |name| self.role_rules.range((Some(name), Query::ALL)..))
+            .take_while(move |(role, _)| role.is_some_and(|name| name.starts_with(prefix)))
+            .map(move |(_, query)| (query, &self.rules[query], self.meta.get(query)))
+    } // rules_by_role_prefix
+
+    /// Marks role as bypass role. Queries for a bypass role skip rule evaluation entirely and are
+    /// always allowed, even if a rule explicitly denies access. Unlike `allow_all` this is not
+    /// inherited by descendant roles. Returns an error if role is undefined or the `Acl` is locked.
+    pub fn set_bypass_role(&mut self, role: &'static str) -> Result<(), Error> {
+        trace!("setting bypass role {}", role);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if !self.roles.contains_key(role) {
+            warn!("missing role while setting bypass role: {}", role);
+            return Err(Error::MissingRole(String::from(role)));
+        } // if
+        if self.bypass.insert(role) {
+            self.track(Item::Bypass(role), true);
+        } // if
+        Ok(())
+    } // set_bypass_role
+
+    /// Removes the bypass designation of role. Returns true if role was a bypass role. Returns an
+    /// error if the `Acl` is locked.
+    pub fn unset_bypass_role(&mut self, role: &'static str) -> Result<bool, Error> {
+        trace!("unsetting bypass role {}", role);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if !self.bypass.remove(role) {
+            return Ok(false);
+        } // if
+        self.track(Item::Bypass(role), false);
+        Ok(true)
+    } // unset_bypass_role
+
+    /// Returns true if role is a bypass role.
+    #[inline]
+    pub fn is_bypass_role(&self, role: &'static str) -> bool {
+        self.bypass.contains(role)
+    } // is_bypass_role
+
+    /// Sets the role queries without role are decided for, e.g. for unauthenticated requests.
+    /// Without default role None is a wildcard which only matches rules defined for all roles.
+    /// Returns an error if role is undefined or the `Acl` is locked.
+    pub fn set_default_role(&mut self, role: &'static str) -> Result<(), Error> {
+        trace!("setting default role {}", role);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if !self.roles.contains_key(role) {
+            warn!("missing role while setting default role: {}", role);
+            return Err(Error::MissingRole(String::from(role)));
+        } // if
+        self.unset_default_role()?;
+        self.default_role = Some(role);
+        self.track(Item::DefaultRole(role), true);
+        Ok(())
+    } // set_default_role
+
+    /// Removes the default role. Returns true if a default role was set. Returns an error if the
+    /// `Acl` is locked.
+    pub fn unset_default_role(&mut self) -> Result<bool, Error> {
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        match self.default_role.take() {
+            Some(role) => {
+                self.track(Item::DefaultRole(role), false);
+                Ok(true)
+            }, // Some
+            None       => Ok(false),
+        } // match
+    } // unset_default_role
+
+    /// Returns the role queries without role are decided for.
+    #[inline]
+    pub fn default_role(&self) -> Option<&'static str> {
+        self.default_role
+    } // default_role
+
+    /// Returns the canonical form of query, which is decided and cached in its place: a query
+    /// without role is decided for the default role. Equivalent queries thus share their cached
+    /// decision, see module `cache`.
+    #[inline]
+    pub fn normalize_query(&self, query: Query) -> Query {
+        Query{role: query.role.or(self.default_role), ..query}
+    } // normalize_query
+
+    /// Returns true if privilege on resource is allowed for anonymous requests, i.e. the default
+    /// role. Equal to `is_allowed` without role.
+    #[inline]
+    pub fn is_allowed_anonymous(&self, resource: Resource, privilege: Privilege) -> bool {
+        self.is_allowed(None, resource, privilege)
+    } // is_allowed_anonymous
+
+    /// Returns true if privilege on resource is denied for anonymous requests, i.e. the default
+    /// role. Equal to `is_denied` without role.
+    #[inline]
+    pub fn is_denied_anonymous(&self, resource: Resource, privilege: Privilege) -> bool {
+        self.is_denied(None, resource, privilege)
+    } // is_denied_anonymous
+
+    /// Returns true if role is allowed all privileges on all resources by a role-level wildcard
+    /// rule, either defined for the role itself or inherited from its ancestors. More specific
+    /// deny rules may still apply.
+    #[inline]
+    pub fn is_role_unrestricted(&self, role: &'static str) -> bool {
+        self.roles.contains_key(role) && self.is_allowed(Some(role), None, None)
+    } // is_role_unrestricted
+
+    /// Rules for which holds is false are skipped, see module `condition`.
+    fn query_privileges<'r>(rules: &'r BTreeMap<Query, Rule>, resource: &Resource, role: &Role, privilege: &Privilege, holds: &dyn Fn(&Rule) -> bool) -> Option<(&'r Query, &'r Rule)> {
         // query specific privilege
-        if let Some(_) = privilege {
+        if privilege.is_some() {
             trace!("querying rule for {:?} on {:?} to {:?}", role, resource, privilege);
-            if let Some(rule) = self.get_one_rule(*role, *resource, *privilege) {
-                return Some(rule);
+            if let Some(found) = rules.get_key_value(&Query{resource: *resource, role: *role, privilege: *privilege}).filter(|(_, rule)| holds(rule)) {
+                return Some(found);
             } // if let
         }  // if
         // query wildcard privilage if query isn't equal to Query::ALL
         if resource.is_some() || role.is_some() {
             trace!("querying rule for {:?} on {:?} to None", role, resource);
-            return self.get_one_rule(*role, *resource, None);
+            return rules.get_key_value(&Query{resource: *resource, role: *role, privilege: None}).filter(|(_, rule)| holds(rule));
         } // if
         None
     } // query_privileges
 
-    fn query_roles(&self, resource: &Resource, roles: &Roles, privilege: &Privilege) -> Option<&Rule> {
+    fn query_roles<'r>(rules: &'r BTreeMap<Query, Rule>, resource: &Resource, roles: Roles, privilege: &Privilege, order: ParentOrder, holds: &dyn Fn(&Rule) -> bool) -> Option<(&'r Query, &'r Rule)> {
         // specific roles in lineage
         if let Some(names) = roles {
-            for name in names {
-                if let Some(rule) = self.query_privileges(resource, &Some(name), privilege) {
-                    return Some(rule);
+            let mut allowed = None;
+
+            for (i, name) in names.iter().enumerate() {
+                if let Some(found) = Self::query_privileges(rules, resource, &Some(name), privilege, holds) {
+                    // an inherited allow rule is kept until no ancestor denies
+                    if order != ParentOrder::DenyFirst || i == 0 || found.1.acc == Access::Deny {
+                        return Some(found);
+                    } // if
+                    allowed = allowed.or(Some(found));
                 } // if let
             } // for
+            if allowed.is_some() {
+                return allowed;
+            } // if
         } // if let
         // wildcrad role
-        self.query_privileges(resource, &None, privilege)
+        Self::query_privileges(rules, resource, &None, privilege, holds)
     } // query_roles
 
-    fn query_precedence(&self, role: Role, resource: Resource, privilege: Privilege) -> Option<&Rule> {
-        let resources = if let Some(name) = resource {
-            Some(self.get_resource_lineage(name))
-        } else { None };
-        let roles = if let Some(name) = role {
-            Some(self.get_role_lineage(name))
-        } else { None };
+    fn query_all_privileges<'r>(rules: &[&'r BTreeMap<Query, Rule>], resource: Resource, role: Role, holds: &dyn Fn(&Rule) -> bool) -> Option<(&'r Query, &'r Rule)> {
+        // any privilege specific deny rule denies all privileges
+        let deny = rules.iter()
+            .flat_map(|rules| rules.iter())
+            .filter(|(query, rule)| query.resource == resource && query.role == role
+                && query.privilege.is_some() && rule.acc == Access::Deny && holds(rule))
+            .min_by_key(|(query, _)| query.privilege);
+
+        deny.or_else(|| rules.iter()
+            .find_map(|rules| rules.get_key_value(&Query{resource, role, privilege: None}))
+            .filter(|(_, rule)| holds(rule)))
+    } // query_all_privileges
+
+    /// Searches rules for the wildcard privilege like laminas, the roles in lineage.
+    fn query_compat<'r>(&'r self, rules: &[&'r BTreeMap<Query, Rule>], lineage: &[&'static str], query: Query, conditional: &Cell<bool>) -> (&'r Query, &'r Rule) {
+        let holds = |rule: &Rule| self.holds(rule, &query, conditional);
+
+        let mut resources: Vec<Resource> = match query.resource {
+            Some(name) => self.get_resource_lineage(name).into_iter().map(Some).collect(),
+            None       => vec![],
+        }; // match
+
+        resources.push(None);
+        for resource in resources {
+            for name in lineage {
+                if let Some(found) = Self::query_all_privileges(rules, resource, Some(name), &holds) {
+                    return found;
+                } // if let
+            } // for
+            if let Some(found) = Self::query_all_privileges(rules, resource, None, &holds) {
+                return found;
+            } // if let
+        } // for
+        (&Query::ALL, self.rules.index(&Query::ALL))
+    } // query_compat
+
+    /// Searches rules for the query in order of precedence, using the lineage of roles and
+    /// resources defined in this `Acl`.
+    /// The role lineage is collected into a reused scratch buffer and the resource lineage is
+    /// iterated lazily, so queries of defined roles and resources don't allocate. Sets conditional
+    /// if the condition of any rule has been evaluated, see module `condition`.
+    #[inline]
+    pub(crate) fn query_precedence_in<'r>(&self, rules: &'r BTreeMap<Query, Rule>, role: Role, resource: Resource, privilege: Privilege, conditional: &Cell<bool>) -> Option<(&'r Query, &'r Rule)> {
+        self.query_precedence_ordered(rules, role, resource, privilege, self.parent_order, conditional)
+    } // query_precedence_in
+
+    /// Like `query_precedence_in`, but searches the parents of roles in order.
+    fn query_precedence_ordered<'r>(&self, rules: &'r BTreeMap<Query, Rule>, role: Role, resource: Resource, privilege: Privilege, order: ParentOrder, conditional: &Cell<bool>) -> Option<(&'r Query, &'r Rule)> {
+        let query       = Query{resource, role, privilege};
+        let holds       = |rule: &Rule| self.holds(rule, &query, conditional);
+        let mut owned   = vec![];
+        let mut scratch = self.scratch.try_borrow_mut();
+        // the buffer is only taken if queries are nested, e.g. by a provider
+        let lineage     = match &mut scratch {
+            Ok(buffer) => &mut **buffer,
+            Err(_)     => &mut owned,
+        }; // match
+
+        lineage.clear();
+        if let Some(name) = role {
+            self.extend_role_lineage(name, order, lineage);
+        } // if
+        let roles = role.map(|_| &lineage[..]);
 
         // specific resource
-        if let Some(names) = resources {
-            for name in names {
-                if let Some(rule) = self.query_roles(&Some(name), &roles, &privilege) {
-                    return Some(rule);
+        if let Some(name) = resource {
+            for name in self.iter_resource_lineage(name) {
+                if let Some(found) = Self::query_roles(rules, &Some(name), roles, &privilege, order, &holds) {
+                    return Some(found);
                 } // if let
             } // for
         } // if
         // wildcard resource
-        self.query_roles(&None, &roles, &privilege)
-    } // get_query_precedence
+        Self::query_roles(rules, &None, roles, &privilege, order, &holds)
+    } // query_precedence_ordered
 
     /// This always returns a rule. If no specific rule is defined by the query, the corresponding
     /// catch-all rule is returned. Utilizes and updates cache if `Acl` is locked.
@@ -718,47 +1597,214 @@ impl Acl {
     /// Rules are searched depth first. The lineage of the resource and rule is retrieved.
     /// Resources are iterated in the outer for-loop, rules in the inner for-loop. In this inner
     /// loop privileges are queried with the specific name or the wildcard placeholder. If no rule
-    /// is found the catch-all rule ist returned.
+    /// is found the default of the resource for the privilege is returned, see module `defaults`,
+    /// otherwise the catch-all rule. Rules with priority override this order, see module
+    /// `priority`.
+    #[inline]
     pub fn get_rule(&self, role: Role, resource: Resource, privilege: Privilege) -> Rule {
+        self.decide(role, resource, privilege).rule
+    } // get_rule
+
+    /// Like `get_rule`, but also returns the query of the deciding rule. See `get_rule` for the
+    /// order of precedence. The decision is reported to the audit sink, see module `audit`.
+    #[inline]
+    pub fn decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        self.decide_with_options(role, resource, privilege, QueryOptions::default())
+    } // decide
+
+    /// Like `decide`, but with options overriding the configuration of the `Acl` for this query
+    /// only. Decisions with a parent order other than the `Acl`'s bypass the cache.
+    ///
+    /// ```
+    /// # extern crate zorq_acl;
+    /// # use zorq_acl::{Acl, ParentOrder, QueryOptions};
+    /// let mut acl = Acl::new();
+    ///
+    /// acl.add_role("guest", vec![]).unwrap();
+    /// acl.add_role("member", vec![]).unwrap();
+    /// acl.add_role("someUser", vec!["guest", "member"]).unwrap();
+    /// acl.deny(Some("guest"), None, None).unwrap();
+    /// acl.allow(Some("member"), None, None).unwrap();
+    ///
+    /// let strict = QueryOptions{parent_order: Some(ParentOrder::DenyFirst)};
+    ///
+    /// assert!(acl.is_allowed(Some("someUser"), None, Some("delete")));
+    /// assert!(acl.is_denied_with_options(Some("someUser"), None, Some("delete"), strict));
+    /// ```
+    pub fn decide_with_options(&self, role: Role, resource: Resource, privilege: Privilege, options: QueryOptions) -> Decision {
+        #[cfg(feature = "metrics")]
+        let start    = std::time::Instant::now();
+        #[cfg(feature = "otel")]
+        let span     = otel::start(None, role, resource, privilege);
+        let decision = self.evaluate_ordered(role, resource, privilege, options.parent_order.unwrap_or(self.parent_order));
+
+        self.count_hit(&decision);
+        #[cfg(feature = "metrics")]
+        metrics::decision(&decision, start);
+        #[cfg(feature = "otel")]
+        otel::end(span, &decision, self.fingerprint);
+        self.audit(None, &decision);
+        decision
+    } // decide_with_options
+
+    /// Returns true if privilege is allowed for role on resource with options, see
+    /// `decide_with_options`.
+    #[inline]
+    pub fn is_allowed_with_options(&self, role: Role, resource: Resource, privilege: Privilege, options: QueryOptions) -> bool {
+        self.decide_with_options(role, resource, privilege, options).rule.acc == Access::Allow
+    } // is_allowed_with_options
+
+    /// Returns true if privilege is denied for role on resource with options, see
+    /// `decide_with_options`.
+    #[inline]
+    pub fn is_denied_with_options(&self, role: Role, resource: Resource, privilege: Privilege, options: QueryOptions) -> bool {
+        self.decide_with_options(role, resource, privilege, options).rule.acc == Access::Deny
+    } // is_denied_with_options
+
+    /// Decides the query without reporting the decision. Queries without role are decided for
+    /// the default role, if set.
+    #[inline]
+    pub(crate) fn evaluate(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        self.evaluate_ordered(role, resource, privilege, self.parent_order)
+    } // evaluate
+
+    /// Like `evaluate`, but searches the parents of roles in order. The cache only holds
+    /// decisions in the order of this `Acl`. If a provider fails to resolve a role or resource,
+    /// the query is decided by the fallback access of the provider, see module `breaker`.
+    fn evaluate_ordered(&self, role: Role, resource: Resource, privilege: Privilege, order: ParentOrder) -> Decision {
+        self.provider_failure.set(None);
+
+        let decision = self.evaluate_rules(role, resource, privilege, order);
+
+        match self.provider_failure.take() {
+            Some(access) => {
+                warn!("deciding {} by provider fallback {}", decision.query, access);
+                Decision{query: decision.query, matched: decision.query, rule: Rule{acc: access, cond: None}, bypass: false}
+            }, // Some
+            None         => decision,
+        } // match
+    } // evaluate_ordered
+
+    /// Searches the rules for the query, see `evaluate_ordered`.
+    fn evaluate_rules(&self, role: Role, resource: Resource, privilege: Privilege, order: ParentOrder) -> Decision {
         trace!("getting rule for {:?} on {:?} to {:?}", role, resource, privilege);
-        // try direct query first
-        if let Some(rule) = self.rules.get(&Query{resource, role, privilege}) {
-            trace!("    matching direct query");
-            return *rule;
-        } // if
+        let query       = self.normalize_query(Query{resource, role, privilege});
+        // decisions of conditional rules aren't cached
+        let conditional = Cell::new(false);
+        let lineage     = |roles: &mut Vec<&'static str>| {
+            if let Some(name) = query.role {
+                self.extend_role_lineage(name, order, roles);
+            } // if
+        }; // lineage
 
-        // omit if equal to Query::ALL
-        if resource.is_some() || role.is_some() || privilege.is_some() {
-            // if this is locked try utilzing cache
-            if let Some(cache) = &self.lock {
-                let cache = cache.borrow(); 
-                let rule  = cache.get(&Query{resource, role, privilege});
+        self.resolve(query, &[&self.rules], lineage, &conditional, || {
+            // try direct query first
+            if let Some(rule) = self.rules.get(&query).filter(|rule| self.holds(rule, &query, &conditional)) {
+                trace!("    matching direct query");
+                return Some((query, *rule));
+            } // if
+
+            // omit if equal to Query::ALL
+            if query == Query::ALL {
+                return None;
+            } // if
+            let cached = self.lock.is_some() && order == self.parent_order;
 
-                if let Some(rule) = rule {
+            // if this is locked try utilzing cache
+            if cached {
+                if let Some(found) = self.cached(&query) {
                     trace!("    cache hit");
-                    return *rule;
+                    self.count_cache(true);
+                    return Some(found);
                 } // if
+                self.count_cache(false);
             } // if
-            if let Some(rule) = self.query_precedence(role, resource, privilege) {
-                trace!("    matched query");
-                // if this is locked add this rule to the cache.
-                if let Some(cache) = &self.lock {
-                    trace!("    caching rule");
-                    cache.borrow_mut().insert(Query{resource, role, privilege}, *rule);
-                } // if
-                return *rule;
-            } // if let
+            let (matched, rule) = self.query_precedence_ordered(&self.rules, query.role, query.resource, query.privilege, order, &conditional)?;
+
+            trace!("    matched query");
+            // if this is locked add this rule to the cache.
+            if cached && !conditional.get() && self.provider_failure.get().is_none() {
+                trace!("    caching rule");
+                self.cache_decision(query, *matched, *rule);
+            } // if
+            Some((*matched, *rule))
+        }) // resolve
+    } // evaluate_rules
+
+    /// Decides the normalized query without caching or reporting it. Bypass roles are allowed
+    /// everything, otherwise the laminas compatibility mode, rules with priority, precedence and
+    /// the defaults of the resource decide in this order, the catch-all rule last. rules hold the
+    /// rules of the roles lineage adds to its buffer, which is only called if priorities or the
+    /// compatibility mode apply. precedence searches by precedence, e.g. through the cache. This
+    /// is the only place the order is written: all views deciding like `decide` share it, see
+    /// `effective`, and `warm_cache` warms through `evaluate`.
+    pub(crate) fn resolve<L, P>(&self, query: Query, rules: &[&BTreeMap<Query, Rule>], lineage: L, conditional: &Cell<bool>, precedence: P) -> Decision
+    where
+        L: FnOnce(&mut Vec<&'static str>),
+        P: FnOnce() -> Option<(Query, Rule)>,
+    {
+        let decided = |matched, rule| Decision{query, matched, rule, bypass: false};
+
+        // bypass roles skip rule evaluation entirely
+        if query.role.is_some_and(|name| self.bypass.contains(name)) {
+            trace!("    bypass role");
+            return Decision{query, matched: query, rule: Rule{acc: Access::Allow, cond: None}, bypass: true};
+        } // if
+
+        let compat    = self.compat && query.privilege.is_none();
+        let mut roles = vec![];
+
+        if compat || !self.priorities.is_empty() {
+            lineage(&mut roles);
+        } // if
+
+        // laminas queries all privileges if privilege is a wildcard
+        if compat {
+            let (matched, rule) = self.query_compat(rules, &roles, query, conditional);
+
+            trace!("    matched all privileges query");
+            return decided(*matched, *rule);
+        } // if
+
+        // rules with priority override precedence, see module `priority`
+        if !self.priorities.is_empty() {
+            if let Some((matched, rule)) = self.query_priorities(&roles, query, conditional) {
+                trace!("    matched rule with priority");
+                return decided(*matched, *rule);
+            } // if
+        } // if
+
+        if let Some((matched, rule)) = precedence() {
+            return decided(matched, rule);
+        } // if
+
+        // defaults of the resource decide before the catch-all rule, see module `defaults`
+        if let Some((matched, access)) = self.query_defaults(query.resource, query.privilege) {
+            trace!("    matched resource default");
+            return decided(matched, Rule{acc: access, cond: None});
         } // if
 
         // no specific rule defined, return rule for Query::ALL, this is always defined
         trace!("    matching catch-all");
-        *self.rules.index(&Query::ALL)
-    } // get_rule
+        decided(Query::ALL, *self.rules.index(&Query::ALL))
+    } // resolve
 
     /// Some(...) is a specific definition and None is a wildcard. All roles, resources or
-    /// privileges which are not None must be predefined.
+    /// privileges which are not None must be predefined. Privileges are only checked once any
+    /// are registered, see module `domain`.
+    #[inline]
     pub fn set_rule(&mut self, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
-        trace!("setting rule for {:?} on {:?} with {:?} privilege", role, resource, privilege);
+        self.set_rule_op(Operation::Add, role, resource, privilege, access).map(|_| ())
+    } // set_rule
+
+    /// Adds or removes rules like laminas' `setRule`. Adding treats None as a wildcard, see
+    /// `set_rule`. Removing treats None as all: every rule with the given access matching the
+    /// specific role, resource and privilege is removed, including wildcard rules. Removing the
+    /// catch-all rule resets it to deny. Returns the number of rules added or removed. All roles
+    /// and resources which are not None must be predefined, privileges must be registered if any
+    /// are. Adding a rule returns an error if a limit is exceeded, see module `limits`.
+    pub fn set_rule_op(&mut self, operation: Operation, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<usize, Error> {
+        trace!("{:?} {} rule for {:?} on {:?} with {:?} privilege", operation, access, role, resource, privilege);
 
         // if this is locked, no new rules
         if self.lock.is_some() {
@@ -779,16 +1825,75 @@ impl Acl {
             } // if
         } // if
 
+        // bundles expand to the rules of their privileges, see module `bundle`
+        if let Some(bundle) = privilege.filter(|name| self.bundles.contains_key(name)) {
+            return self.set_bundle_rule_op(operation, role, resource, bundle, access);
+        } // if
+
+        // ensure that privilege is registered and declared
+        self.check_privilege(resource, privilege)?;
+
         let query = Query{resource, role, privilege};
 
-        if query != Query::ALL {
-            self.rules.insert(query, Rule{acc: access});
-        } // if
-        Ok(())
-    } // set_rule
+        match operation {
+            Operation::Add    => {
+                // the catch-all rule is fixed unless in laminas compatibility mode
+                if query != Query::ALL || self.compat {
+                    self.check_rule_limits(&query)?;
+                    self.insert_rule(query, Rule{acc: access, cond: None});
+                    return Ok(1);
+                } // if
+                Ok(0)
+            }, // Add
+            Operation::Remove => {
+                let matches = |other: &Query| (role.is_none() || other.role == role)
+                    && (resource.is_none() || other.resource == resource)
+                    && (privilege.is_none() || other.privilege == privilege);
+                let removed: Vec<Query> = self.rules.iter()
+                    .filter(|(other, rule)| rule.acc == access && **other != Query::ALL && matches(other))
+                    .map(|(other, _)| *other)
+                    .collect();
+
+                for other in &removed {
+                    self.remove_rule(other);
+                    self.meta.remove(other);
+                } // for
+                self.bundle_rules.retain(|other, other_access| *other_access != access || !matches(other));
+                // the catch-all rule is reset instead of removed
+                if query == Query::ALL && access == Access::Allow && self.rules[&Query::ALL].acc == Access::Allow {
+                    self.insert_rule(Query::ALL, Rule{acc: Access::Deny, cond: None});
+                    self.meta.remove(&Query::ALL);
+                    return Ok(removed.len() + 1);
+                } // if
+                Ok(removed.len())
+            }, // Remove
+        } // match
+    } // set_rule_op
+
+    /// Removes allow rules for role on resource to privilege. None matches all, see
+    /// `set_rule_op`. Returns the number of rules removed.
+    #[inline]
+    pub fn remove_allow(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<usize, Error> {
+        self.set_rule_op(Operation::Remove, role, resource, privilege, Access::Allow)
+    } // remove_allow
+
+    /// Removes deny rules for role on resource to privilege. None matches all, see
+    /// `set_rule_op`. Returns the number of rules removed.
+    #[inline]
+    pub fn remove_deny(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<usize, Error> {
+        self.set_rule_op(Operation::Remove, role, resource, privilege, Access::Deny)
+    } // remove_deny
 
 } // impl Acl
 
+impl Default for Acl {
+
+    fn default() -> Self {
+        Self::new()
+    } // default
+
+} // impl Default for Acl
+
 impl fmt::Debug for Acl {
 
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
@@ -798,9 +1903,111 @@ impl fmt::Debug for Acl {
 } // impl fmt::Debug for Acl
 
 
+// RoleLineage ////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Iterates a role lineage depth first, see `Acl::iter_role_lineage`.
+struct RoleLineage<'a> {
+    acl:   &'a Acl,
+    first: Option<&'static str>,
+    seen:  Vec<&'static str>,
+    stack: Vec<(Cow<'a, [&'static str]>, usize)>,
+} // struct RoleLineage
+
+impl Iterator for RoleLineage<'_> {
+
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(name) = self.first.take() {
+            self.seen.push(name);
+            return Some(name);
+        } // if
+        loop {
+            let (parents, i) = self.stack.last_mut()?;
+
+            if *i == parents.len() {
+                self.stack.pop();
+                continue;
+            } // if
+            // parents are stored in LIFO order
+            let parent = match self.acl.parent_order {
+                ParentOrder::Lifo => parents[*i],
+                _                 => parents[parents.len() - 1 - *i],
+            }; // match
+
+            *i += 1;
+            // ancestors of provided roles may be cyclic
+            if self.seen.contains(&parent) {
+                continue;
+            } // if
+            self.seen.push(parent);
+            if let Some(grandparents) = self.acl.role_parents(parent) {
+                self.stack.push((grandparents, 0));
+            } // if
+            return Some(parent);
+        } // loop
+    } // next
+
+} // impl Iterator for RoleLineage
+
+
 // Error //////////////////////////////////////////////////////////////////////////////////////////
 
 
+/// A problem found while validating a policy document. The path locates the offending value, e.g.
+/// `rules[3].role`, within the document named by source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaError {
+    pub source:  Option<String>,
+    pub path:    String,
+    pub message: String,
+} // struct SchemaError
+
+impl SchemaError {
+
+    /// Creates a new `SchemaError` without source.
+    pub fn new(path: &str, message: &str) -> Self {
+        SchemaError{source: None, path: String::from(path), message: String::from(message)}
+    } // new
+
+    /// Creates a new `SchemaError` within the document named by source.
+    pub fn at(source: Option<&str>, path: &str, message: &str) -> Self {
+        SchemaError{source: source.map(String::from), path: String::from(path), message: String::from(message)}
+    } // at
+
+} // impl SchemaError
+
+impl fmt::Display for SchemaError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if let Some(source) = &self.source {
+            write!(f, "{}: ", source)?;
+        } // if
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        } // else
+    } // fmt
+
+} // impl fmt::Display for SchemaError
+
+/// The category of an `Error`, see `Error::category`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// the input is invalid or exceeds a limit
+    Validation,
+    /// a role, resource, privilege, rule, change or group is undefined
+    NotFound,
+    /// a role or resource is defined already
+    Conflict,
+    /// the `Acl` is locked
+    Locked,
+    /// a file, transport or remote policy failed, which may be transient
+    Backend,
+} // enum ErrorCategory
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Error {
     DuplicateRole(String),
@@ -808,7 +2015,22 @@ pub enum Error {
     MissingParent(String),
     DuplicateResource(String),
     MissingResource(String),
+    MissingPrivilege(String),
+    MissingRule(String),
+    MissingChange(u64),
+    MissingGroup(String),
+    TooManyRoles(usize),
+    TooManyResources(usize),
+    TooManyRules(usize),
+    TooManyParents(usize),
+    TooDeep(String),
+    NameTooLong(usize),
     Locked,
+    NotPermitted(String),
+    Io(String),
+    Parse(String),
+    Schema(Vec<SchemaError>),
+    SchemaVersion(u64),
 } // enum Error
 
 impl fmt::Display for Error {
@@ -825,13 +2047,93 @@ impl fmt::Display for Error {
                 write!(f, "Duplicate resource: {}", s),
             Error::MissingResource(s) =>
                 write!(f, "Missing resource: {}", s),
+            Error::MissingPrivilege(s) =>
+                write!(f, "Missing privilege: {}", s),
+            Error::MissingRule(s) =>
+                write!(f, "Missing rule: {}", s),
+            Error::MissingChange(id) =>
+                write!(f, "Missing change: {}", id),
+            Error::MissingGroup(s) =>
+                write!(f, "Missing group: {}", s),
+            Error::TooManyRoles(max) =>
+                write!(f, "Too many roles: at most {} allowed", max),
+            Error::TooManyResources(max) =>
+                write!(f, "Too many resources: at most {} allowed", max),
+            Error::TooManyRules(max) =>
+                write!(f, "Too many rules: at most {} allowed", max),
+            Error::TooManyParents(max) =>
+                write!(f, "Too many parents: at most {} allowed", max),
+            Error::TooDeep(s) =>
+                write!(f, "Inheritance too deep: {}", s),
+            Error::NameTooLong(len) =>
+                write!(f, "Name too long: {} bytes", len),
             Error::Locked =>
                 write!(f, "acl is locked, no new rules may be defined"),
+            Error::NotPermitted(s) =>
+                write!(f, "Not permitted: {}", s),
+            Error::Io(s) =>
+                write!(f, "I/O error: {}", s),
+            Error::Parse(s) =>
+                write!(f, "Parse error: {}", s),
+            Error::Schema(errors) => {
+                write!(f, "Invalid policy document:")?;
+                for error in errors {
+                    write!(f, "\n    {}", error)?;
+                } // for
+                Ok(())
+            }, // Error::Schema
+            Error::SchemaVersion(version) =>
+                write!(f, "Unsupported schema version: {}", version),
         } // match
     } // fmt
 
 } // impl fmt::Display for Error
 
+impl Error {
+
+    /// Returns the category of the error, e.g. to tell backend failures from misconfigured
+    /// policies.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::MissingRole(_)
+            | Error::MissingParent(_)
+            | Error::MissingResource(_)
+            | Error::MissingPrivilege(_)
+            | Error::MissingRule(_)
+            | Error::MissingChange(_)
+            | Error::MissingGroup(_)      => ErrorCategory::NotFound,
+            Error::DuplicateRole(_)
+            | Error::DuplicateResource(_) => ErrorCategory::Conflict,
+            Error::TooManyRoles(_)
+            | Error::TooManyResources(_)
+            | Error::TooManyRules(_)
+            | Error::TooManyParents(_)
+            | Error::TooDeep(_)
+            | Error::NameTooLong(_)
+            | Error::NotPermitted(_)
+            | Error::Parse(_)
+            | Error::Schema(_)
+            | Error::SchemaVersion(_)     => ErrorCategory::Validation,
+            Error::Locked                 => ErrorCategory::Locked,
+            Error::Io(_)                  => ErrorCategory::Backend,
+        } // match
+    } // category
+
+    /// Returns true if retrying the operation may succeed, i.e. for backend failures.
+    #[inline]
+    pub fn is_retryable(&self) -> bool {
+        self.category() == ErrorCategory::Backend
+    } // is_retryable
+
+    /// Returns true if the error is caused by the input, e.g. a misconfigured policy, which
+    /// retrying doesn't fix.
+    #[inline]
+    pub fn is_user_error(&self) -> bool {
+        matches!(self.category(), ErrorCategory::Validation | ErrorCategory::NotFound | ErrorCategory::Conflict)
+    } // is_user_error
+
+} // impl Error
+
 
 // Tests //////////////////////////////////////////////////////////////////////////////////////////
 
@@ -959,6 +2261,76 @@ mod tests {
         assert_eq!(acl.get_role_lineage("supervisor"), vec!["supervisor", "editor", "staff", "guest"]);
     } // lineage
 
+    #[test]
+    fn parent_order() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("member", vec![]).is_ok());
+        assert!(acl.add_role("admin", vec![]).is_ok());
+        assert!(acl.add_role("someUser", vec!["guest", "member", "admin"]).is_ok());
+        assert!(acl.add_resource("someResource", None).is_ok());
+        assert!(acl.deny(Some("guest"), Some("someResource"), None).is_ok());
+        assert!(acl.allow(Some("member"), Some("someResource"), None).is_ok());
+        assert!(acl.allow(Some("admin"), None, Some("view")).is_ok());
+        acl.lock();
+
+        // the last declared parent is searched first
+        assert_eq!(acl.parent_order(), ParentOrder::Lifo);
+        assert_eq!(acl.get_role_lineage("someUser"), vec!["someUser", "admin", "member", "guest"]);
+        assert!(acl.is_allowed(Some("someUser"), Some("someResource"), None));
+
+        // the first declared parent is searched first
+        acl.set_parent_order(ParentOrder::Fifo);
+        assert_eq!(acl.get_role_lineage("someUser"), vec!["someUser", "guest", "member", "admin"]);
+        assert!(acl.is_denied(Some("someUser"), Some("someResource"), None));
+        assert_eq!(acl.decide(Some("someUser"), Some("someResource"), Some("view")).matched.to_string(), "guest→someResource: *");
+
+        // any inherited deny rule wins, regardless of the position of the parent
+        acl.unlock();
+        assert!(acl.remove_deny(Some("guest"), Some("someResource"), None).is_ok());
+        assert!(acl.deny(Some("admin"), Some("someResource"), None).is_ok());
+        assert!(acl.is_allowed(Some("someUser"), Some("someResource"), None));
+        acl.set_parent_order(ParentOrder::DenyFirst);
+        assert_eq!(acl.get_role_lineage("someUser"), vec!["someUser", "guest", "member", "admin"]);
+        assert!(acl.is_denied(Some("someUser"), Some("someResource"), None));
+        assert!(acl.is_allowed(Some("someUser"), None, Some("view")));
+
+        // rules of the role itself take precedence
+        assert!(acl.allow(Some("someUser"), Some("someResource"), None).is_ok());
+        assert!(acl.is_allowed(Some("someUser"), Some("someResource"), None));
+    } // parent_order
+
+    #[test]
+    fn query_options() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("member", vec![]).is_ok());
+        assert!(acl.add_role("someUser", vec!["guest", "member"]).is_ok());
+        assert!(acl.add_resource("someResource", None).is_ok());
+        assert!(acl.deny(Some("guest"), Some("someResource"), None).is_ok());
+        assert!(acl.allow(Some("member"), Some("someResource"), None).is_ok());
+        acl.lock();
+
+        let strict = QueryOptions{parent_order: Some(ParentOrder::DenyFirst)};
+        let fifo   = QueryOptions{parent_order: Some(ParentOrder::Fifo)};
+
+        // overrides neither read nor pollute the cache
+        assert!(acl.is_allowed(Some("someUser"), Some("someResource"), Some("view")));
+        assert!(acl.is_denied_with_options(Some("someUser"), Some("someResource"), Some("view"), strict));
+        assert_eq!(acl.decide_with_options(Some("someUser"), Some("someResource"), Some("view"), fifo).matched.to_string(), "guest→someResource: *");
+        assert!(acl.is_allowed_with_options(Some("someUser"), Some("someResource"), Some("view"), QueryOptions::default()));
+        assert!(acl.is_allowed(Some("someUser"), Some("someResource"), Some("view")));
+        assert_eq!(acl.cache_stats().entries, 1);
+        assert_eq!(acl.parent_order(), ParentOrder::Lifo);
+
+        // the laminas compatibility mode searches parents in order as well
+        acl.set_laminas_compat(true);
+        assert!(acl.is_allowed(Some("someUser"), Some("someResource"), None));
+        assert!(acl.is_denied_with_options(Some("someUser"), Some("someResource"), None, fifo));
+    } // query_options
+
     #[test]
     fn ancestor() {
         let mut acl = Acl::new();
@@ -1124,4 +2496,381 @@ mod tests {
         assert!( acl.is_denied (Some("admin"), Some("anouncement"), Some("archive")));
     } // rules
 
+    #[test]
+    fn display() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+
+        assert_eq!(Access::Allow.to_string(), "ALLOW");
+        assert_eq!(Access::Deny.to_string(), "DENY");
+        assert_eq!(acl.get_rule(Some("guest"), None, Some("view")).to_string(), "ALLOW");
+        assert_eq!(Query::ALL.to_string(), "*→*: *");
+
+        let decision = acl.decide(Some("staff"), Some("latest"), Some("revise"));
+
+        assert!(decision.is_denied());
+        assert_eq!(decision.to_string(), "DENY staff→latest: revise");
+        assert_eq!(decision.matched, Query{resource: Some("latest"), role: Some("staff"), privilege: Some("revise")});
+
+        let decision = acl.decide(Some("editor"), None, Some("view"));
+
+        assert!(decision.is_allowed());
+        assert_eq!(decision.to_string(), "ALLOW editor→*: view");
+        assert_eq!(decision.matched.to_string(), "guest→*: view");
+    } // display
+
+    #[test]
+    fn allow_all() {
+        let mut acl = setup_acl();
+
+        assert!(acl.add_role("root", vec!["admin"]).is_ok());
+        assert!(acl.add_role("banned", vec!["staff"]).is_ok());
+        assert!(acl.deny_all("banned").is_ok());
+
+        assert!( acl.is_role_unrestricted("admin"));
+        assert!( acl.is_role_unrestricted("root"));
+        assert!(!acl.is_role_unrestricted("staff"));
+        assert!(!acl.is_role_unrestricted("banned"));
+        assert!(!acl.is_role_unrestricted("unknown"));
+        assert!( acl.is_denied(Some("banned"), None, Some("view")));
+
+        assert!(acl.allow_all("staff").is_ok());
+        assert!(acl.is_role_unrestricted("staff"));
+        assert!(acl.is_allowed(Some("staff"), None, Some("publish")));
+
+        assert_eq!(acl.revoke_all("staff"), Ok(true));
+        assert_eq!(acl.revoke_all("staff"), Ok(false));
+        assert!(!acl.is_role_unrestricted("staff"));
+        assert!( acl.is_denied(Some("staff"), None, Some("publish")));
+
+        assert_eq!(acl.allow_all("unknown"), Err(Error::MissingRole(String::from("unknown"))));
+        assert_eq!(acl.revoke_all("unknown"), Err(Error::MissingRole(String::from("unknown"))));
+
+        acl.lock();
+        assert_eq!(acl.revoke_all("admin"), Err(Error::Locked));
+    } // allow_all
+
+    #[test]
+    fn remove() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+
+        // all allow rules for marketing on all resources
+        assert_eq!(acl.remove_allow(Some("marketing"), None, None), Ok(4));
+        assert!(acl.is_denied (Some("marketing"), Some("latest"), Some("publish")));
+        assert!(acl.is_allowed(Some("marketing"), Some("latest"), Some("edit")));
+
+        // deny rules are kept
+        assert_eq!(acl.remove_allow(Some("staff"), Some("latest"), None), Ok(0));
+        assert_eq!(acl.remove_deny(None, None, Some("archive")), Ok(1));
+        assert!(acl.is_allowed(Some("admin"), Some("anouncement"), Some("archive")));
+
+        assert_eq!(acl.set_rule_op(Operation::Add, Some("guest"), None, Some("print"), Access::Allow), Ok(1));
+        assert!(acl.is_allowed(Some("staff"), None, Some("print")));
+        assert_eq!(acl.set_rule_op(Operation::Remove, None, None, Some("print"), Access::Allow), Ok(1));
+        assert!(acl.is_denied(Some("staff"), None, Some("print")));
+
+        // all deny rules, but the catch-all rule is never removed
+        assert_eq!(acl.remove_deny(None, None, None), Ok(1));
+        assert!(acl.is_denied(None, None, None));
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("revise")));
+
+        assert_eq!(acl.remove_allow(Some("unknown"), None, None), Err(Error::MissingRole(String::from("unknown"))));
+        acl.lock();
+        assert_eq!(acl.remove_allow(None, None, None), Err(Error::Locked));
+    } // remove
+
+    #[test]
+    fn error_category() {
+        let mut acl = setup_acl();
+
+        assert_eq!(acl.add_role("guest", vec![]).unwrap_err().category(), ErrorCategory::Conflict);
+        assert_eq!(acl.allow(Some("nobody"), None, None).unwrap_err().category(), ErrorCategory::NotFound);
+        assert!(Error::Parse(String::from("unexpected end of input")).is_user_error());
+        assert!(Error::Io(String::from("connection refused")).is_retryable());
+        acl.lock();
+        let locked = acl.allow(Some("guest"), None, None).unwrap_err();
+
+        assert_eq!(locked.category(), ErrorCategory::Locked);
+        assert!(!locked.is_retryable());
+        assert!(!locked.is_user_error());
+    } // error_category
+
+    #[test]
+    fn remove_resource() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+        assert!(acl.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(acl.set_resource_default("latest", "view", Access::Deny).is_ok());
+
+        let etag = acl.etag();
+
+        assert!(acl.add_resource("today", Some("latest")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("today"), None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("today"), Some("view")).is_ok());
+        assert!(acl.set_quota(Some("staff"), Some("today"), Some("view"), 1).is_ok());
+        assert!(acl.deny_subject("sally", Some("today"), Some("view")).is_ok());
+
+        assert_eq!(acl.remove_resource("latest"), Err(Error::NotPermitted(String::from("removing resource latest with children"))));
+        assert!(acl.remove_resource("today").is_ok());
+        assert!(!acl.has_resource("today"));
+        assert_eq!(acl.subjects().count(), 0);
+        assert_eq!(acl.etag(), etag);
+        assert!(acl.rules().all(|(query, _, _)| query.resource != Some("today")));
+
+        // descendants and their rules are removed first
+        assert!(acl.add_resource("today", Some("latest")).is_ok());
+        assert_eq!(acl.remove_resource_recursive("news"), Ok(vec!["today", "anouncement", "latest", "news"]));
+        assert_eq!(acl.resources().collect::<Vec<_>>(), vec!["newsletter"]);
+        assert!(acl.rules().all(|(query, _, _)| query.resource.is_none_or(|name| name == "newsletter")));
+        assert_eq!(acl.get_resource_default("latest", "view"), None);
+        assert!(acl.is_allowed(Some("marketing"), Some("newsletter"), Some("publish")));
+
+        // names are free to be defined again
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.rules().all(|(query, _, _)| query.resource != Some("news")));
+        assert_eq!(acl.remove_resource_recursive("nothing"), Err(Error::MissingResource(String::from("nothing"))));
+        acl.lock();
+        assert_eq!(acl.remove_resource("news"), Err(Error::Locked));
+    } // remove_resource
+
+    #[test]
+    fn revoke() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+        assert!(acl.deny(Some("marketing"), Some("anouncement"), Some("publish")).is_ok());
+        acl.lock();
+        assert!(acl.is_allowed(Some("marketing"), Some("latest"), Some("publish")));
+        assert!(acl.is_allowed(Some("editor"), Some("latest"), Some("publish")));
+
+        // exactly one rule is removed, even while locked
+        assert!(acl.revoke(Some("marketing"), Some("latest"), Some("publish")));
+        assert!(!acl.revoke(Some("marketing"), Some("latest"), Some("publish")));
+        assert!(acl.is_denied(Some("marketing"), Some("latest"), Some("publish")));
+        assert!(acl.is_allowed(Some("marketing"), Some("latest"), Some("archive")));
+        assert!(acl.is_allowed(Some("editor"), Some("latest"), Some("publish")));
+
+        // None is a wildcard
+        assert!(!acl.revoke(Some("editor"), None, None));
+        assert!(acl.revoke(Some("editor"), None, Some("publish")));
+        assert!(acl.is_denied(Some("editor"), Some("latest"), Some("publish")));
+        assert!(acl.revoke(Some("marketing"), Some("anouncement"), Some("publish")));
+        assert!(!acl.revoke(None, None, None));
+
+        acl.unlock();
+        acl.set_laminas_compat(true);
+        assert!(acl.allow(None, None, None).is_ok());
+        assert!(acl.revoke(None, None, None));
+        assert!(acl.is_denied(None, None, None));
+
+        // bundles and quotas
+        assert!(acl.define_bundle("suite", &["share", "embed"]).is_ok());
+        assert!(acl.allow(Some("editor"), Some("news"), Some("suite")).is_ok());
+        assert!(acl.set_quota(Some("editor"), Some("news"), Some("embed"), 1).is_ok());
+        assert!(acl.consume(Some("editor"), Some("news"), Some("embed")));
+        assert!(acl.revoke(Some("editor"), Some("news"), Some("suite")));
+        assert!(!acl.revoke(Some("editor"), Some("news"), Some("suite")));
+        assert!(acl.is_denied(Some("editor"), Some("news"), Some("share")));
+        assert_eq!(acl.get_bundle_rule(Some("editor"), Some("news"), "suite"), None);
+        assert_eq!(acl.remaining_quota(Some("editor"), Some("news"), Some("embed")), None);
+        assert!(acl.allow(Some("editor"), Some("news"), Some("embed")).is_ok());
+        assert!(acl.consume(Some("editor"), Some("news"), Some("embed")));
+    } // revoke
+
+    #[test]
+    fn bypass() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_role("operator", vec!["root"]).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        assert!(acl.deny(Some("root"), Some("news"), None).is_ok());
+        assert!(acl.is_bypass_role("root"));
+        assert!(!acl.is_bypass_role("admin"));
+
+        // allowed past the global deny on announcements and the explicit deny on the role
+        assert!(acl.is_allowed(Some("root"), Some("anouncement"), Some("archive")));
+        assert!(acl.is_allowed(Some("root"), Some("news"), Some("view")));
+        assert!(acl.decide(Some("root"), None, None).bypass);
+        assert!(!acl.decide(Some("admin"), None, None).bypass);
+
+        // admin is allowed all, but an explicit deny still overrides
+        assert!(acl.is_denied(Some("admin"), Some("anouncement"), Some("archive")));
+
+        // descendants are evaluated as usual
+        assert!(acl.is_denied(Some("operator"), Some("news"), Some("view")));
+
+        assert_eq!(acl.set_bypass_role("unknown"), Err(Error::MissingRole(String::from("unknown"))));
+        assert_eq!(acl.unset_bypass_role("root"), Ok(true));
+        assert!(acl.is_denied(Some("root"), Some("news"), Some("view")));
+
+        acl.lock();
+        assert_eq!(acl.set_bypass_role("root"), Err(Error::Locked));
+    } // bypass
+
+    #[test]
+    fn meta() {
+        let mut acl = setup_acl();
+        let meta    = RuleMeta{
+            description: Some(String::from("staff may revise content")),
+            author:      Some(String::from("zorq")),
+            created_at:  Some(SystemTime::UNIX_EPOCH),
+            ticket:      Some(String::from("CR-42")),
+            provenance:  None,
+            message:     None,
+        }; // RuleMeta
+
+        assert!(acl.set_rule_meta(Some("staff"), None, Some("revise"), meta.clone()).is_ok());
+        assert_eq!(acl.get_rule_meta(Some("staff"), None, Some("revise")), Some(&meta));
+        assert_eq!(acl.get_rule_meta(Some("staff"), None, Some("edit")), None);
+
+        let decision = acl.decide(Some("editor"), None, Some("revise"));
+
+        assert_eq!(acl.get_decision_meta(&decision), Some(&meta));
+        assert_eq!(acl.rules().filter(|(_, _, meta)| meta.is_some()).count(), 1);
+        assert_eq!(acl.rules().count(), 9);
+
+        let res = acl.set_rule_meta(Some("guest"), None, Some("edit"), RuleMeta::default());
+
+        assert_eq!(res, Err(Error::MissingRule(String::from("guest→*: edit"))));
+
+        assert!(acl.set_rule_meta(Some("admin"), None, None, meta).is_ok());
+        assert_eq!(acl.revoke_all("admin"), Ok(true));
+        assert_eq!(acl.get_rule_meta(Some("admin"), None, None), None);
+    } // meta
+
+    #[test]
+    fn provenance() {
+        let mut acl    = setup_acl();
+        let provenance = Provenance{
+            file:     String::from("policy.json"),
+            line:     Some(12),
+            position: Some(3),
+            batch:    Some(String::from("7")),
+        }; // Provenance
+        let meta       = RuleMeta{provenance: Some(provenance.clone()), ..RuleMeta::default()};
+
+        assert!(acl.set_rule_meta(Some("guest"), None, Some("view"), meta).is_ok());
+        assert_eq!(acl.get_rule_provenance(Some("guest"), None, Some("view")), Some(&provenance));
+        assert_eq!(acl.get_rule_provenance(Some("staff"), None, Some("edit")), None);
+        assert_eq!(provenance.to_string(), "policy.json:12:3 (batch 7)");
+    } // provenance
+
+    #[test]
+    fn message() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+        assert!(acl.set_rule_meta(None, Some("anouncement"), Some("archive"), RuleMeta{ticket: Some(String::from("CR-7")), ..RuleMeta::default()}).is_ok());
+        assert!(acl.deny_with_message(None, Some("anouncement"), Some("archive"), "announcements auto-expire; archiving is disabled").is_ok());
+        assert!(acl.deny_with_message(None, None, None, "ask an administrator for access").is_ok());
+
+        let decision = acl.decide(Some("editor"), Some("anouncement"), Some("archive"));
+
+        assert_eq!(acl.get_denial_message(&decision), Some("announcements auto-expire; archiving is disabled"));
+        assert_eq!(acl.get_decision_meta(&decision).and_then(|meta| meta.ticket.as_deref()), Some("CR-7"));
+        assert_eq!(acl.get_denial_message(&acl.decide(Some("guest"), None, Some("edit"))), Some("ask an administrator for access"));
+        assert_eq!(acl.get_denial_message(&acl.decide(Some("editor"), Some("latest"), Some("archive"))), None);
+        assert!(acl.is_denied(None, None, None));
+        assert!(acl.deny_with_message(Some("unknown"), None, None, "never").is_err());
+    } // message
+
+    #[test]
+    fn default_role() {
+        let mut acl = setup_acl();
+        let etag    = acl.etag();
+
+        assert!(acl.is_denied_anonymous(Some("latest"), Some("view")));
+        assert!(acl.set_default_role("guest").is_ok());
+        assert_eq!(acl.default_role(), Some("guest"));
+        assert_ne!(acl.etag(), etag);
+        assert!(acl.is_allowed_anonymous(Some("latest"), Some("view")));
+        assert!(acl.is_allowed(None, Some("latest"), Some("view")));
+        assert_eq!(acl.decide(None, Some("latest"), Some("view")).query.role, Some("guest"));
+        assert!(acl.is_denied_anonymous(Some("latest"), Some("edit")));
+
+        acl.lock();
+        assert!(acl.is_allowed_anonymous(Some("latest"), Some("view")));
+        assert_eq!(acl.unset_default_role(), Err(Error::Locked));
+        acl.unlock();
+
+        assert_eq!(acl.set_default_role("unknown"), Err(Error::MissingRole(String::from("unknown"))));
+        assert!(acl.set_default_role("staff").is_ok());
+        assert!(acl.set_default_role("guest").is_ok());
+        assert_eq!(acl.unset_default_role(), Ok(true));
+        assert_eq!(acl.unset_default_role(), Ok(false));
+        assert_eq!(acl.etag(), etag);
+        assert!(acl.is_denied_anonymous(Some("latest"), Some("view")));
+    } // default_role
+
+    #[test]
+    fn ordered() {
+        let mut acl      = setup_acl();
+        let mut reversed = Acl::new();
+
+        extend_acl(&mut acl);
+        for (query, rule, _) in acl.rules().collect::<Vec<_>>().into_iter().rev() {
+            reversed.insert_rule(*query, *rule);
+        } // for
+        assert_eq!(format!("{:?}", acl), format!("{:?}", reversed));
+        assert_eq!(acl.rules().next().map(|(query, _, _)| *query), Some(Query::ALL));
+        assert!(acl.rules().zip(acl.rules().skip(1)).all(|((a, _, _), (b, _, _))| a < b));
+
+        // resource prefixes skip news itself, which has no rules
+        let privileges: Vec<_> = acl.rules_by_resource_prefix("news").map(|(query, _, _)| query.privilege).collect();
+
+        assert_eq!(privileges, vec![Some("archive"), Some("publish")]);
+        assert_eq!(acl.rules_by_resource_prefix("").count(), 6);
+        assert_eq!(acl.rules_by_resource_prefix("unknown").count(), 0);
+
+        let roles: Vec<_> = acl.rules_by_role_prefix("ed").map(|(query, _, _)| query.role).collect();
+
+        assert_eq!(roles, vec![Some("editor"); 3]);
+        assert_eq!(acl.rules_by_role_prefix("").count(), 13);
+        assert_eq!(acl.remove_allow(Some("editor"), None, None), Ok(3));
+        assert_eq!(acl.rules_by_role_prefix("ed").count(), 0);
+    } // ordered
+
+    #[test]
+    fn scratch() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+        assert!(acl.is_denied(Some("marketing"), Some("latest"), Some("revise")));
+
+        let buffer = acl.scratch.borrow().as_ptr();
+
+        // the lineage buffer is reused by later queries
+        assert_eq!(acl.scratch.borrow()[..], ["marketing", "staff", "guest"]);
+        assert!(acl.is_allowed(Some("editor"), Some("anouncement"), Some("publish")));
+        assert!(acl.is_denied(Some("guest"), Some("newsletter"), Some("publish")));
+        assert_eq!(acl.scratch.borrow().as_ptr(), buffer);
+
+        for name in ["latest", "anouncement", "newsletter", "unknown"] {
+            assert_eq!(acl.iter_resource_lineage(name).collect::<Vec<_>>(), acl.get_resource_lineage(name));
+        } // for
+    } // scratch
+
+    #[test]
+    fn lineage_iterators() {
+        let mut acl = setup_acl();
+
+        extend_acl(&mut acl);
+        assert!(acl.add_role("writer", vec!["guest", "marketing", "editor"]).is_ok());
+        for order in [ParentOrder::Lifo, ParentOrder::Fifo, ParentOrder::DenyFirst] {
+            acl.set_parent_order(order);
+            for name in ["writer", "marketing", "admin", "unknown"] {
+                assert_eq!(acl.iter_role_lineage(name).collect::<Vec<_>>(), acl.get_role_lineage(name));
+            } // for
+        } // for
+        assert_eq!(acl.iter_role_lineage("writer").nth(1), Some("guest"));
+        assert_eq!(acl.iter_resource_lineage("latest").collect::<Vec<_>>(), vec!["latest", "news"]);
+        assert_eq!(acl.iter_resource_lineage("unknown").next(), None);
+    } // lineage_iterators
+
 } // mod tests
\ No newline at end of file
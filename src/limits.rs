@@ -0,0 +1,219 @@
+//! Limits for untrusted policies.
+//!
+//! Services which let their customers define policies may bound the size of an `Acl`, so a
+//! malicious or buggy policy can't exhaust memory. The limits are enforced whenever roles,
+//! resources and rules are added, and by the policy loader before any name is borrowed, see
+//! `PolicyLoader::set_limits`. All limits are disabled by default. Setting limits doesn't check
+//! what is already defined.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Acl, Error};
+//! # use zorq_acl::limits::Limits;
+//! let mut acl = Acl::new();
+//!
+//! acl.set_limits(Limits{max_roles: Some(1), max_depth: Some(3), ..Limits::default()});
+//! acl.add_role("guest", vec![]).unwrap();
+//! assert_eq!(acl.add_role("staff", vec!["guest"]), Err(Error::TooManyRoles(1)));
+//! ```
+
+use crate::{Acl, Error, Query};
+use std::collections::{HashMap, HashSet};
+
+
+// Limits /////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Bounds the size of an `Acl`. None disables a limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// the maximum number of roles
+    pub max_roles:       Option<usize>,
+    /// the maximum number of resources
+    pub max_resources:   Option<usize>,
+    /// the maximum number of rules besides the catch-all rule, including the rules of disabled
+    /// groups and inactive environments
+    pub max_rules:       Option<usize>,
+    /// the maximum number of ancestors of a role or resource along the longest path
+    pub max_depth:       Option<usize>,
+    /// the maximum number of parents of a role
+    pub max_parents:     Option<usize>,
+    /// the maximum number of roles in the lineage of a role, counting ancestors shared by
+    /// diamond inheritance once, see module `hierarchy`
+    pub max_lineage:     Option<usize>,
+    /// the maximum length of role, resource and privilege names in bytes
+    pub max_name_length: Option<usize>,
+} // struct Limits
+
+impl Limits {
+
+    /// Returns an error if a name is longer than allowed.
+    pub(crate) fn check_name(&self, name: &str) -> Result<(), Error> {
+        match self.max_name_length {
+            Some(max) if name.len() > max => Err(Error::NameTooLong(name.len())),
+            _                             => Ok(()),
+        } // match
+    } // check_name
+
+    /// Returns an error if count exceeds max.
+    pub(crate) fn check_count(max: Option<usize>, count: usize, error: fn(usize) -> Error) -> Result<(), Error> {
+        match max {
+            Some(max) if count > max => Err(error(max)),
+            _                        => Ok(()),
+        } // match
+    } // check_count
+
+} // impl Limits
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Sets the limits enforced by further mutations.
+    #[inline]
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    } // set_limits
+
+    /// Returns the limits enforced by mutations.
+    #[inline]
+    pub fn limits(&self) -> Limits {
+        self.limits
+    } // limits
+
+    /// Returns an error if adding role with parents exceeds a limit.
+    pub(crate) fn check_role_limits(&self, name: &str, parents: &[&'static str]) -> Result<(), Error> {
+        fn depth(acl: &Acl, name: &'static str, depths: &mut HashMap<&'static str, usize>) -> usize {
+            if let Some(depth) = depths.get(name) {
+                return *depth;
+            } // if
+
+            let mut max = 0;
+
+            for parent in &acl.roles[name] {
+                max = max.max(depth(acl, parent, depths) + 1);
+            } // for
+            depths.insert(name, max);
+            max
+        } // depth
+
+        self.limits.check_name(name)?;
+        Limits::check_count(self.limits.max_roles, self.roles.len() + 1, Error::TooManyRoles)?;
+        Limits::check_count(self.limits.max_parents, parents.len(), Error::TooManyParents)?;
+        if let Some(max) = self.limits.max_lineage {
+            let lineage: HashSet<&'static str> = parents.iter().flat_map(|parent| self.iter_role_lineage(parent)).collect();
+
+            if lineage.len() + 1 > max {
+                return Err(Error::TooDeep(String::from(name)));
+            } // if
+        } // if
+        if let Some(max) = self.limits.max_depth {
+            let mut depths = HashMap::new();
+
+            if parents.iter().any(|parent| depth(self, parent, &mut depths) + 1 > max) {
+                return Err(Error::TooDeep(String::from(name)));
+            } // if
+        } // if
+        Ok(())
+    } // check_role_limits
+
+    /// Returns an error if adding resource with parent exceeds a limit.
+    pub(crate) fn check_resource_limits(&self, name: &str, parent: Option<&'static str>) -> Result<(), Error> {
+        self.limits.check_name(name)?;
+        Limits::check_count(self.limits.max_resources, self.resources.len() + 1, Error::TooManyResources)?;
+        if let (Some(max), Some(parent)) = (self.limits.max_depth, parent) {
+            if self.iter_resource_lineage(parent).count() > max {
+                return Err(Error::TooDeep(String::from(name)));
+            } // if
+        } // if
+        Ok(())
+    } // check_resource_limits
+
+    /// Returns an error if setting a rule for query exceeds a limit.
+    #[inline]
+    pub(crate) fn check_rule_limits(&self, query: &Query) -> Result<(), Error> {
+        self.check_rules_limits(std::slice::from_ref(query), 0)
+    } // check_rule_limits
+
+    /// Returns an error if setting rules for distinct queries exceeds a limit once the removed
+    /// number of other rules is removed. Changes of several rules are checked before any is made.
+    pub(crate) fn check_rules_limits(&self, queries: &[Query], removed: usize) -> Result<(), Error> {
+        for name in queries.iter().filter_map(|query| query.privilege) {
+            self.limits.check_name(name)?;
+        } // for
+
+        let added = queries.iter().filter(|query| !self.rules.contains_key(query)).count();
+
+        if self.limits.max_rules.is_none() || added == 0 {
+            return Ok(());
+        } // if
+
+        let aside = self.groups.values().chain(self.environments.values())
+            .map(|group| group.aside.values().filter(|rule| rule.is_some()).count())
+            .sum::<usize>();
+
+        // the catch-all rule isn't counted, the new rules are
+        Limits::check_count(self.limits.max_rules, (self.rules.len() + aside + added).saturating_sub(1 + removed), Error::TooManyRules)
+    } // check_rules_limits
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn limits() {
+        let mut acl = Acl::new();
+
+        acl.set_limits(Limits{
+            max_roles:       Some(3),
+            max_resources:   Some(3),
+            max_rules:       Some(3),
+            max_depth:       Some(1),
+            max_parents:     Some(2),
+            max_lineage:     Some(3),
+            max_name_length: Some(8),
+        }); // Limits
+        assert_eq!(acl.limits().max_rules, Some(3));
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert_eq!(acl.add_role("editor", vec!["staff"]), Err(Error::TooDeep(String::from("editor"))));
+        assert_eq!(acl.add_role("marketing", vec![]), Err(Error::NameTooLong(9)));
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert_eq!(acl.add_role("admin", vec![]), Err(Error::TooManyRoles(3)));
+        acl.set_limits(Limits{max_roles: Some(8), max_depth: Some(2), ..acl.limits()});
+        assert_eq!(acl.add_role("admin", vec!["guest", "staff", "root"]), Err(Error::TooManyParents(2)));
+        assert_eq!(acl.add_role("admin", vec!["staff", "root"]), Err(Error::TooDeep(String::from("admin"))));
+        assert!(acl.add_role("admin", vec!["guest", "root"]).is_ok());
+        acl.set_limits(Limits{max_roles: Some(3), max_depth: Some(1), ..acl.limits()});
+
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert_eq!(acl.add_resource("today", Some("latest")), Err(Error::TooDeep(String::from("today"))));
+        assert!(acl.add_resource("archive", None).is_ok());
+        assert_eq!(acl.add_resource("drafts", None), Err(Error::TooManyResources(3)));
+
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert_eq!(acl.allow(Some("staff"), None, Some("moderate")), Ok(()));
+        assert_eq!(acl.allow(Some("staff"), None, Some("republish")), Err(Error::NameTooLong(9)));
+        assert!(acl.group("beta", |group| group.allow(Some("staff"), Some("news"), None)).is_ok());
+        assert_eq!(acl.disable_group("beta"), Ok(true));
+        assert_eq!(acl.allow_in("dev", Some("root"), None, None), Err(Error::TooManyRules(3)));
+
+        // replacing a rule and the catch-all rule don't count
+        assert!(acl.deny(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.remove_deny(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow_all("root").is_ok());
+        assert_eq!(acl.allow_all("guest"), Err(Error::TooManyRules(3)));
+    } // limits
+
+} // mod tests
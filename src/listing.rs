@@ -0,0 +1,152 @@
+//! Filtered and paginated rule listings.
+//!
+//! `find_rules` selects rules by role, resource subtree, privilege prefix and access and returns
+//! one page of them together with the total number of matching rules, e.g. for an admin UI over a
+//! big policy. Rules are ordered by role, resource and privilege, wildcards first, so pages are
+//! stable as long as the policy doesn't change.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::listing::RuleFilter;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.add_resource("latest", Some("news")).unwrap();
+//! acl.allow(Some("staff"), Some("news"), Some("news.view")).unwrap();
+//! acl.allow(Some("staff"), Some("latest"), Some("news.edit")).unwrap();
+//! acl.deny(Some("staff"), None, Some("delete")).unwrap();
+//!
+//! let page = acl.find_rules(&RuleFilter{resource: Some("news"), privilege_prefix: Some(String::from("news.")), limit: Some(1), ..RuleFilter::default()});
+//!
+//! assert_eq!(page.total, 2);
+//! assert_eq!(page.rules[0].0.to_string(), "staff→latest: news.edit");
+//! ```
+
+use crate::{Access, Acl, Query, Role, Rule, RuleMeta};
+use log::trace;
+
+
+// RuleFilter /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Selects rules and a page of them. Unset criteria match all rules.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RuleFilter {
+    /// rules defined for this role
+    pub role:             Role,
+    /// rules defined for this resource or its descendants
+    pub resource:         Option<&'static str>,
+    /// rules for privileges starting with this prefix
+    pub privilege_prefix: Option<String>,
+    /// rules granting this access
+    pub access:           Option<Access>,
+    /// the number of matching rules skipped
+    pub offset:           usize,
+    /// the maximum number of rules returned
+    pub limit:            Option<usize>,
+} // struct RuleFilter
+
+impl RuleFilter {
+
+    /// Returns true if the rule for query matches the criteria, where the resource lies within the
+    /// subtree if its lineage contains the filtered resource.
+    fn matches(&self, acl: &Acl, query: &Query, rule: &Rule) -> bool {
+        (self.role.is_none() || query.role == self.role)
+            && self.access.map(|access| rule.acc == access).unwrap_or(true)
+            && self.privilege_prefix.as_ref()
+                .map(|prefix| query.privilege.map(|privilege| privilege.starts_with(prefix.as_str())).unwrap_or(false))
+                .unwrap_or(true)
+            && self.resource
+                .map(|subtree| query.resource.map(|name| acl.get_resource_lineage(name).contains(&subtree)).unwrap_or(false))
+                .unwrap_or(true)
+    } // matches
+
+} // impl RuleFilter
+
+
+// RulePage ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A page of rules matching a `RuleFilter`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RulePage<'a> {
+    /// the number of matching rules on all pages
+    pub total: usize,
+    /// the rules of the page with their metadata
+    pub rules: Vec<(&'a Query, &'a Rule, Option<&'a RuleMeta>)>,
+} // struct RulePage
+
+impl Acl {
+
+    /// Returns the page of rules selected by filter, including the catch-all rule if it matches.
+    pub fn find_rules(&self, filter: &RuleFilter) -> RulePage<'_> {
+        trace!("finding rules by {:?}", filter);
+        let mut rules: Vec<(&Query, &Rule, Option<&RuleMeta>)> = self.rules()
+            .filter(|(query, rule, _)| filter.matches(self, query, rule))
+            .collect();
+
+        rules.sort_by_key(|(query, _, _)| (query.role, query.resource, query.privilege));
+
+        let total = rules.len();
+        let rules = rules.into_iter()
+            .skip(filter.offset)
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        RulePage{total, rules}
+    } // find_rules
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn find_rules() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.add_resource("blog", None).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("latest"), Some("edit.title")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("delete")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("blog"), None).is_ok());
+
+        let listed = |filter: RuleFilter| {
+            let page = acl.find_rules(&filter);
+
+            (page.total, page.rules.iter().map(|(query, rule, _)| format!("{} {}", rule, query)).collect::<Vec<_>>())
+        };
+
+        assert_eq!(listed(RuleFilter::default()).0, 6);
+        assert_eq!(listed(RuleFilter::default()).1[0], "DENY *→*: *");
+        assert_eq!(listed(RuleFilter{role: Some("staff"), access: Some(Access::Allow), ..RuleFilter::default()}), (3, vec![
+            String::from("ALLOW staff→blog: *"),
+            String::from("ALLOW staff→latest: edit.title"),
+            String::from("ALLOW staff→news: edit"),
+        ]));
+        assert_eq!(listed(RuleFilter{resource: Some("news"), ..RuleFilter::default()}).0, 3);
+        assert_eq!(listed(RuleFilter{privilege_prefix: Some(String::from("edit")), ..RuleFilter::default()}).0, 2);
+
+        // pages
+        let filter = RuleFilter{role: Some("staff"), limit: Some(3), ..RuleFilter::default()};
+
+        assert_eq!(listed(RuleFilter{offset: 0, ..filter.clone()}).1.len(), 3);
+        assert_eq!(listed(RuleFilter{offset: 3, ..filter.clone()}), (4, vec![String::from("ALLOW staff→news: edit")]));
+        assert_eq!(listed(RuleFilter{offset: 9, ..filter}), (4, vec![]));
+    } // find_rules
+
+} // mod tests
@@ -0,0 +1,212 @@
+//! Delegated administration of the policy.
+//!
+//! An `AdminAcl` models the management of the policy as permissions within the policy itself:
+//! every mutation names the acting role, which must be allowed the privilege `manage` on the
+//! targeted resource. Since rules are inherited along the resource tree, a role allowed to manage
+//! a resource manages its whole subtree, e.g. a team admin manages the branch of the team. The
+//! wildcard resource stands for the whole policy, so only roles allowed to manage all resources
+//! may add top-level resources or rules for all resources.
+//!
+//! Roles are assigned to a resource subtree when added: only roles allowed to manage the subtree
+//! may add the role or define rules for it. Roles added to the wildcard resource, and roles of
+//! the wrapped `Acl`, are managed by the roles allowed to manage all resources.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::manage::{AdminAcl, MANAGE};
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("team-lead", vec![]).unwrap();
+//! acl.add_resource("team", None).unwrap();
+//! acl.allow(Some("team-lead"), Some("team"), Some(MANAGE)).unwrap();
+//!
+//! let mut admin = AdminAcl::new(acl);
+//!
+//! admin.add_resource("team-lead", "wiki", Some("team")).unwrap();
+//! admin.add_role("team-lead", "team-member", vec![], Some("team")).unwrap();
+//! admin.allow("team-lead", Some("team-member"), Some("wiki"), Some("edit")).unwrap();
+//! assert!(admin.add_resource("team-lead", "billing", None).is_err());
+//!
+//! assert!(admin.acl().is_allowed(Some("team-member"), Some("wiki"), Some("edit")));
+//! ```
+
+use crate::{Access, Acl, Error, Privilege, Resource, Role};
+use log::{trace, warn};
+use std::collections::HashMap;
+
+/// The privilege to manage the policy of a resource subtree.
+pub const MANAGE: &str = "manage";
+
+
+// AdminAcl ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Wraps an `Acl` whose mutations are authorized by the `Acl` itself, see module `manage`.
+pub struct AdminAcl {
+    acl:    Acl,
+    scopes: HashMap<&'static str, &'static str>,
+} // struct AdminAcl
+
+impl AdminAcl {
+
+    /// Wraps acl. Its roles are managed by the roles allowed to manage all resources.
+    pub fn new(acl: Acl) -> Self {
+        AdminAcl{acl, scopes: HashMap::new()}
+    } // new
+
+    /// Returns the wrapped `Acl`, e.g. to query it.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Unwraps the `Acl`.
+    #[inline]
+    pub fn into_inner(self) -> Acl {
+        self.acl
+    } // into_inner
+
+    /// Returns the resource subtree role is assigned to, or None if it is managed by the roles
+    /// allowed to manage all resources.
+    #[inline]
+    pub fn role_scope(&self, role: &str) -> Resource {
+        self.scopes.get(role).copied()
+    } // role_scope
+
+    /// Returns true if actor may manage the subtree of resource, or the whole policy if resource is
+    /// None.
+    pub fn may_manage(&self, actor: &'static str, resource: Resource) -> bool {
+        self.acl.is_allowed(Some(actor), resource, Some(MANAGE))
+    } // may_manage
+
+    /// Adds a resource below parent. actor must manage parent.
+    pub fn add_resource(&mut self, actor: &'static str, name: &'static str, parent: Option<&'static str>) -> Result<(), Error> {
+        trace!("{} adding resource {} below {:?}", actor, name, parent);
+        self.check(actor, parent)?;
+        self.acl.add_resource(name, parent)
+    } // add_resource
+
+    /// Adds a role assigned to the subtree of scope, see module `manage`. actor must manage scope.
+    pub fn add_role(&mut self, actor: &'static str, name: &'static str, parents: Vec<&'static str>, scope: Resource) -> Result<(), Error> {
+        trace!("{} adding role {} to {:?}", actor, name, scope);
+        self.check(actor, scope)?;
+        self.acl.add_role(name, parents)?;
+        if let Some(scope) = scope {
+            self.scopes.insert(name, scope);
+        } // if
+        Ok(())
+    } // add_role
+
+    /// Sets a rule like `Acl::set_rule`. actor must manage resource and the scope of role, or the
+    /// whole policy for rules of all roles.
+    pub fn set_rule(&mut self, actor: &'static str, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
+        trace!("{} setting rule for {:?} on {:?} with {:?} privilege", actor, role, resource, privilege);
+        self.check_rule(actor, role, resource)?;
+        self.acl.set_rule(role, resource, privilege, access)
+    } // set_rule
+
+    /// Allows privilege for role on resource, see `set_rule`.
+    #[inline]
+    pub fn allow(&mut self, actor: &'static str, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(actor, role, resource, privilege, Access::Allow)
+    } // allow
+
+    /// Denies privilege for role on resource, see `set_rule`.
+    #[inline]
+    pub fn deny(&mut self, actor: &'static str, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(actor, role, resource, privilege, Access::Deny)
+    } // deny
+
+    /// Removes allow rules like `Acl::remove_allow`. actor must manage resource and the scope of
+    /// role, None matches all and requires managing the whole policy.
+    pub fn remove_allow(&mut self, actor: &'static str, role: Role, resource: Resource, privilege: Privilege) -> Result<usize, Error> {
+        self.check_rule(actor, role, resource)?;
+        self.acl.remove_allow(role, resource, privilege)
+    } // remove_allow
+
+    /// Removes deny rules like `Acl::remove_deny`, see `remove_allow`.
+    pub fn remove_deny(&mut self, actor: &'static str, role: Role, resource: Resource, privilege: Privilege) -> Result<usize, Error> {
+        self.check_rule(actor, role, resource)?;
+        self.acl.remove_deny(role, resource, privilege)
+    } // remove_deny
+
+    /// Returns an error unless actor may manage resource and the scope of role.
+    fn check_rule(&self, actor: &'static str, role: Role, resource: Resource) -> Result<(), Error> {
+        self.check(actor, resource)?;
+        match role {
+            Some(role) => self.check(actor, self.role_scope(role)),
+            None       => self.check(actor, None),
+        } // match
+    } // check_rule
+
+    /// Returns an error unless actor may manage resource.
+    fn check(&self, actor: &'static str, resource: Resource) -> Result<(), Error> {
+        if self.may_manage(actor, resource) {
+            return Ok(());
+        } // if
+        warn!("{} may not manage {}", actor, resource.unwrap_or("*"));
+        Err(Error::NotPermitted(format!("{} may not manage {}", actor, resource.unwrap_or("*"))))
+    } // check
+
+} // impl AdminAcl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn denied<T>(actor: &str, resource: &str) -> Result<T, Error> {
+        Err(Error::NotPermitted(format!("{} may not manage {}", actor, resource)))
+    } // denied
+
+    #[test]
+    fn manage() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_role("lead", vec![]).is_ok());
+        assert!(acl.add_resource("teams", None).is_ok());
+        assert!(acl.add_resource("red", Some("teams")).is_ok());
+        assert!(acl.add_resource("blue", Some("teams")).is_ok());
+        assert!(acl.allow(Some("root"), None, Some(MANAGE)).is_ok());
+        assert!(acl.allow(Some("lead"), Some("red"), Some(MANAGE)).is_ok());
+
+        let mut admin = AdminAcl::new(acl);
+
+        // leads manage the subtree of their team only
+        assert!(admin.add_resource("lead", "wiki", Some("red")).is_ok());
+        assert!(admin.may_manage("lead", Some("wiki")));
+        assert_eq!(admin.add_resource("lead", "board", Some("blue")), denied("lead", "blue"));
+        assert_eq!(admin.add_resource("lead", "board", None), denied("lead", "*"));
+        assert!(admin.add_resource("root", "board", Some("blue")).is_ok());
+
+        assert!(admin.add_role("lead", "member", vec![], Some("red")).is_ok());
+        assert_eq!(admin.role_scope("member"), Some("red"));
+        assert_eq!(admin.add_role("lead", "guest", vec![], None), denied("lead", "*"));
+        assert!(admin.add_role("root", "guest", vec![], None).is_ok());
+
+        assert!(admin.allow("lead", Some("member"), Some("wiki"), Some("edit")).is_ok());
+        assert!(admin.acl().is_allowed(Some("member"), Some("wiki"), Some("edit")));
+        assert_eq!(admin.allow("lead", Some("member"), Some("board"), Some("edit")), denied("lead", "board"));
+        assert_eq!(admin.allow("lead", Some("guest"), Some("wiki"), Some("view")), denied("lead", "*"));
+        assert_eq!(admin.allow("lead", None, Some("wiki"), Some("view")), denied("lead", "*"));
+        assert_eq!(admin.allow("guest", Some("member"), Some("wiki"), Some("view")), denied("guest", "wiki"));
+
+        // leads may delegate the management of their subtree
+        assert!(admin.allow("lead", Some("member"), Some("wiki"), Some(MANAGE)).is_ok());
+        assert!(admin.add_role("member", "intern", vec![], Some("wiki")).is_ok());
+        assert!(admin.deny("member", Some("intern"), Some("wiki"), Some("delete")).is_ok());
+        assert_eq!(admin.deny("member", Some("member"), Some("wiki"), Some("delete")), denied("member", "red"));
+        assert_eq!(admin.remove_deny("member", Some("intern"), Some("wiki"), Some("delete")), Ok(1));
+        assert_eq!(admin.remove_allow("lead", Some("member"), None, None), denied("lead", "*"));
+        assert_eq!(admin.remove_allow("root", Some("member"), None, None), Ok(2));
+        assert!(admin.into_inner().is_denied(Some("member"), Some("wiki"), Some("edit")));
+    } // manage
+
+} // mod tests
@@ -0,0 +1,300 @@
+//! Permission matrices as CSV.
+//!
+//! A permission matrix is a spreadsheet with a row per role and a column per resource and
+//! privilege, named `resource:privilege`. A cell holds `allow`, `deny` or nothing. The first row
+//! names the columns, the first column the roles. `*` is the wildcard for roles, resources and
+//! privileges.
+//!
+//! ```text
+//! role,news:view,news:edit,*:*
+//! guest,allow,,
+//! staff,,allow,
+//! root,,,allow
+//! ```
+//!
+//! Importing sets a rule for each filled cell. Roles and resources must be defined, blank cells
+//! leave rules untouched. The whole matrix is validated before any rule is set, so that all
+//! problems are reported at once with the line and column of the offending cell. With
+//! `import_csv_with` invalid cells may be skipped instead, see module `import`. Exporting writes
+//! the unconditional rules in effect, except the catch-all rule.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("staff", vec!["guest"]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! assert_eq!(acl.import_csv("role,news:view,news:edit\nguest,allow,\nstaff,,allow\n"), Ok(2));
+//!
+//! assert!(acl.is_allowed(Some("staff"), Some("news"), Some("view")));
+//! assert_eq!(acl.to_csv(), "role,news:edit,news:view\nguest,,allow\nstaff,allow,\n");
+//! ```
+
+use crate::{Access, Acl, Error, Privilege, Query, Resource, Role, Rule, SchemaError};
+use crate::import::{ErrorMode, ImportReport};
+use log::{trace, warn};
+use std::collections::{BTreeSet, HashMap};
+
+const WILDCARD: &str = "*";
+
+impl Acl {
+
+    /// Sets the rules of a CSV permission matrix. Returns the number of rules set. Returns an
+    /// error if the matrix is malformed, names undefined roles or resources, or a rule can't be
+    /// set, e.g. because the `Acl` is locked.
+    #[inline]
+    pub fn import_csv(&mut self, source: &str) -> Result<usize, Error> {
+        self.import_csv_with(source, ErrorMode::CollectAll).map(|report| report.imported)
+    } // import_csv
+
+    /// Like `import_csv`, but handles invalid cells, rows and columns by mode, see module `import`.
+    /// Skipped rows and columns are reported by their first cell.
+    pub fn import_csv_with(&mut self, source: &str, mode: ErrorMode) -> Result<ImportReport, Error> {
+        trace!("importing permission matrix with {:?}", mode);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+
+        let mut reader  = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(source.as_bytes());
+        let mut records = reader.records();
+        let header      = match records.next() {
+            Some(record) => record.map_err(|e| Error::Parse(e.to_string()))?,
+            None         => return Ok(ImportReport::default()),
+        }; // match
+        let mut errors  = vec![];
+        let mut leaked  = HashMap::new();
+        let mut columns = vec![];
+
+        for (i, cell) in header.iter().enumerate().skip(1) {
+            let path = format!("1:{}", i + 1);
+
+            match cell.trim().rsplit_once(':') {
+                Some((resource, privilege)) => match self.matrix_resource(resource) {
+                    Ok(resource) => columns.push(Some((resource, self.matrix_privilege(privilege, &mut leaked)))),
+                    Err(message) => {
+                        mode.reject(&mut errors, SchemaError::new(&path, &message))?;
+                        columns.push(None);
+                    }, // Err
+                }, // Some
+                None                        => {
+                    mode.reject(&mut errors, SchemaError::new(&path, &format!("expected \"resource:privilege\", found \"{}\"", cell)))?;
+                    columns.push(None);
+                }, // None
+            } // match
+        } // for
+
+        let mut rules = vec![];
+
+        for record in records {
+            let record = record.map_err(|e| Error::Parse(e.to_string()))?;
+            let line   = record.position().map(|position| position.line()).unwrap_or_default();
+            let role   = match self.matrix_role(record.get(0).unwrap_or_default()) {
+                Ok(role)     => role,
+                Err(message) => {
+                    mode.reject(&mut errors, SchemaError::new(&format!("{}:1", line), &message))?;
+                    continue;
+                }, // Err
+            }; // match
+
+            for (i, cell) in record.iter().enumerate().skip(1) {
+                let path   = format!("{}:{}", line, i + 1);
+                let access = match cell.trim().to_ascii_lowercase().as_str() {
+                    ""      => continue,
+                    "allow" => Access::Allow,
+                    "deny"  => Access::Deny,
+                    _       => {
+                        mode.reject(&mut errors, SchemaError::new(&path, &format!("expected \"allow\", \"deny\" or nothing, found \"{}\"", cell)))?;
+                        continue;
+                    }, // _
+                }; // match
+
+                match columns.get(i - 1) {
+                    Some(Some((resource, privilege))) => rules.push((path, role, *resource, *privilege, access)),
+                    Some(None)                        => (),
+                    None                              => mode.reject(&mut errors, SchemaError::new(&path, "cell without column"))?,
+                } // match
+            } // for
+        } // for
+        let mut report = ImportReport{imported: 0, skipped: mode.check(errors)?};
+
+        // rules rejected by the acl, e.g. for unregistered privileges, are skipped as well
+        for (path, role, resource, privilege, access) in &rules {
+            match self.set_rule(*role, *resource, *privilege, *access) {
+                Ok(())                                       => report.imported += 1,
+                Err(Error::Locked)                           => return Err(Error::Locked),
+                Err(error) if mode == ErrorMode::SkipInvalid => {
+                    warn!("skipping rule at {}: {}", path, error);
+                    report.skipped.push(SchemaError::new(path, &error.to_string()));
+                }, // Err
+                Err(error)                                   => return Err(error),
+            } // match
+        } // for
+        Ok(report)
+    } // import_csv_with
+
+    /// Exports the unconditional rules in effect as CSV permission matrix. Rows are ordered like
+    /// `roles` with the wildcard role first, columns by resource and privilege.
+    pub fn to_csv(&self) -> String {
+        let exported = |query: &Query, rule: &Rule| *query != Query::ALL && rule.condition().is_none();
+        let columns: BTreeSet<(Resource, Privilege)> = self.rules.iter()
+            .filter(|(query, rule)| exported(query, rule))
+            .map(|(query, _)| (query.resource, query.privilege))
+            .collect();
+        let mut roles: Vec<Role> = self.roles.keys().map(|name| Some(*name)).collect();
+
+        if self.rules.iter().any(|(query, rule)| query.role.is_none() && exported(query, rule)) {
+            roles.insert(0, None);
+        } // if
+
+        let name       = |name: Option<&str>| String::from(name.unwrap_or(WILDCARD));
+        let mut writer = csv::Writer::from_writer(vec![]);
+        let mut header = vec![String::from("role")];
+
+        header.extend(columns.iter().map(|(resource, privilege)| format!("{}:{}", name(*resource), name(*privilege))));
+        // writing into a vector doesn't fail
+        writer.write_record(&header).unwrap_or_default();
+        for role in roles {
+            let mut row = vec![name(role)];
+
+            for (resource, privilege) in &columns {
+                let query = Query{resource: *resource, role, privilege: *privilege};
+
+                row.push(String::from(match self.rules.get(&query).filter(|rule| exported(&query, rule)) {
+                    Some(rule) if rule.access() == Access::Allow => "allow",
+                    Some(_)                                      => "deny",
+                    None                                         => "",
+                })); // push
+            } // for
+            writer.write_record(&row).unwrap_or_default();
+        } // for
+
+        String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+    } // to_csv
+
+    /// Resolves the role of a row, which must be defined.
+    fn matrix_role(&self, name: &str) -> Result<Role, String> {
+        match name.trim() {
+            WILDCARD => Ok(None),
+            name     => self.roles.get_key_value(name).map(|(name, _)| Some(*name))
+                .ok_or_else(|| format!("unknown role \"{}\"", name)),
+        } // match
+    } // matrix_role
+
+    /// Resolves the resource of a column, which must be defined.
+    fn matrix_resource(&self, name: &str) -> Result<Resource, String> {
+        match name.trim() {
+            WILDCARD => Ok(None),
+            name     => self.resources.get_key_value(name).map(|(name, _)| Some(*name))
+                .ok_or_else(|| format!("unknown resource \"{}\"", name)),
+        } // match
+    } // matrix_resource
+
+    /// Resolves the privilege of a column. Names unknown to this `Acl` are leaked once.
+    fn matrix_privilege(&self, name: &str, leaked: &mut HashMap<String, &'static str>) -> Privilege {
+        let name = name.trim();
+
+        if name == WILDCARD {
+            return None;
+        } // if
+        let known = self.privileges.get(name).copied()
+            .or_else(|| self.rules.keys().filter_map(|query| query.privilege).find(|privilege| *privilege == name));
+
+        Some(known.unwrap_or_else(|| *leaked.entry(String::from(name))
+            .or_insert_with(|| Box::leak(String::from(name).into_boxed_str()))))
+    } // matrix_privilege
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    const MATRIX: &str = "role,news:view,latest:*,*:*\n*,,deny,\nguest,allow,,\nstaff,,Allow,\nroot,,, allow \n";
+
+    fn setup() -> Acl {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        acl
+    } // setup
+
+    #[test]
+    fn import() {
+        let mut acl = setup();
+
+        assert_eq!(acl.import_csv(MATRIX), Ok(4));
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("view")));
+        assert!(acl.is_denied (Some("guest"), Some("latest"), Some("view")));
+        assert!(acl.is_allowed(Some("root"), None, Some("delete")));
+        assert_eq!(acl.import_csv(""), Ok(0));
+
+        let errors: Vec<String> = match acl.import_csv("role,news:view,news,unknown:view\nguest,allow,,\nnobody,allow\nstaff,maybe,,,deny\n") {
+            Err(Error::Schema(errors)) => errors.iter().map(|e| e.to_string()).collect(),
+            other                      => panic!("unexpected result {:?}", other),
+        }; // match
+
+        assert_eq!(errors, vec![
+            "1:3: expected \"resource:privilege\", found \"news\"",
+            "1:4: unknown resource \"unknown\"",
+            "3:1: unknown role \"nobody\"",
+            "4:2: expected \"allow\", \"deny\" or nothing, found \"maybe\"",
+            "4:5: cell without column",
+        ]);
+        acl.lock();
+        assert_eq!(acl.import_csv(MATRIX), Err(Error::Locked));
+    } // import
+
+    #[test]
+    fn error_modes() {
+        let source  = "role,news:view,news,latest:edit\nguest,allow,,\nnobody,allow\nstaff,maybe,,allow\n";
+        let mut acl = setup();
+
+        assert_eq!(acl.import_csv_with(source, ErrorMode::FailFast),
+            Err(Error::Schema(vec![SchemaError::new("1:3", "expected \"resource:privilege\", found \"news\"")])));
+        assert!(matches!(acl.import_csv_with(source, ErrorMode::CollectAll), Err(Error::Schema(errors)) if errors.len() == 3));
+        assert!(acl.is_denied(Some("guest"), Some("news"), Some("view")));
+
+        let report = acl.import_csv_with(source, ErrorMode::SkipInvalid).unwrap();
+
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.skipped.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), vec!["1:3", "3:1", "4:2"]);
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("view")));
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+
+        // rules rejected by the acl are skipped
+        acl.add_privilege("view");
+        assert_eq!(acl.import_csv_with("role,news:view,news:edit\nguest,deny,allow\n", ErrorMode::SkipInvalid).map(|report| report.imported), Ok(1));
+        assert!(acl.is_denied(Some("guest"), Some("news"), Some("view")));
+    } // error_modes
+
+    #[test]
+    fn export() {
+        let mut acl = setup();
+
+        assert_eq!(acl.to_csv(), "role\nguest\nroot\nstaff\n");
+        assert!(acl.import_csv(MATRIX).is_ok());
+
+        let csv = acl.to_csv();
+
+        assert_eq!(csv, "role,*:*,latest:*,news:view\n*,,deny,\nguest,,,allow\nroot,allow,,\nstaff,,allow,\n");
+
+        let mut other = setup();
+
+        assert_eq!(other.import_csv(&csv), Ok(4));
+        assert_eq!(other.etag(), acl.etag());
+    } // export
+
+} // mod tests
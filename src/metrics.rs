@@ -0,0 +1,128 @@
+//! Production metrics.
+//!
+//! With the `metrics` feature decisions and policy sizes are emitted through the `metrics` facade,
+//! so any installed recorder, e.g. `metrics-exporter-prometheus`, exports them:
+//!
+//! | Metric                                  | Type      | Description                            |
+//! |-----------------------------------------|-----------|----------------------------------------|
+//! | `zorq_acl_decisions_total`              | counter   | decisions by `outcome`, allow or deny  |
+//! | `zorq_acl_decision_duration_seconds`    | histogram | evaluation latency of `decide`         |
+//! | `zorq_acl_cache_hits_total`             | counter   | cached decisions of a locked `Acl`     |
+//! | `zorq_acl_cache_misses_total`           | counter   | uncached decisions of a locked `Acl`   |
+//! | `zorq_acl_roles`                        | gauge     | number of roles                        |
+//! | `zorq_acl_resources`                    | gauge     | number of resources                    |
+//! | `zorq_acl_rules`                        | gauge     | number of rules                        |
+//!
+//! The cache hit ratio is `hits / (hits + misses)`. Policy sizes are recorded by `lock` and
+//! `record_policy_metrics`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.lock();
+//!
+//! // counted as denied decision and cache miss
+//! assert!(acl.is_denied(Some("guest"), None, Some("view")));
+//! ```
+
+use crate::{Acl, Decision};
+use std::time::Instant;
+
+/// The counter of decisions, labeled by `outcome`.
+pub const DECISIONS: &str = "zorq_acl_decisions_total";
+
+/// The histogram of evaluation latencies in seconds.
+pub const DECISION_DURATION: &str = "zorq_acl_decision_duration_seconds";
+
+/// The counter of decisions served by the cache.
+pub const CACHE_HITS: &str = "zorq_acl_cache_hits_total";
+
+/// The counter of decisions missing the cache.
+pub const CACHE_MISSES: &str = "zorq_acl_cache_misses_total";
+
+/// The gauge of defined roles.
+pub const ROLES: &str = "zorq_acl_roles";
+
+/// The gauge of defined resources.
+pub const RESOURCES: &str = "zorq_acl_resources";
+
+/// The gauge of defined rules including the catch-all rule.
+pub const RULES: &str = "zorq_acl_rules";
+
+/// Records decision evaluated since start.
+pub(crate) fn decision(decision: &Decision, start: Instant) {
+    let outcome = if decision.is_allowed() { "allow" } else { "deny" };
+
+    ::metrics::counter!(DECISIONS, "outcome" => outcome).increment(1);
+    ::metrics::histogram!(DECISION_DURATION).record(start.elapsed().as_secs_f64());
+} // decision
+
+/// Records a lookup of the cache.
+pub(crate) fn cache(hit: bool) {
+    ::metrics::counter!(if hit { CACHE_HITS } else { CACHE_MISSES }).increment(1);
+} // cache
+
+impl Acl {
+
+    /// Records the number of roles, resources and rules. Called by `lock`, call it after changing
+    /// an unlocked `Acl` to keep the gauges current.
+    pub fn record_policy_metrics(&self) {
+        ::metrics::gauge!(ROLES).set(self.roles.len() as f64);
+        ::metrics::gauge!(RESOURCES).set(self.resources.len() as f64);
+        ::metrics::gauge!(RULES).set(self.rules.len() as f64);
+    } // record_policy_metrics
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use metrics_util::MetricKind;
+    use test_env_log::test;
+
+    #[test]
+    fn metrics() {
+        let recorder  = DebuggingRecorder::new();
+        let snapshot  = recorder.snapshotter();
+        let mut acl   = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.deny(Some("guest"), None, None).is_ok());
+
+        ::metrics::with_local_recorder(&recorder, || {
+            acl.lock();
+            assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+            assert!(acl.is_denied(Some("guest"), None, Some("edit")));
+            assert!(acl.is_denied(Some("guest"), None, Some("edit")));
+        }); // with_local_recorder
+
+        let values: Vec<_> = snapshot.snapshot().into_vec().into_iter()
+            .map(|(key, _, _, value)| {
+                let labels: Vec<String> = key.key().labels().map(|label| format!("{}={}", label.key(), label.value())).collect();
+
+                (key.kind(), key.key().name().to_string(), labels, value)
+            }).collect();
+        let find = |name: &str, labels: &[&str]| values.iter()
+            .find(|(_, other, other_labels, _)| other == name && other_labels.iter().map(String::as_str).eq(labels.iter().copied()))
+            .map(|(_, _, _, value)| value);
+
+        assert_eq!(find(DECISIONS, &["outcome=allow"]), Some(&DebugValue::Counter(1)));
+        assert_eq!(find(DECISIONS, &["outcome=deny"]), Some(&DebugValue::Counter(2)));
+        assert_eq!(find(CACHE_HITS, &[]), Some(&DebugValue::Counter(1)));
+        assert_eq!(find(CACHE_MISSES, &[]), Some(&DebugValue::Counter(1)));
+        assert!(matches!(find(DECISION_DURATION, &[]), Some(DebugValue::Histogram(samples)) if samples.len() == 3));
+        assert!(matches!(find(RULES, &[]), Some(DebugValue::Gauge(rules)) if rules.into_inner() == 3.0));
+        assert!(values.iter().any(|(kind, name, _, _)| *kind == MetricKind::Gauge && name == ROLES));
+    } // metrics
+
+} // mod tests
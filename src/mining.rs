@@ -0,0 +1,221 @@
+//! Role mining from decision logs.
+//!
+//! `suggest_roles` analyzes a decision log written by `JsonLinesSink` offline. The allowed
+//! resources and privileges of each principal, i.e. the subject or the role if no subject has been
+//! recorded, form its permission set. A greedy heuristic repeatedly picks the set of permissions
+//! shared by several principals which saves the most rules if granted by a common role, and
+//! removes it from the principals holding it. A suggested role inherits from another one if all of
+//! its members are members of the other role as well.
+//!
+//! Candidates are the permission sets of the principals and their pairwise intersections, so the
+//! analysis is cubic in the number of principals. Sample large logs first.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::mining::suggest_roles;
+//! let log = r#"
+//! {"subject": "sally", "role": "user", "resource": "news", "privilege": "view", "decision": "allow"}
+//! {"subject": "sally", "role": "user", "resource": "news", "privilege": "edit", "decision": "allow"}
+//! {"subject": "bob",   "role": "user", "resource": "news", "privilege": "view", "decision": "allow"}
+//! {"subject": "bob",   "role": "user", "resource": "news", "privilege": "edit", "decision": "allow"}
+//! {"subject": "eve",   "role": "user", "resource": "news", "privilege": "view", "decision": "allow"}
+//! {"subject": "eve",   "role": "user", "resource": "news", "privilege": "edit", "decision": "allow"}
+//! {"subject": "bob",   "role": "user", "resource": "blog", "privilege": "view", "decision": "deny"}
+//! "#;
+//! let roles = suggest_roles(log.as_bytes()).unwrap();
+//!
+//! assert_eq!(roles[0].members, vec!["bob", "eve", "sally"]);
+//! assert_eq!(roles[0].permissions.len(), 2);
+//! ```
+
+use crate::audit::read_log;
+use crate::{Access, Error};
+use log::trace;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufRead;
+
+
+// Suggestions ////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A resource and privilege, None denotes a wildcard.
+pub type Permission = (Option<String>, Option<String>);
+
+/// Tunes the heuristic of `suggest_roles_with`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MiningOptions {
+    /// the minimum number of principals sharing a suggested role
+    pub min_members:     usize,
+    /// the minimum number of permissions of a suggested role
+    pub min_permissions: usize,
+    /// the maximum number of suggested roles
+    pub max_roles:       usize,
+} // struct MiningOptions
+
+impl Default for MiningOptions {
+
+    fn default() -> Self {
+        MiningOptions{min_members: 2, min_permissions: 2, max_roles: 32}
+    } // default
+
+} // impl Default for MiningOptions
+
+/// A candidate role.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoleSuggestion {
+    /// a generated name like `role-1`, in order of suggestion
+    pub name:        String,
+    /// the permissions granted by the role
+    pub permissions: Vec<Permission>,
+    /// the principals which should be given the role
+    pub members:     Vec<String>,
+    /// the names of suggested roles to inherit from
+    pub parents:     Vec<String>,
+} // struct RoleSuggestion
+
+/// Suggests roles with the default `MiningOptions`. Returns an error if the log is malformed.
+pub fn suggest_roles<R: BufRead>(log: R) -> Result<Vec<RoleSuggestion>, Error> {
+    suggest_roles_with(log, MiningOptions::default())
+} // suggest_roles
+
+/// Suggests roles from the allowed decisions of log, the most rules saved first. Returns an error
+/// if the log is malformed.
+pub fn suggest_roles_with<R: BufRead>(log: R, options: MiningOptions) -> Result<Vec<RoleSuggestion>, Error> {
+    let mut principals: BTreeMap<String, BTreeSet<Permission>> = BTreeMap::new();
+
+    for event in read_log(log) {
+        let (_, event, access) = event?;
+        let principal          = event["subject"].as_str().or_else(|| event["role"].as_str());
+        let string             = |key: &str| event[key].as_str().map(String::from);
+
+        if let (Access::Allow, Some(principal)) = (access, principal) {
+            principals.entry(String::from(principal)).or_default().insert((string("resource"), string("privilege")));
+        } // if
+    } // for
+    trace!("mining roles of {} principals", principals.len());
+
+    let mut suggestions: Vec<(BTreeSet<Permission>, Vec<String>)> = vec![];
+
+    while suggestions.len() < options.max_roles {
+        let (permissions, members) = match best_candidate(&principals, options) {
+            Some(best) => best,
+            None       => break,
+        }; // match
+
+        for member in &members {
+            if let Some(remaining) = principals.get_mut(member) {
+                remaining.retain(|permission| !permissions.contains(permission));
+            } // if
+        } // for
+        suggestions.push((permissions, members));
+    } // while
+
+    let names: Vec<String> = (1..=suggestions.len()).map(|i| format!("role-{}", i)).collect();
+
+    Ok(suggestions.iter().enumerate().map(|(i, (permissions, members))| {
+        // all members of the role are members of the parent
+        let parents = suggestions.iter().enumerate()
+            .filter(|(j, (_, others))| *j != i && others.len() > members.len() && members.iter().all(|member| others.contains(member)))
+            .map(|(j, _)| names[j].clone())
+            .collect();
+
+        RoleSuggestion{
+            name:        names[i].clone(),
+            permissions: permissions.iter().cloned().collect(),
+            members:     members.clone(),
+            parents,
+        } // RoleSuggestion
+    }).collect())
+} // suggest_roles_with
+
+/// Returns the permission set saving the most rules and the principals holding it.
+fn best_candidate(principals: &BTreeMap<String, BTreeSet<Permission>>, options: MiningOptions) -> Option<(BTreeSet<Permission>, Vec<String>)> {
+    let sets: Vec<&BTreeSet<Permission>> = principals.values().filter(|set| set.len() >= options.min_permissions).collect();
+    let mut candidates: BTreeSet<BTreeSet<Permission>> = sets.iter().map(|set| (*set).clone()).collect();
+
+    for (i, first) in sets.iter().enumerate() {
+        for second in &sets[i + 1..] {
+            let shared: BTreeSet<Permission> = first.intersection(second).cloned().collect();
+
+            if shared.len() >= options.min_permissions {
+                candidates.insert(shared);
+            } // if
+        } // for
+    } // for
+
+    let mut best: Option<(usize, BTreeSet<Permission>, Vec<String>)> = None;
+
+    for candidate in candidates {
+        let members: Vec<String> = principals.iter()
+            .filter(|(_, set)| candidate.is_subset(set))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        if members.len() < options.min_members {
+            continue;
+        } // if
+        // rules granted directly minus the rules and assignments of the role
+        let saved = (members.len() * candidate.len()).saturating_sub(members.len() + candidate.len());
+
+        if saved > 0 && best.as_ref().map(|(most, _, _)| saved > *most).unwrap_or(true) {
+            best = Some((saved, candidate, members));
+        } // if
+    } // for
+    best.map(|(_, candidate, members)| (candidate, members))
+} // best_candidate
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn event(subject: &str, resource: &str, privilege: &str) -> String {
+        format!(r#"{{"subject": "{}", "role": "user", "resource": "{}", "privilege": "{}", "decision": "allow"}}"#, subject, resource, privilege)
+    } // event
+
+    #[test]
+    fn suggest() {
+        let mut log = vec![];
+
+        // everybody reads, editors also write
+        for subject in &["ann", "bob", "eve", "joe", "kim", "lea", "max", "sam"] {
+            for resource in &["news", "blog", "wiki"] {
+                log.push(event(subject, resource, "view"));
+            } // for
+        } // for
+        for subject in &["ann", "bob", "eve"] {
+            for resource in &["news", "blog", "wiki"] {
+                log.push(event(subject, resource, "edit"));
+            } // for
+        } // for
+        log.push(event("joe", "wiki", "edit"));
+
+        let roles = suggest_roles(log.join("\n").as_bytes()).unwrap();
+
+        assert_eq!(roles.len(), 2);
+        assert_eq!(roles[0].name, "role-1");
+        assert_eq!(roles[0].members.len(), 8);
+        assert_eq!(roles[0].permissions, vec![
+            (Some(String::from("blog")), Some(String::from("view"))),
+            (Some(String::from("news")), Some(String::from("view"))),
+            (Some(String::from("wiki")), Some(String::from("view"))),
+        ]);
+        assert_eq!(roles[0].parents, Vec::<String>::new());
+        assert_eq!(roles[1].members, vec!["ann", "bob", "eve"]);
+        assert_eq!(roles[1].permissions.len(), 3);
+        assert_eq!(roles[1].parents, vec!["role-1"]);
+
+        // a single role at most
+        let options = MiningOptions{max_roles: 1, ..MiningOptions::default()};
+
+        assert_eq!(suggest_roles_with(log.join("\n").as_bytes(), options).unwrap().len(), 1);
+        assert!(suggest_roles("{}".as_bytes()).is_err());
+        assert_eq!(suggest_roles("".as_bytes()), Ok(vec![]));
+    } // suggest
+
+} // mod tests
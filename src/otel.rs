@@ -0,0 +1,153 @@
+//! OpenTelemetry tracing.
+//!
+//! With the `otel` feature every call of `decide` or `decide_for`, and thus of `is_allowed` and
+//! the like, is traced as span `zorq_acl.decide` by the globally installed tracer provider. The
+//! span is a child of the current span, so authorization shows up within the trace of the
+//! enclosing request. It carries these attributes:
+//!
+//! | Attribute                     | Description                                             |
+//! |-------------------------------|---------------------------------------------------------|
+//! | `enduser.id`                  | the subject, see `decide_for`                           |
+//! | `zorq_acl.role`               | the queried role, omitted for a wildcard                |
+//! | `zorq_acl.resource`           | the queried resource, omitted for a wildcard            |
+//! | `zorq_acl.privilege`          | the queried privilege, omitted for a wildcard           |
+//! | `zorq_acl.decision`           | `allow` or `deny`                                       |
+//! | `zorq_acl.matched`            | the deciding rule like `staff→*: edit`                  |
+//! | `zorq_acl.bypass`             | true if the role is a bypass role                       |
+//! | `zorq_acl.policy.fingerprint` | the fingerprint of the policy, see module `etag`        |
+//!
+//! Loading a policy document adds the event `zorq_acl.policy.loaded` with the number of roles,
+//! resources and rules and the fingerprint to the current span, or `zorq_acl.policy.rejected`
+//! with the number of problems if the document is invalid.
+
+use crate::{Decision, Privilege, Resource, Role};
+use opentelemetry::trace::{SpanKind, Tracer};
+use opentelemetry::{global, Context, KeyValue};
+
+pub use opentelemetry::global::BoxedSpan;
+
+#[cfg(feature = "json")]
+use crate::Acl;
+#[cfg(feature = "json")]
+use opentelemetry::trace::TraceContextExt;
+
+/// The name of the tracer and instrumentation scope.
+pub const TRACER: &str = "zorq-acl";
+
+/// The name of the decision span.
+pub const DECIDE: &str = "zorq_acl.decide";
+
+/// Starts the span of a decision as child of the current span.
+pub(crate) fn start(subject: Option<&str>, role: Role, resource: Resource, privilege: Privilege) -> BoxedSpan {
+    let tracer         = global::tracer(TRACER);
+    let mut attributes = vec![];
+
+    for (key, value) in &[("enduser.id", subject), ("zorq_acl.role", role), ("zorq_acl.resource", resource), ("zorq_acl.privilege", privilege)] {
+        if let Some(value) = value {
+            attributes.push(KeyValue::new(*key, value.to_string()));
+        } // if
+    } // for
+    tracer.span_builder(DECIDE)
+        .with_kind(SpanKind::Internal)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, &Context::current())
+} // start
+
+/// Ends the span of decision.
+pub(crate) fn end(mut span: BoxedSpan, decision: &Decision, fingerprint: u64) {
+    use opentelemetry::trace::Span;
+
+    span.set_attribute(KeyValue::new("zorq_acl.decision", if decision.is_allowed() { "allow" } else { "deny" }));
+    span.set_attribute(KeyValue::new("zorq_acl.matched", decision.matched.to_string()));
+    span.set_attribute(KeyValue::new("zorq_acl.bypass", decision.bypass));
+    span.set_attribute(KeyValue::new("zorq_acl.policy.fingerprint", format!("{:016x}", fingerprint)));
+    span.end();
+} // end
+
+/// Adds the event of a loaded policy to the current span.
+#[cfg(feature = "json")]
+pub(crate) fn policy_loaded(acl: &Acl) {
+    Context::current().span().add_event("zorq_acl.policy.loaded", vec![
+        KeyValue::new("zorq_acl.policy.roles", acl.roles.len() as i64),
+        KeyValue::new("zorq_acl.policy.resources", acl.resources.len() as i64),
+        KeyValue::new("zorq_acl.policy.rules", acl.rules.len() as i64),
+        KeyValue::new("zorq_acl.policy.fingerprint", format!("{:016x}", acl.fingerprint)),
+    ]); // add_event
+} // policy_loaded
+
+/// Adds the event of a rejected policy to the current span.
+#[cfg(feature = "json")]
+pub(crate) fn policy_rejected(problems: usize) {
+    Context::current().span().add_event("zorq_acl.policy.rejected", vec![
+        KeyValue::new("zorq_acl.policy.problems", problems as i64),
+    ]); // add_event
+} // policy_rejected
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Acl;
+    use opentelemetry::trace::{Span, SpanId, TraceContextExt};
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+    use test_env_log::test;
+
+    fn attribute(span: &SpanData, key: &str) -> Option<Value> {
+        span.attributes.iter().find(|kv| kv.key.as_str() == key).map(|kv| kv.value.clone())
+    } // attribute
+
+    #[test]
+    fn tracing() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let mut acl  = Acl::new();
+
+        global::set_tracer_provider(provider.clone());
+        assert!(acl.add_role("otel", vec![]).is_ok());
+        assert!(acl.allow(Some("otel"), None, Some("trace")).is_ok());
+
+        // decisions are children of the request span
+        let tracer  = global::tracer("test");
+        let request = tracer.start("request");
+        let parent  = request.span_context().span_id();
+        let cx      = Context::current_with_span(request);
+
+        {
+            let _guard = cx.clone().attach();
+
+            assert!(acl.is_allowed(Some("otel"), None, Some("trace")));
+            assert!(acl.decide_for("sally", Some("otel"), None, Some("export")).is_denied());
+            #[cfg(feature = "json")]
+            assert!(Acl::from_json(r#"{"roles": [{"name": "otel"}]}"#).is_ok());
+        } // guard
+        cx.span().end();
+
+        let spans: Vec<SpanData> = exporter.get_finished_spans().unwrap().into_iter()
+            .filter(|span| span.parent_span_id == parent || span.span_context.span_id() == parent)
+            .collect();
+        let decisions: Vec<&SpanData> = spans.iter().filter(|span| span.name == DECIDE).collect();
+
+        assert_eq!(decisions.len(), 2);
+        assert_ne!(parent, SpanId::INVALID);
+        assert_eq!(attribute(decisions[0], "zorq_acl.role"), Some(Value::from("otel")));
+        assert_eq!(attribute(decisions[0], "zorq_acl.resource"), None);
+        assert_eq!(attribute(decisions[0], "zorq_acl.decision"), Some(Value::from("allow")));
+        assert_eq!(attribute(decisions[0], "zorq_acl.matched"), Some(Value::from("otel→*: trace")));
+        assert_eq!(attribute(decisions[1], "enduser.id"), Some(Value::from("sally")));
+        assert_eq!(attribute(decisions[1], "zorq_acl.decision"), Some(Value::from("deny")));
+
+        #[cfg(feature = "json")]
+        {
+            let request = spans.iter().find(|span| span.name == "request").unwrap();
+
+            assert_eq!(request.events.events[0].name, "zorq_acl.policy.loaded");
+        } // cfg
+        let _ = provider.shutdown();
+    } // tracing
+
+} // mod tests
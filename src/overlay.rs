@@ -0,0 +1,175 @@
+//! Session scoped permission overlays.
+//!
+//! A `SessionOverlay` holds temporary grants and denies for a session, e.g. for a "sudo mode" or
+//! after step-up authentication. Overlay rules are evaluated before the rules of the base `Acl`,
+//! in the same order of precedence, and are discarded with the overlay. The base `Acl` is never
+//! modified.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::overlay::SessionOverlay;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.add_resource("settings", None).unwrap();
+//!
+//! let mut session = SessionOverlay::new(&acl);
+//!
+//! // step-up authentication succeeded
+//! session.allow(Some("staff"), Some("settings"), Some("edit")).unwrap();
+//! assert!(session.is_allowed(Some("staff"), Some("settings"), Some("edit")));
+//! assert!(acl.is_denied(Some("staff"), Some("settings"), Some("edit")));
+//! ```
+
+use crate::{Access, Acl, Decision, Error, Privilege, Query, Resource, Role, Rule};
+use log::trace;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+
+// SessionOverlay /////////////////////////////////////////////////////////////////////////////////
+
+
+/// Holds temporary rules evaluated before the rules of the base `Acl`.
+pub struct SessionOverlay<'a> {
+    acl:   &'a Acl,
+    rules: BTreeMap<Query, Rule>,
+} // struct SessionOverlay
+
+impl<'a> SessionOverlay<'a> {
+
+    /// Creates a new, empty `SessionOverlay` on top of acl.
+    pub fn new(acl: &'a Acl) -> Self {
+        SessionOverlay{acl, rules: BTreeMap::new()}
+    } // new
+
+    /// Returns the base `Acl`.
+    #[inline]
+    pub fn acl(&self) -> &'a Acl {
+        self.acl
+    } // acl
+
+    /// Sets a temporary rule. Like `Acl::set_rule` all roles and resources which are not None
+    /// must be defined in the base `Acl`, privileges must be registered if any are, and the
+    /// catch-all rule can't be overridden.
+    pub fn set_rule(&mut self, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
+        trace!("setting session rule for {:?} on {:?} with {:?} privilege", role, resource, privilege);
+        if let Some(name) = resource {
+            if !self.acl.has_resource(name) {
+                return Err(Error::MissingResource(String::from(name)));
+            } // if
+        } // if
+        if let Some(name) = role {
+            if !self.acl.has_role(name) {
+                return Err(Error::MissingRole(String::from(name)));
+            } // if
+        } // if
+        self.acl.check_privilege(resource, privilege)?;
+
+        let query = Query{resource, role, privilege};
+
+        if query != Query::ALL {
+            self.rules.insert(query, Rule{acc: access, cond: None});
+        } // if
+        Ok(())
+    } // set_rule
+
+    /// Temporarily allows privilege for role on resource.
+    #[inline]
+    pub fn allow(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(role, resource, privilege, Access::Allow)
+    } // allow
+
+    /// Temporarily denies privilege for role on resource.
+    #[inline]
+    pub fn deny(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(role, resource, privilege, Access::Deny)
+    } // deny
+
+    /// Removes the temporary rule for role on resource to privilege. Returns true if a rule has
+    /// been removed.
+    pub fn revoke(&mut self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.rules.remove(&Query{resource, role, privilege}).is_some()
+    } // revoke
+
+    /// Removes all temporary rules, e.g. when leaving the sudo mode.
+    pub fn clear(&mut self) {
+        self.rules.clear();
+    } // clear
+
+    /// Returns true if there are no temporary rules.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    } // is_empty
+
+    /// Returns the decision of the temporary rules, if one applies, and the decision of the base
+    /// `Acl` otherwise.
+    pub fn decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        let query = Query{resource, role, privilege};
+
+        if let Some((matched, rule)) = self.acl.query_precedence_in(&self.rules, role, resource, privilege, &Cell::new(false)) {
+            trace!("session rule {} matched {}", matched, query);
+            return Decision{query, matched: *matched, rule: *rule, bypass: false};
+        } // if
+        self.acl.decide(role, resource, privilege)
+    } // decide
+
+    /// Returns true if privilege is allowed for role on resource.
+    #[inline]
+    pub fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_allowed()
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource.
+    #[inline]
+    pub fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_denied()
+    } // is_denied
+
+} // impl SessionOverlay
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn overlay() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), None).is_ok());
+        acl.lock();
+
+        let mut session = SessionOverlay::new(&acl);
+
+        assert!(session.is_empty());
+        assert!(session.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+
+        // temporary rules inherit like regular rules and take precedence
+        assert!(session.deny(Some("guest"), Some("latest"), None).is_ok());
+        assert!(session.allow(Some("guest"), None, Some("publish")).is_ok());
+        assert!(session.is_denied (Some("staff"), Some("latest"), Some("edit")));
+        assert!(session.is_allowed(Some("staff"), Some("news"), Some("edit")));
+        assert!(session.is_allowed(Some("staff"), None, Some("publish")));
+        assert_eq!(session.decide(Some("staff"), Some("latest"), Some("view")).to_string(), "DENY staff→latest: view");
+
+        assert_eq!(session.allow(Some("root"), None, None), Err(Error::MissingRole(String::from("root"))));
+        assert!(session.revoke(Some("guest"), Some("latest"), None));
+        assert!(session.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+        session.clear();
+        assert!(session.is_denied(Some("staff"), None, Some("publish")));
+    } // overlay
+
+} // mod tests
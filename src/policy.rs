@@ -0,0 +1,1440 @@
+//! Loading of policy documents.
+//!
+//! A policy document declares roles, resources and rules. Roles and resources must be declared
+//! before they are referenced as parents. A `null` or missing role, resource or privilege of a rule
+//! is a wildcard. The document is validated as a whole before the `Acl` is built, so that all
+//! problems are reported at once with the path to the offending value.
+//!
+//! ```json
+//! {
+//!     "version": 1,
+//!     "roles": [
+//!         {"name": "guest"},
+//!         {"name": "staff", "parents": ["guest"]}
+//!     ],
+//!     "resources": [
+//!         {"name": "news"},
+//!         {"name": "latest", "parent": "news"}
+//!     ],
+//!     "rules": [
+//!         {"access": "allow", "role": "guest", "privilege": "view"},
+//!         {"access": "deny", "role": "staff", "resource": "latest", "privilege": "revise",
+//!          "description": "latest news are revised by editors", "author": "zorq", "ticket": "CR-42",
+//!          "message": "ask an editor to revise the latest news"}
+//!     ],
+//!     "bypass": ["root"],
+//!     "default_role": "guest"
+//! }
+//! ```
+//!
+//! A rule with a `condition` only applies if the assertion of that name holds, see module
+//! `condition`. A rule with an `environment` only applies in that environment, see module
+//! `environment`. Environment-scoped rules can't carry metadata. A rule with an integer
+//! `priority` overrides the order of precedence, see module `priority`. A resource may declare the
+//! default access of privileges for its subtree, e.g. `"defaults": {"view": "allow"}`, see module
+//! `defaults`, as well as a `kind` and string-valued `meta`, see module `kind`.
+//!
+//! The `version` of the document format is written by every export, see `SCHEMA_VERSION`.
+//! Documents without version predate the field and are read as version 1, the first version.
+//! Loading a document of a newer version returns `Error::SchemaVersion` instead of misreading it,
+//! and later versions will migrate documents of older versions when loaded. The binary and protobuf
+//! formats carry the version alike, see modules `binary` and `proto`.
+//!
+//! The `_with` variants of the loaders take an `ErrorMode`, see module `import`. Skipping invalid
+//! entries skips the roles, resources, rules, bypass roles and default role located by a problem,
+//! then the entries referencing skipped ones, until the remaining document is valid. The report
+//! counts the rules imported.
+//!
+//! Names are borrowed for the `'static` lifetime by the `Acl`, hence the loaded names are leaked.
+//! Load policies once, e.g. at startup, and not repeatedly.
+
+use crate::{Access, Acl, Error, Provenance, Query, RuleMeta, SchemaError};
+use crate::import::{ErrorMode, ImportReport};
+use crate::kind::ResourceInfo;
+use crate::limits::Limits;
+use log::{trace, warn};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+/// The version of the policy document format written by this crate.
+pub const SCHEMA_VERSION: u64 = 1;
+
+
+// Document ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A role as declared in a policy document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct RoleEntry {
+    pub source:  Option<String>,
+    pub index:   usize,
+    pub name:    String,
+    pub parents: Vec<String>,
+} // struct RoleEntry
+
+/// A resource as declared in a policy document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ResourceEntry {
+    pub source:   Option<String>,
+    pub index:    usize,
+    pub name:     String,
+    pub parent:   Option<String>,
+    pub defaults: Vec<(String, Access)>,
+    pub info:     ResourceInfo,
+} // struct ResourceEntry
+
+/// A rule as declared in a policy document.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RuleEntry {
+    pub source:    Option<String>,
+    pub index:     usize,
+    pub access:    Access,
+    pub role:      Option<String>,
+    pub resource:  Option<String>,
+    pub privilege: Option<String>,
+    pub condition:   Option<String>,
+    pub environment: Option<String>,
+    pub priority:    Option<i32>,
+    pub meta:        RuleMeta,
+} // struct RuleEntry
+
+/// A bypass role as declared in a policy document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct BypassEntry {
+    pub source: Option<String>,
+    pub index:  usize,
+    pub name:   String,
+} // struct BypassEntry
+
+/// The parsed but not yet validated content of a policy document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Document {
+    pub roles:        Vec<RoleEntry>,
+    pub resources:    Vec<ResourceEntry>,
+    pub rules:        Vec<RuleEntry>,
+    pub bypass:       Vec<BypassEntry>,
+    /// the default role and the source declaring it
+    pub default_role: Option<(Option<String>, String)>,
+} // struct Document
+
+impl Document {
+
+    /// Parses the document from value. Problems are appended to errors, the offending values are
+    /// skipped. The source names the origin of the document, e.g. its file name.
+    pub fn parse(value: &Value, source: Option<&str>, errors: &mut Vec<SchemaError>) -> Document {
+        let mut doc = Document::default();
+        let first   = errors.len();
+        let root    = match value {
+            Value::Object(map) => map,
+            _                  => {
+                errors.push(SchemaError::new("", "expected an object"));
+                return doc;
+            }, // _
+        }; // match
+
+        check_fields(root, "", &["version", "roles", "resources", "rules", "bypass", "default_role"], errors);
+        match root.get("version") {
+            None | Some(Value::Null) => (),
+            Some(value)              => match value.as_u64() {
+                Some(version) if version > SCHEMA_VERSION => errors.push(SchemaError::new("version",
+                    &format!("unsupported version {}, at most {} supported", version, SCHEMA_VERSION))),
+                Some(version) if version > 0              => (),
+                _                                         => errors.push(SchemaError::new("version", "expected a positive integer")),
+            }, // Some
+        } // match
+        for (i, item) in items(root, "roles", "", errors).iter().enumerate() {
+            let path = format!("roles[{}]", i);
+
+            if let Some(map) = object(item, &path, errors) {
+                check_fields(map, &path, &["name", "parents"], errors);
+                if let Some(name) = required_name(map, &path, errors) {
+                    let parents = string_list(map, "parents", &path, errors);
+
+                    doc.roles.push(RoleEntry{source: source.map(String::from), index: i, name, parents});
+                } // if
+            } // if
+        } // for
+        for (i, item) in items(root, "resources", "", errors).iter().enumerate() {
+            let path = format!("resources[{}]", i);
+
+            if let Some(map) = object(item, &path, errors) {
+                check_fields(map, &path, &["name", "parent", "defaults", "kind", "meta"], errors);
+                if let Some(name) = required_name(map, &path, errors) {
+                    let parent       = optional_string(map, "parent", &path, errors);
+                    let mut defaults = vec![];
+
+                    match map.get("defaults") {
+                        None | Some(Value::Null)     => (),
+                        Some(Value::Object(entries)) => for (privilege, value) in entries {
+                            match value.as_str() {
+                                Some("allow") => defaults.push((privilege.clone(), Access::Allow)),
+                                Some("deny")  => defaults.push((privilege.clone(), Access::Deny)),
+                                _             => errors.push(SchemaError::new(&format!("{}.defaults.{}", path, privilege),
+                                    &format!("expected \"allow\" or \"deny\", found {}", value))),
+                            } // match
+                        }, // Some
+                        Some(_)                      =>
+                            errors.push(SchemaError::new(&format!("{}.defaults", path), "expected an object or null")),
+                    } // match
+                    let mut info     = ResourceInfo{kind: optional_string(map, "kind", &path, errors), ..ResourceInfo::default()};
+
+                    match map.get("meta") {
+                        None | Some(Value::Null)     => (),
+                        Some(Value::Object(entries)) => for (key, value) in entries {
+                            match value {
+                                Value::String(value) => { info.meta.insert(key.clone(), value.clone()); },
+                                _                    => errors.push(SchemaError::new(&format!("{}.meta.{}", path, key), "expected a string")),
+                            } // match
+                        }, // Some
+                        Some(_)                      =>
+                            errors.push(SchemaError::new(&format!("{}.meta", path), "expected an object or null")),
+                    } // match
+                    doc.resources.push(ResourceEntry{source: source.map(String::from), index: i, name, parent, defaults, info});
+                } // if
+            } // if
+        } // for
+        for (i, item) in items(root, "rules", "", errors).iter().enumerate() {
+            let path = format!("rules[{}]", i);
+
+            if let Some(map) = object(item, &path, errors) {
+                check_fields(map, &path, &[
+                    "access", "role", "resource", "privilege", "condition", "environment", "priority", "description", "author",
+                    "ticket", "message",
+                ], errors);
+
+                let access = match map.get("access") {
+                    Some(Value::String(s)) if s == "allow" => Some(Access::Allow),
+                    Some(Value::String(s)) if s == "deny"  => Some(Access::Deny),
+                    Some(value)                            => {
+                        errors.push(SchemaError::new(&format!("{}.access", path),
+                            &format!("expected \"allow\" or \"deny\", found {}", value)));
+                        None
+                    }, // Some
+                    None                                   => {
+                        errors.push(SchemaError::new(&format!("{}.access", path), "missing field"));
+                        None
+                    }, // None
+                }; // match
+                let role      = optional_string(map, "role", &path, errors);
+                let resource  = optional_string(map, "resource", &path, errors);
+                let privilege = optional_string(map, "privilege", &path, errors);
+                let condition   = optional_string(map, "condition", &path, errors);
+                let environment = optional_string(map, "environment", &path, errors);
+                let priority    = match map.get("priority") {
+                    None | Some(Value::Null) => None,
+                    Some(value)              => {
+                        let priority = value.as_i64().and_then(|n| i32::try_from(n).ok());
+
+                        if priority.is_none() {
+                            errors.push(SchemaError::new(&format!("{}.priority", path), "expected a 32-bit integer or null"));
+                        } // if
+                        priority
+                    }, // Some
+                }; // match
+                let meta        = RuleMeta{
+                    description: optional_string(map, "description", &path, errors),
+                    author:      optional_string(map, "author", &path, errors),
+                    ticket:      optional_string(map, "ticket", &path, errors),
+                    message:     optional_string(map, "message", &path, errors),
+                    ..RuleMeta::default()
+                }; // RuleMeta
+
+                if let Some(access) = access {
+                    doc.rules.push(RuleEntry{
+                        source: source.map(String::from), index: i, access, role, resource, privilege, condition, environment, priority,
+                        meta,
+                    }); // RuleEntry
+                } // if
+            } // if
+        } // for
+        for (i, item) in items(root, "bypass", "", errors).iter().enumerate() {
+            match item {
+                Value::String(name) => doc.bypass.push(BypassEntry{
+                    source: source.map(String::from), index: i, name: name.clone(),
+                }), // Value::String
+                _                   => errors.push(SchemaError::new(&format!("bypass[{}]", i), "expected a string")),
+            } // match
+        } // for
+        doc.default_role = optional_string(root, "default_role", "", errors).map(|name| (source.map(String::from), name));
+        for error in &mut errors[first..] {
+            error.source = source.map(String::from);
+        } // for
+        doc
+    } // parse
+
+    /// Merges an overlay into this document. Roles and resources of the overlay replace those
+    /// with the same name, rules replace those for the same role, resource, privilege and
+    /// environment. All other entries are appended.
+    pub fn merge(&mut self, overlay: Document) {
+        let roles: HashMap<String, usize>     = self.roles.iter().enumerate()
+            .map(|(i, role)| (role.name.clone(), i)).collect();
+        let resources: HashMap<String, usize> = self.resources.iter().enumerate()
+            .map(|(i, resource)| (resource.name.clone(), i)).collect();
+        let rules: HashMap<_, usize>          = self.rules.iter().enumerate()
+            .map(|(i, rule)| ((rule.role.clone(), rule.resource.clone(), rule.privilege.clone(), rule.environment.clone()), i))
+            .collect();
+
+        for role in overlay.roles {
+            match roles.get(&role.name) {
+                Some(i) => self.roles[*i] = role,
+                None    => self.roles.push(role),
+            } // match
+        } // for
+        for resource in overlay.resources {
+            match resources.get(&resource.name) {
+                Some(i) => self.resources[*i] = resource,
+                None    => self.resources.push(resource),
+            } // match
+        } // for
+        for rule in overlay.rules {
+            match rules.get(&(rule.role.clone(), rule.resource.clone(), rule.privilege.clone(), rule.environment.clone())) {
+                Some(i) => self.rules[*i] = rule,
+                None    => self.rules.push(rule),
+            } // match
+        } // for
+        for entry in overlay.bypass {
+            if !self.bypass.iter().any(|bypass| bypass.name == entry.name) {
+                self.bypass.push(entry);
+            } // if
+        } // for
+        if overlay.default_role.is_some() {
+            self.default_role = overlay.default_role;
+        } // if
+    } // merge
+
+    /// Validates references between the entries of the document. Roles and resources must be
+    /// declared uniquely and before they are referenced.
+    pub fn validate(&self, errors: &mut Vec<SchemaError>) {
+        let mut roles     = HashMap::new();
+        let mut resources = HashMap::new();
+
+        for (pos, role) in self.roles.iter().enumerate() {
+            roles.entry(role.name.as_str()).or_insert(pos);
+        } // for
+        for (pos, resource) in self.resources.iter().enumerate() {
+            resources.entry(resource.name.as_str()).or_insert(pos);
+        } // for
+
+        for (pos, role) in self.roles.iter().enumerate() {
+            let source = role.source.as_deref();
+            let i      = role.index;
+
+            if roles[role.name.as_str()] != pos {
+                errors.push(SchemaError::at(source, &format!("roles[{}].name", i),
+                    &format!("duplicate role \"{}\"", role.name)));
+            } // if
+            for (j, parent) in role.parents.iter().enumerate() {
+                let path = format!("roles[{}].parents[{}]", i, j);
+
+                match roles.get(parent.as_str()) {
+                    None                 => errors.push(SchemaError::at(source, &path,
+                        &format!("unknown role \"{}\"", parent))),
+                    Some(k) if *k >= pos => errors.push(SchemaError::at(source, &path,
+                        &format!("role \"{}\" must be declared before it is referenced", parent))),
+                    Some(_)              => (),
+                } // match
+            } // for
+        } // for
+        for (pos, resource) in self.resources.iter().enumerate() {
+            let source = resource.source.as_deref();
+            let i      = resource.index;
+
+            if resources[resource.name.as_str()] != pos {
+                errors.push(SchemaError::at(source, &format!("resources[{}].name", i),
+                    &format!("duplicate resource \"{}\"", resource.name)));
+            } // if
+            if let Some(parent) = &resource.parent {
+                let path = format!("resources[{}].parent", i);
+
+                match resources.get(parent.as_str()) {
+                    None                 => errors.push(SchemaError::at(source, &path,
+                        &format!("unknown resource \"{}\"", parent))),
+                    Some(k) if *k >= pos => errors.push(SchemaError::at(source, &path,
+                        &format!("resource \"{}\" must be declared before it is referenced", parent))),
+                    Some(_)              => (),
+                } // match
+            } // if
+        } // for
+        for rule in &self.rules {
+            let source = rule.source.as_deref();
+
+            if let Some(role) = &rule.role {
+                if !roles.contains_key(role.as_str()) {
+                    errors.push(SchemaError::at(source, &format!("rules[{}].role", rule.index),
+                        &format!("unknown role \"{}\"", role)));
+                } // if
+            } // if
+            if let Some(resource) = &rule.resource {
+                if !resources.contains_key(resource.as_str()) {
+                    errors.push(SchemaError::at(source, &format!("rules[{}].resource", rule.index),
+                        &format!("unknown resource \"{}\"", resource)));
+                } // if
+            } // if
+            if rule.environment.is_some() && rule.meta != RuleMeta::default() {
+                errors.push(SchemaError::at(source, &format!("rules[{}].environment", rule.index),
+                    "environment-scoped rules can't carry metadata"));
+            } // if
+            if rule.priority.is_some() && rule.environment.is_some() {
+                errors.push(SchemaError::at(source, &format!("rules[{}].priority", rule.index),
+                    "environment-scoped rules can't carry a priority"));
+            } // if
+            if rule.priority.is_some() && rule.role.is_none() && rule.resource.is_none() && rule.privilege.is_none() {
+                errors.push(SchemaError::at(source, &format!("rules[{}].priority", rule.index),
+                    "the catch-all rule can't carry a priority"));
+            } // if
+        } // for
+        for entry in &self.bypass {
+            if !roles.contains_key(entry.name.as_str()) {
+                errors.push(SchemaError::at(entry.source.as_deref(), &format!("bypass[{}]", entry.index),
+                    &format!("unknown role \"{}\"", entry.name)));
+            } // if
+        } // for
+        if let Some((source, name)) = &self.default_role {
+            if !roles.contains_key(name.as_str()) {
+                errors.push(SchemaError::at(source.as_deref(), "default_role", &format!("unknown role \"{}\"", name)));
+            } // if
+        } // if
+    } // validate
+
+    /// Removes the entries located by errors, see module `import`. Returns true if any entry has
+    /// been removed.
+    pub fn remove_invalid(&mut self, errors: &[SchemaError]) -> bool {
+        let invalid = |source: &Option<String>, entry: String| errors.iter().any(|error| {
+            error.source == *source && (error.path == entry || error.path.starts_with(&format!("{}.", entry)))
+        });
+        let count   = self.roles.len() + self.resources.len() + self.rules.len() + self.bypass.len();
+
+        self.roles.retain(|role| !invalid(&role.source, format!("roles[{}]", role.index)));
+        self.resources.retain(|resource| !invalid(&resource.source, format!("resources[{}]", resource.index)));
+        self.rules.retain(|rule| !invalid(&rule.source, format!("rules[{}]", rule.index)));
+        self.bypass.retain(|entry| !invalid(&entry.source, format!("bypass[{}]", entry.index)));
+
+        let removed = count != self.roles.len() + self.resources.len() + self.rules.len() + self.bypass.len();
+
+        match &self.default_role {
+            Some((source, _)) if invalid(source, String::from("default_role")) => {
+                self.default_role = None;
+                true
+            }, // Some
+            _                                                                  => removed,
+        } // match
+    } // remove_invalid
+
+    /// Validates the document, handles the problems found while parsing and validating by mode
+    /// and builds the `Acl`, see module `import`.
+    pub fn finish(mut self, mut errors: Vec<SchemaError>, mode: ErrorMode, limits: Limits) -> Result<(Acl, ImportReport), Error> {
+        self.validate(&mut errors);
+        if mode == ErrorMode::SkipInvalid {
+            let mut skipped = vec![];
+
+            // skipping entries may invalidate the entries referencing them
+            loop {
+                let removed = self.remove_invalid(&errors);
+
+                skipped.append(&mut errors);
+                if !removed {
+                    break;
+                } // if
+                self.validate(&mut errors);
+            } // loop
+            errors = skipped;
+        } // if
+        if !errors.is_empty() {
+            warn!("invalid policy document with {} problems", errors.len());
+            #[cfg(feature = "otel")]
+            if mode != ErrorMode::SkipInvalid {
+                crate::otel::policy_rejected(errors.len());
+            } // if
+        } // if
+
+        let skipped = mode.check(errors)?;
+        let acl     = self.build(limits)?;
+
+        Ok((acl, ImportReport{imported: self.rules.len(), skipped}))
+    } // finish
+
+    /// Returns an error if the document exceeds the limits on the number of entries or the length
+    /// of names. Checked before any name is borrowed, the depth is checked while building.
+    pub fn check_limits(&self, limits: &Limits) -> Result<(), Error> {
+        Limits::check_count(limits.max_roles, self.roles.len(), Error::TooManyRoles)?;
+        Limits::check_count(limits.max_resources, self.resources.len(), Error::TooManyResources)?;
+        Limits::check_count(limits.max_rules, self.rules.len(), Error::TooManyRules)?;
+
+        let names = self.roles.iter().map(|role| &role.name)
+            .chain(self.resources.iter().map(|resource| &resource.name))
+            .chain(self.rules.iter().flat_map(|rule| rule.privilege.iter().chain(&rule.condition).chain(&rule.environment)));
+
+        for name in names {
+            limits.check_name(name)?;
+        } // for
+        Ok(())
+    } // check_limits
+
+    /// Builds an `Acl` enforcing limits from a validated document. The provenance of each rule
+    /// with a known source is recorded.
+    pub fn build(&self, limits: Limits) -> Result<Acl, Error> {
+        let mut acl = Acl::new();
+
+        self.check_limits(&limits)?;
+        acl.set_limits(limits);
+
+        for role in &self.roles {
+            acl.add_role(intern(&role.name), role.parents.iter().map(|p| intern(p)).collect())?;
+        } // for
+        for resource in &self.resources {
+            acl.add_resource(intern(&resource.name), resource.parent.as_deref().map(intern))?;
+        } // for
+        for resource in &self.resources {
+            let name = intern(&resource.name);
+
+            for (privilege, access) in &resource.defaults {
+                acl.set_resource_default(name, intern(privilege), *access)?;
+            } // for
+            acl.set_resource_kind(name, resource.info.kind.as_deref())?;
+            for (key, value) in &resource.info.meta {
+                acl.set_resource_meta(name, key, Some(value))?;
+            } // for
+        } // for
+        for rule in &self.rules {
+            let role      = rule.role.as_deref().map(intern);
+            let resource  = rule.resource.as_deref().map(intern);
+            let privilege = rule.privilege.as_deref().map(intern);
+            let mut meta  = rule.meta.clone();
+            let condition = rule.condition.as_deref().map(intern);
+            let set       = |acl: &mut Acl| match condition {
+                Some(name) => acl.set_conditional_rule(role, resource, privilege, rule.access, name),
+                None       => acl.set_rule(role, resource, privilege, rule.access),
+            }; // match
+
+            if let Some(environment) = &rule.environment {
+                acl.set_scoped_rule(intern(environment), Query{resource, role, privilege}, set)?;
+                continue;
+            } // if
+            set(&mut acl)?;
+            if let Some(file) = &rule.source {
+                meta.provenance = Some(Provenance{
+                    file:     file.clone(),
+                    line:     None,
+                    position: Some(rule.index),
+                    batch:    None,
+                }); // Provenance
+            } // if
+            if meta != RuleMeta::default() {
+                acl.set_rule_meta(role, resource, privilege, meta)?;
+            } // if
+            if let Some(priority) = rule.priority {
+                acl.set_rule_priority(role, resource, privilege, priority)?;
+            } // if
+        } // for
+        for entry in &self.bypass {
+            acl.set_bypass_role(intern(&entry.name))?;
+        } // for
+        if let Some((_, name)) = &self.default_role {
+            acl.set_default_role(intern(name))?;
+        } // if
+        #[cfg(feature = "otel")]
+        crate::otel::policy_loaded(&acl);
+        Ok(acl)
+    } // build
+
+} // impl Document
+
+
+// Loader /////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Validates a policy document and returns all problems found. An empty vector denotes a valid
+/// document.
+pub fn validate(value: &Value) -> Vec<SchemaError> {
+    let mut errors = vec![];
+    let doc        = Document::parse(value, None, &mut errors);
+
+    doc.validate(&mut errors);
+    errors
+} // validate
+
+/// Returns an error if the document is of a version newer than `SCHEMA_VERSION`.
+pub(crate) fn check_version(value: &Value) -> Result<(), Error> {
+    match value.get("version").and_then(Value::as_u64) {
+        Some(version) if version > SCHEMA_VERSION => {
+            warn!("unsupported policy document version {}", version);
+            Err(Error::SchemaVersion(version))
+        }, // Some
+        _                                         => Ok(()),
+    } // match
+} // check_version
+
+/// Validates the document and builds the `Acl`.
+pub(crate) fn load(value: &Value, origin: Option<&str>) -> Result<Acl, Error> {
+    load_with(value, origin, ErrorMode::CollectAll).map(|(acl, _)| acl)
+} // load
+
+/// Validates the document, handling invalid entries by mode, and builds the `Acl`.
+pub(crate) fn load_with(value: &Value, origin: Option<&str>, mode: ErrorMode) -> Result<(Acl, ImportReport), Error> {
+    trace!("loading policy from {:?} with {:?}", origin, mode);
+    check_version(value)?;
+
+    let mut errors = vec![];
+    let doc        = Document::parse(value, origin, &mut errors);
+
+    doc.finish(errors, mode, Limits::default())
+} // load_with
+
+/// Exports the roles, resources, rules, bypass roles and default role of acl as policy document of
+/// the current version. Parents are exported before their descendants, everything else in lexical
+/// order.
+pub(crate) fn export(acl: &Acl) -> Value {
+    fn visit_role(acl: &Acl, name: &'static str, seen: &mut Vec<&'static str>) {
+        if !seen.contains(&name) {
+            // parents are stored in search order, i.e. reversed
+            for parent in acl.roles[name].iter().rev() {
+                visit_role(acl, parent, seen);
+            } // for
+            seen.push(name);
+        } // if
+    } // visit_role
+
+    let mut roles = vec![];
+
+    for name in acl.roles.keys() {
+        visit_role(acl, name, &mut roles);
+    } // for
+
+    let mut resources: Vec<&'static str> = acl.resources.keys().copied().collect();
+
+    resources.sort_by_key(|name| acl.get_resource_lineage(name).len());
+
+    let mut rules = acl.scoped_rules();
+
+    rules.sort_by_key(|(query, _, environment)| (query.role, query.resource, query.privilege, *environment));
+
+    let roles: Vec<Value> = roles.into_iter().map(|name| {
+        let parents: Vec<&str> = acl.roles[name].iter().rev().copied().collect();
+
+        if parents.is_empty() {
+            json!({"name": name})
+        } else {
+            json!({"name": name, "parents": parents})
+        } // else
+    }).collect();
+    let resources: Vec<Value> = resources.into_iter().map(|name| {
+        let mut resource = match acl.resources[name] {
+            Some(parent) => json!({"name": name, "parent": parent}),
+            None         => json!({"name": name}),
+        }; // match
+        let defaults     = acl.resource_defaults(name);
+
+        if let Some(info) = acl.get_resource_info(name) {
+            if let Some(kind) = &info.kind {
+                resource["kind"] = json!(kind);
+            } // if
+            if !info.meta.is_empty() {
+                resource["meta"] = json!(info.meta);
+            } // if
+        } // if
+
+        if !defaults.is_empty() {
+            resource["defaults"] = defaults.into_iter().map(|(privilege, access)| (String::from(privilege), json!(match access {
+                Access::Allow => "allow",
+                Access::Deny  => "deny",
+            }))).collect::<Map<String, Value>>().into();
+        } // if
+        resource
+    }).collect();
+    let rules: Vec<Value> = rules.into_iter().map(|(query, rule, environment)| {
+        let mut map = Map::new();
+
+        map.insert(String::from("access"), json!(match rule.access() {
+            Access::Allow => "allow",
+            Access::Deny  => "deny",
+        })); // insert
+        for (key, value) in &[("role", query.role), ("resource", query.resource), ("privilege", query.privilege), ("condition", rule.condition()),
+            ("environment", environment)] {
+            if let Some(value) = value {
+                map.insert(String::from(*key), json!(value));
+            } // if
+        } // for
+        if let Some(priority) = acl.priorities.get(&query).filter(|_| environment.is_none()) {
+            map.insert(String::from("priority"), json!(priority));
+        } // if
+        if let Some(meta) = acl.meta.get(&query).filter(|_| environment.is_none()) {
+            for (key, value) in &[("description", &meta.description), ("author", &meta.author), ("ticket", &meta.ticket), ("message", &meta.message)] {
+                if let Some(value) = value {
+                    map.insert(String::from(*key), json!(value));
+                } // if
+            } // for
+        } // if
+        Value::Object(map)
+    }).collect();
+    let mut doc = json!({"version": SCHEMA_VERSION, "roles": roles, "resources": resources, "rules": rules});
+
+    if !acl.bypass.is_empty() {
+        doc["bypass"] = json!(acl.bypass.iter().collect::<Vec<_>>());
+    } // if
+    if let Some(name) = acl.default_role {
+        doc["default_role"] = json!(name);
+    } // if
+    doc
+} // export
+
+fn read(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))
+} // read
+
+impl Acl {
+
+    /// Creates an `Acl` from a JSON policy document. Returns an error if the document is malformed
+    /// or invalid. See module `policy` for the document format.
+    pub fn from_json(source: &str) -> Result<Acl, Error> {
+        let value = serde_json::from_str(source).map_err(|e| Error::Parse(e.to_string()))?;
+
+        load(&value, None)
+    } // from_json
+
+    /// Like `from_json`, but handles invalid entries by mode, see module `import`.
+    pub fn from_json_with(source: &str, mode: ErrorMode) -> Result<(Acl, ImportReport), Error> {
+        let value = serde_json::from_str(source).map_err(|e| Error::Parse(e.to_string()))?;
+
+        load_with(&value, None, mode)
+    } // from_json_with
+
+    /// Exports the `Acl` as JSON policy document. Loading the document yields an equal `Acl`,
+    /// except for the provenance and creation time of rules.
+    pub fn to_json(&self) -> String {
+        export(self).to_string()
+    } // to_json
+
+    /// Creates an `Acl` from a JSON policy file and records the provenance of each rule.
+    pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Acl, Error> {
+        let path  = path.as_ref();
+        let value = serde_json::from_str(&read(path)?)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+
+        load(&value, Some(&path.display().to_string()))
+    } // load_json
+
+    /// Like `load_json`, but handles invalid entries by mode, see module `import`.
+    pub fn load_json_with<P: AsRef<Path>>(path: P, mode: ErrorMode) -> Result<(Acl, ImportReport), Error> {
+        let path  = path.as_ref();
+        let value = serde_json::from_str(&read(path)?)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+
+        load_with(&value, Some(&path.display().to_string()), mode)
+    } // load_json_with
+
+    /// Creates an `Acl` from a YAML policy document. The document format equals the JSON format.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(source: &str) -> Result<Acl, Error> {
+        let value = serde_yaml::from_str(source).map_err(|e| Error::Parse(e.to_string()))?;
+
+        load(&value, None)
+    } // from_yaml
+
+    /// Like `from_yaml`, but handles invalid entries by mode, see module `import`.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_with(source: &str, mode: ErrorMode) -> Result<(Acl, ImportReport), Error> {
+        let value = serde_yaml::from_str(source).map_err(|e| Error::Parse(e.to_string()))?;
+
+        load_with(&value, None, mode)
+    } // from_yaml_with
+
+    /// Creates an `Acl` from a YAML policy file and records the provenance of each rule.
+    #[cfg(feature = "yaml")]
+    pub fn load_yaml<P: AsRef<Path>>(path: P) -> Result<Acl, Error> {
+        let path  = path.as_ref();
+        let value = serde_yaml::from_str(&read(path)?)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+
+        load(&value, Some(&path.display().to_string()))
+    } // load_yaml
+
+    /// Like `load_yaml`, but handles invalid entries by mode, see module `import`.
+    #[cfg(feature = "yaml")]
+    pub fn load_yaml_with<P: AsRef<Path>>(path: P, mode: ErrorMode) -> Result<(Acl, ImportReport), Error> {
+        let path  = path.as_ref();
+        let value = serde_yaml::from_str(&read(path)?)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+
+        load_with(&value, Some(&path.display().to_string()), mode)
+    } // load_yaml_with
+
+} // impl Acl
+
+
+// PolicyLoader ///////////////////////////////////////////////////////////////////////////////////
+
+
+/// Merges a base policy document with environment-specific overlays into a single validated `Acl`.
+/// Overlays are merged in the order they were added. Roles and resources of an overlay replace
+/// those with the same name, rules replace those for the same role, resource and privilege. Variable
+/// references like `${ADMIN_ROLE}` or `${ADMIN_ROLE:-admin}` within string values are substituted
+/// by variables set on the loader or, if enabled, by environment variables. `$$` yields `$`.
+///
+/// ```
+/// # extern crate zorq_acl;
+/// # use zorq_acl::policy::PolicyLoader;
+/// let mut loader = PolicyLoader::new();
+///
+/// loader
+///     .add_json(r#"{"roles": [{"name": "${ADMIN_ROLE:-admin}"}]}"#).unwrap()
+///     .add_json(r#"{"rules": [{"access": "allow", "role": "${ADMIN_ROLE:-admin}"}]}"#).unwrap()
+///     .set_var("ADMIN_ROLE", "root");
+///
+/// let acl = loader.load().unwrap();
+///
+/// assert!(acl.is_allowed(Some("root"), None, Some("delete")));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PolicyLoader {
+    layers: Vec<(Option<String>, Value)>,
+    vars:   HashMap<String, String>,
+    env:    bool,
+    limits: Limits,
+    mode:   ErrorMode,
+} // struct PolicyLoader
+
+impl PolicyLoader {
+
+    /// Creates a new `PolicyLoader` without layers. Environment variables are substituted by
+    /// default.
+    pub fn new() -> Self {
+        PolicyLoader{layers: vec![], vars: HashMap::new(), env: true, limits: Limits::default(), mode: ErrorMode::default()}
+    } // new
+
+    /// Adds a JSON policy document as layer.
+    pub fn add_json(&mut self, source: &str) -> Result<&mut Self, Error> {
+        let value = serde_json::from_str(source).map_err(|e| Error::Parse(e.to_string()))?;
+
+        self.layers.push((None, value));
+        Ok(self)
+    } // add_json
+
+    /// Adds a JSON policy file as layer.
+    pub fn add_json_file<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        let path  = path.as_ref();
+        let value = serde_json::from_str(&read(path)?)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+
+        self.layers.push((Some(path.display().to_string()), value));
+        Ok(self)
+    } // add_json_file
+
+    /// Adds a YAML policy document as layer.
+    #[cfg(feature = "yaml")]
+    pub fn add_yaml(&mut self, source: &str) -> Result<&mut Self, Error> {
+        let value = serde_yaml::from_str(source).map_err(|e| Error::Parse(e.to_string()))?;
+
+        self.layers.push((None, value));
+        Ok(self)
+    } // add_yaml
+
+    /// Adds a YAML policy file as layer.
+    #[cfg(feature = "yaml")]
+    pub fn add_yaml_file<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        let path  = path.as_ref();
+        let value = serde_yaml::from_str(&read(path)?)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+
+        self.layers.push((Some(path.display().to_string()), value));
+        Ok(self)
+    } // add_yaml_file
+
+    /// Sets a variable for substitution. Variables set on the loader take precedence over
+    /// environment variables.
+    pub fn set_var(&mut self, name: &str, value: &str) -> &mut Self {
+        self.vars.insert(String::from(name), String::from(value));
+        self
+    } // set_var
+
+    /// Enables or disables the substitution by environment variables.
+    pub fn use_env(&mut self, enabled: bool) -> &mut Self {
+        self.env = enabled;
+        self
+    } // use_env
+
+    /// Sets the limits enforced while building the `Acl` and by the built `Acl`, see module
+    /// `limits`.
+    pub fn set_limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    } // set_limits
+
+    /// Sets how invalid entries are handled, see module `import`. All problems are reported at
+    /// once by default.
+    pub fn set_error_mode(&mut self, mode: ErrorMode) -> &mut Self {
+        self.mode = mode;
+        self
+    } // set_error_mode
+
+    /// Substitutes variables, merges all layers, validates the result and builds the `Acl`.
+    /// Problems of all layers are reported at once, unless another error mode is set.
+    pub fn load(&self) -> Result<Acl, Error> {
+        self.load_with_report().map(|(acl, _)| acl)
+    } // load
+
+    /// Like `load`, but returns the report of the skipped entries as well.
+    pub fn load_with_report(&self) -> Result<(Acl, ImportReport), Error> {
+        trace!("loading policy from {} layers with {:?}", self.layers.len(), self.mode);
+        let mut errors = vec![];
+        let mut doc    = Document::default();
+
+        for (_, value) in &self.layers {
+            check_version(value)?;
+        } // for
+        for (source, value) in &self.layers {
+            let mut value = value.clone();
+            let first     = errors.len();
+
+            self.substitute(&mut value, String::new(), &mut errors);
+            for error in &mut errors[first..] {
+                error.source = source.clone();
+            } // for
+            doc.merge(Document::parse(&value, source.as_deref(), &mut errors));
+        } // for
+        doc.finish(errors, self.mode, self.limits)
+    } // load_with_report
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        match self.vars.get(name) {
+            Some(value)         => Some(value.clone()),
+            None if self.env    => std::env::var(name).ok(),
+            None                => None,
+        } // match
+    } // lookup
+
+    fn substitute(&self, value: &mut Value, path: String, errors: &mut Vec<SchemaError>) {
+        match value {
+            Value::String(text) => match self.expand(text) {
+                Ok(expanded) => *text = expanded,
+                Err(message) => errors.push(SchemaError::new(&path, &message)),
+            }, // Value::String
+            Value::Array(list)  => for (i, item) in list.iter_mut().enumerate() {
+                self.substitute(item, format!("{}[{}]", path, i), errors);
+            }, // Value::Array
+            Value::Object(map)  => for (key, item) in map.iter_mut() {
+                self.substitute(item, join(&path, key), errors);
+            }, // Value::Object
+            _                   => (),
+        } // match
+    } // substitute
+
+    fn expand(&self, text: &str) -> Result<String, String> {
+        let mut out  = String::new();
+        let mut rest = text;
+
+        while let Some(i) = rest.find('$') {
+            out.push_str(&rest[..i]);
+            rest = &rest[i + 1..];
+            if let Some(tail) = rest.strip_prefix('$') {
+                out.push('$');
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix('{') {
+                let end = tail.find('}')
+                    .ok_or_else(|| String::from("unterminated variable reference"))?;
+                let (name, default) = match tail[..end].find(":-") {
+                    Some(k) => (&tail[..k], Some(&tail[k + 2..end])),
+                    None    => (&tail[..end], None),
+                }; // match
+
+                match (self.lookup(name), default) {
+                    (Some(value), _)    => out.push_str(&value),
+                    (None, Some(value)) => out.push_str(value),
+                    (None, None)        => return Err(format!("undefined variable \"{}\"", name)),
+                } // match
+                rest = &tail[end + 1..];
+            } else {
+                out.push('$');
+            } // else
+        } // while
+        out.push_str(rest);
+        Ok(out)
+    } // expand
+
+} // impl PolicyLoader
+
+impl Default for PolicyLoader {
+
+    fn default() -> Self {
+        Self::new()
+    } // default
+
+} // impl Default for PolicyLoader
+
+
+// Helpers ////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Leaks name to obtain a `'static` reference.
+pub(crate) fn intern(name: &str) -> &'static str {
+    Box::leak(String::from(name).into_boxed_str())
+} // intern
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        String::from(field)
+    } else {
+        format!("{}.{}", path, field)
+    } // else
+} // join
+
+fn check_fields(map: &Map<String, Value>, path: &str, known: &[&str], errors: &mut Vec<SchemaError>) {
+    for key in map.keys() {
+        if !known.contains(&key.as_str()) {
+            errors.push(SchemaError::new(&join(path, key), "unknown field"));
+        } // if
+    } // for
+} // check_fields
+
+fn items<'a>(map: &'a Map<String, Value>, field: &str, path: &str, errors: &mut Vec<SchemaError>) -> &'a [Value] {
+    match map.get(field) {
+        None | Some(Value::Null) => &[],
+        Some(Value::Array(list)) => list,
+        Some(_)                  => {
+            errors.push(SchemaError::new(&join(path, field), "expected an array"));
+            &[]
+        }, // Some
+    } // match
+} // items
+
+fn object<'a>(value: &'a Value, path: &str, errors: &mut Vec<SchemaError>) -> Option<&'a Map<String, Value>> {
+    match value {
+        Value::Object(map) => Some(map),
+        _                  => {
+            errors.push(SchemaError::new(path, "expected an object"));
+            None
+        }, // _
+    } // match
+} // object
+
+fn required_name(map: &Map<String, Value>, path: &str, errors: &mut Vec<SchemaError>) -> Option<String> {
+    match map.get("name") {
+        Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+        Some(Value::String(_))                  => {
+            errors.push(SchemaError::new(&join(path, "name"), "empty name"));
+            None
+        }, // Some
+        Some(_)                                 => {
+            errors.push(SchemaError::new(&join(path, "name"), "expected a string"));
+            None
+        }, // Some
+        None                                    => {
+            errors.push(SchemaError::new(&join(path, "name"), "missing field"));
+            None
+        }, // None
+    } // match
+} // required_name
+
+fn optional_string(map: &Map<String, Value>, field: &str, path: &str, errors: &mut Vec<SchemaError>) -> Option<String> {
+    match map.get(field) {
+        None | Some(Value::Null)   => None,
+        Some(Value::String(s))     => Some(s.clone()),
+        Some(_)                    => {
+            errors.push(SchemaError::new(&join(path, field), "expected a string or null"));
+            None
+        }, // Some
+    } // match
+} // optional_string
+
+fn string_list(map: &Map<String, Value>, field: &str, path: &str, errors: &mut Vec<SchemaError>) -> Vec<String> {
+    let mut list = vec![];
+
+    for (i, item) in items(map, field, path, errors).iter().enumerate() {
+        match item {
+            Value::String(s) => list.push(s.clone()),
+            _                => errors.push(SchemaError::new(
+                &format!("{}[{}]", join(path, field), i), "expected a string")),
+        } // match
+    } // for
+    list
+} // string_list
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    const POLICY: &str = r#"{
+        "roles": [
+            {"name": "guest"},
+            {"name": "staff", "parents": ["guest"]},
+            {"name": "marketing", "parents": ["staff"]}
+        ],
+        "resources": [
+            {"name": "news"},
+            {"name": "latest", "parent": "news"}
+        ],
+        "rules": [
+            {"access": "allow", "role": "guest", "privilege": "view"},
+            {"access": "allow", "role": "marketing", "resource": "latest", "privilege": "publish"},
+            {"access": "deny", "role": "staff", "resource": "latest", "privilege": "revise",
+             "ticket": "CR-42", "message": "ask an editor"}
+        ]
+    }"#;
+
+    #[test]
+    fn from_json() {
+        let acl = Acl::from_json(POLICY).unwrap();
+
+        assert!(acl.is_allowed(Some("marketing"), Some("latest"), Some("view")));
+        assert!(acl.is_allowed(Some("marketing"), Some("latest"), Some("publish")));
+        assert!(acl.is_denied (Some("staff"), Some("latest"), Some("publish")));
+        assert_eq!(acl.get_resource_parent("latest"), Ok(Some("news")));
+
+        let meta = acl.get_rule_meta(Some("staff"), Some("latest"), Some("revise")).unwrap();
+
+        assert_eq!(meta.ticket.as_deref(), Some("CR-42"));
+        assert_eq!(acl.get_rule_meta(Some("guest"), None, Some("view")), None);
+    } // from_json
+
+    #[test]
+    fn all_errors() {
+        let res = Acl::from_json(r#"{
+            "roles": [
+                {"name": "guest"},
+                {"name": "staff", "parents": ["guets", "editor"]},
+                {"name": "editor", "parents": "staff"},
+                {"name": "guest"}
+            ],
+            "resources": [{"parent": "news"}],
+            "rules": [
+                {"access": "allow", "role": "guest"},
+                {"access": "permit", "role": "staff"},
+                {"access": "deny", "role": "staf", "resource": "news", "privilege": 1}
+            ],
+            "groups": []
+        }"#);
+        let errors: Vec<String> = match res {
+            Err(Error::Schema(errors)) => errors.iter().map(|e| e.to_string()).collect(),
+            other                      => panic!("unexpected result {:?}", other),
+        }; // match
+
+        assert_eq!(errors, vec![
+            "groups: unknown field",
+            "roles[2].parents: expected an array",
+            "resources[0].name: missing field",
+            "rules[1].access: expected \"allow\" or \"deny\", found \"permit\"",
+            "rules[2].privilege: expected a string or null",
+            "roles[1].parents[0]: unknown role \"guets\"",
+            "roles[1].parents[1]: role \"editor\" must be declared before it is referenced",
+            "roles[3].name: duplicate role \"guest\"",
+            "rules[2].role: unknown role \"staf\"",
+            "rules[2].resource: unknown resource \"news\"",
+        ]);
+    } // all_errors
+
+    #[test]
+    fn parse_error() {
+        assert!(matches!(Acl::from_json("{\"roles\": ["), Err(Error::Parse(_))));
+        assert!(matches!(Acl::load_json("/nonexistent/policy.json"), Err(Error::Io(_))));
+    } // parse_error
+
+    #[test]
+    fn load_json() {
+        let path = std::env::temp_dir().join("zorq-acl-load-json.json");
+
+        fs::write(&path, POLICY).unwrap();
+
+        let acl        = Acl::load_json(&path).unwrap();
+        let provenance = acl.get_rule_provenance(Some("staff"), Some("latest"), Some("revise")).unwrap();
+
+        assert_eq!(provenance.file, path.display().to_string());
+        assert_eq!(provenance.position, Some(2));
+        fs::remove_file(&path).unwrap();
+    } // load_json
+
+    #[test]
+    fn to_json() {
+        let mut acl = Acl::from_json(POLICY).unwrap();
+
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_role("chief", vec!["marketing", "root"]).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        assert!(acl.set_default_role("guest").is_ok());
+
+        let json = acl.to_json();
+
+        assert_eq!(json, concat!(
+            r#"{"bypass":["root"],"default_role":"guest","resources":[{"name":"news"},{"name":"latest","parent":"news"}],"#,
+            r#""roles":[{"name":"guest"},{"name":"staff","parents":["guest"]},"#,
+            r#"{"name":"marketing","parents":["staff"]},{"name":"root"},"#,
+            r#"{"name":"chief","parents":["marketing","root"]}],"#,
+            r#""rules":[{"access":"allow","privilege":"view","role":"guest"},"#,
+            r#"{"access":"allow","privilege":"publish","resource":"latest","role":"marketing"},"#,
+            r#"{"access":"deny","message":"ask an editor","privilege":"revise","resource":"latest","role":"staff","ticket":"CR-42"}],"#,
+            r#""version":1}"#));
+        assert_eq!(Acl::from_json(&json).unwrap().to_json(), json);
+        assert!(matches!(Acl::from_json(r#"{"bypass": ["nobody"]}"#), Err(Error::Schema(_))));
+        assert!(matches!(Acl::from_json(r#"{"default_role": "nobody"}"#), Err(Error::Schema(_))));
+    } // to_json
+
+    #[test]
+    fn version() {
+        assert!(Acl::from_json(r#"{"version": 1, "roles": [{"name": "guest"}]}"#).is_ok());
+        assert_eq!(Acl::from_json(r#"{"version": 2}"#).err(), Some(Error::SchemaVersion(2)));
+        assert_eq!(validate(&json!({"version": 2})), vec![SchemaError::new("version", "unsupported version 2, at most 1 supported")]);
+        assert_eq!(validate(&json!({"version": "1"})), vec![SchemaError::new("version", "expected a positive integer")]);
+        assert_eq!(validate(&json!({"version": 0})), vec![SchemaError::new("version", "expected a positive integer")]);
+
+        let mut loader = PolicyLoader::new();
+
+        assert!(loader.add_json(POLICY).unwrap().add_json(r#"{"version": 3}"#).is_ok());
+        assert_eq!(loader.load().err(), Some(Error::SchemaVersion(3)));
+    } // version
+
+    #[test]
+    fn condition() {
+        let mut acl = Acl::from_json(POLICY).unwrap();
+
+        assert!(acl.allow_if(Some("staff"), Some("latest"), Some("edit"), "owner").is_ok());
+
+        let json = acl.to_json();
+
+        assert!(json.contains(r#"{"access":"allow","condition":"owner","privilege":"edit","resource":"latest","role":"staff"}"#));
+
+        let acl = Acl::from_json(&json).unwrap();
+
+        assert_eq!(acl.decide(Some("staff"), Some("latest"), Some("edit")).rule.condition(), None);
+        assert_eq!(acl.rules().find(|(query, _, _)| query.privilege == Some("edit")).map(|(_, rule, _)| rule.condition()), Some(Some("owner")));
+        assert_eq!(acl.to_json(), json);
+    } // condition
+
+    #[test]
+    fn defaults() {
+        let acl = Acl::from_json(r#"{
+            "resources": [{"name": "public", "defaults": {"view": "allow", "comment": "deny"}}, {"name": "blog", "parent": "public"}]
+        }"#).unwrap();
+
+        assert!(acl.is_allowed(None, Some("blog"), Some("view")));
+        assert_eq!(acl.get_resource_default("public", "comment"), Some(Access::Deny));
+        assert!(acl.to_json().contains(r#"{"defaults":{"comment":"deny","view":"allow"},"name":"public"}"#));
+        assert_eq!(Acl::from_json(&acl.to_json()).unwrap().to_json(), acl.to_json());
+
+        assert_eq!(Acl::from_json(r#"{
+            "resources": [{"name": "public", "defaults": {"view": true}}, {"name": "blog", "defaults": ["view"]}]
+        }"#).unwrap_err().to_string(), "Invalid policy document:\n    \
+            resources[0].defaults.view: expected \"allow\" or \"deny\", found true\n    \
+            resources[1].defaults: expected an object or null");
+    } // defaults
+
+    #[test]
+    fn resource_info() {
+        let acl = Acl::from_json(r#"{
+            "resources": [{"name": "handbook", "kind": "document", "meta": {"owner": "hr", "lang": "en"}}, {"name": "shared"}]
+        }"#).unwrap();
+
+        assert_eq!(acl.get_resource_kind("handbook"), Some("document"));
+        assert_eq!(acl.get_resource_meta("handbook", "owner"), Some("hr"));
+        assert_eq!(acl.get_resource_info("shared"), None);
+        assert!(acl.to_json().contains(r#"{"kind":"document","meta":{"lang":"en","owner":"hr"},"name":"handbook"}"#));
+        assert_eq!(Acl::from_json(&acl.to_json()).unwrap().to_json(), acl.to_json());
+
+        assert_eq!(Acl::from_json(r#"{
+            "resources": [{"name": "handbook", "kind": 1, "meta": {"pages": 12}}, {"name": "shared", "meta": "hr"}]
+        }"#).unwrap_err().to_string(), "Invalid policy document:\n    \
+            resources[0].kind: expected a string or null\n    \
+            resources[0].meta.pages: expected a string\n    \
+            resources[1].meta: expected an object or null");
+    } // resource_info
+
+    #[test]
+    fn priority() {
+        let acl = Acl::from_json(r#"{
+            "roles": [{"name": "staff"}],
+            "resources": [{"name": "news"}],
+            "rules": [
+                {"access": "deny", "role": "staff", "resource": "news"},
+                {"access": "allow", "privilege": "view", "priority": 5}
+            ]
+        }"#).unwrap();
+
+        assert_eq!(acl.get_rule_priority(None, None, Some("view")), Some(5));
+        assert!(acl.is_allowed(Some("staff"), Some("news"), Some("view")));
+        assert!(acl.to_json().contains(r#"{"access":"allow","priority":5,"privilege":"view"}"#));
+        assert_eq!(Acl::from_json(&acl.to_json()).unwrap().to_json(), acl.to_json());
+
+        assert_eq!(Acl::from_json(r#"{
+            "roles": [{"name": "staff"}],
+            "rules": [
+                {"access": "allow", "role": "staff", "priority": "high"},
+                {"access": "allow", "role": "staff", "environment": "dev", "priority": 1},
+                {"access": "allow", "priority": 1}
+            ]
+        }"#).unwrap_err().to_string(), "Invalid policy document:\n    \
+            rules[0].priority: expected a 32-bit integer or null\n    \
+            rules[1].priority: environment-scoped rules can't carry a priority\n    \
+            rules[2].priority: the catch-all rule can't carry a priority");
+    } // priority
+
+    #[test]
+    fn environment() {
+        assert!(matches!(Acl::from_json(r#"{
+            "roles": [{"name": "staff"}],
+            "rules": [{"access": "deny", "role": "staff", "environment": "prod", "description": "no exceptions"}]
+        }"#), Err(Error::Schema(_))));
+
+        let mut acl = Acl::from_json(r#"{
+            "roles": [{"name": "staff"}],
+            "rules": [
+                {"access": "allow", "role": "staff", "privilege": "debug", "environment": "dev"},
+                {"access": "allow", "role": "staff", "environment": "dev"},
+                {"access": "deny", "role": "staff", "privilege": "debug"}
+            ]
+        }"#).unwrap();
+
+        assert!(acl.is_denied(Some("staff"), None, Some("debug")));
+        acl.set_environment(Some("dev"));
+        assert!(acl.is_allowed(Some("staff"), None, Some("debug")));
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+
+        let json = acl.to_json();
+
+        assert!(json.ends_with(concat!(
+            r#""rules":[{"access":"allow","environment":"dev","role":"staff"},"#,
+            r#"{"access":"deny","privilege":"debug","role":"staff"},"#,
+            r#"{"access":"allow","environment":"dev","privilege":"debug","role":"staff"}],"version":1}"#)));
+        assert_eq!(Acl::from_json(&json).unwrap().to_json(), json);
+    } // environment
+
+    #[test]
+    fn loader() {
+        let mut loader = PolicyLoader::new();
+
+        loader
+            .add_json(POLICY).unwrap()
+            .add_json(r#"{
+                "roles": [
+                    {"name": "staff", "parents": []},
+                    {"name": "${ADMIN_ROLE}"}
+                ],
+                "rules": [
+                    {"access": "allow", "role": "staff", "resource": "latest", "privilege": "revise"},
+                    {"access": "allow", "role": "${ADMIN_ROLE}", "description": "costs $$5"}
+                ]
+            }"#).unwrap()
+            .set_var("ADMIN_ROLE", "root")
+            .use_env(false);
+
+        let acl = loader.load().unwrap();
+
+        // staff no longer inherits from guest and may revise the latest news
+        assert_eq!(acl.get_role_parents("staff"), Ok(vec![]));
+        assert!(acl.is_denied (Some("staff"), Some("latest"), Some("view")));
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("revise")));
+        assert!(acl.is_allowed(Some("marketing"), Some("latest"), Some("publish")));
+        assert!(acl.is_allowed(Some("root"), None, None));
+
+        let meta = acl.get_rule_meta(Some("root"), None, None).unwrap();
+
+        assert_eq!(meta.description.as_deref(), Some("costs $5"));
+    } // loader
+
+    #[test]
+    fn loader_errors() {
+        let mut loader = PolicyLoader::new();
+
+        loader
+            .add_json(r#"{"roles": [{"name": "${MISSING_ROLE}"}, {"name": "${OPEN"}]}"#).unwrap()
+            .add_json(r#"{"rules": [{"access": "allow", "role": "staff"}]}"#).unwrap()
+            .use_env(false);
+
+        let errors: Vec<String> = match loader.load() {
+            Err(Error::Schema(errors)) => errors.iter().map(|e| e.to_string()).collect(),
+            other                      => panic!("unexpected result {:?}", other),
+        }; // match
+
+        assert_eq!(errors, vec![
+            "roles[0].name: undefined variable \"MISSING_ROLE\"",
+            "roles[1].name: unterminated variable reference",
+            "rules[0].role: unknown role \"staff\"",
+        ]);
+    } // loader_errors
+
+    #[test]
+    fn error_modes() {
+        let source = r#"{
+            "roles": [{"name": "guest"}, {"name": "staff", "parents": ["nobody"]}, {"name": "editor", "parents": ["staff"]}],
+            "resources": [{"name": "news"}],
+            "rules": [
+                {"access": "allow", "role": "guest", "resource": "news", "privilege": "view"},
+                {"access": "allow", "role": "editor", "privilege": "edit"},
+                {"access": "allow", "role": 7, "privilege": "delete"},
+                {"access": "maybe"}
+            ],
+            "default_role": "editor"
+        }"#;
+        let problems = |mode| match Acl::from_json_with(source, mode) {
+            Err(Error::Schema(errors)) => errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            other                      => panic!("unexpected result {:?}", other.map(|(_, report)| report)),
+        }; // match
+
+        assert_eq!(problems(ErrorMode::FailFast), vec!["rules[2].role: expected a string or null"]);
+        assert_eq!(problems(ErrorMode::CollectAll).len(), 3);
+
+        // skipping staff skips its descendants and their rules, an invalid role must not become a wildcard
+        let (acl, report) = Acl::from_json_with(source, ErrorMode::SkipInvalid).unwrap();
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.skipped.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "rules[2].role: expected a string or null",
+            "rules[3].access: expected \"allow\" or \"deny\", found \"maybe\"",
+            "roles[1].parents[0]: unknown role \"nobody\"",
+            "roles[2].parents[0]: unknown role \"staff\"",
+            "rules[1].role: unknown role \"editor\"",
+            "default_role: unknown role \"editor\"",
+        ]);
+        assert_eq!(acl.roles().collect::<Vec<_>>(), vec!["guest"]);
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("view")));
+        assert!(acl.is_denied(None, None, Some("delete")));
+
+        let mut loader = PolicyLoader::new();
+
+        loader.add_json(source).unwrap().set_error_mode(ErrorMode::SkipInvalid);
+        assert_eq!(loader.load_with_report().map(|(_, report)| report.skipped.len()), Ok(6));
+        assert!(matches!(loader.set_error_mode(ErrorMode::FailFast).load().map(|_| ()), Err(Error::Schema(errors)) if errors.len() == 1));
+    } // error_modes
+
+    #[test]
+    fn loader_limits() {
+        let mut loader = PolicyLoader::new();
+
+        loader.add_json(POLICY).unwrap().set_limits(Limits{max_rules: Some(2), ..Limits::default()});
+        assert_eq!(loader.load().map(|_| ()), Err(Error::TooManyRules(2)));
+        loader.set_limits(Limits{max_name_length: Some(6), ..Limits::default()});
+        assert_eq!(loader.load().map(|_| ()), Err(Error::NameTooLong(9)));
+        loader.set_limits(Limits{max_depth: Some(1), ..Limits::default()});
+        assert_eq!(loader.load().map(|_| ()), Err(Error::TooDeep(String::from("marketing"))));
+        loader.set_limits(Limits{max_roles: Some(3), max_rules: Some(3), ..Limits::default()});
+
+        let mut acl = loader.load().unwrap();
+
+        assert_eq!(acl.limits().max_roles, Some(3));
+        assert_eq!(acl.add_role("root", vec![]), Err(Error::TooManyRoles(3)));
+    } // loader_limits
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn from_yaml() {
+        let acl = Acl::from_yaml("
+roles:
+  - name: guest
+  - name: staff
+    parents: [guest]
+rules:
+  - access: allow
+    role: guest
+    privilege: view
+").unwrap();
+
+        assert!(acl.is_allowed(Some("staff"), None, Some("view")));
+        assert!(matches!(Acl::from_yaml("roles:\n  - name: [guest"), Err(Error::Parse(_))));
+    } // from_yaml
+
+} // mod tests
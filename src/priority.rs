@@ -0,0 +1,226 @@
+//! Priorities of rules.
+//!
+//! Rules are found in the order of precedence described by `Acl::get_rule`: the most specific
+//! resource, then role, then privilege wins. A rule with a priority overrides this order, e.g. to
+//! let a narrowly scoped exception for all roles beat a deny rule of a more specific role. Of the
+//! rules applying to a query, the rule with the highest priority wins over any rule without
+//! priority. Rules of equal priority are ordered by precedence, so decisions are deterministic.
+//! Only if no rule with priority applies, the query is decided by precedence.
+//!
+//! A rule applies to a query if its resource is the queried resource, one of its ancestors or a
+//! wildcard, likewise for the role, and its privilege is the queried privilege or a wildcard.
+//! Conditional rules apply only if their assertion holds. The catch-all rule has no priority.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("contractor", vec![]).unwrap();
+//! acl.add_resource("wiki", None).unwrap();
+//! acl.add_resource("onboarding", Some("wiki")).unwrap();
+//! acl.deny(Some("contractor"), Some("onboarding"), None).unwrap();
+//! acl.allow(None, Some("wiki"), Some("view")).unwrap();
+//! assert!(acl.is_denied(Some("contractor"), Some("onboarding"), Some("view")));
+//!
+//! acl.set_rule_priority(None, Some("wiki"), Some("view"), 10).unwrap();
+//! assert!(acl.is_allowed(Some("contractor"), Some("onboarding"), Some("view")));
+//! ```
+
+use crate::{Acl, Error, Privilege, Query, Resource, Role, Rule};
+use crate::etag::Item;
+use log::{trace, warn};
+use std::cell::Cell;
+use std::cmp::Reverse;
+
+impl Acl {
+
+    /// Sets the priority of the rule defined for role on resource to privilege, see module
+    /// `priority`. Replaces the priority set before. Returns an error if no such rule is defined,
+    /// it is the catch-all rule or the `Acl` is locked.
+    pub fn set_rule_priority(&mut self, role: Role, resource: Resource, privilege: Privilege, priority: i32) -> Result<(), Error> {
+        trace!("setting priority of rule for {:?} on {:?} to {:?} to {}", role, resource, privilege, priority);
+        let query = Query{resource, role, privilege};
+
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if query == Query::ALL {
+            return Err(Error::NotPermitted(String::from("priority of the catch-all rule")));
+        } // if
+        if !self.rules.contains_key(&query) {
+            warn!("missing rule while setting priority: {}", query);
+            return Err(Error::MissingRule(query.to_string()));
+        } // if
+        if let Some(previous) = self.priorities.insert(query, priority) {
+            self.track(Item::Priority(&query, previous), false);
+        } // if
+        self.track(Item::Priority(&query, priority), true);
+        Ok(())
+    } // set_rule_priority
+
+    /// Removes the priority of the rule defined for role on resource to privilege. Returns the
+    /// priority removed. Returns an error if the `Acl` is locked.
+    pub fn clear_rule_priority(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<Option<i32>, Error> {
+        trace!("clearing priority of rule for {:?} on {:?} to {:?}", role, resource, privilege);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let query   = Query{resource, role, privilege};
+        let removed = self.priorities.remove(&query);
+
+        if let Some(priority) = removed {
+            self.track(Item::Priority(&query, priority), false);
+        } // if
+        Ok(removed)
+    } // clear_rule_priority
+
+    /// Returns the priority of the rule defined for role on resource to privilege.
+    #[inline]
+    pub fn get_rule_priority(&self, role: Role, resource: Resource, privilege: Privilege) -> Option<i32> {
+        self.priorities.get(&Query{resource, role, privilege}).copied()
+    } // get_rule_priority
+
+    /// Returns the rule with the highest priority applying to the query, the most specific of
+    /// those with equal priority, for the roles in lineage. Rules for which the assertion doesn't
+    /// hold are skipped.
+    pub(crate) fn query_priorities(&self, lineage: &[&'static str], query: Query, conditional: &Cell<bool>) -> Option<(&Query, &Rule)> {
+        let Query{resource, privilege, ..} = query;
+        let resources: Vec<&'static str> = resource.map(|name| self.iter_resource_lineage(name).collect()).unwrap_or_default();
+        // the rank of a name within its lineage, wildcards rank last
+        let rank      = |lineage: &[&'static str], name: Option<&'static str>| match name {
+            Some(name) => lineage.iter().position(|other| *other == name),
+            None       => Some(lineage.len()),
+        }; // rank
+        let mut best  = None;
+        let mut found = None;
+
+        for (other, priority) in &self.priorities {
+            if other.privilege.is_some() && other.privilege != privilege {
+                continue;
+            } // if
+            let position = match (rank(&resources, other.resource), rank(lineage, other.role)) {
+                (Some(resource), Some(role)) => (resource, role, other.privilege.is_none()),
+                _                            => continue,
+            }; // match
+            // higher priority first, then precedence
+            let key      = (*priority, Reverse(position));
+
+            if best.is_some_and(|best| best > key) {
+                continue;
+            } // if
+            if let Some(rule) = self.rules.get(other).filter(|rule| self.holds(rule, &query, conditional)) {
+                best  = Some(key);
+                found = Some((other, rule));
+            } // if
+        } // for
+        found
+    } // query_priorities
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Access;
+    use test_env_log::test;
+
+    #[test]
+    fn priority() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("edit")).is_ok());
+        assert!(acl.allow(Some("guest"), Some("news"), None).is_ok());
+        assert!(acl.allow(None, None, Some("edit")).is_ok());
+        assert!(acl.deny(None, Some("news"), Some("edit")).is_ok());
+        assert!(acl.is_denied(Some("staff"), Some("latest"), Some("edit")));
+
+        // the highest priority wins
+        assert!(acl.set_rule_priority(Some("guest"), Some("news"), None, 1).is_ok());
+        assert_eq!(acl.decide(Some("staff"), Some("latest"), Some("edit")).matched,
+            Query{resource: Some("news"), role: Some("guest"), privilege: None});
+        assert!(acl.set_rule_priority(None, Some("news"), Some("edit"), 2).is_ok());
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("view")));
+        assert!(acl.is_denied(Some("staff"), Some("latest"), Some("edit")));
+        assert_eq!(acl.decide(Some("staff"), Some("latest"), Some("edit")).matched,
+            Query{resource: Some("news"), role: None, privilege: Some("edit")});
+
+        // equal priorities are ordered by precedence
+        assert!(acl.set_rule_priority(None, None, Some("edit"), 2).is_ok());
+        assert_eq!(acl.decide(Some("staff"), Some("latest"), Some("edit")).rule.access(), Access::Deny);
+        assert!(acl.set_rule_priority(None, None, Some("edit"), 3).is_ok());
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+        assert_eq!(acl.get_rule_priority(None, None, Some("edit")), Some(3));
+
+        // rules apply along the lineages only
+        assert!(acl.add_resource("billing", None).is_ok());
+        assert!(acl.is_denied(Some("staff"), Some("billing"), Some("view")));
+        assert!(acl.is_denied(None, Some("latest"), Some("view")));
+
+        // clearing and removing rules drops their priority
+        assert_eq!(acl.clear_rule_priority(None, None, Some("edit")), Ok(Some(3)));
+        assert!(acl.is_denied(Some("staff"), Some("latest"), Some("edit")));
+        assert_eq!(acl.remove_deny(None, Some("news"), Some("edit")), Ok(1));
+        assert_eq!(acl.get_rule_priority(None, Some("news"), Some("edit")), None);
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+
+        assert_eq!(acl.set_rule_priority(Some("staff"), None, None, 1), Err(Error::MissingRule(String::from("staff→*: *"))));
+        assert!(acl.set_rule_priority(None, None, None, 1).is_err());
+        acl.lock();
+        assert_eq!(acl.set_rule_priority(Some("guest"), Some("news"), None, 5), Err(Error::Locked));
+        assert!(acl.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+
+        // decisions by priority aren't searched by precedence, so warming skips them
+        acl.purge_cache();
+        assert_eq!(acl.warm_cache(vec![Query{resource: Some("latest"), role: Some("staff"), privilege: Some("view")}]), 0);
+        assert_eq!(acl.warm_cache(vec![Query{resource: Some("billing"), role: Some("staff"), privilege: Some("edit")}]), 1);
+    } // priority
+
+    #[test]
+    fn views() {
+        use crate::shard::ShardedAcl;
+
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(None, None, Some("read")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.allow(Some("guest"), Some("latest"), Some("edit")).is_ok());
+        assert!(acl.set_rule_priority(None, None, Some("read"), 5).is_ok());
+        assert!(acl.set_rule_priority(Some("guest"), Some("latest"), Some("edit"), 1).is_ok());
+        assert!(acl.is_allowed(Some("staff"), Some("news"), Some("read")));
+
+        // residual policies and shards decide like `decide`
+        let residual = acl.specialize("staff").unwrap();
+        let mut base = Acl::new();
+
+        std::mem::swap(&mut base, &mut acl);
+        let mut sharded = ShardedAcl::new(base, 4);
+
+        assert!(sharded.add_role("user:sally", vec!["staff"]).is_ok());
+        for resource in [None, Some("news"), Some("latest")] {
+            for privilege in [None, Some("read"), Some("edit"), Some("share")] {
+                let decision = sharded.base().decide(Some("staff"), resource, privilege);
+
+                assert_eq!(sharded.base().effective(Some("staff"), resource, privilege), (decision.matched, decision.rule));
+                assert_eq!(residual.is_allowed(None, resource, privilege), decision.is_allowed(), "{}", decision);
+                assert_eq!(sharded.decide(Some("user:sally"), resource, privilege).rule, decision.rule, "{}", decision);
+            } // for
+        } // for
+        assert!(sharded.base().to_markdown_report().contains("### staff\n\n| Resource | * | edit | read |\n| --- | --- | --- | --- |\n\
+            | * | deny | deny | allow |\n| news | deny | deny | allow |\n| latest | deny | allow | allow |\n"));
+    } // views
+
+} // mod tests
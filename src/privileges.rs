@@ -0,0 +1,221 @@
+//! Privileges declared per resource and their descriptions.
+//!
+//! A resource added by `add_resource_with_privileges` declares the privileges meaningful on it,
+//! e.g. for generating the checkboxes of a permission UI. Descendants without declarations of
+//! their own inherit the privileges of their closest declaring ancestor. Rules and subject
+//! overrides on such a resource are rejected for undeclared privileges, wildcard privileges and
+//! rules for the wildcard resource are always accepted. Queries aren't checked by `decide`, use
+//! `try_decide` to reject undeclared privileges.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("editor", vec![]).unwrap();
+//! acl.add_resource_with_privileges("article", None, &["view", "edit", "publish"]).unwrap();
+//! acl.add_resource("draft", Some("article")).unwrap();
+//! acl.allow(Some("editor"), Some("draft"), Some("publish")).unwrap();
+//!
+//! assert!(acl.allow(Some("editor"), Some("draft"), Some("pubilsh")).is_err());
+//! assert!(acl.try_decide(Some("editor"), Some("draft"), Some("delete")).is_err());
+//! assert_eq!(acl.get_resource_privileges("draft"), Some(&["view", "edit", "publish"][..]));
+//! ```
+//!
+//! Privileges may be described by a label and a description for permission-management UIs and
+//! reports, either by `describe_privilege` or by `AclPrivilege::label` and
+//! `AclPrivilege::description` when registered, see module `domain`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.describe_privilege("publish", Some("Publish"), Some("Publish article to the public site")).unwrap();
+//!
+//! assert_eq!(acl.get_privilege_label("publish"), "Publish");
+//! assert_eq!(acl.get_privilege_label("edit"), "edit");
+//! assert_eq!(acl.get_privilege_info("publish").unwrap().description.as_deref(), Some("Publish article to the public site"));
+//! ```
+
+use crate::{Acl, Decision, Error, Privilege, Resource, Role};
+use log::{trace, warn};
+use std::collections::BTreeSet;
+
+
+// PrivilegeInfo //////////////////////////////////////////////////////////////////////////////////
+
+
+/// The human-readable label and description of a privilege.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrivilegeInfo {
+    /// a short name like "Publish"
+    pub label:       Option<String>,
+    /// a sentence like "Publish article to the public site"
+    pub description: Option<String>,
+} // struct PrivilegeInfo
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Adds a new resource declaring the privileges meaningful on it. Returns an error if resource
+    /// is already defined, parent is unknown or privileges are registered and one of the declared
+    /// isn't, see module `domain`.
+    pub fn add_resource_with_privileges(&mut self, name: &'static str, parent: Option<&'static str>, privileges: &[&'static str]) -> Result<(), Error> {
+        trace!("adding resource {} with privileges {:?}", name, privileges);
+        for privilege in privileges {
+            self.check_privilege(None, Some(privilege))?;
+        } // for
+        self.add_resource(name, parent)?;
+
+        let mut declared: Vec<&'static str> = vec![];
+
+        for privilege in privileges {
+            if !declared.contains(privilege) {
+                declared.push(privilege);
+            } // if
+        } // for
+        self.resource_privileges.insert(name, declared);
+        Ok(())
+    } // add_resource_with_privileges
+
+    /// Returns the privileges declared for resource or inherited from its closest declaring
+    /// ancestor, in order of declaration. Returns None if no privileges are declared or resource
+    /// is undefined.
+    pub fn get_resource_privileges(&self, name: &'static str) -> Option<&[&'static str]> {
+        self.get_resource_lineage(name).iter()
+            .find_map(|name| self.resource_privileges.get(name))
+            .map(|privileges| privileges.as_slice())
+    } // get_resource_privileges
+
+    /// Returns true if privilege is meaningful on resource, i.e. resource declares no privileges
+    /// or privilege is one of them.
+    pub fn is_privilege_declared(&self, name: &'static str, privilege: &str) -> bool {
+        self.get_resource_privileges(name)
+            .map(|privileges| privileges.contains(&privilege))
+            .unwrap_or(true)
+    } // is_privilege_declared
+
+    /// Like `decide`, but returns an error instead of a decision if privileges are declared for
+    /// resource and privilege isn't one of them.
+    pub fn try_decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Result<Decision, Error> {
+        if let (Some(name), Some(privilege)) = (resource, privilege) {
+            if !self.is_privilege_declared(name, privilege) {
+                warn!("undeclared privilege {} queried on {}", privilege, name);
+                return Err(Error::MissingPrivilege(format!("{} on {}", privilege, name)));
+            } // if
+        } // if
+        Ok(self.decide(role, resource, privilege))
+    } // try_decide
+
+    /// Sets the label and description of privilege, None removes them. Returns an error if
+    /// privileges are registered and privilege isn't one of them.
+    pub fn describe_privilege(&mut self, name: &'static str, label: Option<&str>, description: Option<&str>) -> Result<(), Error> {
+        trace!("describing privilege {} as {:?}", name, label);
+        self.check_privilege(None, Some(name))?;
+        if label.is_none() && description.is_none() {
+            self.privilege_info.remove(name);
+        } else {
+            self.privilege_info.insert(name, PrivilegeInfo{
+                label:       label.map(String::from),
+                description: description.map(String::from),
+            }); // PrivilegeInfo
+        } // else
+        Ok(())
+    } // describe_privilege
+
+    /// Returns the label and description of privilege or None if it isn't described.
+    pub fn get_privilege_info(&self, name: &str) -> Option<&PrivilegeInfo> {
+        self.privilege_info.get(name)
+    } // get_privilege_info
+
+    /// Returns the label of privilege or the privilege itself if it has no label.
+    pub fn get_privilege_label<'a>(&'a self, name: &'a str) -> &'a str {
+        self.privilege_info.get(name)
+            .and_then(|info| info.label.as_deref())
+            .unwrap_or(name)
+    } // get_privilege_label
+
+    /// Returns the privileges registered, named by rules or by resource defaults, i.e. all
+    /// privileges which may be decided differently from an unnamed one.
+    pub(crate) fn known_privileges(&self) -> BTreeSet<&'static str> {
+        self.privileges.iter().copied()
+            .chain(self.rules.keys().filter_map(|query| query.privilege))
+            .chain(self.resource_defaults.keys().map(|(_, privilege)| *privilege))
+            .collect()
+    } // known_privileges
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn declared() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("editor", vec![]).is_ok());
+        assert!(acl.add_resource("site", None).is_ok());
+        assert!(acl.add_resource_with_privileges("article", Some("site"), &["view", "edit", "view"]).is_ok());
+        assert!(acl.add_resource_with_privileges("draft", Some("article"), &["edit", "publish"]).is_ok());
+        assert!(acl.add_resource("comment", Some("article")).is_ok());
+        assert_eq!(acl.add_resource_with_privileges("article", None, &[]), Err(Error::DuplicateResource(String::from("article"))));
+        assert_eq!(acl.get_resource_privileges("site"), None);
+        assert_eq!(acl.get_resource_privileges("article"), Some(&["view", "edit"][..]));
+        assert_eq!(acl.get_resource_privileges("draft"), Some(&["edit", "publish"][..]));
+        assert_eq!(acl.get_resource_privileges("comment"), Some(&["view", "edit"][..]));
+        assert_eq!(acl.get_resource_privileges("missing"), None);
+
+        // rules
+        assert!(acl.allow(Some("editor"), Some("site"), Some("publish")).is_ok());
+        assert!(acl.allow(Some("editor"), Some("draft"), Some("publish")).is_ok());
+        assert!(acl.allow(Some("editor"), Some("comment"), None).is_ok());
+        assert!(acl.allow(Some("editor"), None, Some("delete")).is_ok());
+        assert_eq!(acl.allow(Some("editor"), Some("comment"), Some("publish")), Err(Error::MissingPrivilege(String::from("publish on comment"))));
+        assert!(acl.deny_subject("sally", Some("article"), Some("publish")).is_err());
+        assert!(crate::overlay::SessionOverlay::new(&acl).allow(None, Some("draft"), Some("view")).is_err());
+
+        // queries
+        assert!(acl.try_decide(Some("editor"), Some("draft"), Some("publish")).unwrap().is_allowed());
+        assert!(acl.try_decide(Some("editor"), Some("site"), Some("delete")).unwrap().is_allowed());
+        assert!(acl.try_decide(Some("editor"), Some("article"), None).unwrap().is_denied());
+        assert!(acl.try_decide(Some("editor"), Some("article"), Some("delete")).is_err());
+        assert!(acl.is_allowed(Some("editor"), Some("article"), Some("delete")));
+
+        // declared privileges must be registered if any are
+        acl.privileges.insert("view");
+        assert_eq!(acl.add_resource_with_privileges("page", None, &["view", "share"]), Err(Error::MissingPrivilege(String::from("share"))));
+        assert!(!acl.has_resource("page"));
+    } // declared
+
+    #[test]
+    fn described() {
+        let mut acl = Acl::new();
+
+        assert!(acl.describe_privilege("publish", Some("Publish"), None).is_ok());
+        assert!(acl.describe_privilege("edit", None, Some("Change the text")).is_ok());
+        assert_eq!(acl.get_privilege_info("publish"), Some(&PrivilegeInfo{label: Some(String::from("Publish")), description: None}));
+        assert_eq!(acl.get_privilege_label("publish"), "Publish");
+        assert_eq!(acl.get_privilege_label("edit"), "edit");
+        assert_eq!(acl.get_privilege_info("view"), None);
+
+        assert!(acl.describe_privilege("publish", None, None).is_ok());
+        assert_eq!(acl.get_privilege_info("publish"), None);
+
+        // only registered privileges may be described once any are
+        acl.privileges.insert("view");
+        assert!(acl.describe_privilege("view", Some("View"), None).is_ok());
+        assert_eq!(acl.describe_privilege("share", Some("Share"), None), Err(Error::MissingPrivilege(String::from("share"))));
+    } // described
+
+} // mod tests
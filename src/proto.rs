@@ -0,0 +1,270 @@
+//! Protobuf exchange of policies.
+//!
+//! The messages are defined in `proto/acl.proto`, package `zorq.acl.v1`, and mirror the JSON
+//! policy document, see module `policy`. The types of this module are equal to the ones generated
+//! by prost, so services in other languages use their own generator on the same schema. Policies
+//! are validated like JSON documents when loaded. Fields are only ever added to the schema, a
+//! policy without version was written before the version was added and is read as version 1.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//!
+//! let bytes = acl.to_proto_bytes();
+//! let acl   = Acl::from_proto_bytes(&bytes).unwrap();
+//!
+//! assert!(acl.is_allowed(Some("guest"), None, Some("view")));
+//! ```
+
+use crate::policy::{export, load};
+use crate::{Acl, Error};
+use prost::Message;
+use serde_json::{json, Map, Value};
+use std::convert::TryFrom;
+
+
+// Messages ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The roles, resources, rules, bypass roles and default role of an `Acl`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Policy {
+    #[prost(message, repeated, tag = "1")]
+    pub roles:        Vec<Role>,
+    #[prost(message, repeated, tag = "2")]
+    pub resources:    Vec<Resource>,
+    #[prost(message, repeated, tag = "3")]
+    pub rules:        Vec<Rule>,
+    #[prost(string, repeated, tag = "4")]
+    pub bypass:       Vec<String>,
+    /// the version of the policy document format, 0 if unset
+    #[prost(uint64, tag = "5")]
+    pub version:      u64,
+    #[prost(string, optional, tag = "6")]
+    pub default_role: Option<String>,
+} // struct Policy
+
+/// A role with its parents in order of declaration.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Role {
+    #[prost(string, tag = "1")]
+    pub name:    String,
+    #[prost(string, repeated, tag = "2")]
+    pub parents: Vec<String>,
+} // struct Role
+
+/// A resource with its parent.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Resource {
+    #[prost(string, tag = "1")]
+    pub name:   String,
+    #[prost(string, optional, tag = "2")]
+    pub parent: Option<String>,
+} // struct Resource
+
+/// The access granted by a rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Access {
+    Deny  = 0,
+    Allow = 1,
+} // enum Access
+
+/// A rule. Unset role, resource or privilege is a wildcard.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Rule {
+    #[prost(enumeration = "Access", tag = "1")]
+    pub access:      i32,
+    #[prost(string, optional, tag = "2")]
+    pub role:        Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub resource:    Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub privilege:   Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub description: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub author:      Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub ticket:      Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub message:     Option<String>,
+    #[prost(string, optional, tag = "9")]
+    pub condition:   Option<String>,
+    #[prost(string, optional, tag = "10")]
+    pub environment: Option<String>,
+} // struct Rule
+
+
+// Conversion /////////////////////////////////////////////////////////////////////////////////////
+
+
+fn string(value: &Value) -> Option<String> {
+    value.as_str().map(String::from)
+} // string
+
+fn strings(value: &Value) -> Vec<String> {
+    value.as_array().map(|values| values.iter().filter_map(string).collect()).unwrap_or_default()
+} // strings
+
+impl From<&Value> for Policy {
+
+    /// Converts an exported policy document.
+    fn from(doc: &Value) -> Self {
+        let entries = |key: &str| doc[key].as_array().cloned().unwrap_or_default();
+
+        Policy{
+            roles:        entries("roles").iter().map(|role| Role{
+                name:    string(&role["name"]).unwrap_or_default(),
+                parents: strings(&role["parents"]),
+            }).collect(),
+            resources:    entries("resources").iter().map(|resource| Resource{
+                name:   string(&resource["name"]).unwrap_or_default(),
+                parent: string(&resource["parent"]),
+            }).collect(),
+            rules:        entries("rules").iter().map(|rule| Rule{
+                access:      if rule["access"] == "allow" { Access::Allow } else { Access::Deny } as i32,
+                role:        string(&rule["role"]),
+                resource:    string(&rule["resource"]),
+                privilege:   string(&rule["privilege"]),
+                description: string(&rule["description"]),
+                author:      string(&rule["author"]),
+                ticket:      string(&rule["ticket"]),
+                message:     string(&rule["message"]),
+                condition:   string(&rule["condition"]),
+                environment: string(&rule["environment"]),
+            }).collect(),
+            bypass:       strings(&doc["bypass"]),
+            version:      doc["version"].as_u64().unwrap_or_default(),
+            default_role: string(&doc["default_role"]),
+        } // Policy
+    } // from
+
+} // impl From<&Value> for Policy
+
+impl From<&Policy> for Value {
+
+    /// Converts into a policy document, which is yet to be validated.
+    fn from(policy: &Policy) -> Self {
+        let roles: Vec<Value> = policy.roles.iter()
+            .map(|role| json!({"name": role.name, "parents": role.parents}))
+            .collect();
+        let resources: Vec<Value> = policy.resources.iter()
+            .map(|resource| json!({"name": resource.name, "parent": resource.parent}))
+            .collect();
+        let rules: Vec<Value> = policy.rules.iter().map(|rule| {
+            let mut map = Map::new();
+
+            map.insert(String::from("access"), match Access::try_from(rule.access) {
+                Ok(Access::Allow) => json!("allow"),
+                Ok(Access::Deny)  => json!("deny"),
+                Err(_)            => json!(rule.access),
+            }); // insert
+            for (key, value) in &[("role", &rule.role), ("resource", &rule.resource), ("privilege", &rule.privilege),
+                                  ("description", &rule.description), ("author", &rule.author), ("ticket", &rule.ticket),
+                                  ("message", &rule.message), ("condition", &rule.condition), ("environment", &rule.environment)] {
+                if let Some(value) = value {
+                    map.insert(String::from(*key), json!(value));
+                } // if
+            } // for
+            Value::Object(map)
+        }).collect();
+
+        let mut doc = json!({"roles": roles, "resources": resources, "rules": rules, "bypass": policy.bypass});
+
+        // an unset version precedes versioning
+        if policy.version > 0 {
+            doc["version"] = json!(policy.version);
+        } // if
+        if let Some(name) = &policy.default_role {
+            doc["default_role"] = json!(name);
+        } // if
+        doc
+    } // from
+
+} // impl From<&Policy> for Value
+
+impl Acl {
+
+    /// Exports the `Acl` as protobuf `Policy`, in the order of `to_json`.
+    pub fn to_proto(&self) -> Policy {
+        Policy::from(&export(self))
+    } // to_proto
+
+    /// Creates an `Acl` from a protobuf `Policy`. Returns an error if the policy is invalid.
+    pub fn from_proto(policy: &Policy) -> Result<Acl, Error> {
+        load(&Value::from(policy), None)
+    } // from_proto
+
+    /// Exports the `Acl` as encoded protobuf `Policy`.
+    pub fn to_proto_bytes(&self) -> Vec<u8> {
+        self.to_proto().encode_to_vec()
+    } // to_proto_bytes
+
+    /// Creates an `Acl` from an encoded protobuf `Policy`. Returns an error if the message is
+    /// malformed or the policy is invalid.
+    pub fn from_proto_bytes(bytes: &[u8]) -> Result<Acl, Error> {
+        let policy = Policy::decode(bytes).map_err(|e| Error::Parse(e.to_string()))?;
+
+        Self::from_proto(&policy)
+    } // from_proto_bytes
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn round_trip() {
+        let acl = Acl::from_json(r#"{
+            "roles":     [{"name": "guest"}, {"name": "staff", "parents": ["guest"]}, {"name": "root"}],
+            "resources": [{"name": "news"}, {"name": "latest", "parent": "news"}],
+            "rules":     [{"access": "allow", "role": "guest", "privilege": "view"},
+                          {"access": "deny", "role": "staff", "resource": "latest", "privilege": "revise", "ticket": "CR-42",
+                           "message": "ask an editor"},
+                          {"access": "allow", "role": "staff", "privilege": "edit", "condition": "owner"},
+                          {"access": "allow", "role": "staff", "privilege": "debug", "environment": "dev"}],
+            "bypass":    ["root"],
+            "default_role": "guest"
+        }"#).unwrap();
+        let policy = acl.to_proto();
+
+        assert_eq!(policy.roles[2], Role{name: String::from("staff"), parents: vec![String::from("guest")]});
+        assert_eq!(policy.rules[3].access, Access::Deny as i32);
+        assert_eq!(policy.bypass, vec![String::from("root")]);
+        assert_eq!(policy.version, crate::policy::SCHEMA_VERSION);
+        assert_eq!(Acl::from_proto_bytes(&acl.to_proto_bytes()).unwrap().to_json(), acl.to_json());
+
+        // policies without version precede versioning, newer ones are rejected
+        let mut policy = acl.to_proto();
+
+        policy.version = 0;
+        assert_eq!(Acl::from_proto(&policy).unwrap().to_json(), acl.to_json());
+        policy.version = crate::policy::SCHEMA_VERSION + 1;
+        assert_eq!(Acl::from_proto(&policy).err(), Some(Error::SchemaVersion(crate::policy::SCHEMA_VERSION + 1)));
+    } // round_trip
+
+    #[test]
+    fn invalid() {
+        let mut policy = Policy::default();
+
+        policy.rules.push(Rule{access: 7, role: Some(String::from("guest")), ..Rule::default()});
+        match Acl::from_proto(&policy) {
+            Err(Error::Schema(errors)) => assert_eq!(errors[0].path, "rules[0].access"),
+            _                          => panic!("expected schema errors"),
+        } // match
+        assert!(matches!(Acl::from_proto_bytes(&[0xff]), Err(Error::Parse(_))));
+    } // invalid
+
+} // mod tests
@@ -0,0 +1,264 @@
+//! Lazily resolved roles and resources.
+//!
+//! A `RoleProvider` is consulted when a queried role is not defined in the `Acl`, e.g. to fetch
+//! the parents of per-user roles from an identity service on first use. Likewise a
+//! `ResourceProvider` resolves undefined resources, e.g. per-object resources created at runtime.
+//! Resolved roles and resources are cached by the `Acl`. They take part in queries only, rules
+//! must still be defined for roles and resources added with `add_role` and `add_resource`.
+//!
+//! Any closure taking the role name and returning its parents is a role provider. Any closure
+//! taking the resource name and returning its parent is a resource provider.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.allow(Some("staff"), None, Some("edit")).unwrap();
+//! acl.set_role_provider(|name: &'static str| {
+//!     // ask the identity service
+//!     if name.starts_with("user:") { Some(vec!["staff"]) } else { None }
+//! });
+//!
+//! assert!(acl.is_allowed(Some("user:sally"), None, Some("edit")));
+//! assert!(acl.is_denied(Some("robot:r2d2"), None, Some("edit")));
+//! ```
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_resource("documents", None).unwrap();
+//! acl.allow(Some("guest"), Some("documents"), Some("view")).unwrap();
+//! acl.set_resource_provider(|name: &'static str| {
+//!     // every document belongs to the documents resource
+//!     if name.starts_with("document:") { Some(Some("documents")) } else { None }
+//! });
+//!
+//! assert!(acl.is_allowed(Some("guest"), Some("document:42"), Some("view")));
+//! ```
+
+use crate::{Access, Acl};
+use log::trace;
+
+/// Resolves roles which are not defined in the `Acl`.
+pub trait RoleProvider {
+
+    /// Returns the parents of role in declaration order, or None if role is unknown. Parents
+    /// which are not defined in the `Acl` are resolved by the provider as well.
+    fn parents(&self, role: &'static str) -> Option<Vec<&'static str>>;
+
+    /// Like `parents`, but returns the access to decide queries of role with if resolving it
+    /// failed, see module `breaker`. Failures aren't cached.
+    fn try_parents(&self, role: &'static str) -> Result<Option<Vec<&'static str>>, Access> {
+        Ok(self.parents(role))
+    } // try_parents
+
+} // trait RoleProvider
+
+impl<F: Fn(&'static str) -> Option<Vec<&'static str>>> RoleProvider for F {
+
+    fn parents(&self, role: &'static str) -> Option<Vec<&'static str>> {
+        self(role)
+    } // parents
+
+} // impl RoleProvider for F
+
+/// Resolves resources which are not defined in the `Acl`.
+pub trait ResourceProvider {
+
+    /// Returns the parent of resource, or None if resource is unknown. A parent which is not
+    /// defined in the `Acl` is resolved by the provider as well.
+    fn parent(&self, resource: &'static str) -> Option<Option<&'static str>>;
+
+    /// Like `parent`, but returns the access to decide queries of resource with if resolving it
+    /// failed, see module `breaker`. Failures aren't cached.
+    fn try_parent(&self, resource: &'static str) -> Result<Option<Option<&'static str>>, Access> {
+        Ok(self.parent(resource))
+    } // try_parent
+
+} // trait ResourceProvider
+
+impl<F: Fn(&'static str) -> Option<Option<&'static str>>> ResourceProvider for F {
+
+    fn parent(&self, resource: &'static str) -> Option<Option<&'static str>> {
+        self(resource)
+    } // parent
+
+} // impl ResourceProvider for F
+
+impl Acl {
+
+    /// Sets the provider consulted for roles which are not defined. Purges roles resolved by a
+    /// previous provider and the cache.
+    pub fn set_role_provider<P: RoleProvider + 'static>(&mut self, provider: P) {
+        trace!("setting role provider");
+        self.role_provider = Some(Box::new(provider));
+        self.purge_provided_roles();
+    } // set_role_provider
+
+    /// Purges the roles resolved by the role provider and the cache, so roles are resolved again
+    /// on next use.
+    pub fn purge_provided_roles(&mut self) {
+        self.provided_roles.get_mut().clear();
+        self.advance_generation();
+        self.purge_cache();
+    } // purge_provided_roles
+
+    /// Sets the provider consulted for resources which are not defined. Purges resources resolved
+    /// by a previous provider and the cache.
+    pub fn set_resource_provider<P: ResourceProvider + 'static>(&mut self, provider: P) {
+        trace!("setting resource provider");
+        self.resource_provider = Some(Box::new(provider));
+        self.purge_provided_resources();
+    } // set_resource_provider
+
+    /// Purges the resources resolved by the resource provider and the cache, so resources are
+    /// resolved again on next use.
+    pub fn purge_provided_resources(&mut self) {
+        self.provided_resources.get_mut().clear();
+        self.advance_generation();
+        self.purge_cache();
+    } // purge_provided_resources
+
+    /// Returns the parent of resource, consulting the resource provider for undefined resources.
+    pub(crate) fn lookup_resource(&self, name: &'static str) -> Option<Option<&'static str>> {
+        if let Some(parent) = self.resources.get(name) {
+            return Some(*parent);
+        } // if
+        let provider = self.resource_provider.as_ref()?;
+
+        if let Some(parent) = self.provided_resources.borrow().get(name) {
+            return *parent;
+        } // if
+        trace!("resolving resource {} by provider", name);
+        let parent = match provider.try_parent(name) {
+            Ok(parent)  => parent,
+            Err(access) => {
+                self.provider_failure.set(Some(access));
+                return None;
+            }, // Err
+        }; // match
+
+        self.provided_resources.borrow_mut().insert(name, parent);
+        parent
+    } // lookup_resource
+
+    /// Returns the parents of role in search order, consulting the role provider for undefined
+    /// roles.
+    pub(crate) fn lookup_role(&self, name: &'static str) -> Option<Vec<&'static str>> {
+        if let Some(parents) = self.roles.get(name) {
+            return Some(parents.clone());
+        } // if
+        let provider = self.role_provider.as_ref()?;
+
+        if let Some(parents) = self.provided_roles.borrow().get(name) {
+            return parents.clone();
+        } // if
+        trace!("resolving role {} by provider", name);
+        let parents = match provider.try_parents(name) {
+            Ok(parents) => parents.map(|mut parents| {
+                parents.reverse();
+                parents
+            }),
+            Err(access) => {
+                self.provider_failure.set(Some(access));
+                return None;
+            }, // Err
+        }; // match
+
+        self.provided_roles.borrow_mut().insert(name, parents.clone());
+        parents
+    } // lookup_role
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use test_env_log::test;
+
+    #[test]
+    fn roles() {
+        let calls   = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), None, Some("edit")).is_ok());
+        acl.set_role_provider(move |name: &'static str| {
+            counter.set(counter.get() + 1);
+            match name {
+                "team:news"  => Some(vec!["guest"]),
+                "user:sally" => Some(vec!["team:news", "staff"]),
+                "loop:a"     => Some(vec!["loop:b"]),
+                "loop:b"     => Some(vec!["loop:a"]),
+                _            => None,
+            } // match
+        });
+        acl.lock();
+
+        assert!(!acl.has_role("user:sally"));
+        assert_eq!(acl.get_role_lineage("user:sally"), vec!["user:sally", "staff", "guest", "team:news"]);
+        assert!(acl.is_allowed(Some("user:sally"), None, Some("edit")));
+        assert!(acl.is_allowed(Some("team:news"), None, Some("view")));
+        assert!(acl.is_denied (Some("team:news"), None, Some("edit")));
+        assert!(acl.is_denied (Some("user:bob"), None, Some("view")));
+        assert!(acl.is_denied (Some("user:bob"), None, Some("edit")));
+        assert_eq!(calls.get(), 3);
+        assert_eq!(acl.get_role_lineage("loop:a"), vec!["loop:a", "loop:b"]);
+        assert_eq!(acl.iter_role_lineage("loop:b").collect::<Vec<_>>(), vec!["loop:b", "loop:a"]);
+        assert_eq!(calls.get(), 5);
+
+        // rules require defined roles
+        acl.unlock();
+        assert!(acl.allow(Some("user:sally"), None, None).is_err());
+
+        acl.purge_provided_roles();
+        assert!(acl.is_allowed(Some("user:sally"), None, Some("edit")));
+        assert_eq!(calls.get(), 7);
+    } // roles
+
+    #[test]
+    fn resources() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_resource("documents", None).is_ok());
+        assert!(acl.allow(Some("guest"), Some("documents"), Some("view")).is_ok());
+        acl.set_resource_provider(|name: &'static str| match name {
+            "folder:1"   => Some(Some("documents")),
+            "document:1" => Some(Some("folder:1")),
+            "orphan:1"   => Some(None),
+            "loop:1"     => Some(Some("loop:2")),
+            "loop:2"     => Some(Some("loop:1")),
+            _            => None,
+        });
+
+        assert!(!acl.has_resource("document:1"));
+        assert_eq!(acl.get_resource_lineage("document:1"), vec!["document:1", "folder:1", "documents"]);
+        assert_eq!(acl.get_resource_lineage("orphan:1"), vec!["orphan:1"]);
+        assert_eq!(acl.get_resource_lineage("loop:1"), vec!["loop:1", "loop:2"]);
+        assert_eq!(acl.iter_resource_lineage("loop:2").collect::<Vec<_>>(), vec!["loop:2", "loop:1"]);
+        assert_eq!(acl.get_resource_lineage("unknown"), Vec::<&str>::new());
+        assert!(acl.is_allowed(Some("guest"), Some("document:1"), Some("view")));
+        assert!(acl.is_denied (Some("guest"), Some("orphan:1"), Some("view")));
+        assert!(acl.is_denied (Some("guest"), Some("loop:1"), Some("view")));
+
+        // rules require defined resources
+        assert!(acl.allow(Some("guest"), Some("folder:1"), None).is_err());
+    } // resources
+
+} // mod tests
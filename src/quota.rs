@@ -0,0 +1,165 @@
+//! Quota-limited privileges.
+//!
+//! A quota limits how often an allow rule may be used, e.g. a trial role may export at most five
+//! times per day. `consume` decides the query like `is_allowed` and, if the deciding rule has a
+//! quota, uses it up by one. Once the quota is exhausted, `consume` denies access until the
+//! quota is reset, e.g. by a daily maintenance job. `is_allowed` ignores quotas. Removing or
+//! revoking a rule removes its quota.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("trial", vec![]).unwrap();
+//! acl.allow(Some("trial"), None, Some("export")).unwrap();
+//! acl.set_quota(Some("trial"), None, Some("export"), 2).unwrap();
+//!
+//! assert!( acl.consume(Some("trial"), None, Some("export")));
+//! assert!( acl.consume(Some("trial"), None, Some("export")));
+//! assert!(!acl.consume(Some("trial"), None, Some("export")));
+//!
+//! acl.reset_quotas();
+//! assert!(acl.consume(Some("trial"), None, Some("export")));
+//! ```
+
+use crate::{Access, Acl, Error, Privilege, Query, Resource, Role};
+use crate::etag::Item;
+use log::{debug, trace};
+use std::cell::Cell;
+
+/// The limit and usage of an allow rule.
+#[derive(Debug)]
+pub(crate) struct Quota {
+    pub(crate) limit: u32,
+    used:             Cell<u32>,
+} // struct Quota
+
+impl Acl {
+
+    /// Limits how often the allow rule defined for role on resource to privilege may be consumed.
+    /// Replaces a previous quota and resets its usage. Returns an error if no allow rule is
+    /// defined or the `Acl` is locked.
+    pub fn set_quota(&mut self, role: Role, resource: Resource, privilege: Privilege, limit: u32) -> Result<(), Error> {
+        trace!("setting quota for {:?} on {:?} to {:?} to {}", role, resource, privilege, limit);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let query = Query{resource, role, privilege};
+
+        match self.rules.get(&query) {
+            Some(rule) if rule.acc == Access::Allow => {
+                if let Some(previous) = self.quotas.insert(query, Quota{limit, used: Cell::new(0)}) {
+                    self.track(Item::Quota(&query, previous.limit), false);
+                } // if
+                self.track(Item::Quota(&query, limit), true);
+                Ok(())
+            }, // Some
+            _ => Err(Error::MissingRule(query.to_string())),
+        } // match
+    } // set_quota
+
+    /// Removes the quota of the rule defined for role on resource to privilege. Returns true if a
+    /// quota has been removed. Returns an error if the `Acl` is locked.
+    pub fn unset_quota(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<bool, Error> {
+        trace!("unsetting quota for {:?} on {:?} to {:?}", role, resource, privilege);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let query = Query{resource, role, privilege};
+
+        match self.quotas.remove(&query) {
+            Some(quota) => {
+                self.track(Item::Quota(&query, quota.limit), false);
+                Ok(true)
+            }, // Some
+            None        => Ok(false),
+        } // match
+    } // unset_quota
+
+    /// Returns the remaining uses of the rule defined for role on resource to privilege, or None if
+    /// the rule has no quota.
+    pub fn remaining_quota(&self, role: Role, resource: Resource, privilege: Privilege) -> Option<u32> {
+        self.quotas.get(&Query{resource, role, privilege}).map(|quota| quota.limit.saturating_sub(quota.used.get()))
+    } // remaining_quota
+
+    /// Returns true if privilege is allowed for role on resource and uses up the quota of the
+    /// deciding rule by one. Returns false if the quota is exhausted.
+    pub fn consume(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        let decision = self.decide(role, resource, privilege);
+
+        if decision.is_denied() {
+            return false;
+        } // if
+        if let Some(quota) = self.quotas.get(&decision.matched) {
+            if quota.used.get() >= quota.limit {
+                debug!("quota of {} exhausted by {}", decision.matched, decision.query);
+                return false;
+            } // if
+            quota.used.set(quota.used.get() + 1);
+        } // if
+        true
+    } // consume
+
+    /// Resets the usage of all quotas.
+    pub fn reset_quotas(&self) {
+        trace!("resetting quotas");
+        for quota in self.quotas.values() {
+            quota.used.set(0);
+        } // for
+    } // reset_quotas
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn quotas() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("trial", vec![]).is_ok());
+        assert!(acl.add_role("sally", vec!["trial"]).is_ok());
+        assert!(acl.add_resource("reports", None).is_ok());
+        assert!(acl.allow(Some("trial"), None, Some("export")).is_ok());
+        assert!(acl.allow(Some("trial"), None, Some("view")).is_ok());
+        assert!(acl.set_quota(Some("trial"), None, Some("export"), 2).is_ok());
+        assert_eq!(acl.set_quota(Some("trial"), None, Some("delete"), 2), Err(Error::MissingRule(String::from("trial→*: delete"))));
+
+        // the quota of the deciding rule is shared
+        assert!( acl.consume(Some("sally"), Some("reports"), Some("export")));
+        assert!( acl.consume(Some("trial"), None, Some("export")));
+        assert!(!acl.consume(Some("sally"), None, Some("export")));
+        assert!( acl.is_allowed(Some("sally"), None, Some("export")));
+        assert_eq!(acl.remaining_quota(Some("trial"), None, Some("export")), Some(0));
+
+        // rules without quota and denied queries
+        assert!( acl.consume(Some("sally"), None, Some("view")));
+        assert!(!acl.consume(Some("sally"), None, Some("delete")));
+        assert_eq!(acl.remaining_quota(Some("trial"), None, Some("view")), None);
+
+        acl.reset_quotas();
+        assert_eq!(acl.remaining_quota(Some("trial"), None, Some("export")), Some(2));
+        assert_eq!(acl.unset_quota(Some("trial"), None, Some("export")), Ok(true));
+        assert_eq!(acl.unset_quota(Some("trial"), None, Some("export")), Ok(false));
+        for _ in 0..3 {
+            assert!(acl.consume(Some("sally"), None, Some("export")));
+        } // for
+
+        // quotas are part of the policy, unlike their usage
+        assert!(acl.set_quota(Some("trial"), None, Some("export"), 1).is_ok());
+        acl.lock();
+        assert_eq!(acl.set_quota(Some("trial"), None, Some("view"), 1), Err(Error::Locked));
+        assert_eq!(acl.unset_quota(Some("trial"), None, Some("export")), Err(Error::Locked));
+        assert!( acl.consume(Some("sally"), None, Some("export")));
+        assert!(!acl.consume(Some("sally"), None, Some("export")));
+    } // quotas
+
+} // mod tests
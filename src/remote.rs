@@ -0,0 +1,247 @@
+//! Client for a remote policy decision point.
+//!
+//! `RemoteAcl` defers queries to a central policy server through a `Transport` and caches the
+//! decisions. Allowed and denied decisions are cached with separate time to live. If the server
+//! is unavailable, expired decisions are still served within a grace period, afterwards queries
+//! fail closed, i.e. access is denied.
+//!
+//! The transport, e.g. an HTTP or gRPC client, is provided by the embedder. Any closure taking a
+//! `Query` and returning the `Access` is a transport.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Access, Query};
+//! # use zorq_acl::remote::RemoteAcl;
+//! let remote = RemoteAcl::new(|query: Query| {
+//!     // ask the policy server
+//!     Ok(if query.role == Some("admin") { Access::Allow } else { Access::Deny })
+//! });
+//!
+//! assert!(remote.is_allowed(Some("admin"), None, Some("delete")));
+//! assert!(remote.is_denied(Some("guest"), None, Some("delete")));
+//! ```
+
+use crate::{Access, Error, Privilege, Query, Resource, Role};
+use log::{trace, warn};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+
+// Transport //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Queries the remote policy decision point.
+pub trait Transport {
+
+    /// Returns the access decided by the remote policy decision point.
+    fn query(&self, query: Query) -> Result<Access, Error>;
+
+} // trait Transport
+
+impl<F: Fn(Query) -> Result<Access, Error>> Transport for F {
+
+    fn query(&self, query: Query) -> Result<Access, Error> {
+        self(query)
+    } // query
+
+} // impl Transport for F
+
+
+// RemoteAcl //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A cached decision and the time it has been fetched.
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    access:  Access,
+    fetched: Instant,
+} // struct Entry
+
+/// Defers queries to a remote policy decision point and caches the decisions.
+pub struct RemoteAcl<T: Transport> {
+    transport:    T,
+    ttl:          Duration,
+    negative_ttl: Duration,
+    grace:        Duration,
+    cache:        RefCell<HashMap<Query, Entry>>,
+} // struct RemoteAcl
+
+impl<T: Transport> RemoteAcl<T> {
+
+    /// The default time to live of allowed decisions.
+    pub const TTL:          Duration = Duration::from_secs(60);
+    /// The default time to live of denied decisions.
+    pub const NEGATIVE_TTL: Duration = Duration::from_secs(10);
+    /// The default grace period expired decisions are served for while the server is unavailable.
+    pub const GRACE:        Duration = Duration::from_secs(300);
+
+    /// Creates a new `RemoteAcl` with default time to live and grace period.
+    pub fn new(transport: T) -> Self {
+        RemoteAcl{
+            transport,
+            ttl:          Self::TTL,
+            negative_ttl: Self::NEGATIVE_TTL,
+            grace:        Self::GRACE,
+            cache:        RefCell::new(HashMap::new()),
+        } // RemoteAcl
+    } // new
+
+    /// Sets the time to live of allowed decisions.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+    } // set_ttl
+
+    /// Sets the time to live of denied decisions. A zero duration disables negative caching.
+    pub fn set_negative_ttl(&mut self, ttl: Duration) {
+        self.negative_ttl = ttl;
+    } // set_negative_ttl
+
+    /// Sets the grace period, beyond the time to live, expired decisions are served for while the
+    /// server is unavailable.
+    pub fn set_grace(&mut self, grace: Duration) {
+        self.grace = grace;
+    } // set_grace
+
+    /// Returns the access for role on resource to privilege. Returns an error if the server is
+    /// unavailable and no decision is cached within the grace period.
+    pub fn try_access(&self, role: Role, resource: Resource, privilege: Privilege) -> Result<Access, Error> {
+        let query = Query{resource, role, privilege};
+        let now   = Instant::now();
+        let entry = self.cache.borrow().get(&query).copied();
+
+        if let Some(entry) = entry {
+            let ttl = match entry.access {
+                Access::Allow => self.ttl,
+                Access::Deny  => self.negative_ttl,
+            }; // match
+
+            if now.duration_since(entry.fetched) < ttl {
+                trace!("remote cache hit for {}", query);
+                return Ok(entry.access);
+            } // if
+        } // if
+        match self.transport.query(query) {
+            Ok(access) => {
+                self.cache.borrow_mut().insert(query, Entry{access, fetched: now});
+                Ok(access)
+            }, // Ok
+            Err(e)     => match entry {
+                Some(entry) if now.duration_since(entry.fetched) < self.ttl.max(self.negative_ttl) + self.grace => {
+                    warn!("serving stale decision for {}: {}", query, e);
+                    Ok(entry.access)
+                }, // Some
+                _ => {
+                    warn!("remote query failed for {}: {}", query, e);
+                    Err(e)
+                }, // _
+            }, // Err
+        } // match
+    } // try_access
+
+    /// Returns true if privilege is allowed for role on resource. Fails closed if the server is
+    /// unavailable.
+    #[inline]
+    pub fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.try_access(role, resource, privilege) == Ok(Access::Allow)
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource. Fails closed if the server is
+    /// unavailable.
+    #[inline]
+    pub fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        !self.is_allowed(role, resource, privilege)
+    } // is_denied
+
+    /// Purges the cache.
+    pub fn purge_cache(&self) {
+        self.cache.borrow_mut().clear();
+    } // purge_cache
+
+} // impl RemoteAcl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Acl;
+    use std::cell::Cell;
+    use std::thread::sleep;
+    use test_env_log::test;
+
+    struct Server {
+        acl:   Acl,
+        calls: Cell<usize>,
+        down:  Cell<bool>,
+    } // struct Server
+
+    impl Transport for &Server {
+
+        fn query(&self, query: Query) -> Result<Access, Error> {
+            self.calls.set(self.calls.get() + 1);
+            if self.down.get() {
+                return Err(Error::Io(String::from("connection refused")));
+            } // if
+            Ok(self.acl.get_rule(query.role, query.resource, query.privilege).access())
+        } // query
+
+    } // impl Transport for &Server
+
+    fn server() -> Server {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        Server{acl, calls: Cell::new(0), down: Cell::new(false)}
+    } // server
+
+    #[test]
+    fn cache() {
+        let server     = server();
+        let mut remote = RemoteAcl::new(&server);
+
+        remote.set_negative_ttl(Duration::from_secs(0));
+        assert!(remote.is_allowed(Some("guest"), None, Some("view")));
+        assert!(remote.is_allowed(Some("guest"), None, Some("view")));
+        assert_eq!(server.calls.get(), 1);
+
+        // negative caching is disabled
+        assert!(remote.is_denied(Some("guest"), None, Some("edit")));
+        assert!(remote.is_denied(Some("guest"), None, Some("edit")));
+        assert_eq!(server.calls.get(), 3);
+
+        remote.purge_cache();
+        assert!(remote.is_allowed(Some("guest"), None, Some("view")));
+        assert_eq!(server.calls.get(), 4);
+    } // cache
+
+    #[test]
+    fn outage() {
+        let server     = server();
+        let mut remote = RemoteAcl::new(&server);
+
+        remote.set_ttl(Duration::from_millis(1));
+        remote.set_grace(Duration::from_secs(60));
+        assert!(remote.is_allowed(Some("guest"), None, Some("view")));
+        server.down.set(true);
+        sleep(Duration::from_millis(5));
+
+        // expired, but served within the grace period
+        assert!(remote.is_allowed(Some("guest"), None, Some("view")));
+        assert_eq!(server.calls.get(), 2);
+
+        // never cached, fails closed
+        assert!(remote.is_denied(Some("guest"), None, Some("edit")));
+        assert_eq!(remote.try_access(Some("guest"), None, Some("edit")),
+            Err(Error::Io(String::from("connection refused"))));
+
+        remote.set_grace(Duration::from_secs(0));
+        remote.set_negative_ttl(Duration::from_secs(0));
+        assert!(remote.is_denied(Some("guest"), None, Some("view")));
+    } // outage
+
+} // mod tests
@@ -0,0 +1,224 @@
+//! Markdown permission reports.
+//!
+//! `to_markdown_report` renders the policy as a document for a wiki or a release note: the role
+//! hierarchy, the resource tree, the described privileges and a table of effective permissions
+//! per role. Rows are the wildcard resource and all resources in tree order, columns the wildcard
+//! privilege and all privileges registered or named by rules or resource defaults, labeled if
+//! described. The report is deterministic, so two reports can be diffed.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.allow(Some("guest"), Some("news"), Some("view")).unwrap();
+//!
+//! let report = acl.to_markdown_report();
+//!
+//! assert!(report.contains("### guest\n\n| Resource | * | view |\n"));
+//! assert!(report.contains("| news | deny | allow |\n"));
+//! ```
+
+use crate::{Acl, Privilege, Query, Resource, Role, Rule};
+use log::trace;
+use std::cell::Cell;
+use std::fmt::Write;
+
+/// Escapes the pipes of a table cell.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+} // cell
+
+impl Acl {
+
+    /// Returns the report of the policy, see module `report`.
+    pub fn to_markdown_report(&self) -> String {
+        trace!("reporting {} roles and {} resources", self.roles.len(), self.resources.len());
+        let mut report = String::from("# Permission report\n");
+
+        // roles
+        report.push_str("\n## Roles\n\n");
+        if self.roles.is_empty() {
+            report.push_str("No roles are defined.\n");
+        } else {
+            report.push_str("| Role | Parents | Lineage |\n| --- | --- | --- |\n");
+            for name in self.roles.keys() {
+                let parents = self.get_role_parents(name).unwrap_or_default();
+                let bypass  = if self.bypass.contains(name) { " (bypass)" } else { "" };
+
+                let _ = writeln!(report, "| {}{} | {} | {} |", name, bypass, parents.join(", "), self.get_role_lineage(name).join(" → "));
+            } // for
+        } // else
+
+        // resources
+        let resources = self.resource_tree();
+
+        report.push_str("\n## Resources\n\n");
+        if resources.is_empty() {
+            report.push_str("No resources are defined.\n");
+        } // if
+        for (depth, name) in &resources {
+            let _ = writeln!(report, "{}- {}", "  ".repeat(*depth), name);
+        } // for
+
+        // privileges
+        let privileges = self.known_privileges();
+
+        if !self.privilege_info.is_empty() {
+            report.push_str("\n## Privileges\n\n| Privilege | Label | Description |\n| --- | --- | --- |\n");
+            for name in &privileges {
+                let info = self.privilege_info.get(name);
+
+                let _ = writeln!(report, "| {} | {} | {} |", name,
+                    cell(info.and_then(|info| info.label.as_deref()).unwrap_or("")),
+                    cell(info.and_then(|info| info.description.as_deref()).unwrap_or("")));
+            } // for
+        } // if
+
+        // effective permissions
+        let columns: Vec<Privilege> = Some(None).into_iter().chain(privileges.iter().map(|name| Some(*name))).collect();
+        let rows: Vec<Resource>     = Some(None).into_iter().chain(resources.iter().map(|(_, name)| Some(*name))).collect();
+
+        report.push_str("\n## Effective permissions\n");
+        for role in self.roles.keys() {
+            let _ = write!(report, "\n### {}\n\n| Resource |", role);
+            for privilege in &columns {
+                let _ = write!(report, " {} |", cell(privilege.map(|name| self.get_privilege_label(name)).unwrap_or("*")));
+            } // for
+            let _ = writeln!(report, "\n| --- |{}", " --- |".repeat(columns.len()));
+            for resource in &rows {
+                let _ = write!(report, "| {} |", resource.unwrap_or("*"));
+                for privilege in &columns {
+                    let _ = write!(report, " {} |", self.effective(Some(role), *resource, *privilege).1.acc.to_string().to_lowercase());
+                } // for
+                report.push('\n');
+            } // for
+        } // for
+        report
+    } // to_markdown_report
+
+    /// Returns the resources depth first with their depth, siblings in lexical order.
+    pub(crate) fn resource_tree(&self) -> Vec<(usize, &'static str)> {
+        let mut tree  = vec![];
+        let mut stack: Vec<(usize, &'static str)> = self.resources.iter()
+            .filter(|(_, parent)| parent.is_none())
+            .map(|(name, _)| (0, *name))
+            .rev()
+            .collect();
+
+        while let Some((depth, name)) = stack.pop() {
+            tree.push((depth, name));
+            stack.extend(self.resources.iter()
+                .filter(|(_, parent)| **parent == Some(name))
+                .map(|(child, _)| (depth + 1, *child))
+                .rev());
+        } // while
+        tree
+    } // resource_tree
+
+    /// Decides the query like `decide`, but without caching, counting or reporting it. Queries
+    /// without role are decided for the default role, if set. Returns the deciding rule and its
+    /// query.
+    pub(crate) fn effective(&self, role: Role, resource: Resource, privilege: Privilege) -> (Query, Rule) {
+        let query       = self.normalize_query(Query{resource, role, privilege});
+        let conditional = Cell::new(false);
+        let lineage     = |roles: &mut Vec<&'static str>| {
+            if let Some(name) = query.role {
+                self.extend_role_lineage(name, self.parent_order, roles);
+            } // if
+        }; // lineage
+        let decision    = self.resolve(query, &[&self.rules], lineage, &conditional, || {
+            self.query_precedence_in(&self.rules, query.role, resource, privilege, &conditional).map(|(matched, rule)| (*matched, *rule))
+        }); // resolve
+
+        (decision.matched, decision.rule)
+    } // effective
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn report() {
+        let mut acl = Acl::new();
+
+        assert_eq!(acl.to_markdown_report(), "# Permission report\n\n## Roles\n\nNo roles are defined.\n\n## Resources\n\nNo resources are defined.\n\n## Effective permissions\n");
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.add_resource("blog", None).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), None).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        assert!(acl.describe_privilege("edit", Some("Edit | revise"), Some("Change the text")).is_ok());
+
+        assert_eq!(acl.to_markdown_report(), "\
+# Permission report
+
+## Roles
+
+| Role | Parents | Lineage |
+| --- | --- | --- |
+| guest |  | guest |
+| root (bypass) |  | root |
+| staff | guest | staff → guest |
+
+## Resources
+
+- blog
+- news
+  - latest
+
+## Privileges
+
+| Privilege | Label | Description |
+| --- | --- | --- |
+| edit | Edit \\| revise | Change the text |
+| view |  |  |
+
+## Effective permissions
+
+### guest
+
+| Resource | * | Edit \\| revise | view |
+| --- | --- | --- | --- |
+| * | deny | deny | allow |
+| blog | deny | deny | allow |
+| news | deny | deny | allow |
+| latest | deny | deny | allow |
+
+### root
+
+| Resource | * | Edit \\| revise | view |
+| --- | --- | --- | --- |
+| * | allow | allow | allow |
+| blog | allow | allow | allow |
+| news | allow | allow | allow |
+| latest | allow | allow | allow |
+
+### staff
+
+| Resource | * | Edit \\| revise | view |
+| --- | --- | --- | --- |
+| * | deny | deny | allow |
+| blog | deny | deny | allow |
+| news | deny | allow | allow |
+| latest | deny | deny | deny |
+");
+    } // report
+
+} // mod tests
@@ -0,0 +1,347 @@
+//! PostgreSQL row-level security policies.
+//!
+//! A `TableMap` maps resources to tables and the SQL commands `SELECT`, `INSERT`, `UPDATE` and
+//! `DELETE` to privileges. `Acl::rls_grants` decides each command on each mapped table for every
+//! role, `Acl::to_rls_sql` emits the statements enabling row-level security and creating a
+//! permissive policy per allowed role and command. Roles are database roles of the same name.
+//! Since permissive policies can only grant, `PUBLIC` is granted only if the wildcard role and
+//! all roles are allowed, otherwise users without any of the roles are denied.
+//!
+//! `Acl::rls_drift` compares the grants of the `Acl` to the permissive policies existing in the
+//! database, e.g. queried by `PG_POLICIES_QUERY` and converted by `RlsGrant::from_pg_policy`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::rls::{Command, TableMap};
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("staff", vec!["guest"]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.allow(Some("guest"), Some("news"), Some("view")).unwrap();
+//! acl.allow(Some("staff"), Some("news"), Some("edit")).unwrap();
+//!
+//! let mut map = TableMap::new();
+//!
+//! map.table("news", "cms.articles").command(Command::Select, "view").command(Command::Update, "edit");
+//!
+//! let sql = acl.to_rls_sql(&map);
+//!
+//! assert!(sql.contains("ALTER TABLE \"cms\".\"articles\" ENABLE ROW LEVEL SECURITY;\n"));
+//! assert!(sql.contains("CREATE POLICY \"zorq_acl_select_guest\" ON \"cms\".\"articles\" AS PERMISSIVE FOR SELECT TO \"guest\" USING (true);\n"));
+//! assert!(!sql.contains("FOR UPDATE TO \"guest\""));
+//! ```
+
+use crate::{Access, Acl, Error};
+use log::trace;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The prefix of generated policy names.
+pub const POLICY_PREFIX: &str = "zorq_acl_";
+
+/// Queries the existing permissive policies for `RlsGrant::from_pg_policy`.
+pub const PG_POLICIES_QUERY: &str = "SELECT schemaname, tablename, cmd, roles FROM pg_policies WHERE permissive = 'PERMISSIVE'";
+
+/// Returns name quoted as SQL identifier.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+} // quote
+
+/// Returns the qualified table quoted.
+fn quote_table(table: &str) -> String {
+    table.split('.').map(quote).collect::<Vec<_>>().join(".")
+} // quote_table
+
+/// Returns table qualified by the `public` schema, unless qualified.
+fn qualify(table: &str) -> String {
+    if table.contains('.') {
+        String::from(table)
+    } else {
+        format!("public.{}", table)
+    } // else
+} // qualify
+
+
+// TableMap ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A SQL command subject to row-level security.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Command {
+    Select,
+    Insert,
+    Update,
+    Delete,
+} // enum Command
+
+impl Command {
+
+    /// All commands.
+    pub const ALL: [Command; 4] = [Command::Select, Command::Insert, Command::Update, Command::Delete];
+
+} // impl Command
+
+impl fmt::Display for Command {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Command::Select => write!(f, "SELECT"),
+            Command::Insert => write!(f, "INSERT"),
+            Command::Update => write!(f, "UPDATE"),
+            Command::Delete => write!(f, "DELETE"),
+        } // match
+    } // fmt
+
+} // impl fmt::Display for Command
+
+/// Maps resources to tables and commands to privileges.
+#[derive(Clone, Debug, Default)]
+pub struct TableMap {
+    tables:   BTreeMap<&'static str, String>,
+    commands: BTreeMap<Command, &'static str>,
+} // struct TableMap
+
+impl TableMap {
+
+    /// Creates a new, empty `TableMap`.
+    pub fn new() -> Self {
+        TableMap{tables: BTreeMap::new(), commands: BTreeMap::new()}
+    } // new
+
+    /// Maps resource to table, optionally qualified by its schema. Replaces a previous mapping.
+    pub fn table(&mut self, resource: &'static str, table: &str) -> &mut Self {
+        self.tables.insert(resource, String::from(table));
+        self
+    } // table
+
+    /// Maps command to privilege. Replaces a previous mapping, unmapped commands aren't granted.
+    pub fn command(&mut self, command: Command, privilege: &'static str) -> &mut Self {
+        self.commands.insert(command, privilege);
+        self
+    } // command
+
+} // impl TableMap
+
+
+// RlsGrant ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A command on a table granted to a role by a permissive policy. None is `PUBLIC`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RlsGrant {
+    /// the table qualified by its schema
+    pub table:   String,
+    pub command: Command,
+    pub role:    Option<String>,
+} // struct RlsGrant
+
+impl RlsGrant {
+
+    /// Returns the grants of a row of `PG_POLICIES_QUERY`. The command `ALL` grants all
+    /// commands, the role `public` is `PUBLIC`.
+    pub fn from_pg_policy(schema: &str, table: &str, command: &str, roles: &[&str]) -> Result<Vec<RlsGrant>, Error> {
+        let commands = match command.to_uppercase().as_str() {
+            "ALL"    => Command::ALL.to_vec(),
+            "SELECT" => vec![Command::Select],
+            "INSERT" => vec![Command::Insert],
+            "UPDATE" => vec![Command::Update],
+            "DELETE" => vec![Command::Delete],
+            _        => return Err(Error::Parse(format!("invalid policy command: {}", command))),
+        }; // match
+        let mut grants = vec![];
+
+        for command in commands {
+            for role in roles {
+                let role = if *role == "public" { None } else { Some(String::from(*role)) };
+
+                grants.push(RlsGrant{table: format!("{}.{}", schema, table), command, role});
+            } // for
+        } // for
+        Ok(grants)
+    } // from_pg_policy
+
+    /// Returns the name of the generated policy.
+    pub fn policy_name(&self) -> String {
+        format!("{}{}_{}", POLICY_PREFIX, self.command.to_string().to_lowercase(), self.role.as_deref().unwrap_or("public"))
+    } // policy_name
+
+    /// Returns the statement creating the policy.
+    pub fn to_sql(&self) -> String {
+        let role      = self.role.as_deref().map(quote).unwrap_or_else(|| String::from("PUBLIC"));
+        let condition = match self.command {
+            Command::Select | Command::Delete => "USING (true)",
+            Command::Insert                   => "WITH CHECK (true)",
+            Command::Update                   => "USING (true) WITH CHECK (true)",
+        }; // match
+
+        format!("CREATE POLICY {} ON {} AS PERMISSIVE FOR {} TO {} {};", quote(&self.policy_name()), quote_table(&self.table), self.command, role, condition)
+    } // to_sql
+
+} // impl RlsGrant
+
+/// The difference of the grants of an `Acl` and those existing in the database.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RlsDrift {
+    /// granted by the `Acl`, but not by the database
+    pub missing: Vec<RlsGrant>,
+    /// granted by the database, but not by the `Acl`
+    pub excess:  Vec<RlsGrant>,
+} // struct RlsDrift
+
+impl RlsDrift {
+
+    /// Returns true if the database grants exactly what the `Acl` grants.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.excess.is_empty()
+    } // is_empty
+
+} // impl RlsDrift
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Returns the grants of all mapped commands on all mapped tables in order, see module `rls`.
+    pub fn rls_grants(&self, map: &TableMap) -> Vec<RlsGrant> {
+        trace!("generating grants of {} tables", map.tables.len());
+        let mut grants = vec![];
+
+        for (resource, table) in &map.tables {
+            for (command, privilege) in &map.commands {
+                let is_allowed = |role| self.effective(role, Some(resource), Some(privilege)).1.acc == Access::Allow;
+                let allowed: Vec<&'static str> = self.roles.keys().copied().filter(|role| is_allowed(Some(role))).collect();
+
+                if is_allowed(None) && allowed.len() == self.roles.len() {
+                    grants.push(RlsGrant{table: qualify(table), command: *command, role: None});
+                    continue;
+                } // if
+                grants.extend(allowed.into_iter().map(|role| RlsGrant{table: qualify(table), command: *command, role: Some(String::from(role))}));
+            } // for
+        } // for
+        grants.sort();
+        grants
+    } // rls_grants
+
+    /// Returns the SQL script enabling row-level security on all mapped tables and replacing the
+    /// generated policies.
+    pub fn to_rls_sql(&self, map: &TableMap) -> String {
+        let grants = self.rls_grants(map);
+        let tables: BTreeSet<String> = map.tables.values().map(|table| qualify(table)).collect();
+        let mut sql = String::new();
+
+        for table in &tables {
+            sql.push_str(&format!("ALTER TABLE {} ENABLE ROW LEVEL SECURITY;\n", quote_table(table)));
+            for command in Command::ALL.iter() {
+                for role in Some(None).into_iter().chain(self.roles.keys().map(|role| Some(String::from(*role)))) {
+                    let name = RlsGrant{table: table.clone(), command: *command, role}.policy_name();
+
+                    sql.push_str(&format!("DROP POLICY IF EXISTS {} ON {};\n", quote(&name), quote_table(table)));
+                } // for
+            } // for
+        } // for
+        for grant in &grants {
+            sql.push_str(&grant.to_sql());
+            sql.push('\n');
+        } // for
+        sql
+    } // to_rls_sql
+
+    /// Compares the grants of this `Acl` to existing grants, considering only mapped tables and
+    /// commands.
+    pub fn rls_drift(&self, map: &TableMap, existing: &[RlsGrant]) -> RlsDrift {
+        let tables: BTreeSet<String> = map.tables.values().map(|table| qualify(table)).collect();
+        let expected: BTreeSet<RlsGrant> = self.rls_grants(map).into_iter().collect();
+        let existing: BTreeSet<RlsGrant> = existing.iter()
+            .filter(|grant| tables.contains(&grant.table) && map.commands.contains_key(&grant.command))
+            .cloned()
+            .collect();
+
+        RlsDrift{
+            missing: expected.difference(&existing).cloned().collect(),
+            excess:  existing.difference(&expected).cloned().collect(),
+        } // RlsDrift
+    } // rls_drift
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn setup() -> (Acl, TableMap) {
+        let mut acl = Acl::new();
+        let mut map = TableMap::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("blog", None).is_ok());
+        assert!(acl.allow(None, Some("news"), Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), None, Some("edit")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("blog"), Some("view")).is_ok());
+        assert!(acl.allow(Some("guest"), Some("blog"), Some("view")).is_ok());
+
+        map.table("news", "news").table("blog", "cms.posts")
+            .command(Command::Select, "view").command(Command::Update, "edit").command(Command::Insert, "edit");
+        (acl, map)
+    } // setup
+
+    fn grant(table: &str, command: Command, role: Option<&str>) -> RlsGrant {
+        RlsGrant{table: String::from(table), command, role: role.map(String::from)}
+    } // grant
+
+    #[test]
+    fn grants() {
+        let (acl, map) = setup();
+
+        assert_eq!(acl.rls_grants(&map), vec![
+            grant("cms.posts", Command::Select, Some("guest")),
+            grant("cms.posts", Command::Insert, Some("staff")),
+            grant("cms.posts", Command::Update, Some("staff")),
+            grant("public.news", Command::Select, None),
+            grant("public.news", Command::Insert, Some("staff")),
+            grant("public.news", Command::Update, Some("staff")),
+        ]);
+        assert_eq!(grant("cms.posts", Command::Update, Some("sta\"ff")).to_sql(),
+            "CREATE POLICY \"zorq_acl_update_sta\"\"ff\" ON \"cms\".\"posts\" AS PERMISSIVE FOR UPDATE TO \"sta\"\"ff\" USING (true) WITH CHECK (true);");
+        assert_eq!(grant("public.news", Command::Select, None).to_sql(),
+            "CREATE POLICY \"zorq_acl_select_public\" ON \"public\".\"news\" AS PERMISSIVE FOR SELECT TO PUBLIC USING (true);");
+
+        let sql = acl.to_rls_sql(&map);
+
+        assert!(sql.starts_with("ALTER TABLE \"cms\".\"posts\" ENABLE ROW LEVEL SECURITY;\nDROP POLICY IF EXISTS \"zorq_acl_select_public\" ON \"cms\".\"posts\";\n"));
+        assert!(sql.ends_with("CREATE POLICY \"zorq_acl_update_staff\" ON \"public\".\"news\" AS PERMISSIVE FOR UPDATE TO \"staff\" USING (true) WITH CHECK (true);\n"));
+        assert_eq!(sql.lines().filter(|line| line.starts_with("DROP POLICY")).count(), 2 * 4 * 3);
+    } // grants
+
+    #[test]
+    fn drift() {
+        let (acl, map) = setup();
+        let mut existing = vec![];
+
+        assert_eq!(acl.rls_drift(&map, &existing).missing.len(), 6);
+
+        existing.extend(RlsGrant::from_pg_policy("public", "news", "ALL", &["staff"]).unwrap());
+        existing.extend(RlsGrant::from_pg_policy("public", "news", "SELECT", &["public"]).unwrap());
+        existing.extend(RlsGrant::from_pg_policy("cms", "posts", "select", &["guest"]).unwrap());
+        existing.extend(RlsGrant::from_pg_policy("cms", "comments", "ALL", &["guest"]).unwrap());
+        existing.extend(acl.rls_grants(&map).into_iter().filter(|grant| grant.table == "cms.posts"));
+
+        assert_eq!(acl.rls_drift(&map, &existing), RlsDrift{
+            missing: vec![],
+            excess:  vec![grant("public.news", Command::Select, Some("staff"))],
+        }); // RlsDrift
+        assert_eq!(RlsGrant::from_pg_policy("cms", "posts", "TRUNCATE", &[]), Err(Error::Parse(String::from("invalid policy command: TRUNCATE"))));
+    } // drift
+
+} // mod tests
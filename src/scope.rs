@@ -0,0 +1,173 @@
+//! Views of an `Acl` restricted to a resource subtree.
+//!
+//! `Acl::scoped_view` returns a `ScopedView` for a resource, e.g. the branch owned by a plugin.
+//! Queries of the view are relative to its root: the wildcard resource denotes the root itself,
+//! named resources must be the root or one of its descendants, including resources resolved by
+//! a resource provider. Queries outside the subtree are refused with `Error::NotPermitted`, so
+//! the holder of a view can't learn about other branches.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.add_resource("latest", Some("news")).unwrap();
+//! acl.add_resource("billing", None).unwrap();
+//! acl.allow(Some("staff"), Some("news"), Some("edit")).unwrap();
+//!
+//! let view = acl.scoped_view("news").unwrap();
+//!
+//! assert!(view.is_allowed(Some("staff"), None, Some("edit")));
+//! assert!(view.is_allowed(Some("staff"), Some("latest"), Some("edit")));
+//! assert!(view.decide(Some("staff"), Some("billing"), Some("edit")).is_err());
+//! ```
+
+use crate::{Acl, Decision, Error, Privilege, Resource, Role};
+use log::{trace, warn};
+
+
+// ScopedView /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Answers queries within a resource subtree of an `Acl`, see module `scope`.
+pub struct ScopedView<'a> {
+    acl:  &'a Acl,
+    root: &'static str,
+} // struct ScopedView
+
+impl<'a> ScopedView<'a> {
+
+    /// Returns the underlying `Acl`.
+    #[inline]
+    pub fn acl(&self) -> &'a Acl {
+        self.acl
+    } // acl
+
+    /// Returns the root resource of the subtree.
+    #[inline]
+    pub fn root(&self) -> &'static str {
+        self.root
+    } // root
+
+    /// Returns true if resource is the root or one of its descendants.
+    pub fn contains(&self, resource: &'static str) -> bool {
+        self.acl.iter_resource_lineage(resource).any(|name| name == self.root)
+    } // contains
+
+    /// Returns an iterator over the defined resources of the subtree in lexical order.
+    pub fn resources(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.acl.resources().filter(move |name| self.contains(name))
+    } // resources
+
+    /// Returns the resource of the underlying `Acl` which resource denotes within the view.
+    /// Returns an error if resource is outside the subtree.
+    pub fn resolve(&self, resource: Resource) -> Result<&'static str, Error> {
+        match resource {
+            None                              => Ok(self.root),
+            Some(name) if self.contains(name) => Ok(name),
+            Some(name)                        => {
+                warn!("refusing resource {} outside scope {}", name, self.root);
+                Err(Error::NotPermitted(format!("resource {} outside scope {}", name, self.root)))
+            }, // Some
+        } // match
+    } // resolve
+
+    /// Decides the query like `Acl::decide` with resource resolved within the view. Returns an
+    /// error if resource is outside the subtree.
+    pub fn decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Result<Decision, Error> {
+        trace!("deciding {:?} on {:?} to {:?} in scope {}", role, resource, privilege, self.root);
+        let resource = self.resolve(resource)?;
+
+        Ok(self.acl.decide(role, Some(resource), privilege))
+    } // decide
+
+    /// Returns true if privilege is allowed for role on resource. Queries outside the subtree are
+    /// never allowed.
+    #[inline]
+    pub fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_ok_and(|decision| decision.is_allowed())
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource. Queries outside the subtree are
+    /// always denied.
+    #[inline]
+    pub fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        !self.is_allowed(role, resource, privilege)
+    } // is_denied
+
+} // impl ScopedView
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Returns a view restricted to the subtree of resource, see module `scope`. Returns an error
+    /// if resource is undefined.
+    pub fn scoped_view(&self, resource: &'static str) -> Result<ScopedView<'_>, Error> {
+        trace!("creating view of scope {}", resource);
+        match self.resources.get_key_value(resource) {
+            Some((root, _)) => Ok(ScopedView{acl: self, root}),
+            None            => Err(Error::MissingResource(String::from(resource))),
+        } // match
+    } // scoped_view
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn scope() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.add_resource("archive", Some("news")).is_ok());
+        assert!(acl.add_resource("billing", None).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), Some("view")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("archive"), Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("billing"), Some("view")).is_ok());
+        acl.set_resource_provider(|name: &'static str| {
+            if name.starts_with("article:") { Some(Some("latest")) } else { None }
+        });
+
+        let view = acl.scoped_view("news").unwrap();
+
+        assert_eq!(view.root(), "news");
+        assert_eq!(view.resources().collect::<Vec<_>>(), vec!["archive", "latest", "news"]);
+        assert!(view.contains("article:1"));
+        assert!(!view.contains("billing"));
+        assert!(!view.contains("unknown"));
+
+        assert_eq!(view.decide(Some("staff"), None, Some("view")).unwrap().query.resource, Some("news"));
+        assert!(view.is_allowed(Some("staff"), None, Some("view")));
+        assert!(view.is_allowed(Some("staff"), Some("article:1"), Some("view")));
+        assert!(view.is_denied(Some("staff"), Some("archive"), Some("view")));
+
+        // other branches are refused, even if allowed
+        assert_eq!(view.decide(Some("staff"), Some("billing"), Some("view")),
+            Err(Error::NotPermitted(String::from("resource billing outside scope news"))));
+        assert!(view.is_denied(Some("staff"), Some("billing"), Some("view")));
+        assert!(view.decide(Some("staff"), Some("unknown"), Some("view")).is_err());
+
+        // views of leaves hold the leaf only
+        let view = acl.scoped_view("latest").unwrap();
+
+        assert_eq!(view.resolve(Some("news")), Err(Error::NotPermitted(String::from("resource news outside scope latest"))));
+        assert!(view.is_allowed(Some("staff"), None, Some("view")));
+        assert!(acl.scoped_view("nothing").is_err());
+    } // scope
+
+} // mod tests
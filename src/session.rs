@@ -0,0 +1,384 @@
+//! Role resolution from web sessions.
+//!
+//! The roles of a user are stored in the session under `ROLES_KEY` at login, e.g. beside
+//! `Identity::login` of actix-identity. The extractor `Authorized<T>` reads them from the session
+//! of the request and rejects the request with 403, unless one of the roles is allowed the
+//! privilege on the resource required by `T`. Requests without roles are decided anonymously, i.e.
+//! for the default role if set, see `Acl::set_default_role`, otherwise for the wildcard role. With
+//! the `axum` feature sessions are those of tower-sessions, with the `actix` feature those of
+//! actix-session.
+//!
+//! Since an `Acl` can't be shared across threads, extractors consult `Grants`, a snapshot of all
+//! decisions of the `Acl` taken by `Acl::grants`. Take a new snapshot whenever the policy changes.
+//! Roles and resources unknown to the snapshot are decided like the wildcard, privileges unknown
+//! to the snapshot like any privilege neither rules nor resource defaults name.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::session::Permission;
+//! struct EditNews;
+//!
+//! impl Permission for EditNews {
+//!     const RESOURCE:  Option<&'static str> = Some("news");
+//!     const PRIVILEGE: Option<&'static str> = Some("edit");
+//! }
+//!
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("staff", vec!["guest"]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.allow(Some("staff"), Some("news"), Some("edit")).unwrap();
+//!
+//! let grants = acl.grants();
+//!
+//! assert!( grants.is_permitted::<EditNews>(&["guest", "staff"]));
+//! assert!(!grants.is_permitted::<EditNews>(&["guest"]));
+//! assert!(!grants.is_permitted::<EditNews>(&[]));
+//! ```
+//!
+//! Handlers of axum take `Authorized<EditNews>` as argument, given an `Arc<Grants>` derivable
+//! from the router state and a `SessionManagerLayer`, see example `axum`. Handlers of actix-web
+//! take it likewise, given `web::Data<Grants>` as app data and a `SessionMiddleware`.
+
+use crate::{Access, Acl, Privilege, Query, Resource, Role};
+use log::trace;
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+
+/// The session key of the roles, a list of role names.
+pub const ROLES_KEY: &str = "zorq_acl.roles";
+
+
+// Grants /////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A snapshot of the decisions of an `Acl`, see `Acl::grants`. The default denies everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Grants {
+    roles:      BTreeSet<&'static str>,
+    resources:  BTreeSet<&'static str>,
+    privileges: BTreeSet<&'static str>,
+    decisions:  HashMap<Query, bool>,
+    // decisions of privileges neither rules nor resource defaults name
+    unnamed:    HashMap<(Role, Resource), bool>,
+} // struct Grants
+
+impl Grants {
+
+    /// Returns true if privilege on resource is allowed for role.
+    pub fn is_allowed(&self, role: Option<&str>, resource: Option<&str>, privilege: Option<&str>) -> bool {
+        let role     = role.and_then(|name| self.roles.get(name).copied());
+        let resource = resource.and_then(|name| self.resources.get(name).copied());
+
+        let allowed  = match privilege {
+            None       => self.decisions.get(&Query{resource, role, privilege: None}),
+            Some(name) => match self.privileges.get(name) {
+                Some(name) => self.decisions.get(&Query{resource, role, privilege: Some(name)}),
+                None       => self.unnamed.get(&(role, resource)),
+            }, // Some
+        }; // match
+
+        // empty grants deny everything
+        allowed.copied().unwrap_or(false)
+    } // is_allowed
+
+    /// Returns true if privilege on resource is allowed for any of roles, or anonymously if roles
+    /// are empty, see `Acl::is_allowed_anonymous`.
+    pub fn is_allowed_any<S: AsRef<str>>(&self, roles: &[S], resource: Option<&str>, privilege: Option<&str>) -> bool {
+        if roles.is_empty() {
+            return self.is_allowed(None, resource, privilege);
+        } // if
+        roles.iter().any(|role| self.is_allowed(Some(role.as_ref()), resource, privilege))
+    } // is_allowed_any
+
+    /// Returns true if the permission `T` is allowed for any of roles, see `is_allowed_any`.
+    pub fn is_permitted<T: Permission>(&self, roles: &[&str]) -> bool {
+        self.is_allowed_any(roles, T::RESOURCE, T::PRIVILEGE)
+    } // is_permitted
+
+} // impl Grants
+
+impl Acl {
+
+    /// Returns a snapshot of the decisions for all roles, resources and privileges registered or
+    /// named by rules or resource defaults, including the wildcards. The wildcard role is decided
+    /// for the default role, if set. Roles and resources of providers are excluded, decisions
+    /// aren't audited and don't consider subject overrides or quotas.
+    pub fn grants(&self) -> Grants {
+        trace!("taking grants of {} roles and {} resources", self.roles.len(), self.resources.len());
+        let privileges = self.known_privileges();
+        let mut grants = Grants{
+            roles:      self.roles.keys().copied().collect(),
+            resources:  self.resources.keys().copied().collect(),
+            privileges,
+            decisions:  HashMap::new(),
+            unnamed:    HashMap::new(),
+        }; // Grants
+        let is_allowed = |role, resource, privilege| self.effective(role, resource, privilege).1.acc == Access::Allow;
+
+        for role in Some(None).into_iter().chain(grants.roles.iter().map(|name| Some(*name))) {
+            for resource in Some(None).into_iter().chain(grants.resources.iter().map(|name| Some(*name))) {
+                for privilege in Some(None).into_iter().chain(grants.privileges.iter().map(|name| Some(*name))) {
+                    grants.decisions.insert(Query{resource, role, privilege}, is_allowed(role, resource, privilege));
+                } // for
+                // privileges named nowhere are decided alike, e.g. like the empty one
+                grants.unnamed.insert((role, resource), is_allowed(role, resource, Some("")));
+            } // for
+        } // for
+        grants
+    } // grants
+
+} // impl Acl
+
+
+// Authorized /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A privilege on a resource required by `Authorized`.
+pub trait Permission {
+
+    /// the required resource, None is the wildcard
+    const RESOURCE:  Resource;
+
+    /// the required privilege, None is the wildcard
+    const PRIVILEGE: Privilege;
+
+} // trait Permission
+
+/// Extracts the roles of the session, if allowed the permission `T`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Authorized<T> {
+    roles:      Vec<String>,
+    permission: PhantomData<fn() -> T>,
+} // struct Authorized
+
+impl<T: Permission> Authorized<T> {
+
+    /// Returns the roles, if allowed by grants.
+    pub fn authorize(grants: &Grants, roles: Vec<String>) -> Option<Self> {
+        trace!("authorizing {:?} on {:?} to {:?}", roles, T::RESOURCE, T::PRIVILEGE);
+        if grants.is_allowed_any(&roles, T::RESOURCE, T::PRIVILEGE) {
+            return Some(Authorized{roles, permission: PhantomData});
+        } // if
+        None
+    } // authorize
+
+    /// Returns the roles of the session.
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    } // roles
+
+} // impl Authorized
+
+
+// axum ///////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Stores the roles in the session of tower-sessions.
+#[cfg(feature = "axum")]
+pub async fn insert_session_roles(session: &tower_sessions::Session, roles: &[&str]) -> Result<(), tower_sessions::session::Error> {
+    session.insert(ROLES_KEY, roles).await
+} // insert_session_roles
+
+#[cfg(feature = "axum")]
+impl<S, T> axum_core::extract::FromRequestParts<S> for Authorized<T>
+where
+    S: Send + Sync,
+    T: Permission,
+    std::sync::Arc<Grants>: axum_core::extract::FromRef<S>,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut http::request::Parts, state: &S) -> Result<Self, Self::Rejection> {
+        use axum_core::extract::FromRef;
+
+        let session = tower_sessions::Session::from_request_parts(parts, state).await?;
+        let roles   = session.get::<Vec<String>>(ROLES_KEY).await
+            .map_err(|_| (http::StatusCode::INTERNAL_SERVER_ERROR, "Can't read roles from session"))?
+            .unwrap_or_default();
+
+        Authorized::authorize(&std::sync::Arc::<Grants>::from_ref(state), roles)
+            .ok_or((http::StatusCode::FORBIDDEN, "Forbidden"))
+    } // from_request_parts
+
+} // impl FromRequestParts for Authorized
+
+
+// actix //////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Stores the roles in the session of actix-session.
+#[cfg(feature = "actix")]
+pub fn insert_actix_roles(session: &actix_session::Session, roles: &[&str]) -> Result<(), actix_session::SessionInsertError> {
+    session.insert(ROLES_KEY, roles)
+} // insert_actix_roles
+
+#[cfg(feature = "actix")]
+impl<T: Permission> actix_web::FromRequest for Authorized<T> {
+
+    type Error  = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        use actix_session::SessionExt;
+        use actix_web::error::{ErrorForbidden, ErrorInternalServerError};
+
+        let authorized = match request.app_data::<actix_web::web::Data<Grants>>() {
+            None         => Err(ErrorInternalServerError("Can't find grants. Is `web::Data<Grants>` registered?")),
+            Some(grants) => request.get_session().get::<Vec<String>>(ROLES_KEY)
+                .map_err(|_| ErrorInternalServerError("Can't read roles from session"))
+                .and_then(|roles| Authorized::authorize(grants, roles.unwrap_or_default()).ok_or_else(|| ErrorForbidden("Forbidden"))),
+        }; // match
+
+        std::future::ready(authorized)
+    } // from_request
+
+} // impl FromRequest for Authorized
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    struct ViewNews;
+
+    impl Permission for ViewNews {
+        const RESOURCE:  Resource  = Some("news");
+        const PRIVILEGE: Privilege = Some("view");
+    } // impl Permission for ViewNews
+
+    fn setup_acl() -> Acl {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(None, Some("news"), Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("delete")).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        acl
+    } // setup_acl
+
+    #[test]
+    fn grants() {
+        let acl    = setup_acl();
+        let grants = acl.grants();
+
+        for role in [None, Some("guest"), Some("staff"), Some("root")].iter() {
+            for resource in [None, Some("news"), Some("latest")].iter() {
+                for privilege in [None, Some("view"), Some("delete"), Some("publish")].iter() {
+                    assert_eq!(grants.is_allowed(*role, *resource, *privilege), acl.is_allowed(*role, *resource, *privilege),
+                        "{:?} {:?} {:?}", role, resource, privilege);
+                } // for
+            } // for
+        } // for
+
+        // unknown names are decided like the wildcard role and resource
+        assert!( grants.is_allowed(Some("nobody"), Some("latest"), Some("view")));
+        assert!(!grants.is_allowed(Some("nobody"), Some("latest"), Some("publish")));
+        assert!(!grants.is_allowed(Some("staff"), Some("archive"), Some("publish")));
+
+        assert!(grants.is_allowed_any(&["guest", "staff"], Some("latest"), Some("publish")));
+        assert!(!grants.is_allowed_any(&["guest"], Some("latest"), Some("publish")));
+        assert!(grants.is_allowed_any::<&str>(&[], Some("news"), Some("view")));
+    } // grants
+
+    #[test]
+    fn priorities() {
+        let mut acl = setup_acl();
+
+        assert!(acl.allow(None, None, Some("read")).is_ok());
+        assert!(acl.deny(Some("staff"), Some("news"), Some("read")).is_ok());
+        assert!(acl.set_rule_priority(None, None, Some("read"), 5).is_ok());
+
+        let grants = acl.grants();
+
+        for role in [None, Some("guest"), Some("staff")] {
+            for resource in [None, Some("news"), Some("latest")] {
+                assert_eq!(grants.is_allowed(role, resource, Some("read")), acl.is_allowed(role, resource, Some("read")));
+            } // for
+        } // for
+        assert!(grants.is_allowed(Some("staff"), Some("news"), Some("read")));
+    } // priorities
+
+    #[test]
+    fn defaults() {
+        let mut acl = setup_acl();
+
+        assert!(acl.add_resource("public", None).is_ok());
+        assert!(acl.set_resource_default("public", "read", Access::Allow).is_ok());
+
+        let grants = acl.grants();
+
+        assert!(grants.is_allowed(Some("staff"), Some("public"), Some("read")));
+        assert!(!grants.is_allowed(Some("staff"), Some("public"), Some("write")));
+        assert!(acl.is_allowed(Some("staff"), Some("public"), Some("read")));
+    } // defaults
+
+    #[test]
+    fn default_role() {
+        let mut acl = setup_acl();
+
+        assert!(acl.allow(Some("guest"), Some("latest"), Some("comment")).is_ok());
+        assert!(!acl.grants().is_allowed_any::<&str>(&[], Some("latest"), Some("comment")));
+        assert!(acl.set_default_role("guest").is_ok());
+
+        let grants = acl.grants();
+
+        assert!(acl.is_allowed_anonymous(Some("latest"), Some("comment")));
+        assert!(grants.is_allowed_any::<&str>(&[], Some("latest"), Some("comment")));
+        assert!(grants.is_allowed(None, Some("latest"), Some("comment")));
+        assert!(!grants.is_allowed_any(&["staff"], Some("latest"), Some("delete")));
+    } // default_role
+
+    #[test]
+    fn authorize() {
+        let grants = setup_acl().grants();
+
+        assert_eq!(Authorized::<ViewNews>::authorize(&grants, vec![]).map(|authorized| authorized.roles().len()), Some(0));
+        assert!(Authorized::<ViewNews>::authorize(&Grants::default(), vec![String::from("root")]).is_none());
+    } // authorize
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn axum() {
+        use axum_core::extract::FromRequestParts;
+        use http::StatusCode;
+        use std::sync::Arc;
+        use tower_sessions::{MemoryStore, Session};
+
+        struct DeleteLatest;
+
+        impl Permission for DeleteLatest {
+            const RESOURCE:  Resource  = Some("latest");
+            const PRIVILEGE: Privilege = Some("delete");
+        } // impl Permission for DeleteLatest
+
+        let grants         = Arc::new(setup_acl().grants());
+        let session        = Session::new(None, Arc::new(MemoryStore::default()), None);
+        let (mut parts, _) = http::Request::new(()).into_parts();
+
+        futures::executor::block_on(async {
+            assert_eq!(Authorized::<ViewNews>::from_request_parts(&mut parts, &grants).await.err().map(|(status, _)| status), Some(StatusCode::INTERNAL_SERVER_ERROR));
+
+            parts.extensions.insert(session.clone());
+            assert!(insert_session_roles(&session, &["guest", "staff"]).await.is_ok());
+
+            let authorized = Authorized::<ViewNews>::from_request_parts(&mut parts, &grants).await.ok();
+
+            assert_eq!(authorized.as_ref().map(|authorized| authorized.roles()), Some(&[String::from("guest"), String::from("staff")][..]));
+            assert_eq!(Authorized::<DeleteLatest>::from_request_parts(&mut parts, &grants).await.err().map(|(status, _)| status), Some(StatusCode::FORBIDDEN));
+        }); // block_on
+    } // axum
+
+} // mod tests
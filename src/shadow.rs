@@ -0,0 +1,218 @@
+//! Shadow evaluation of a candidate policy.
+//!
+//! A `ShadowAcl` answers every query from the active policy, but evaluates the candidate policy as
+//! well. Queries for which the candidate comes to a different decision are counted and sampled.
+//! This allows to bake a new policy in production before the cutover.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::shadow::ShadowAcl;
+//! let mut active    = Acl::new();
+//! let mut candidate = Acl::new();
+//!
+//! active.add_role("staff", vec![]).unwrap();
+//! candidate.add_role("staff", vec![]).unwrap();
+//! candidate.allow(Some("staff"), None, Some("view")).unwrap();
+//!
+//! let shadow = ShadowAcl::new(active, candidate);
+//!
+//! assert!(shadow.is_denied(Some("staff"), None, Some("view")));
+//! assert_eq!(shadow.divergences(), 1);
+//! ```
+
+use crate::{Acl, Decision, Privilege, Resource, Role};
+use log::{debug, trace};
+use std::cell::{Cell, RefCell};
+
+
+// Divergence /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A query for which the active and the candidate policy come to different decisions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Divergence {
+    /// the decision of the active policy, which has been returned
+    pub active:    Decision,
+    /// the decision of the candidate policy
+    pub candidate: Decision,
+} // struct Divergence
+
+
+// ShadowAcl //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Holds an active and a candidate policy. Queries are answered by the active policy, divergent
+/// decisions of the candidate policy are recorded.
+pub struct ShadowAcl {
+    active:      Acl,
+    candidate:   Acl,
+    limit:       usize,
+    queries:     Cell<u64>,
+    divergences: Cell<u64>,
+    samples:     RefCell<Vec<Divergence>>,
+} // struct ShadowAcl
+
+impl ShadowAcl {
+
+    /// The number of divergences sampled by default.
+    pub const SAMPLE_LIMIT: usize = 100;
+
+    /// Creates a new `ShadowAcl` sampling up to `SAMPLE_LIMIT` divergences.
+    pub fn new(active: Acl, candidate: Acl) -> Self {
+        ShadowAcl{
+            active,
+            candidate,
+            limit:       Self::SAMPLE_LIMIT,
+            queries:     Cell::new(0),
+            divergences: Cell::new(0),
+            samples:     RefCell::new(vec![]),
+        } // ShadowAcl
+    } // new
+
+    /// Sets the maximum number of sampled divergences. Divergences beyond the limit are counted
+    /// only.
+    pub fn set_sample_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.samples.get_mut().truncate(limit);
+    } // set_sample_limit
+
+    /// Returns the active policy.
+    #[inline]
+    pub fn active(&self) -> &Acl {
+        &self.active
+    } // active
+
+    /// Returns the candidate policy.
+    #[inline]
+    pub fn candidate(&self) -> &Acl {
+        &self.candidate
+    } // candidate
+
+    /// Returns the decision of the active policy and records a divergent decision of the candidate
+    /// policy.
+    pub fn decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        let active    = self.active.decide(role, resource, privilege);
+        let candidate = self.candidate.decide(role, resource, privilege);
+
+        self.queries.set(self.queries.get() + 1);
+        if active.rule.access() != candidate.rule.access() {
+            debug!("shadow divergence: active {}, candidate {}", active, candidate);
+            self.divergences.set(self.divergences.get() + 1);
+
+            let mut samples = self.samples.borrow_mut();
+
+            if samples.len() < self.limit {
+                samples.push(Divergence{active, candidate});
+            } // if
+        } // if
+        active
+    } // decide
+
+    /// Returns true if privilege is allowed for role on resource by the active policy.
+    #[inline]
+    pub fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_allowed()
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource by the active policy.
+    #[inline]
+    pub fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_denied()
+    } // is_denied
+
+    /// Returns the number of queries answered.
+    #[inline]
+    pub fn queries(&self) -> u64 {
+        self.queries.get()
+    } // queries
+
+    /// Returns the number of queries for which the candidate policy diverged.
+    #[inline]
+    pub fn divergences(&self) -> u64 {
+        self.divergences.get()
+    } // divergences
+
+    /// Returns the sampled divergences in order of occurrence.
+    pub fn samples(&self) -> Vec<Divergence> {
+        self.samples.borrow().clone()
+    } // samples
+
+    /// Resets counters and samples.
+    pub fn reset(&self) {
+        trace!("resetting shadow statistics");
+        self.queries.set(0);
+        self.divergences.set(0);
+        self.samples.borrow_mut().clear();
+    } // reset
+
+    /// Finishes the shadow evaluation and returns the candidate policy for the cutover.
+    pub fn promote(self) -> Acl {
+        self.candidate
+    } // promote
+
+    /// Finishes the shadow evaluation and returns the active and the candidate policy.
+    pub fn into_inner(self) -> (Acl, Acl) {
+        (self.active, self.candidate)
+    } // into_inner
+
+} // impl ShadowAcl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn setup() -> ShadowAcl {
+        let mut active    = Acl::new();
+        let mut candidate = Acl::new();
+
+        for acl in [&mut active, &mut candidate].iter_mut() {
+            assert!(acl.add_role("guest", vec![]).is_ok());
+            assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+            assert!(acl.add_resource("news", None).is_ok());
+            assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        } // for
+        assert!(active.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(candidate.deny(Some("staff"), Some("news"), None).is_ok());
+        ShadowAcl::new(active, candidate)
+    } // setup
+
+    #[test]
+    fn divergences() {
+        let mut shadow = setup();
+
+        assert!(shadow.is_allowed(Some("staff"), None, Some("view")));
+        assert!(shadow.is_allowed(Some("staff"), Some("news"), Some("edit")));
+        assert!(shadow.is_allowed(Some("staff"), Some("news"), Some("view")));
+        assert!(shadow.is_denied (Some("guest"), Some("news"), Some("edit")));
+        assert_eq!(shadow.queries(), 4);
+        assert_eq!(shadow.divergences(), 2);
+
+        let samples = shadow.samples();
+
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].active.to_string(), "ALLOW staff→news: edit");
+        assert_eq!(samples[0].candidate.to_string(), "DENY staff→news: edit");
+
+        shadow.set_sample_limit(1);
+        assert!(shadow.is_allowed(Some("staff"), Some("news"), Some("edit")));
+        assert_eq!(shadow.divergences(), 3);
+        assert_eq!(shadow.samples().len(), 1);
+
+        shadow.reset();
+        assert_eq!(shadow.queries(), 0);
+        assert_eq!(shadow.divergences(), 0);
+        assert!(shadow.samples().is_empty());
+
+        let acl = shadow.promote();
+
+        assert!(acl.is_denied(Some("staff"), Some("news"), Some("edit")));
+    } // divergences
+
+} // mod tests
@@ -0,0 +1,373 @@
+//! Sharded storage for large numbers of roles.
+//!
+//! Deployments with a role per user define hundreds of thousands of roles, each inheriting from a
+//! few shared roles. A `ShardedAcl` keeps the shared roles, the resources and their rules in a base
+//! `Acl` and distributes the per-user roles and their rules by the hash of the role name over a
+//! fixed number of shards. Each shard caches its decisions, so defining or changing a per-user role
+//! only purges the cache of its shard. Changes of the base `Acl` purge all caches.
+//!
+//! Per-user roles inherit from roles of the base `Acl` only. Their rules are searched in the order
+//! of precedence of the base `Acl` as if the role was defined there.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::shard::ShardedAcl;
+//! let mut base = Acl::new();
+//!
+//! base.add_role("staff", vec![]).unwrap();
+//! base.add_resource("news", None).unwrap();
+//! base.allow(Some("staff"), Some("news"), Some("view")).unwrap();
+//!
+//! let mut acl = ShardedAcl::new(base, 16);
+//!
+//! acl.add_role("user:sally", vec!["staff"]).unwrap();
+//! acl.allow(Some("user:sally"), Some("news"), Some("edit")).unwrap();
+//!
+//! assert!(acl.is_allowed(Some("user:sally"), Some("news"), Some("view")));
+//! assert!(acl.is_allowed(Some("user:sally"), Some("news"), Some("edit")));
+//! assert!(acl.is_denied (Some("staff"), Some("news"), Some("edit")));
+//! ```
+
+use crate::etag::fnv1a;
+use crate::{Access, Acl, Decision, Error, ParentOrder, Privilege, Query, Resource, Role, Rule};
+use log::{trace, warn};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+
+// Shard //////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The per-user roles, their rules and cached decisions with a common hash.
+#[derive(Default)]
+struct Shard {
+    roles: HashMap<&'static str, Vec<&'static str>>,
+    rules: BTreeMap<Query, Rule>,
+    cache: RefCell<HashMap<Query, (Query, Rule)>>,
+} // struct Shard
+
+impl Shard {
+
+    fn purge(&self) {
+        self.cache.borrow_mut().clear();
+    } // purge
+
+} // impl Shard
+
+
+// ShardedAcl /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Holds shared roles, resources and rules in a base `Acl` and per-user roles in shards.
+pub struct ShardedAcl {
+    base:   Acl,
+    shards: Vec<Shard>,
+} // struct ShardedAcl
+
+impl ShardedAcl {
+
+    /// Creates a new `ShardedAcl` with the given number of shards, at least one, on top of base.
+    pub fn new(base: Acl, shards: usize) -> Self {
+        trace!("creating sharded acl with {} shards", shards);
+        ShardedAcl{base, shards: (0..shards.max(1)).map(|_| Shard::default()).collect()}
+    } // new
+
+    /// Returns the base `Acl`.
+    #[inline]
+    pub fn base(&self) -> &Acl {
+        &self.base
+    } // base
+
+    /// Returns the base `Acl` for changes. Purges the caches of all shards.
+    pub fn base_mut(&mut self) -> &mut Acl {
+        for shard in &self.shards {
+            shard.purge();
+        } // for
+        &mut self.base
+    } // base_mut
+
+    /// Returns the number of shards.
+    #[inline]
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    } // shard_count
+
+    /// Returns the shard of role. The shard is stable across processes and platforms.
+    pub fn shard_of(&self, role: &str) -> usize {
+        (fnv1a(role.as_bytes()) % self.shards.len() as u64) as usize
+    } // shard_of
+
+    /// Returns the number of decisions cached by shard.
+    pub fn cached(&self, shard: usize) -> usize {
+        self.shards.get(shard).map(|shard| shard.cache.borrow().len()).unwrap_or(0)
+    } // cached
+
+    /// Returns the number of per-user roles.
+    pub fn role_count(&self) -> usize {
+        self.shards.iter().map(|shard| shard.roles.len()).sum()
+    } // role_count
+
+    /// Returns true if role is defined as per-user role or in the base `Acl`.
+    pub fn has_role(&self, name: &'static str) -> bool {
+        self.shards[self.shard_of(name)].roles.contains_key(name) || self.base.has_role(name)
+    } // has_role
+
+    /// Adds a new per-user role inheriting from roles of the base `Acl`. Returns an error if role
+    /// is already defined or a parent isn't a role of the base `Acl`.
+    pub fn add_role(&mut self, name: &'static str, parents: Vec<&'static str>) -> Result<(), Error> {
+        trace!("adding sharded role {} with parents {:?}", name, parents);
+        if self.has_role(name) {
+            warn!("adding duplicate role: {}", name);
+            return Err(Error::DuplicateRole(String::from(name)));
+        } // if
+        if let Some(parent) = parents.iter().find(|parent| !self.base.has_role(parent)) {
+            warn!("missing parent for new role: {}", parent);
+            return Err(Error::MissingParent(String::from(*parent)));
+        } // if
+        let mut reversed = parents;
+        let shard        = self.shard_of(name);
+
+        // parents are stored in search order like in `Acl`
+        reversed.reverse();
+        self.shards[shard].roles.insert(name, reversed);
+        self.shards[shard].purge();
+        Ok(())
+    } // add_role
+
+    /// Removes a per-user role and its rules. Returns true if the role has been removed.
+    pub fn remove_role(&mut self, name: &'static str) -> bool {
+        trace!("removing sharded role {}", name);
+        let index = self.shard_of(name);
+        let shard = &mut self.shards[index];
+
+        if shard.roles.remove(name).is_none() {
+            return false;
+        } // if
+        shard.rules.retain(|query, _| query.role != Some(name));
+        shard.purge();
+        true
+    } // remove_role
+
+    /// Sets a rule. Rules of per-user roles are stored in their shard and purge its cache only,
+    /// all other rules are set in the base `Acl`, see `Acl::set_rule`.
+    pub fn set_rule(&mut self, role: Role, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
+        let name = match role {
+            Some(name) if !self.base.has_role(name) => name,
+            _                                       => return self.base_mut().set_rule(role, resource, privilege, access),
+        }; // match
+        let shard = self.shard_of(name);
+
+        trace!("setting {} rule for {} on {:?} with {:?} privilege in shard {}", access, name, resource, privilege, shard);
+        if !self.shards[shard].roles.contains_key(name) {
+            return Err(Error::MissingRole(String::from(name)));
+        } // if
+        if let Some(resource) = resource {
+            if !self.base.has_resource(resource) {
+                return Err(Error::MissingResource(String::from(resource)));
+            } // if
+        } // if
+        self.base.check_privilege(resource, privilege)?;
+        self.shards[shard].rules.insert(Query{resource, role, privilege}, Rule{acc: access, cond: None});
+        self.shards[shard].purge();
+        Ok(())
+    } // set_rule
+
+    /// Allows privilege for role on resource.
+    #[inline]
+    pub fn allow(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(role, resource, privilege, Access::Allow)
+    } // allow
+
+    /// Denies privilege for role on resource.
+    #[inline]
+    pub fn deny(&mut self, role: Role, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_rule(role, resource, privilege, Access::Deny)
+    } // deny
+
+    /// Decides the query. Queries for roles other than per-user roles are decided by the base
+    /// `Acl`. The decision is reported to the audit sink of the base `Acl`.
+    pub fn decide(&self, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        let name = match role {
+            Some(name) => name,
+            None       => return self.base.decide(role, resource, privilege),
+        }; // match
+        let shard = &self.shards[self.shard_of(name)];
+
+        if !shard.roles.contains_key(name) {
+            return self.base.decide(role, resource, privilege);
+        } // if
+        let query    = Query{resource, role, privilege};
+        let cached   = shard.cache.borrow().get(&query).copied();
+        let decision = match cached {
+            Some((matched, rule)) => Decision{query, matched, rule, bypass: false},
+            None                  => {
+                let conditional     = Cell::new(false);
+                let (matched, rule) = self.search(shard, name, query, &conditional);
+
+                // decisions of conditional rules aren't cached
+                if !conditional.get() {
+                    shard.cache.borrow_mut().insert(query, (matched, rule));
+                } // if
+                Decision{query, matched, rule, bypass: false}
+            }, // None
+        }; // match
+
+        self.base.audit(None, &decision);
+        decision
+    } // decide
+
+    /// Returns true if privilege is allowed for role on resource.
+    #[inline]
+    pub fn is_allowed(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_allowed()
+    } // is_allowed
+
+    /// Returns true if privilege is denied for role on resource.
+    #[inline]
+    pub fn is_denied(&self, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide(role, resource, privilege).is_denied()
+    } // is_denied
+
+    /// Decides the query of the per-user role like the base `Acl` decides its roles, searching the
+    /// rules of the role and of the base `Acl` in order of precedence, see `Acl::resolve`.
+    fn search(&self, shard: &Shard, name: &'static str, query: Query, conditional: &Cell<bool>) -> (Query, Rule) {
+        let holds       = |rule: &Rule| self.base.holds(rule, &query, conditional);
+        let mut parents = shard.roles[name].clone();
+        let mut seen    = HashSet::new();
+        let mut lineage = vec![];
+
+        if self.base.parent_order() != ParentOrder::Lifo {
+            parents.reverse();
+        } // if
+        for parent in parents {
+            for ancestor in self.base.get_role_lineage(parent) {
+                if seen.insert(ancestor) {
+                    lineage.push(ancestor);
+                } // if
+            } // for
+        } // for
+
+        let mut resources: Vec<Resource> = match query.resource {
+            Some(resource) => self.base.get_resource_lineage(resource).into_iter().map(Some).collect(),
+            None           => vec![],
+        }; // match
+
+        resources.push(None);
+        let precedence = || {
+            for resource in &resources {
+                if let Some((matched, rule)) = Acl::query_privileges(&shard.rules, resource, &Some(name), &query.privilege, &holds) {
+                    return Some((*matched, *rule));
+                } // if let
+
+                let mut allowed = None;
+
+                // inherited rules like `Acl::query_roles`
+                for ancestor in &lineage {
+                    if let Some(found) = Acl::query_privileges(&self.base.rules, resource, &Some(ancestor), &query.privilege, &holds) {
+                        if self.base.parent_order() != ParentOrder::DenyFirst || found.1.acc == Access::Deny {
+                            return Some((*found.0, *found.1));
+                        } // if
+                        allowed = allowed.or(Some(found));
+                    } // if let
+                } // for
+                if let Some((matched, rule)) = allowed.or_else(|| Acl::query_privileges(&self.base.rules, resource, &None, &query.privilege, &holds)) {
+                    return Some((*matched, *rule));
+                } // if let
+            } // for
+            None
+        }; // precedence
+        let roles      = |roles: &mut Vec<&'static str>| {
+            roles.push(name);
+            roles.extend(&lineage);
+        }; // roles
+        let decision   = self.base.resolve(query, &[&shard.rules, &self.base.rules], roles, conditional, precedence);
+
+        (decision.matched, decision.rule)
+    } // search
+
+} // impl ShardedAcl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    fn setup_base() -> Acl {
+        let mut base = Acl::new();
+
+        assert!(base.add_role("guest", vec![]).is_ok());
+        assert!(base.add_role("staff", vec!["guest"]).is_ok());
+        assert!(base.add_resource("news", None).is_ok());
+        assert!(base.add_resource("latest", Some("news")).is_ok());
+        assert!(base.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(base.allow(Some("staff"), Some("news"), None).is_ok());
+        assert!(base.deny(Some("guest"), Some("latest"), Some("delete")).is_ok());
+        base
+    } // setup_base
+
+    #[test]
+    fn decide() {
+        let mut acl = ShardedAcl::new(setup_base(), 4);
+
+        assert!(acl.add_role("user:sally", vec!["staff"]).is_ok());
+        assert!(acl.add_role("user:bob", vec!["guest"]).is_ok());
+        assert_eq!(acl.add_role("user:bob", vec![]), Err(Error::DuplicateRole(String::from("user:bob"))));
+        assert_eq!(acl.add_role("staff", vec![]), Err(Error::DuplicateRole(String::from("staff"))));
+        assert_eq!(acl.add_role("user:eve", vec!["user:bob"]), Err(Error::MissingParent(String::from("user:bob"))));
+        assert!(acl.deny(Some("user:sally"), Some("news"), Some("publish")).is_ok());
+        assert_eq!(acl.allow(Some("user:eve"), None, None), Err(Error::MissingRole(String::from("user:eve"))));
+        assert_eq!(acl.role_count(), 2);
+
+        // per-user roles decide like roles of the base acl
+        let mut flat = setup_base();
+
+        assert!(flat.add_role("user:sally", vec!["staff"]).is_ok());
+        assert!(flat.add_role("user:bob", vec!["guest"]).is_ok());
+        assert!(flat.deny(Some("user:sally"), Some("news"), Some("publish")).is_ok());
+        for role in &[Some("user:sally"), Some("user:bob"), Some("staff"), None] {
+            for resource in &[None, Some("news"), Some("latest")] {
+                for privilege in &[None, Some("view"), Some("publish"), Some("delete")] {
+                    assert_eq!(acl.decide(*role, *resource, *privilege), flat.decide(*role, *resource, *privilege));
+                } // for
+            } // for
+        } // for
+
+        assert!(acl.remove_role("user:bob"));
+        assert!(!acl.remove_role("user:bob"));
+        assert!(acl.is_denied(Some("user:bob"), None, Some("view")));
+    } // decide
+
+    #[test]
+    fn isolated_caches() {
+        let mut acl = ShardedAcl::new(setup_base(), 8);
+        let names   = ["user:1", "user:2", "user:3", "user:4", "user:5", "user:6", "user:7", "user:8"];
+
+        for name in &names {
+            assert!(acl.add_role(name, vec!["guest"]).is_ok());
+            assert!(acl.is_allowed(Some(name), None, Some("view")));
+        } // for
+
+        let shard  = acl.shard_of("user:1");
+        let other  = names.iter().map(|name| acl.shard_of(name)).find(|other| *other != shard).unwrap();
+        let cached = acl.cached(other);
+
+        // changing a per-user role purges its shard only
+        assert!(acl.cached(shard) > 0 && cached > 0);
+        assert!(acl.deny(Some("user:1"), None, Some("view")).is_ok());
+        assert_eq!(acl.cached(shard), 0);
+        assert_eq!(acl.cached(other), cached);
+        assert!(acl.is_denied(Some("user:1"), None, Some("view")));
+
+        // changing the base acl purges all shards
+        assert!(acl.base_mut().deny(Some("guest"), None, Some("view")).is_ok());
+        assert_eq!((0..acl.shard_count()).map(|shard| acl.cached(shard)).sum::<usize>(), 0);
+        assert!(acl.is_denied(Some("user:2"), None, Some("view")));
+    } // isolated_caches
+
+} // mod tests
@@ -0,0 +1,178 @@
+//! Partial evaluation of a policy for a single role.
+//!
+//! `specialize` fixes the role of all queries and evaluates the policy as far as possible. The
+//! result is a residual `Acl` without roles, holding the resources of the original policy and a
+//! minimal set of rules for the wildcard role. Queried with role `None`, it decides every resource
+//! and privilege like the original policy decides them for the fixed role, so it can be shipped to
+//! a client device or frontend for local permission checks without revealing other roles.
+//!
+//! Subjects, delegations, quotas and the laminas compatibility mode aren't carried over. Wildcard
+//! privileges are decided by precedence as without the compatibility mode. Neither are
+//! assertions, so policies with conditional rules applying to the role can't be specialized,
+//! since their decisions depend on code, see module `condition`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.add_role("staff", vec!["guest"]).unwrap();
+//! acl.add_resource("news", None).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//! acl.allow(Some("staff"), Some("news"), Some("edit")).unwrap();
+//!
+//! let residual = acl.specialize("staff").unwrap();
+//!
+//! assert!(residual.is_allowed(None, Some("news"), Some("view")));
+//! assert!(residual.is_allowed(None, Some("news"), Some("edit")));
+//! assert!(residual.is_denied (None, None,         Some("edit")));
+//! assert_eq!(residual.roles().count(), 0);
+//! ```
+
+use crate::{Access, Acl, Error, Query, Rule};
+use log::{trace, warn};
+use std::collections::BTreeSet;
+
+impl Acl {
+
+    /// Returns the residual policy of role, see module `specialize`. Returns an error if the role
+    /// is undefined or a conditional rule applies to it.
+    pub fn specialize(&self, role: &'static str) -> Result<Acl, Error> {
+        if !self.has_role(role) {
+            warn!("missing role while specializing: {}", role);
+            return Err(Error::MissingRole(String::from(role)));
+        } // if
+        trace!("specializing acl for {}", role);
+
+        let mut residual = Acl::new();
+
+        residual.resources           = self.resources.clone();
+        residual.privileges          = self.privileges.clone();
+        residual.resource_privileges = self.resource_privileges.clone();
+        if self.bypass.contains(role) {
+            residual.insert_rule(Query::ALL, Rule{acc: Access::Allow, cond: None});
+            return Ok(residual);
+        } // if
+
+        // ancestors before descendants, so rules only add to what has been decided already
+        let mut resources: Vec<(usize, Option<&'static str>)> = self.resources.keys()
+            .map(|name| (self.get_resource_lineage(name).len(), Some(*name)))
+            .collect();
+
+        resources.push((0, None));
+        resources.sort();
+
+        let lineage = self.get_role_lineage(role);
+
+        if let Some((query, _)) = self.rules.iter()
+            .find(|(query, rule)| rule.condition().is_some() && query.role.is_none_or(|name| lineage.contains(&name)))
+        {
+            warn!("conditional rule while specializing: {}", query);
+            return Err(Error::NotPermitted(format!("specializing conditional rule {}", query)));
+        } // if
+
+        // privileges of other rules are decided like unnamed ones
+        let mut privileges: BTreeSet<Option<&'static str>> = self.rules.keys()
+            .filter(|query| query.role.map(|name| lineage.contains(&name)).unwrap_or(true))
+            .map(|query| query.privilege)
+            .collect();
+
+        privileges.extend(self.resource_defaults.keys().map(|(_, privilege)| Some(*privilege)));
+        privileges.insert(None);
+
+        for (_, resource) in resources {
+            for privilege in &privileges {
+                // in compatibility mode wildcard privileges are decided like unnamed ones
+                let probe   = privilege.or(if self.compat { Some("") } else { None });
+                let wanted  = self.effective(Some(role), resource, probe).1;
+                let current = residual.effective(None, resource, *privilege).1;
+
+                if wanted != current {
+                    residual.insert_rule(Query{resource, role: None, privilege: *privilege}, wanted);
+                } // if
+            } // for
+        } // for
+        trace!("specialized {} of {} rules", residual.rules.len(), self.rules.len());
+        Ok(residual)
+    } // specialize
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ParentOrder;
+    use test_env_log::test;
+
+    fn setup_acl() -> Acl {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("author", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest", "author"]).is_ok());
+        assert!(acl.add_role("root", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.add_resource("blog", None).is_ok());
+        assert!(acl.allow(None, Some("blog"), Some("view")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.deny(Some("guest"), Some("latest"), None).is_ok());
+        assert!(acl.allow(Some("author"), Some("news"), None).is_ok());
+        assert!(acl.deny(Some("author"), Some("news"), Some("delete")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("latest"), Some("edit")).is_ok());
+        assert!(acl.allow(Some("root"), Some("blog"), Some("delete")).is_ok());
+        acl
+    } // setup_acl
+
+    fn assert_equivalent(acl: &Acl, role: &'static str) {
+        let residual = acl.specialize(role).unwrap();
+
+        for resource in &[None, Some("news"), Some("latest"), Some("blog"), Some("wiki")] {
+            for privilege in &[None, Some("view"), Some("edit"), Some("delete"), Some("share")] {
+                assert_eq!(
+                    residual.get_rule(None, *resource, *privilege),
+                    acl.get_rule(Some(role), *resource, *privilege),
+                    "{} on {:?} to {:?}", role, resource, privilege,
+                );
+            } // for
+        } // for
+    } // assert_equivalent
+
+    #[test]
+    fn specialize() {
+        let mut acl = setup_acl();
+
+        for role in &["guest", "author", "staff", "root"] {
+            assert_equivalent(&acl, role);
+        } // for
+        acl.set_parent_order(ParentOrder::DenyFirst);
+        assert_equivalent(&acl, "staff");
+
+        // only rules changing the decision remain
+        let residual = acl.specialize("guest").unwrap();
+
+        assert_eq!(residual.rules().count(), 3);
+        assert!(residual.has_resource("latest"));
+        assert_eq!(acl.specialize("nobody").unwrap_err(), Error::MissingRole(String::from("nobody")));
+
+        // bypass roles are allowed everything
+        assert!(acl.set_bypass_role("root").is_ok());
+        assert_eq!(acl.specialize("root").unwrap().rules().count(), 1);
+        assert_equivalent(&acl, "root");
+
+        // conditional rules of other roles don't matter
+        acl.add_assertion("open", |_: &Acl, _: &Query| true);
+        assert!(acl.allow_if(Some("root"), Some("news"), Some("edit"), "open").is_ok());
+        assert_equivalent(&acl, "guest");
+        assert!(acl.allow_if(Some("guest"), Some("news"), Some("edit"), "open").is_ok());
+        assert!(acl.is_allowed(Some("guest"), Some("news"), Some("edit")));
+        assert_eq!(acl.specialize("staff").unwrap_err(), Error::NotPermitted(String::from("specializing conditional rule guest→news: edit")));
+    } // specialize
+
+} // mod tests
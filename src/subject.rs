@@ -0,0 +1,210 @@
+//! Per-subject overrides.
+//!
+//! Subjects, e.g. individual users, may be given explicit allows and denies which take
+//! precedence over the decisions derived from their roles. This covers the handful of individual
+//! exceptions which don't justify a new role. Overrides are defined for a resource and privilege
+//! and are inherited along the resource tree like regular rules.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("staff", vec![]).unwrap();
+//! acl.add_resource("reports", None).unwrap();
+//! acl.allow(Some("staff"), Some("reports"), None).unwrap();
+//! acl.deny_subject("sally", Some("reports"), Some("delete")).unwrap();
+//!
+//! assert!(acl.is_denied_for ("sally", Some("staff"), Some("reports"), Some("delete")));
+//! assert!(acl.is_allowed_for("bob", Some("staff"), Some("reports"), Some("delete")));
+//! ```
+
+use crate::{Access, Acl, Decision, Error, Privilege, Query, Resource, Role, Rule};
+use crate::etag::Item;
+use log::trace;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+impl Acl {
+
+    /// Sets an override for subject on resource to privilege. Returns an error if resource is
+    /// undefined, privilege isn't registered or the `Acl` is locked.
+    pub fn set_subject_rule(&mut self, subject: &'static str, resource: Resource, privilege: Privilege, access: Access) -> Result<(), Error> {
+        trace!("setting override for subject {} on {:?} with {:?} privilege", subject, resource, privilege);
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        if let Some(name) = resource {
+            if !self.resources.contains_key(name) {
+                return Err(Error::MissingResource(String::from(name)));
+            } // if
+        } // if
+        self.check_privilege(resource, privilege)?;
+
+        let query = Query{resource, role: None, privilege};
+        let rule  = Rule{acc: access, cond: None};
+
+        if let Some(previous) = self.subjects.entry(subject).or_default().insert(query, rule) {
+            self.track(Item::Subject(subject, &query, previous), false);
+        } // if
+        self.track(Item::Subject(subject, &query, rule), true);
+        Ok(())
+    } // set_subject_rule
+
+    /// Allows privilege on resource for subject regardless of its roles.
+    #[inline]
+    pub fn allow_subject(&mut self, subject: &'static str, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_subject_rule(subject, resource, privilege, Access::Allow)
+    } // allow_subject
+
+    /// Denies privilege on resource for subject regardless of its roles.
+    #[inline]
+    pub fn deny_subject(&mut self, subject: &'static str, resource: Resource, privilege: Privilege) -> Result<(), Error> {
+        self.set_subject_rule(subject, resource, privilege, Access::Deny)
+    } // deny_subject
+
+    /// Removes the override for subject on resource to privilege. Returns true if an override has
+    /// been removed. Returns an error if the `Acl` is locked.
+    pub fn revoke_subject(&mut self, subject: &'static str, resource: Resource, privilege: Privilege) -> Result<bool, Error> {
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let query   = Query{resource, role: None, privilege};
+        let removed = self.subjects.get_mut(subject).and_then(|rules| rules.remove(&query));
+
+        if self.subjects.get(subject).is_some_and(BTreeMap::is_empty) {
+            self.subjects.remove(subject);
+        } // if
+        if let Some(rule) = removed {
+            self.track(Item::Subject(subject, &query, rule), false);
+        } // if
+        Ok(removed.is_some())
+    } // revoke_subject
+
+    /// Removes all overrides for subject. Returns true if any override has been removed. Returns
+    /// an error if the `Acl` is locked.
+    pub fn clear_subject(&mut self, subject: &'static str) -> Result<bool, Error> {
+        if self.lock.is_some() {
+            return Err(Error::Locked);
+        } // if
+        let rules = match self.subjects.remove(subject) {
+            Some(rules) => rules,
+            None        => return Ok(false),
+        }; // match
+
+        for (query, rule) in &rules {
+            self.track(Item::Subject(subject, query, *rule), false);
+        } // for
+        Ok(true)
+    } // clear_subject
+
+    /// Returns an iterator over all subjects with overrides. The order is arbitrary.
+    pub fn subjects(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.subjects.keys().copied()
+    } // subjects
+
+    /// Like `decide`, but an override of subject takes precedence over the rules of role.
+    /// Decisions by overrides are not cached.
+    pub fn decide_for(&self, subject: &'static str, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        #[cfg(feature = "metrics")]
+        let start    = std::time::Instant::now();
+        #[cfg(feature = "otel")]
+        let span     = crate::otel::start(Some(subject), role, resource, privilege);
+        let decision = match self.evaluate_override(subject, role, resource, privilege) {
+            Some(decision) => decision,
+            None           => {
+                let decision = self.evaluate(role, resource, privilege);
+
+                self.count_hit(&decision);
+                decision
+            }, // None
+        }; // match
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::decision(&decision, start);
+        #[cfg(feature = "otel")]
+        crate::otel::end(span, &decision, self.fingerprint);
+        self.audit(Some(subject), &decision);
+        decision
+    } // decide_for
+
+    /// Decides the query for subject without reporting the decision.
+    #[cfg(feature = "audit")]
+    pub(crate) fn evaluate_for(&self, subject: &str, role: Role, resource: Resource, privilege: Privilege) -> Decision {
+        self.evaluate_override(subject, role, resource, privilege)
+            .unwrap_or_else(|| self.evaluate(role, resource, privilege))
+    } // evaluate_for
+
+    /// Decides the query by the overrides of subject, if any applies.
+    fn evaluate_override(&self, subject: &str, role: Role, resource: Resource, privilege: Privilege) -> Option<Decision> {
+        if let Some(rules) = self.subjects.get(subject) {
+            // an override for all resources and privileges is matched last
+            let found = self.query_precedence_in(rules, None, resource, privilege, &Cell::new(false))
+                .or_else(|| rules.get_key_value(&Query::ALL));
+
+            if let Some((matched, rule)) = found {
+                trace!("override of subject {} matched {}", subject, matched);
+                return Some(Decision{query: Query{resource, role, privilege}, matched: *matched, rule: *rule, bypass: false});
+            } // if
+        } // if
+        None
+    } // evaluate_override
+
+    /// Returns true if privilege is allowed for subject in role on resource.
+    #[inline]
+    pub fn is_allowed_for(&self, subject: &'static str, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide_for(subject, role, resource, privilege).is_allowed()
+    } // is_allowed_for
+
+    /// Returns true if privilege is denied for subject in role on resource.
+    #[inline]
+    pub fn is_denied_for(&self, subject: &'static str, role: Role, resource: Resource, privilege: Privilege) -> bool {
+        self.decide_for(subject, role, resource, privilege).is_denied()
+    } // is_denied_for
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn overrides() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("staff", vec![]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), Some("edit")).is_ok());
+        assert!(acl.deny_subject("sally", Some("news"), None).is_ok());
+        assert!(acl.allow_subject("sally", Some("latest"), Some("publish")).is_ok());
+        assert!(acl.allow_subject("bob", None, Some("publish")).is_ok());
+
+        assert!(acl.is_denied_for ("sally", Some("staff"), Some("latest"), Some("edit")));
+        assert!(acl.is_allowed_for("sally", Some("staff"), Some("latest"), Some("publish")));
+        assert!(acl.is_denied_for ("sally", Some("staff"), None, Some("edit")));
+        assert!(acl.is_allowed_for("bob", Some("staff"), Some("latest"), Some("edit")));
+        assert!(acl.is_allowed_for("bob", None, Some("latest"), Some("publish")));
+        assert!(acl.is_denied_for ("eve", Some("staff"), Some("latest"), Some("publish")));
+        assert_eq!(acl.decide_for("sally", Some("staff"), Some("latest"), Some("edit")).matched.to_string(), "*→news: *");
+
+        assert_eq!(acl.allow_subject("bob", Some("unknown"), None), Err(Error::MissingResource(String::from("unknown"))));
+        assert_eq!(acl.revoke_subject("bob", None, Some("publish")), Ok(true));
+        assert_eq!(acl.revoke_subject("bob", None, Some("publish")), Ok(false));
+        assert_eq!(acl.subjects().collect::<Vec<_>>(), vec!["sally"]);
+        assert!(acl.deny_subject("eve", None, None).is_ok());
+        assert!(acl.is_denied_for("eve", Some("staff"), Some("news"), Some("edit")));
+        assert_eq!(acl.clear_subject("sally"), Ok(true));
+        assert!(acl.is_allowed_for("sally", Some("staff"), Some("latest"), Some("edit")));
+
+        acl.lock();
+        assert_eq!(acl.deny_subject("sally", None, None), Err(Error::Locked));
+    } // overrides
+
+} // mod tests
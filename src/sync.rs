@@ -0,0 +1,421 @@
+//! Propagation of policy changes to replicas.
+//!
+//! A `Publisher` owns the primary `Acl`. Every change applied through the publisher is serialized
+//! as JSON message with a sequence number and sent over a `Channel`, e.g. a Redis pub/sub or NATS
+//! subject or a `std::sync::mpsc::Sender`. A `Subscriber` applies received messages to its
+//! replica. If the subscriber detects a gap in the sequence, it reports the gap and ignores all
+//! further changes until it receives a full snapshot, which the publisher sends on request.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::sync::{AclChange, Publisher, Received, Subscriber};
+//! # use std::sync::mpsc::channel;
+//! let (sender, receiver) = channel();
+//! let mut publisher      = Publisher::new(Acl::new(), sender);
+//! let mut subscriber     = Subscriber::new();
+//!
+//! publisher.snapshot().unwrap();
+//! publisher.apply(AclChange::AddRole{name: String::from("guest"), parents: vec![]}).unwrap();
+//!
+//! for message in receiver.try_iter() {
+//!     subscriber.receive(&message).unwrap();
+//! }
+//! assert!(subscriber.acl().has_role("guest"));
+//! ```
+
+use crate::policy::{export, intern, load};
+use crate::{Access, Acl, Error, Query, SchemaError};
+use log::{trace, warn};
+use serde_json::{json, Value};
+use std::sync::mpsc::Sender;
+
+
+// AclChange //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A single change of an `Acl`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AclChange {
+    AddRole{name: String, parents: Vec<String>},
+    AddResource{name: String, parent: Option<String>},
+    SetRule{access: Access, role: Option<String>, resource: Option<String>, privilege: Option<String>},
+    RevokeAll{role: String},
+    SetBypassRole{role: String},
+    UnsetBypassRole{role: String},
+} // enum AclChange
+
+impl AclChange {
+
+    /// Serializes the change as JSON value.
+    pub fn to_json(&self) -> Value {
+        match self {
+            AclChange::AddRole{name, parents} =>
+                json!({"op": "add_role", "name": name, "parents": parents}),
+            AclChange::AddResource{name, parent} =>
+                json!({"op": "add_resource", "name": name, "parent": parent}),
+            AclChange::SetRule{access, role, resource, privilege} => json!({
+                "op":        "set_rule",
+                "access":    match access { Access::Allow => "allow", Access::Deny => "deny" },
+                "role":      role,
+                "resource":  resource,
+                "privilege": privilege,
+            }),
+            AclChange::RevokeAll{role} =>
+                json!({"op": "revoke_all", "role": role}),
+            AclChange::SetBypassRole{role} =>
+                json!({"op": "set_bypass_role", "role": role}),
+            AclChange::UnsetBypassRole{role} =>
+                json!({"op": "unset_bypass_role", "role": role}),
+        } // match
+    } // to_json
+
+    /// Deserializes a change from a JSON value.
+    pub fn from_json(value: &Value) -> Result<AclChange, Error> {
+        fn string(value: &Value, field: &str) -> Result<String, Error> {
+            value[field].as_str().map(String::from)
+                .ok_or_else(|| Error::Schema(vec![SchemaError::new(field, "expected a string")]))
+        } // string
+
+        fn optional(value: &Value, field: &str) -> Result<Option<String>, Error> {
+            match &value[field] {
+                Value::Null      => Ok(None),
+                Value::String(s) => Ok(Some(s.clone())),
+                _                => Err(Error::Schema(vec![SchemaError::new(field, "expected a string or null")])),
+            } // match
+        } // optional
+
+        match value["op"].as_str() {
+            Some("add_role")          => Ok(AclChange::AddRole{
+                name:    string(value, "name")?,
+                parents: match &value["parents"] {
+                    Value::Null        => vec![],
+                    Value::Array(list) => list.iter()
+                        .map(|p| p.as_str().map(String::from)
+                            .ok_or_else(|| Error::Schema(vec![SchemaError::new("parents", "expected strings")])))
+                        .collect::<Result<_, _>>()?,
+                    _                  => return Err(Error::Schema(vec![SchemaError::new("parents", "expected an array")])),
+                }, // match
+            }), // AclChange::AddRole
+            Some("add_resource")      => Ok(AclChange::AddResource{
+                name:   string(value, "name")?,
+                parent: optional(value, "parent")?,
+            }), // AclChange::AddResource
+            Some("set_rule")          => Ok(AclChange::SetRule{
+                access:    match value["access"].as_str() {
+                    Some("allow") => Access::Allow,
+                    Some("deny")  => Access::Deny,
+                    _             => return Err(Error::Schema(vec![
+                        SchemaError::new("access", "expected \"allow\" or \"deny\"")])),
+                }, // match
+                role:      optional(value, "role")?,
+                resource:  optional(value, "resource")?,
+                privilege: optional(value, "privilege")?,
+            }), // AclChange::SetRule
+            Some("revoke_all")        => Ok(AclChange::RevokeAll{role: string(value, "role")?}),
+            Some("set_bypass_role")   => Ok(AclChange::SetBypassRole{role: string(value, "role")?}),
+            Some("unset_bypass_role") => Ok(AclChange::UnsetBypassRole{role: string(value, "role")?}),
+            _                         => Err(Error::Schema(vec![SchemaError::new("op", "unknown operation")])),
+        } // match
+    } // from_json
+
+} // impl AclChange
+
+impl Acl {
+
+    /// Applies a change. Names already known to the `Acl` are reused, new names are leaked to
+    /// obtain the `'static` lifetime.
+    pub fn apply_change(&mut self, change: &AclChange) -> Result<(), Error> {
+        trace!("applying change {:?}", change);
+        match change {
+            AclChange::AddRole{name, parents} => {
+                let parents = parents.iter().map(|p| self.role_name(p)).collect();
+
+                self.add_role(self.role_name(name), parents)
+            }, // AclChange::AddRole
+            AclChange::AddResource{name, parent} => {
+                let parent = parent.as_deref().map(|p| self.resource_name(p));
+
+                self.add_resource(self.resource_name(name), parent)
+            }, // AclChange::AddResource
+            AclChange::SetRule{access, role, resource, privilege} => {
+                let role      = role.as_deref().map(|name| self.role_name(name));
+                let resource  = resource.as_deref().map(|name| self.resource_name(name));
+                let privilege = privilege.as_deref().map(|name| self.privilege_name(name));
+
+                self.set_rule(role, resource, privilege, *access)
+            }, // AclChange::SetRule
+            AclChange::RevokeAll{role} =>
+                self.revoke_all(self.role_name(role)).map(|_| ()),
+            AclChange::SetBypassRole{role} =>
+                self.set_bypass_role(self.role_name(role)),
+            AclChange::UnsetBypassRole{role} =>
+                self.unset_bypass_role(self.role_name(role)).map(|_| ()),
+        } // match
+    } // apply_change
+
+    fn role_name(&self, name: &str) -> &'static str {
+        self.roles.get_key_value(name).map(|(name, _)| *name).unwrap_or_else(|| intern(name))
+    } // role_name
+
+    fn resource_name(&self, name: &str) -> &'static str {
+        self.resources.get_key_value(name).map(|(name, _)| *name).unwrap_or_else(|| intern(name))
+    } // resource_name
+
+    fn privilege_name(&self, name: &str) -> &'static str {
+        self.rules.keys().filter_map(|query: &Query| query.privilege).find(|p| *p == name)
+            .unwrap_or_else(|| intern(name))
+    } // privilege_name
+
+} // impl Acl
+
+
+// Channel ////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The transport of serialized messages from the publisher to its subscribers.
+pub trait Channel {
+
+    /// Sends a message to all subscribers.
+    fn send(&mut self, message: String) -> Result<(), Error>;
+
+} // trait Channel
+
+impl Channel for Sender<String> {
+
+    fn send(&mut self, message: String) -> Result<(), Error> {
+        Sender::send(self, message).map_err(|e| Error::Io(e.to_string()))
+    } // send
+
+} // impl Channel for Sender<String>
+
+
+// Publisher //////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Owns the primary `Acl` and publishes its changes.
+pub struct Publisher<C: Channel> {
+    acl:     Acl,
+    seq:     u64,
+    channel: C,
+} // struct Publisher
+
+impl<C: Channel> Publisher<C> {
+
+    /// Creates a new `Publisher`. Subscribers need a snapshot to catch up with acl.
+    pub fn new(acl: Acl, channel: C) -> Self {
+        Publisher{acl, seq: 0, channel}
+    } // new
+
+    /// Returns the primary `Acl`.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Returns the sequence number of the last change.
+    #[inline]
+    pub fn seq(&self) -> u64 {
+        self.seq
+    } // seq
+
+    /// Applies the change to the primary `Acl` and publishes it. Returns the sequence number of
+    /// the change. A change which fails to apply is not published.
+    pub fn apply(&mut self, change: AclChange) -> Result<u64, Error> {
+        self.acl.apply_change(&change)?;
+        self.seq += 1;
+        self.channel.send(json!({"seq": self.seq, "change": change.to_json()}).to_string())?;
+        Ok(self.seq)
+    } // apply
+
+    /// Publishes a full snapshot of the primary `Acl`, e.g. on request of a subscriber which
+    /// detected a gap.
+    pub fn snapshot(&mut self) -> Result<(), Error> {
+        trace!("publishing snapshot at {}", self.seq);
+        self.channel.send(json!({"seq": self.seq, "snapshot": export(&self.acl)}).to_string())
+    } // snapshot
+
+} // impl Publisher
+
+
+// Subscriber /////////////////////////////////////////////////////////////////////////////////////
+
+
+/// The outcome of receiving a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Received {
+    /// the change with this sequence number has been applied
+    Applied(u64),
+    /// the message has been ignored, since it is older than the replica or a snapshot is awaited
+    Ignored(u64),
+    /// a change is missing, a snapshot must be requested
+    Gap{expected: u64, received: u64},
+    /// the replica has been replaced by the snapshot with this sequence number
+    Resynced(u64),
+} // enum Received
+
+/// Holds a replica of the primary `Acl` and applies received messages.
+pub struct Subscriber {
+    acl:     Acl,
+    seq:     u64,
+    in_sync: bool,
+} // struct Subscriber
+
+impl Subscriber {
+
+    /// Creates a new `Subscriber` with an empty replica. The subscriber awaits a snapshot.
+    pub fn new() -> Self {
+        Subscriber{acl: Acl::new(), seq: 0, in_sync: false}
+    } // new
+
+    /// Returns the replica.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Returns the sequence number of the last applied change or snapshot.
+    #[inline]
+    pub fn seq(&self) -> u64 {
+        self.seq
+    } // seq
+
+    /// Returns false if the subscriber awaits a snapshot.
+    #[inline]
+    pub fn is_in_sync(&self) -> bool {
+        self.in_sync
+    } // is_in_sync
+
+    /// Receives a message. Returns an error if the message is malformed or the change cannot be
+    /// applied to the replica, in which case the subscriber awaits a snapshot.
+    pub fn receive(&mut self, message: &str) -> Result<Received, Error> {
+        let value: Value = serde_json::from_str(message).map_err(|e| Error::Parse(e.to_string()))?;
+        let seq          = value["seq"].as_u64()
+            .ok_or_else(|| Error::Schema(vec![SchemaError::new("seq", "expected an unsigned integer")]))?;
+
+        if !value["snapshot"].is_null() {
+            if self.in_sync && seq <= self.seq {
+                return Ok(Received::Ignored(seq));
+            } // if
+
+            let locked = self.acl.lock.is_some();
+
+            self.acl = load(&value["snapshot"], None)?;
+            if locked {
+                self.acl.lock();
+            } // if
+            self.seq     = seq;
+            self.in_sync = true;
+            trace!("resynced replica at {}", seq);
+            return Ok(Received::Resynced(seq));
+        } // if
+
+        let change = AclChange::from_json(&value["change"])?;
+
+        if !self.in_sync || seq <= self.seq {
+            return Ok(Received::Ignored(seq));
+        } // if
+        if seq != self.seq + 1 {
+            warn!("gap in change sequence: expected {}, received {}", self.seq + 1, seq);
+            self.in_sync = false;
+            return Ok(Received::Gap{expected: self.seq + 1, received: seq});
+        } // if
+        if let Err(e) = self.acl.apply_change(&change) {
+            warn!("replica diverged at {}: {}", seq, e);
+            self.in_sync = false;
+            return Err(e);
+        } // if
+        self.seq = seq;
+        Ok(Received::Applied(seq))
+    } // receive
+
+} // impl Subscriber
+
+impl Default for Subscriber {
+
+    fn default() -> Self {
+        Self::new()
+    } // default
+
+} // impl Default for Subscriber
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::mpsc::channel;
+    use test_env_log::test;
+
+    fn role(name: &str, parents: &[&str]) -> AclChange {
+        AclChange::AddRole{name: String::from(name), parents: parents.iter().map(|p| String::from(*p)).collect()}
+    } // role
+
+    fn allow(role: &str, privilege: &str) -> AclChange {
+        AclChange::SetRule{
+            access:    Access::Allow,
+            role:      Some(String::from(role)),
+            resource:  None,
+            privilege: Some(String::from(privilege)),
+        } // AclChange::SetRule
+    } // allow
+
+    #[test]
+    fn round_trip() {
+        let changes = vec![
+            role("staff", &["guest"]),
+            AclChange::AddResource{name: String::from("latest"), parent: Some(String::from("news"))},
+            allow("staff", "view"),
+            AclChange::RevokeAll{role: String::from("staff")},
+            AclChange::SetBypassRole{role: String::from("root")},
+            AclChange::UnsetBypassRole{role: String::from("root")},
+        ]; // vec
+
+        for change in changes {
+            assert_eq!(AclChange::from_json(&change.to_json()), Ok(change));
+        } // for
+        assert!(AclChange::from_json(&json!({"op": "drop"})).is_err());
+    } // round_trip
+
+    #[test]
+    fn replicate() {
+        let (sender, receiver) = channel();
+        let mut publisher      = Publisher::new(Acl::new(), sender);
+        let mut subscriber     = Subscriber::new();
+
+        assert_eq!(publisher.apply(role("guest", &[])), Ok(1));
+
+        // changes are ignored until the first snapshot
+        let message = receiver.recv().unwrap();
+
+        assert_eq!(subscriber.receive(&message), Ok(Received::Ignored(1)));
+        assert!(publisher.snapshot().is_ok());
+        assert_eq!(subscriber.receive(&receiver.recv().unwrap()), Ok(Received::Resynced(1)));
+        assert_eq!(subscriber.receive(&message), Ok(Received::Ignored(1)));
+
+        assert_eq!(publisher.apply(role("staff", &["guest"])), Ok(2));
+        assert_eq!(publisher.apply(allow("guest", "view")), Ok(3));
+        assert!(publisher.apply(role("staff", &[])).is_err());
+        assert_eq!(subscriber.receive(&receiver.recv().unwrap()), Ok(Received::Applied(2)));
+        assert_eq!(subscriber.receive(&receiver.recv().unwrap()), Ok(Received::Applied(3)));
+        assert!(receiver.try_recv().is_err());
+        assert!(subscriber.acl().is_allowed(Some("staff"), None, Some("view")));
+
+        // the change with sequence number 4 gets lost
+        assert_eq!(publisher.apply(role("editor", &["staff"])), Ok(4));
+        assert_eq!(publisher.apply(allow("editor", "publish")), Ok(5));
+        receiver.recv().unwrap();
+        assert_eq!(subscriber.receive(&receiver.recv().unwrap()), Ok(Received::Gap{expected: 4, received: 5}));
+        assert!(!subscriber.is_in_sync());
+
+        assert!(publisher.snapshot().is_ok());
+        assert_eq!(subscriber.receive(&receiver.recv().unwrap()), Ok(Received::Resynced(5)));
+        assert!(subscriber.is_in_sync());
+        assert!(subscriber.acl().is_allowed(Some("editor"), None, Some("publish")));
+        assert_eq!(subscriber.acl().to_json(), publisher.acl().to_json());
+    } // replicate
+
+} // mod tests
@@ -0,0 +1,185 @@
+//! Assertions for policy regression tests.
+//!
+//! `assert_allowed!` and `assert_denied!` take an `Acl` followed by the arguments of `is_allowed`
+//! and optionally a message like `assert!`. On failure they panic with the explanation of the
+//! decision: the deciding rule, its metadata, the lineages searched and the rules which apply to
+//! the query in order of precedence.
+//!
+//! ```
+//! # #[macro_use] extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # fn main() {
+//! let mut acl = Acl::new();
+//!
+//! acl.add_role("guest", vec![]).unwrap();
+//! acl.allow(Some("guest"), None, Some("view")).unwrap();
+//!
+//! assert_allowed!(acl, Some("guest"), None, Some("view"));
+//! assert_denied!(acl, Some("guest"), None, Some("edit"), "guests may not edit");
+//! # }
+//! ```
+
+use crate::{Acl, Privilege, Query, Resource, Role};
+use std::fmt::Write;
+
+/// Asserts that privilege is allowed for role on resource, see module `testing`.
+#[macro_export]
+macro_rules! assert_allowed {
+    ($acl:expr, $role:expr, $resource:expr, $privilege:expr $(,)?) => {
+        $crate::assert_allowed!($acl, $role, $resource, $privilege, "")
+    };
+    ($acl:expr, $role:expr, $resource:expr, $privilege:expr, $($arg:tt)+) => {{
+        let acl: &$crate::Acl = &$acl;
+
+        if !acl.is_allowed($role, $resource, $privilege) {
+            panic!("{}", $crate::testing::failure(acl, $role, $resource, $privilege, true, &format!($($arg)+)));
+        } // if
+    }};
+} // assert_allowed
+
+/// Asserts that privilege is denied for role on resource, see module `testing`.
+#[macro_export]
+macro_rules! assert_denied {
+    ($acl:expr, $role:expr, $resource:expr, $privilege:expr $(,)?) => {
+        $crate::assert_denied!($acl, $role, $resource, $privilege, "")
+    };
+    ($acl:expr, $role:expr, $resource:expr, $privilege:expr, $($arg:tt)+) => {{
+        let acl: &$crate::Acl = &$acl;
+
+        if !acl.is_denied($role, $resource, $privilege) {
+            panic!("{}", $crate::testing::failure(acl, $role, $resource, $privilege, false, &format!($($arg)+)));
+        } // if
+    }};
+} // assert_denied
+
+/// Returns the failure message of `assert_allowed!` or `assert_denied!`.
+#[doc(hidden)]
+pub fn failure(acl: &Acl, role: Role, resource: Resource, privilege: Privilege, allowed: bool, message: &str) -> String {
+    let query   = Query{resource, role, privilege};
+    let mut out = format!("assertion failed: {} is {}", query, if allowed { "allowed" } else { "denied" });
+
+    if !message.is_empty() {
+        let _ = write!(out, ": {}", message);
+    } // if
+    let _ = write!(out, "\n{}", explain(acl, role, resource, privilege));
+    out
+} // failure
+
+/// Explains the decision of a query: the deciding rule and its metadata, the lineages of role and
+/// resource and the rules which apply to the query in order of precedence.
+pub fn explain(acl: &Acl, role: Role, resource: Resource, privilege: Privilege) -> String {
+    let decision = acl.evaluate(role, resource, privilege);
+    let mut out  = String::new();
+
+    let _ = writeln!(out, "decision: {}", decision);
+    if decision.bypass {
+        let _ = writeln!(out, "matched:  bypass role");
+    } else {
+        let _ = writeln!(out, "matched:  {} {}", decision.rule, decision.matched);
+    } // else
+    if let Some(meta) = acl.get_decision_meta(&decision) {
+        for (key, value) in &[("description", &meta.description), ("ticket", &meta.ticket), ("message", &meta.message)] {
+            if let Some(value) = value {
+                let _ = writeln!(out, "{}: {}", key, value);
+            } // if
+        } // for
+    } // if
+
+    // lineages as searched, wildcards last
+    let query         = decision.query;
+    let mut roles     = query.role.map(|name| acl.get_role_lineage(name)).unwrap_or_default()
+        .into_iter().map(Some).collect::<Vec<Role>>();
+    let mut resources = query.resource.map(|name| acl.get_resource_lineage(name)).unwrap_or_default()
+        .into_iter().map(Some).collect::<Vec<Resource>>();
+
+    let _ = writeln!(out, "roles:     {}", lineage(&roles));
+    let _ = writeln!(out, "resources: {}", lineage(&resources));
+    roles.push(None);
+    resources.push(None);
+
+    let _ = writeln!(out, "rules in order of precedence:");
+    for resource in &resources {
+        for role in &roles {
+            for privilege in query.privilege.iter().map(|name| Some(*name)).chain(Some(None)) {
+                let candidate = Query{resource: *resource, role: *role, privilege};
+
+                if let Some(rule) = acl.rules.get(&candidate) {
+                    let marker = if candidate == decision.matched && !decision.bypass { "=>" } else { "  " };
+
+                    let _ = writeln!(out, "  {} {} {}", marker, rule, candidate);
+                } // if let
+            } // for
+        } // for
+    } // for
+    out
+} // explain
+
+/// Formats a lineage, or `*` if empty.
+fn lineage(names: &[Option<&'static str>]) -> String {
+    if names.is_empty() {
+        return String::from("*");
+    } // if
+    names.iter().flatten().copied().collect::<Vec<_>>().join(", ")
+} // lineage
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::RuleMeta;
+    use test_env_log::test;
+
+    fn setup() -> Acl {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        assert!(acl.allow(Some("staff"), Some("news"), None).is_ok());
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("edit")).is_ok());
+        assert!(acl.set_rule_meta(Some("staff"), Some("latest"), Some("edit"), RuleMeta{
+            description: Some(String::from("latest news are edited by editors")),
+            ..RuleMeta::default()
+        }).is_ok());
+        acl
+    } // setup
+
+    #[test]
+    fn explanation() {
+        let acl = setup();
+
+        assert_eq!(explain(&acl, Some("staff"), Some("latest"), Some("edit")), concat!(
+            "decision: DENY staff→latest: edit\n",
+            "matched:  DENY staff→latest: edit\n",
+            "description: latest news are edited by editors\n",
+            "roles:     staff, guest\n",
+            "resources: latest, news\n",
+            "rules in order of precedence:\n",
+            "  => DENY staff→latest: edit\n",
+            "     ALLOW staff→news: *\n",
+            "     DENY *→*: *\n",
+        ));
+        assert!(explain(&acl, None, None, None).ends_with("roles:     *\nresources: *\nrules in order of precedence:\n  => DENY *→*: *\n"));
+        assert_allowed!(acl, Some("staff"), Some("latest"), Some("view"));
+        assert_denied!(acl, Some("guest"), Some("latest"), Some("edit"), "guests may not {}", "edit");
+    } // explanation
+
+    #[test]
+    #[should_panic(expected = "assertion failed: staff→latest: edit is allowed: editors only\ndecision: DENY staff→latest: edit")]
+    fn allowed_failure() {
+        assert_allowed!(setup(), Some("staff"), Some("latest"), Some("edit"), "editors only");
+    } // allowed_failure
+
+    #[test]
+    #[should_panic(expected = "  => ALLOW guest→*: view")]
+    fn denied_failure() {
+        assert_denied!(setup(), Some("guest"), Some("latest"), Some("view"));
+    } // denied_failure
+
+} // mod tests
@@ -0,0 +1,417 @@
+//! Unix permission model.
+//!
+//! Models owner, group and other classes with read, write and execute privileges on top of the
+//! `Acl`. Resources are absolute, normalized paths parented by their directory, missing
+//! directories are added without rules and thus inherit the rules of their directory. Users are roles inheriting from their groups, whose roles
+//! are prefixed with `group:`. `set_unix_mode` defines an allow or deny rule for each class and
+//! privilege on a path: the owner's rules, then the group's, then the wildcard role's for other
+//! users are found first, so the class of a user is exclusive like in Unix. `import_ls` adds the
+//! paths listed by `ls -l`. `unix_access` additionally requires execute on all ancestor
+//! directories. A superuser is a bypass role, see `Acl::set_bypass_role`.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::Acl;
+//! # use zorq_acl::unix::{Mode, EXECUTE, READ, WRITE};
+//! let mut acl = Acl::new();
+//!
+//! acl.add_unix_user("alice", &["staff"]).unwrap();
+//! acl.add_unix_user("bob", &["staff"]).unwrap();
+//! acl.set_unix_mode("/", "root", "root", Mode(0o755)).unwrap();
+//! acl.import_ls("/srv", "\
+//! total 8
+//! drwxr-x--- 2 alice staff 4096 Mar  1 12:00 docs
+//! -rw-r--r-- 1 alice staff  220 Mar  1 12:00 notes.txt").unwrap();
+//! acl.set_unix_mode("/srv/docs/plan.txt", "alice", "staff", Mode::parse("rw-rw----").unwrap()).unwrap();
+//!
+//! assert!( acl.unix_access("bob", "/srv/docs/plan.txt", WRITE));
+//! assert!(!acl.unix_access("eve", "/srv/docs/plan.txt", READ));
+//! assert!( acl.unix_access("eve", "/srv/notes.txt", READ));
+//! assert!(!acl.unix_access("bob", "/srv/docs", WRITE));
+//! assert!(!acl.unix_access("alice", "/srv/notes.txt", EXECUTE));
+//! ```
+
+use crate::{Access, Acl, Error, SchemaError};
+use crate::import::{ErrorMode, ImportReport};
+use log::trace;
+use std::fmt;
+
+/// The read privilege.
+pub const READ:    &str = "read";
+/// The write privilege.
+pub const WRITE:   &str = "write";
+/// The execute privilege, also required on directories to access their entries.
+pub const EXECUTE: &str = "execute";
+
+/// The prefix of group roles.
+pub const GROUP_PREFIX: &str = "group:";
+
+const PRIVILEGES: [&str; 3] = [READ, WRITE, EXECUTE];
+
+/// Leaks name to obtain the `'static` lifetime.
+fn leak(name: &str) -> &'static str {
+    Box::leak(String::from(name).into_boxed_str())
+} // leak
+
+/// Returns path absolute and normalized, `.` and `..` resolved.
+pub fn normalize(path: &str) -> String {
+    let mut names: Vec<&str> = vec![];
+
+    for name in path.split('/') {
+        match name {
+            "" | "." => (),
+            ".."     => { names.pop(); },
+            name     => names.push(name),
+        } // match
+    } // for
+    format!("/{}", names.join("/"))
+} // normalize
+
+/// Returns the directory of a normalized path, None for the root.
+fn directory(path: &str) -> Option<&str> {
+    if path == "/" {
+        return None;
+    } // if
+    path.rfind('/').map(|i| if i == 0 { "/" } else { &path[..i] })
+} // directory
+
+
+// Mode ///////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// A permission class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    Owner,
+    Group,
+    Other,
+} // enum Class
+
+/// The permission bits of a path, e.g. `Mode(0o750)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mode(pub u16);
+
+impl Mode {
+
+    /// Parses octal like `750` or symbolic bits like `rwxr-x---`, optionally preceded by the file
+    /// type and followed by an alternate access marker like `ls -l` prints them. Setuid, setgid and
+    /// sticky bits are only considered for execute.
+    pub fn parse(mode: &str) -> Result<Mode, Error> {
+        let invalid = || Error::Parse(format!("invalid mode: {}", mode));
+
+        if (3..=4).contains(&mode.len()) && mode.chars().all(|c| ('0'..='7').contains(&c)) {
+            return u16::from_str_radix(mode, 8).map(|bits| Mode(bits & 0o777)).map_err(|_| invalid());
+        } // if
+        let bits  = mode.trim_end_matches(['.', '+', '@'].as_ref());
+        let bits  = match bits.len() {
+            9  => bits,
+            10 if "-dlcbps".contains(&bits[..1]) => &bits[1..],
+            _  => return Err(invalid()),
+        }; // match
+        let mut mode = 0;
+
+        for (i, c) in bits.chars().enumerate() {
+            let set = match (i % 3, c) {
+                (_, '-')                       => false,
+                (0, 'r') | (1, 'w') | (2, 'x') => true,
+                (2, 's') | (2, 't')            => true,
+                (2, 'S') | (2, 'T')            => false,
+                _                              => return Err(invalid()),
+            }; // match
+
+            if set {
+                mode |= 1 << (8 - i);
+            } // if
+        } // for
+        Ok(Mode(mode))
+    } // parse
+
+    /// Returns true if the bit of privilege is set for class. Unknown privileges are never set.
+    pub fn allows(&self, class: Class, privilege: &str) -> bool {
+        let shift = match class {
+            Class::Owner => 6,
+            Class::Group => 3,
+            Class::Other => 0,
+        }; // match
+        let bit   = match privilege {
+            READ    => 4,
+            WRITE   => 2,
+            EXECUTE => 1,
+            _       => 0,
+        }; // match
+
+        (self.0 >> shift) & bit != 0
+    } // allows
+
+} // impl Mode
+
+impl fmt::Display for Mode {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for i in 0..9 {
+            let c = if self.0 & (1 << (8 - i)) == 0 { '-' } else { ['r', 'w', 'x'][i % 3] };
+
+            write!(f, "{}", c)?;
+        } // for
+        Ok(())
+    } // fmt
+
+} // impl fmt::Display for Mode
+
+
+// Listing ////////////////////////////////////////////////////////////////////////////////////////
+
+
+/// An entry listed by `ls -l`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// the name of the entry, without the target of a symbolic link
+    pub name:  String,
+    /// the owning user
+    pub owner: String,
+    /// the owning group
+    pub group: String,
+    /// the permission bits
+    pub mode:  Mode,
+} // struct Entry
+
+impl Entry {
+
+    /// Parses a line printed by `ls -l` like `drwxr-x--- 2 alice staff 4096 Mar  1 12:00 docs`.
+    pub fn parse(line: &str) -> Result<Entry, Error> {
+        let invalid    = || Error::Parse(format!("invalid listing: {}", line));
+        let mut fields = vec![];
+        let mut rest   = line.trim();
+
+        // mode, links, owner, group, size, month, day and time or year precede the name
+        for _ in 0..8 {
+            let end = rest.find(char::is_whitespace).ok_or_else(invalid)?;
+
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        } // for
+        let name = match rest.find(" -> ") {
+            Some(i) if fields[0].starts_with('l') => &rest[..i],
+            _                                     => rest,
+        }; // match
+
+        Ok(Entry{name: String::from(name), owner: String::from(fields[2]), group: String::from(fields[3]), mode: Mode::parse(fields[0])?})
+    } // parse
+
+} // impl Entry
+
+
+// Acl ////////////////////////////////////////////////////////////////////////////////////////////
+
+
+impl Acl {
+
+    /// Adds user inheriting from groups, adding the missing group roles. Returns an error if user
+    /// is already defined.
+    pub fn add_unix_user(&mut self, user: &str, groups: &[&str]) -> Result<(), Error> {
+        trace!("adding unix user {} in groups {:?}", user, groups);
+        if self.roles.contains_key(user) {
+            return Err(Error::DuplicateRole(String::from(user)));
+        } // if
+        let mut parents = vec![];
+
+        for group in groups {
+            parents.push(self.unix_group(group)?);
+        } // for
+        self.add_role(leak(user), parents)
+    } // add_unix_user
+
+    /// Defines the rules of mode for the owner, group and other users on path, replacing previous
+    /// rules on path. Adds path with its missing directories as well as the missing owner and group.
+    pub fn set_unix_mode(&mut self, path: &str, owner: &str, group: &str, mode: Mode) -> Result<(), Error> {
+        trace!("setting mode {} of {} to {}:{}", mode, path, owner, group);
+        let path  = self.unix_path(&normalize(path))?;
+        let owner = match self.roles.get_key_value(owner) {
+            Some((name, _)) => *name,
+            None            => {
+                let name = leak(owner);
+
+                self.add_role(name, vec![])?;
+                name
+            }, // None
+        }; // match
+        let group = self.unix_group(group)?;
+
+        self.remove_allow(None, Some(path), None)?;
+        self.remove_deny(None, Some(path), None)?;
+        for (class, role) in [(Class::Owner, Some(owner)), (Class::Group, Some(group)), (Class::Other, None)].iter() {
+            for privilege in PRIVILEGES.iter() {
+                let access = if mode.allows(*class, privilege) { Access::Allow } else { Access::Deny };
+
+                self.set_rule(*role, Some(path), Some(privilege), access)?;
+            } // for
+        } // for
+        Ok(())
+    } // set_unix_mode
+
+    /// Sets the modes of the entries listed by `ls -l` in directory, skipping the total and the
+    /// `.` and `..` entries. Returns the number of entries set. Returns an error locating the first
+    /// malformed line, in which case no entry is set.
+    #[inline]
+    pub fn import_ls(&mut self, directory: &str, listing: &str) -> Result<usize, Error> {
+        self.import_ls_with(directory, listing, ErrorMode::FailFast).map(|report| report.imported)
+    } // import_ls
+
+    /// Like `import_ls`, but handles malformed lines by mode, see module `import`. Problems are
+    /// located by line number.
+    pub fn import_ls_with(&mut self, directory: &str, listing: &str, mode: ErrorMode) -> Result<ImportReport, Error> {
+        trace!("importing listing of {} with {:?}", directory, mode);
+        let mut errors  = vec![];
+        let mut entries = vec![];
+
+        for (i, line) in listing.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with("total ") {
+                continue;
+            } // if
+            match Entry::parse(line) {
+                Ok(entry)  => if entry.name != "." && entry.name != ".." {
+                    entries.push(entry);
+                }, // Ok
+                Err(error) => mode.reject(&mut errors, SchemaError::new(&(i + 1).to_string(), &error.to_string()))?,
+            } // match
+        } // for
+
+        let skipped = mode.check(errors)?;
+
+        for entry in &entries {
+            self.set_unix_mode(&format!("{}/{}", directory, entry.name), &entry.owner, &entry.group, entry.mode)?;
+        } // for
+        Ok(ImportReport{imported: entries.len(), skipped})
+    } // import_ls_with
+
+    /// Returns true if user may access path with privilege: execute must be allowed on all
+    /// ancestor directories and privilege on path. Unknown users are other users, unknown paths
+    /// are denied.
+    pub fn unix_access(&self, user: &str, path: &str, privilege: &'static str) -> bool {
+        let role = self.roles.get_key_value(user).map(|(name, _)| *name);
+        let path = match self.resources.get_key_value(normalize(path).as_str()) {
+            Some((name, _)) => *name,
+            None            => return false,
+        }; // match
+        let lineage = self.get_resource_lineage(path);
+
+        lineage[1..].iter().all(|directory| self.is_allowed(role, Some(directory), Some(EXECUTE)))
+            && self.is_allowed(role, Some(path), Some(privilege))
+    } // unix_access
+
+    /// Returns the defined path or adds it with its missing directories.
+    fn unix_path(&mut self, path: &str) -> Result<&'static str, Error> {
+        if let Some((name, _)) = self.resources.get_key_value(path) {
+            return Ok(name);
+        } // if
+        let parent = match directory(path) {
+            Some(directory) => Some(self.unix_path(directory)?),
+            None            => None,
+        }; // match
+        let name   = leak(path);
+
+        self.add_resource(name, parent)?;
+        Ok(name)
+    } // unix_path
+
+    /// Returns the defined role of group or adds it.
+    fn unix_group(&mut self, group: &str) -> Result<&'static str, Error> {
+        let role = format!("{}{}", GROUP_PREFIX, group);
+
+        if let Some((name, _)) = self.roles.get_key_value(role.as_str()) {
+            return Ok(name);
+        } // if
+        let name = leak(&role);
+
+        self.add_role(name, vec![])?;
+        Ok(name)
+    } // unix_group
+
+} // impl Acl
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn mode() {
+        assert_eq!(Mode::parse("750"), Ok(Mode(0o750)));
+        assert_eq!(Mode::parse("4755"), Ok(Mode(0o755)));
+        assert_eq!(Mode::parse("rwxr-x---"), Ok(Mode(0o750)));
+        assert_eq!(Mode::parse("drwxrwxrwt"), Ok(Mode(0o777)));
+        assert_eq!(Mode::parse("-rwSr--r--."), Ok(Mode(0o644)));
+        assert_eq!(Mode::parse("rwxr-x"), Err(Error::Parse(String::from("invalid mode: rwxr-x"))));
+        assert_eq!(Mode::parse("rwxw-x---"), Err(Error::Parse(String::from("invalid mode: rwxw-x---"))));
+        assert_eq!(Mode(0o640).to_string(), "rw-r-----");
+
+        assert!( Mode(0o640).allows(Class::Owner, WRITE));
+        assert!( Mode(0o640).allows(Class::Group, READ));
+        assert!(!Mode(0o640).allows(Class::Group, WRITE));
+        assert!(!Mode(0o640).allows(Class::Other, READ));
+        assert!(!Mode(0o777).allows(Class::Other, "view"));
+    } // mode
+
+    #[test]
+    fn listing() {
+        assert_eq!(normalize("srv//docs/./old/../plan.txt"), "/srv/docs/plan.txt");
+        assert_eq!(normalize("/"), "/");
+        assert_eq!(directory("/srv/docs"), Some("/srv"));
+        assert_eq!(directory("/srv"), Some("/"));
+        assert_eq!(directory("/"), None);
+
+        assert_eq!(Entry::parse("lrwxrwxrwx  1 root  root     7 Jan  1  2024 my bin -> usr/bin"), Ok(Entry{
+            name: String::from("my bin"), owner: String::from("root"), group: String::from("root"), mode: Mode(0o777),
+        })); // Entry
+        assert!(Entry::parse("total 8").is_err());
+    } // listing
+
+    #[test]
+    fn access() {
+        let mut acl = Acl::new();
+
+        assert!(acl.add_unix_user("alice", &["staff", "alice"]).is_ok());
+        assert!(acl.add_unix_user("bob", &["staff"]).is_ok());
+        assert!(acl.add_unix_user("root", &[]).is_ok());
+        assert!(acl.set_bypass_role("root").is_ok());
+        assert_eq!(acl.import_ls("/home/alice/", "\
+total 12
+drwx--x--- 3 alice alice 4096 Mar  1 12:00 .
+drwxr-xr-x 5 root  root  4096 Mar  1 12:00 ..
+-r--rw-r-- 1 alice staff  220 Mar  1 12:00 todo.txt
+drwxrwx--- 2 carol staff 4096 Mar  1 12:00 shared"), Ok(2));
+
+        assert_eq!(acl.get_resource_lineage("/home/alice/todo.txt"), vec!["/home/alice/todo.txt", "/home/alice", "/home", "/"]);
+        assert_eq!(acl.get_role_lineage("alice"), vec!["alice", "group:alice", "group:staff"]);
+        assert!(acl.has_role("carol"));
+
+        // directories without a mode inherit the rules of their directory, none at all deny
+        assert!(!acl.unix_access("alice", "/home/alice/todo.txt", READ));
+        assert!(acl.set_unix_mode("/", "root", "root", Mode(0o755)).is_ok());
+        assert!( acl.unix_access("alice", "/home/alice/todo.txt", READ));
+        assert!(acl.set_unix_mode("/home/alice", "alice", "alice", Mode(0o700)).is_ok());
+        assert!(!acl.unix_access("bob", "/home/alice/todo.txt", WRITE));
+        assert!(acl.set_unix_mode("/home/alice", "alice", "staff", Mode(0o710)).is_ok());
+
+        // the owner class is exclusive, even if the group may do more
+        assert!( acl.unix_access("alice", "/home/alice/todo.txt", READ));
+        assert!(!acl.unix_access("alice", "/home/alice/todo.txt", WRITE));
+        assert!( acl.unix_access("bob", "/home/alice/todo.txt", WRITE));
+        assert!( acl.unix_access("bob", "/home/alice/shared", WRITE));
+        assert!(!acl.unix_access("eve", "/home/alice/todo.txt", READ));
+        assert!( acl.unix_access("root", "/home/alice/todo.txt", WRITE));
+        assert!(!acl.unix_access("bob", "/home/alice/missing.txt", READ));
+
+        // a new mode replaces all rules
+        assert!(acl.set_unix_mode("/home/alice/todo.txt", "bob", "bob", Mode(0o600)).is_ok());
+        assert!(!acl.unix_access("alice", "/home/alice/todo.txt", READ));
+        assert!( acl.unix_access("bob", "/home/alice/todo.txt", WRITE));
+        assert_eq!(acl.rules().filter(|(query, _, _)| query.resource == Some("/home/alice/todo.txt")).count(), 9);
+    } // access
+
+} // mod tests
@@ -0,0 +1,187 @@
+//! Four-eyes approval of policy changes.
+//!
+//! A `Workflow` owns the live `Acl`. Changes are requested first and applied only when approved
+//! by someone other than the requester. Rejected changes are discarded. The queue of pending
+//! changes can be serialized as JSON to survive restarts.
+//!
+//! ```
+//! # extern crate zorq_acl;
+//! # use zorq_acl::{Access, Acl};
+//! # use zorq_acl::sync::AclChange;
+//! # use zorq_acl::workflow::Workflow;
+//! let mut workflow = Workflow::new(Acl::new());
+//! let id           = workflow.request_grant("sally", AclChange::AddRole{name: String::from("auditor"), parents: vec![]});
+//!
+//! assert!(!workflow.acl().has_role("auditor"));
+//! assert!(workflow.approve(id, "sally").is_err());
+//! assert!(workflow.approve(id, "bob").is_ok());
+//! assert!(workflow.acl().has_role("auditor"));
+//! ```
+
+use crate::sync::AclChange;
+use crate::{Acl, Error, SchemaError};
+use log::{trace, warn};
+use serde_json::{json, Value};
+
+
+// PendingChange //////////////////////////////////////////////////////////////////////////////////
+
+
+/// A requested change awaiting approval.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingChange {
+    /// the id returned by `Workflow::request_grant`
+    pub id:        u64,
+    /// who requested the change
+    pub requester: String,
+    /// the change
+    pub change:    AclChange,
+} // struct PendingChange
+
+
+// Workflow ///////////////////////////////////////////////////////////////////////////////////////
+
+
+/// Owns the live `Acl` and applies requested changes once approved.
+pub struct Workflow {
+    acl:     Acl,
+    pending: Vec<PendingChange>,
+    next:    u64,
+} // struct Workflow
+
+impl Workflow {
+
+    /// Creates a new `Workflow` with an empty queue.
+    pub fn new(acl: Acl) -> Self {
+        Workflow{acl, pending: vec![], next: 0}
+    } // new
+
+    /// Returns the live `Acl`.
+    #[inline]
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    } // acl
+
+    /// Returns the pending changes in order of request.
+    #[inline]
+    pub fn pending(&self) -> &[PendingChange] {
+        &self.pending
+    } // pending
+
+    /// Queues a change requested by requester. Returns the id of the pending change.
+    pub fn request_grant(&mut self, requester: &str, change: AclChange) -> u64 {
+        self.next += 1;
+        trace!("{} requested change {}: {:?}", requester, self.next, change);
+        self.pending.push(PendingChange{id: self.next, requester: String::from(requester), change});
+        self.next
+    } // request_grant
+
+    /// Approves the pending change with id and applies it to the live `Acl`. Returns an error if
+    /// there is no such change, the approver is the requester or the change can't be applied. A
+    /// change which can't be applied stays pending.
+    pub fn approve(&mut self, id: u64, approver: &str) -> Result<PendingChange, Error> {
+        let index = self.position(id)?;
+
+        if self.pending[index].requester == approver {
+            warn!("{} may not approve own change {}", approver, id);
+            return Err(Error::NotPermitted(format!("approval of own change {}", id)));
+        } // if
+        self.acl.apply_change(&self.pending[index].change)?;
+        trace!("{} approved change {}", approver, id);
+        Ok(self.pending.remove(index))
+    } // approve
+
+    /// Rejects and discards the pending change with id. Returns an error if there is no such
+    /// change.
+    pub fn reject(&mut self, id: u64) -> Result<PendingChange, Error> {
+        let index = self.position(id)?;
+
+        trace!("rejected change {}", id);
+        Ok(self.pending.remove(index))
+    } // reject
+
+    /// Serializes the queue of pending changes.
+    pub fn queue_to_json(&self) -> Value {
+        let pending: Vec<Value> = self.pending.iter().map(|pending| json!({
+            "id":        pending.id,
+            "requester": pending.requester,
+            "change":    pending.change.to_json(),
+        })).collect();
+
+        json!({"next": self.next, "pending": pending})
+    } // queue_to_json
+
+    /// Replaces the queue of pending changes by a serialized queue.
+    pub fn load_queue(&mut self, value: &Value) -> Result<(), Error> {
+        let schema  = |path: &str, message: &str| Error::Schema(vec![SchemaError::new(path, message)]);
+        let next    = value["next"].as_u64().ok_or_else(|| schema("next", "expected an unsigned integer"))?;
+        let entries = value["pending"].as_array().ok_or_else(|| schema("pending", "expected an array"))?;
+        let mut pending = vec![];
+
+        for (i, entry) in entries.iter().enumerate() {
+            pending.push(PendingChange{
+                id:        entry["id"].as_u64()
+                    .ok_or_else(|| schema(&format!("pending[{}].id", i), "expected an unsigned integer"))?,
+                requester: entry["requester"].as_str().map(String::from)
+                    .ok_or_else(|| schema(&format!("pending[{}].requester", i), "expected a string"))?,
+                change:    AclChange::from_json(&entry["change"])?,
+            }); // PendingChange
+        } // for
+        self.pending = pending;
+        self.next    = next;
+        Ok(())
+    } // load_queue
+
+    /// Returns the live `Acl`, discarding pending changes.
+    pub fn into_inner(self) -> Acl {
+        self.acl
+    } // into_inner
+
+    fn position(&self, id: u64) -> Result<usize, Error> {
+        self.pending.iter().position(|pending| pending.id == id).ok_or(Error::MissingChange(id))
+    } // position
+
+} // impl Workflow
+
+
+// Tests //////////////////////////////////////////////////////////////////////////////////////////
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Access;
+    use test_env_log::test;
+
+    #[test]
+    fn approval() {
+        let mut workflow = Workflow::new(Acl::new());
+        let role         = workflow.request_grant("sally", AclChange::AddRole{name: String::from("staff"), parents: vec![]});
+        let rule         = workflow.request_grant("sally", AclChange::SetRule{
+            access: Access::Allow, role: Some(String::from("staff")), resource: None, privilege: Some(String::from("edit"))});
+        let other        = workflow.request_grant("bob", AclChange::AddRole{name: String::from("root"), parents: vec![]});
+
+        // rules require the role, the change stays pending
+        assert_eq!(workflow.approve(rule, "bob"), Err(Error::MissingRole(String::from("staff"))));
+        assert_eq!(workflow.approve(role, "sally"), Err(Error::NotPermitted(format!("approval of own change {}", role))));
+        assert_eq!(workflow.approve(role, "bob").map(|pending| pending.id), Ok(role));
+        assert!(workflow.approve(rule, "bob").is_ok());
+        assert!(workflow.acl().is_allowed(Some("staff"), None, Some("edit")));
+        assert_eq!(workflow.approve(rule, "bob"), Err(Error::MissingChange(rule)));
+
+        // the queue survives a restart
+        let queue        = workflow.queue_to_json();
+        let mut workflow = Workflow::new(workflow.into_inner());
+
+        assert!(workflow.load_queue(&queue).is_ok());
+        assert_eq!(workflow.pending().len(), 1);
+        assert_eq!(workflow.reject(other).map(|pending| pending.requester), Ok(String::from("bob")));
+        assert!(workflow.pending().is_empty());
+        assert!(!workflow.acl().has_role("root"));
+        assert_eq!(workflow.request_grant("bob", AclChange::RevokeAll{role: String::from("staff")}), 4);
+
+        assert!(workflow.load_queue(&json!({"next": 1, "pending": [{"id": 1}]})).is_err());
+    } // approval
+
+} // mod tests
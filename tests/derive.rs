@@ -0,0 +1,155 @@
+//! Tests of the derive macros.
+
+#![cfg(feature = "derive")]
+
+extern crate zorq_acl;
+
+use test_env_log::test;
+use zorq_acl::{Acl, Error};
+use zorq_acl::domain::{require_privilege, AccessDenied, AclContext, AclPrivilege, AclResource, AclRole};
+
+#[derive(AclRole)]
+struct User {
+    #[allow(dead_code)]
+    name: String,
+    #[acl(id)]
+    role: String,
+} // struct User
+
+#[derive(AclRole)]
+#[acl(id = "guest")]
+struct Anonymous;
+
+#[derive(AclResource)]
+struct Document<'a>(#[acl(id)] &'a str, #[allow(dead_code)] u64);
+
+#[derive(AclResource)]
+#[acl(id = "newsletter")]
+enum Newsletter {
+    #[allow(dead_code)]
+    Daily,
+    Weekly,
+} // enum Newsletter
+
+#[derive(AclPrivilege, Clone, Copy, Debug, PartialEq)]
+enum Privilege {
+    #[acl(label = "View")]
+    View,
+    #[acl(label = "Publish draft", description = "Publish a draft to the public site")]
+    PublishDraft,
+    #[acl(id = "delete")]
+    Remove,
+} // enum Privilege
+
+#[test]
+fn derive() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+    assert!(acl.add_resource("newsletter", None).is_ok());
+    assert!(acl.add_resource("report", None).is_ok());
+    assert!(acl.allow(Some("guest"), Some("newsletter"), Some("view")).is_ok());
+    assert!(acl.allow(Some("staff"), Some("report"), Some("edit")).is_ok());
+
+    let sally = User{name: String::from("sally"), role: String::from("staff")};
+
+    assert_eq!(sally.role_id(), "staff");
+    assert_eq!(Anonymous.role_id(), "guest");
+    assert_eq!(Document("report", 42).resource_id(), "report");
+    assert_eq!(Newsletter::Weekly.resource_id(), "newsletter");
+
+    assert!(acl.is_allowed_with(&sally, &Newsletter::Weekly, Some("view")));
+    assert!(acl.is_allowed_with(&sally, &Document("report", 42), Some("edit")));
+    assert!(acl.is_denied_with (&Anonymous, &Document("report", 42), Some("edit")));
+    assert!(acl.is_denied_with (&Anonymous, &Document("unknown", 42), Some("view")));
+    assert_eq!(acl.decide_with(&sally, &Newsletter::Weekly, Some("view")).to_string(), "ALLOW staff→newsletter: view");
+} // derive
+
+#[test]
+fn privileges() {
+    let mut acl = Acl::new();
+
+    assert_eq!(Privilege::ALL, &[Privilege::View, Privilege::PublishDraft, Privilege::Remove]);
+    assert_eq!(Privilege::PublishDraft.privilege_id(), "publish_draft");
+    assert_eq!(Privilege::from_privilege_id("delete"), Some(Privilege::Remove));
+    assert_eq!(Privilege::from_privilege_id("remove"), None);
+    assert_eq!(Privilege::View.label(), Some("View"));
+    assert_eq!(Privilege::Remove.description(), None);
+
+    // unregistered privileges are not checked
+    assert!(acl.add_role("staff", vec![]).is_ok());
+    assert!(acl.allow(Some("staff"), None, Some("veiw")).is_ok());
+
+    acl.register_privileges::<Privilege>();
+    assert!(acl.has_privilege("publish_draft"));
+    assert_eq!(acl.privileges().collect::<Vec<_>>(), vec!["delete", "publish_draft", "view"]);
+    assert_eq!(acl.get_privilege_label("publish_draft"), "Publish draft");
+    assert_eq!(acl.get_privilege_info("publish_draft").unwrap().description.as_deref(), Some("Publish a draft to the public site"));
+    assert_eq!(acl.get_privilege_info("delete"), None);
+    assert!(acl.allow(Some("staff"), None, Privilege::View.into()).is_ok());
+    assert!(acl.allow(Some("staff"), None, None).is_ok());
+    assert_eq!(acl.allow(Some("staff"), None, Some("veiw")), Err(Error::MissingPrivilege(String::from("veiw"))));
+    assert_eq!(acl.allow_subject("sally", None, Some("edit")), Err(Error::MissingPrivilege(String::from("edit"))));
+    assert!(acl.is_allowed(Some("staff"), None, Privilege::View.into()));
+} // privileges
+
+struct Request<'a> {
+    acl:  &'a Acl,
+    role: Option<String>,
+} // struct Request
+
+impl<'a> AclContext for Request<'a> {
+
+    fn acl(&self) -> &Acl {
+        self.acl
+    } // acl
+
+    fn role_id(&self) -> Option<&str> {
+        self.role.as_deref()
+    } // role_id
+
+} // impl AclContext for Request
+
+#[derive(Debug, PartialEq)]
+enum AppError {
+    Denied(String),
+} // enum AppError
+
+impl From<AccessDenied> for AppError {
+
+    fn from(denied: AccessDenied) -> Self {
+        AppError::Denied(denied.to_string())
+    } // from
+
+} // impl From<AccessDenied> for AppError
+
+#[require_privilege(resource = "newsletter", privilege = "publish")]
+fn publish(ctx: &Request, issue: u32) -> Result<u32, AppError> {
+    Ok(issue)
+} // publish
+
+#[require_privilege(privilege = "export", context = "request")]
+fn export(request: Request) -> Result<(), AccessDenied> {
+    let _ = request;
+    Ok(())
+} // export
+
+#[test]
+fn require() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.add_role("marketing", vec!["guest"]).is_ok());
+    assert!(acl.add_resource("newsletter", None).is_ok());
+    assert!(acl.allow(Some("marketing"), Some("newsletter"), Some("publish")).is_ok());
+    assert!(acl.allow(None, None, Some("export")).is_ok());
+
+    let marketing = Request{acl: &acl, role: Some(String::from("marketing"))};
+    let guest     = Request{acl: &acl, role: Some(String::from("guest"))};
+
+    assert_eq!(publish(&marketing, 7), Ok(7));
+    assert_eq!(publish(&guest, 7), Err(AppError::Denied(String::from("Access denied: DENY guest→newsletter: publish"))));
+    assert!(export(Request{acl: &acl, role: None}).is_ok());
+    assert_eq!(guest.require(None, Some("publish")).map_err(|denied| denied.decision.is_denied()), Err(true));
+} // require
@@ -0,0 +1,13 @@
+//! Golden-file tests of the policies in `tests/golden`.
+
+#![cfg(feature = "golden")]
+
+extern crate zorq_acl;
+
+use test_env_log::test;
+use zorq_acl::golden::assert_golden;
+
+#[test]
+fn news() {
+    assert_golden(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/news"));
+} // news
@@ -0,0 +1,436 @@
+//! Conformance tests ported from the laminas-permissions-acl unit tests.
+//!
+//! Tests relying on role or resource removal and assertions are not ported, since
+//! the `Acl` does not support these. Behaviors which intentionally differ are tested in both
+//! modes, see the crate documentation.
+
+extern crate zorq_acl;
+
+use test_env_log::test;
+use zorq_acl::{Acl, Error};
+
+fn compat() -> Acl {
+    let mut acl = Acl::new();
+
+    acl.set_laminas_compat(true);
+    acl
+} // compat
+
+#[test]
+fn role_registry_add_and_get_one() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("area", vec![]).is_ok());
+    assert!(acl.has_role("area"));
+    assert!(!acl.has_role("nonexistent"));
+} // role_registry_add_and_get_one
+
+#[test]
+fn role_registry_duplicate() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("area", vec![]).is_ok());
+    assert_eq!(acl.add_role("area", vec![]), Err(Error::DuplicateRole(String::from("area"))));
+} // role_registry_duplicate
+
+#[test]
+fn role_registry_inherits_non_existent() {
+    let mut acl = Acl::new();
+
+    assert_eq!(acl.add_role("guest", vec!["nonexistent"]), Err(Error::MissingParent(String::from("nonexistent"))));
+    assert!(!acl.has_role("guest"));
+} // role_registry_inherits_non_existent
+
+#[test]
+fn role_registry_inherits() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.add_role("member", vec!["guest"]).is_ok());
+    assert!(acl.add_role("editor", vec!["member"]).is_ok());
+    assert_eq!(acl.get_role_parents("guest"), Ok(vec![]));
+    assert_eq!(acl.get_role_parents("member"), Ok(vec!["guest"]));
+    assert_eq!(acl.get_role_ancestors("editor"), vec!["member", "guest"]);
+    assert!(!acl.get_role_ancestors("guest").contains(&"editor"));
+} // role_registry_inherits
+
+#[test]
+fn role_registry_inherits_multiple() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("parent0", vec![]).is_ok());
+    assert!(acl.add_role("parent1", vec![]).is_ok());
+    assert!(acl.add_role("child", vec!["parent0", "parent1"]).is_ok());
+    assert_eq!(acl.get_role_parents("child"), Ok(vec!["parent0", "parent1"]));
+    assert_eq!(acl.get_role_ancestors("child"), vec!["parent1", "parent0"]);
+} // role_registry_inherits_multiple
+
+#[test]
+fn resource_add_and_get_one() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_resource("area", None).is_ok());
+    assert!(acl.has_resource("area"));
+    assert!(!acl.has_resource("nonexistent"));
+} // resource_add_and_get_one
+
+#[test]
+fn resource_duplicate() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_resource("area", None).is_ok());
+    assert_eq!(acl.add_resource("area", None), Err(Error::DuplicateResource(String::from("area"))));
+} // resource_duplicate
+
+#[test]
+fn resource_inherits_non_existent() {
+    let mut acl = Acl::new();
+
+    assert_eq!(acl.add_resource("area", Some("nonexistent")), Err(Error::MissingParent(String::from("nonexistent"))));
+    assert!(!acl.has_resource("area"));
+} // resource_inherits_non_existent
+
+#[test]
+fn resource_inherits() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_resource("city", None).is_ok());
+    assert!(acl.add_resource("building", Some("city")).is_ok());
+    assert!(acl.add_resource("room", Some("building")).is_ok());
+    assert_eq!(acl.get_resource_parent("room"), Ok(Some("building")));
+    assert_eq!(acl.get_resource_ancestors("room"), vec!["building", "city"]);
+    assert!(acl.get_resource_ancestors("city").is_empty());
+} // resource_inherits
+
+#[test]
+fn default_deny() {
+    let acl = Acl::new();
+
+    assert!(!acl.is_allowed(None, None, None));
+    assert!(!acl.is_allowed(None, None, Some("somePrivilege")));
+} // default_deny
+
+#[test]
+fn default_rule_set() {
+    let mut acl = compat();
+
+    assert!(acl.allow(None, None, None).is_ok());
+    assert!(acl.is_allowed(None, None, None));
+    assert!(acl.deny(None, None, None).is_ok());
+    assert!(!acl.is_allowed(None, None, None));
+} // default_rule_set
+
+#[test]
+fn default_rule_set_fixed() {
+    let mut acl = Acl::new();
+
+    // the catch-all rule is fixed outside of compatibility mode
+    assert!(acl.allow(None, None, None).is_ok());
+    assert!(!acl.is_allowed(None, None, None));
+} // default_rule_set_fixed
+
+#[test]
+fn default_rule_set_privilege() {
+    let mut acl = compat();
+
+    assert!(acl.allow(None, None, None).is_ok());
+    assert!(acl.is_allowed(None, None, Some("somePrivilege")));
+    assert!(acl.deny(None, None, None).is_ok());
+    assert!(!acl.is_allowed(None, None, Some("somePrivilege")));
+} // default_rule_set_privilege
+
+#[test]
+fn privilege_allow() {
+    let mut acl = Acl::new();
+
+    assert!(acl.allow(None, None, Some("somePrivilege")).is_ok());
+    assert!(acl.is_allowed(None, None, Some("somePrivilege")));
+} // privilege_allow
+
+#[test]
+fn privilege_deny() {
+    let mut acl = compat();
+
+    assert!(acl.allow(None, None, None).is_ok());
+    assert!(acl.deny(None, None, Some("somePrivilege")).is_ok());
+    assert!(!acl.is_allowed(None, None, Some("somePrivilege")));
+    assert!(!acl.is_allowed(None, None, None));
+} // privilege_deny
+
+#[test]
+fn privileges() {
+    let mut acl = Acl::new();
+
+    for privilege in ["p1", "p2", "p3"].iter() {
+        assert!(acl.allow(None, None, Some(privilege)).is_ok());
+        assert!(acl.is_allowed(None, None, Some(privilege)));
+    } // for
+    assert!(acl.deny(None, None, Some("p1")).is_ok());
+    assert!(!acl.is_allowed(None, None, Some("p1")));
+    assert!(acl.is_allowed(None, None, Some("p2")));
+} // privileges
+
+#[test]
+fn role_default_allow() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.allow(Some("guest"), None, None).is_ok());
+    assert!(acl.is_allowed(Some("guest"), None, None));
+} // role_default_allow
+
+#[test]
+fn role_default_deny() {
+    let mut acl = compat();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.allow(None, None, None).is_ok());
+    assert!(acl.deny(Some("guest"), None, None).is_ok());
+    assert!(!acl.is_allowed(Some("guest"), None, None));
+    assert!(acl.is_allowed(None, None, None));
+} // role_default_deny
+
+#[test]
+fn role_default_rule_set_privilege() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.allow(Some("guest"), None, None).is_ok());
+    assert!(acl.is_allowed(Some("guest"), None, Some("somePrivilege")));
+    assert!(acl.deny(Some("guest"), None, None).is_ok());
+    assert!(!acl.is_allowed(Some("guest"), None, Some("somePrivilege")));
+} // role_default_rule_set_privilege
+
+#[test]
+fn role_privilege_allow() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.allow(Some("guest"), None, Some("somePrivilege")).is_ok());
+    assert!(acl.is_allowed(Some("guest"), None, Some("somePrivilege")));
+} // role_privilege_allow
+
+#[test]
+fn role_privilege_deny() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.allow(Some("guest"), None, None).is_ok());
+    assert!(acl.deny(Some("guest"), None, Some("somePrivilege")).is_ok());
+    assert!(!acl.is_allowed(Some("guest"), None, Some("somePrivilege")));
+} // role_privilege_deny
+
+#[test]
+fn role_default_allow_rule_with_privilege_deny_rule() {
+    let mut acl = compat();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+    assert!(acl.deny(None, None, None).is_ok());
+    assert!(acl.allow(Some("staff"), None, None).is_ok());
+    assert!(acl.deny(Some("staff"), None, Some("privilege1")).is_ok());
+    assert!(acl.deny(Some("staff"), None, Some("privilege2")).is_ok());
+    assert!(!acl.is_allowed(Some("staff"), None, None));
+    assert!(!acl.is_allowed(Some("staff"), None, Some("privilege1")));
+    assert!(acl.is_allowed(Some("staff"), None, Some("privilege3")));
+
+    let decision = acl.decide(Some("staff"), None, None);
+
+    assert_eq!(decision.matched.privilege, Some("privilege1"));
+} // role_default_allow_rule_with_privilege_deny_rule
+
+#[test]
+fn role_default_allow_rule_with_privilege_deny_rule_wildcard() {
+    let mut acl = Acl::new();
+
+    // a wildcard privilege only matches the rules defined for all privileges
+    assert!(acl.add_role("staff", vec![]).is_ok());
+    assert!(acl.allow(Some("staff"), None, None).is_ok());
+    assert!(acl.deny(Some("staff"), None, Some("privilege1")).is_ok());
+    assert!(acl.is_allowed(Some("staff"), None, None));
+} // role_default_allow_rule_with_privilege_deny_rule_wildcard
+
+#[test]
+fn role_default_deny_rule_with_privilege_allow_rule() {
+    let mut acl = compat();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+    assert!(acl.allow(None, None, None).is_ok());
+    assert!(acl.deny(Some("staff"), None, None).is_ok());
+    assert!(acl.allow(Some("staff"), None, Some("privilege1")).is_ok());
+    assert!(acl.allow(Some("staff"), None, Some("privilege2")).is_ok());
+    assert!(!acl.is_allowed(Some("staff"), None, None));
+    assert!(acl.is_allowed(Some("staff"), None, Some("privilege1")));
+    assert!(!acl.is_allowed(Some("staff"), None, Some("privilege3")));
+    assert!(acl.is_allowed(Some("guest"), None, None));
+} // role_default_deny_rule_with_privilege_allow_rule
+
+#[test]
+fn resource_inherits_rules() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_resource("city", None).is_ok());
+    assert!(acl.add_resource("building", Some("city")).is_ok());
+    assert!(acl.add_resource("room", Some("building")).is_ok());
+    assert!(acl.allow(None, Some("city"), None).is_ok());
+    assert!(acl.is_allowed(None, Some("room"), None));
+    assert!(acl.deny(None, Some("building"), Some("enter")).is_ok());
+    assert!(!acl.is_allowed(None, Some("room"), Some("enter")));
+    assert!(acl.is_allowed(None, Some("city"), Some("enter")));
+} // resource_inherits_rules
+
+#[test]
+fn resource_inheritance_precedes_role_inheritance() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("parent", vec![]).is_ok());
+    assert!(acl.add_role("child", vec!["parent"]).is_ok());
+    assert!(acl.add_resource("area", None).is_ok());
+    assert!(acl.add_resource("room", Some("area")).is_ok());
+    assert!(acl.allow(Some("child"), Some("area"), None).is_ok());
+    assert!(acl.deny(Some("parent"), Some("room"), None).is_ok());
+    assert!(!acl.is_allowed(Some("child"), Some("room"), None));
+    assert!(acl.is_allowed(Some("child"), Some("area"), None));
+} // resource_inheritance_precedes_role_inheritance
+
+#[test]
+fn multilevel_resources_with_deny_policy() {
+    let mut acl = compat();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.add_resource("blogposts", None).is_ok());
+    assert!(acl.add_resource("feature", Some("blogposts")).is_ok());
+    assert!(acl.add_resource("post_1", Some("feature")).is_ok());
+    assert!(acl.add_resource("post_2", Some("feature")).is_ok());
+    assert!(acl.deny(None, None, None).is_ok());
+    assert!(acl.allow(Some("guest"), Some("blogposts"), Some("read")).is_ok());
+    assert!(acl.deny(Some("guest"), Some("feature"), Some("read")).is_ok());
+    assert!(acl.allow(Some("guest"), Some("post_1"), Some("read")).is_ok());
+    assert!(acl.is_allowed(Some("guest"), Some("post_1"), Some("read")));
+    assert!(!acl.is_allowed(Some("guest"), Some("post_2"), Some("read")));
+    assert!(acl.is_allowed(Some("guest"), Some("blogposts"), Some("read")));
+    assert!(!acl.is_allowed(Some("guest"), Some("feature"), Some("read")));
+} // multilevel_resources_with_deny_policy
+
+#[test]
+fn multiple_inheritance_lifo() {
+    let mut acl = Acl::new();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.add_role("member", vec![]).is_ok());
+    assert!(acl.add_role("admin", vec![]).is_ok());
+    assert!(acl.add_role("someUser", vec!["guest", "member", "admin"]).is_ok());
+    assert!(acl.add_resource("someResource", None).is_ok());
+    assert!(acl.deny(Some("guest"), Some("someResource"), None).is_ok());
+    assert!(acl.allow(Some("member"), Some("someResource"), None).is_ok());
+    assert!(acl.is_allowed(Some("someUser"), Some("someResource"), None));
+    assert!(acl.deny(Some("admin"), Some("someResource"), None).is_ok());
+    assert!(!acl.is_allowed(Some("someUser"), Some("someResource"), None));
+} // multiple_inheritance_lifo
+
+#[test]
+fn unknown_role_and_resource() {
+    let mut acl = compat();
+
+    // laminas throws, here only wildcard rules apply
+    assert!(acl.allow(None, None, Some("view")).is_ok());
+    assert!(acl.is_allowed(Some("nonexistent"), Some("nonexistent"), Some("view")));
+    assert!(!acl.is_allowed(Some("nonexistent"), Some("nonexistent"), Some("edit")));
+} // unknown_role_and_resource
+
+#[test]
+fn cms_example() {
+    for &mode in [false, true].iter() {
+        let mut acl = Acl::new();
+
+        acl.set_laminas_compat(mode);
+        assert!(acl.add_role("guest", vec![]).is_ok());
+        assert!(acl.add_role("staff", vec!["guest"]).is_ok());
+        assert!(acl.add_role("editor", vec!["staff"]).is_ok());
+        assert!(acl.add_role("administrator", vec![]).is_ok());
+        assert!(acl.allow(Some("guest"), None, Some("view")).is_ok());
+        for privilege in ["edit", "submit", "revise"].iter() {
+            assert!(acl.allow(Some("staff"), None, Some(privilege)).is_ok());
+        } // for
+        for privilege in ["publish", "archive", "delete"].iter() {
+            assert!(acl.allow(Some("editor"), None, Some(privilege)).is_ok());
+        } // for
+        assert!(acl.allow(Some("administrator"), None, None).is_ok());
+
+        assert!( acl.is_allowed(Some("guest"), None, Some("view")));
+        assert!(!acl.is_allowed(Some("guest"), None, Some("edit")));
+        assert!(!acl.is_allowed(Some("staff"), None, Some("publish")));
+        assert!( acl.is_allowed(Some("staff"), None, Some("revise")));
+        assert!( acl.is_allowed(Some("editor"), None, Some("view")));
+        assert!(!acl.is_allowed(Some("editor"), None, Some("update")));
+        assert!( acl.is_allowed(Some("administrator"), None, Some("view")));
+        assert!( acl.is_allowed(Some("administrator"), None, None));
+        assert!( acl.is_allowed(Some("administrator"), None, Some("update")));
+
+        assert!(acl.add_role("marketing", vec!["staff"]).is_ok());
+        assert!(acl.add_resource("newsletter", None).is_ok());
+        assert!(acl.add_resource("news", None).is_ok());
+        assert!(acl.add_resource("latest", Some("news")).is_ok());
+        assert!(acl.add_resource("announcement", Some("news")).is_ok());
+        for resource in ["newsletter", "latest"].iter() {
+            assert!(acl.allow(Some("marketing"), Some(resource), Some("publish")).is_ok());
+            assert!(acl.allow(Some("marketing"), Some(resource), Some("archive")).is_ok());
+        } // for
+        assert!(acl.deny(Some("staff"), Some("latest"), Some("revise")).is_ok());
+        assert!(acl.deny(None, Some("announcement"), Some("archive")).is_ok());
+        acl.lock();
+
+        assert!(!acl.is_allowed(Some("staff"), Some("newsletter"), Some("publish")));
+        assert!( acl.is_allowed(Some("marketing"), Some("newsletter"), Some("publish")));
+        assert!(!acl.is_allowed(Some("staff"), Some("latest"), Some("publish")));
+        assert!( acl.is_allowed(Some("marketing"), Some("latest"), Some("publish")));
+        assert!( acl.is_allowed(Some("marketing"), Some("latest"), Some("archive")));
+        assert!(!acl.is_allowed(Some("marketing"), Some("latest"), Some("revise")));
+        assert!(!acl.is_allowed(Some("editor"), Some("announcement"), Some("archive")));
+        assert!(!acl.is_allowed(Some("administrator"), Some("announcement"), Some("archive")));
+
+        // the deny rule for archiving announcements only counts when querying all privileges
+        assert_eq!(acl.is_allowed(Some("administrator"), Some("announcement"), None), !mode);
+        assert!(acl.is_allowed(Some("administrator"), Some("news"), None));
+    } // for
+} // cms_example
+
+#[test]
+fn rule_remove() {
+    let mut acl = compat();
+
+    assert!(acl.add_role("guest", vec![]).is_ok());
+    assert!(acl.allow(None, None, Some("somePrivilege")).is_ok());
+    assert!(acl.is_allowed(None, None, Some("somePrivilege")));
+    assert_eq!(acl.remove_allow(None, None, Some("somePrivilege")), Ok(1));
+    assert!(!acl.is_allowed(None, None, Some("somePrivilege")));
+
+    assert!(acl.allow(Some("guest"), None, None).is_ok());
+    assert_eq!(acl.remove_deny(Some("guest"), None, None), Ok(0));
+    assert!(acl.is_allowed(Some("guest"), None, None));
+    assert_eq!(acl.remove_allow(Some("guest"), None, None), Ok(1));
+    assert!(!acl.is_allowed(Some("guest"), None, None));
+} // rule_remove
+
+#[test]
+fn rule_remove_default() {
+    let mut acl = compat();
+
+    assert!(acl.allow(None, None, None).is_ok());
+    assert!(acl.is_allowed(None, None, None));
+    assert_eq!(acl.remove_allow(None, None, None), Ok(1));
+    assert!(!acl.is_allowed(None, None, None));
+
+    // removing the default deny rule resets it to deny
+    assert_eq!(acl.remove_deny(None, None, None), Ok(0));
+    assert!(!acl.is_allowed(None, None, None));
+} // rule_remove_default
+
+#[test]
+fn rule_remove_non_existent() {
+    let mut acl = Acl::new();
+
+    assert_eq!(acl.remove_allow(Some("nonexistent"), None, None), Err(Error::MissingRole(String::from("nonexistent"))));
+    assert_eq!(acl.remove_deny(None, Some("nonexistent"), None), Err(Error::MissingResource(String::from("nonexistent"))));
+} // rule_remove_non_existent